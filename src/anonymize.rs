@@ -0,0 +1,79 @@
+//! Removing personal information before sharing a cue sheet
+use crate::comment::{self, Comment};
+use crate::Cuna;
+use std::mem;
+
+/// Keys of `REM` comments which identify the ripper, the drive or the time of ripping
+pub const IDENTIFYING_KEYS: &[&str] = &[
+    "COMMENT",
+    "RIPPER",
+    "RIPDATE",
+    "RIPTIME",
+    "DRIVE",
+    "DRIVESERIAL",
+    "SERIAL",
+    "LOG",
+    "LOGFILE",
+    "EXTRACTED",
+];
+
+impl Cuna {
+    /// Removes information about who ripped the disc, with which drive and when, and where the files were stored
+    ///
+    /// `REM`s with a key in [`IDENTIFYING_KEYS`] or containing a local path, in the header,
+    /// a `FILE` or a `TRACK`, are removed and returned,
+    /// and directories are stripped from `FILE` and `CDTEXTFILE`.
+    /// Musical metadata like `REM GENRE`, `REM DATE` and `REM DISCID` is kept
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// let mut sheet = Cuna::new(r#"REM GENRE Pop
+    /// REM COMMENT "ExactAudioCopy v0.99pb5"
+    /// REM LOGFILE "C:\Users\hana\Music\rip.log"
+    /// FILE "/home/hana/Music/a.flac" WAVE"#).unwrap();
+    /// let removed = sheet.anonymize();
+    /// assert_eq!(removed.len(), 2);
+    /// assert_eq!(sheet.comments[0], "GENRE Pop");
+    /// assert_eq!(sheet.comments.len(), 1);
+    /// assert_eq!(sheet[0].name, "a.flac");
+    /// # }
+    /// ```
+    pub fn anonymize(&mut self) -> Comment {
+        let mut removed = take_identifying(&mut self.comments);
+        for file in &mut self.files {
+            file.name = file_name(&file.name).to_owned();
+            removed.extend(take_identifying(&mut file.comments));
+            for track in &mut file.tracks {
+                removed.extend(take_identifying(&mut track.comments));
+            }
+        }
+        if let Some(cdtextfile) = self.header.cdtextfile.as_mut() {
+            *cdtextfile = file_name(cdtextfile).to_owned();
+        }
+        removed
+    }
+}
+
+fn take_identifying(comments: &mut Comment) -> Comment {
+    let (removed, kept) = mem::take(comments)
+        .into_iter()
+        .partition(|c| is_identifying(c));
+    *comments = kept;
+    removed
+}
+fn is_identifying(rem: &str) -> bool {
+    let (key, value) = comment::split(rem);
+    IDENTIFYING_KEYS.iter().any(|k| k.eq_ignore_ascii_case(key)) || contains_path(value)
+}
+fn contains_path(s: &str) -> bool {
+    let s = s.trim_matches('"');
+    let bytes = s.as_bytes();
+    s.starts_with('/')
+        || s.starts_with("~/")
+        || s.starts_with(r"\\")
+        || (bytes.first().is_some_and(u8::is_ascii_alphabetic) && bytes.get(1..3) == Some(br":\"))
+}
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
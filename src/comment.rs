@@ -1,25 +1,384 @@
-use crate::utils;
-use std::iter::FromIterator;
-use std::ops::Index;
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+use core::ops::Index;
+
+pub const GENRE: &str = "GENRE";
+pub const DATE: &str = "DATE";
+pub const DISCID: &str = "DISCID";
+pub const COMMENT: &str = "COMMENT";
+/// Key of the start of a track generated by [`CueWriter::annotations()`](crate::writer::CueWriter::annotations)
+pub const START: &str = "START";
+/// Key of the length of a track generated by [`CueWriter::annotations()`](crate::writer::CueWriter::annotations)
+pub const LENGTH: &str = "LENGTH";
+/// Keys of the ReplayGain of the album, see [`Comment::album_gain()`]
+pub const REPLAYGAIN_ALBUM_GAIN: &str = "REPLAYGAIN_ALBUM_GAIN";
+pub const REPLAYGAIN_ALBUM_PEAK: &str = "REPLAYGAIN_ALBUM_PEAK";
+/// Keys of the ReplayGain of a track, see [`Comment::track_gain()`]
+pub const REPLAYGAIN_TRACK_GAIN: &str = "REPLAYGAIN_TRACK_GAIN";
+pub const REPLAYGAIN_TRACK_PEAK: &str = "REPLAYGAIN_TRACK_PEAK";
+/// Keys of the `REM` block written by rippers like EAC, in the order they are written
+pub const STANDARD_KEYS: &[&str] = &[GENRE, DATE, DISCID, COMMENT];
+
+/// Keys of `REM` comments which describe the whole disc rather than a track
+pub const HEADER_KEYS: &[&str] = &[
+    GENRE,
+    DATE,
+    DISCID,
+    COMMENT,
+    "TRACKTOTAL",
+    "TOTALTRACKS",
+    "DISCNUMBER",
+    "DISCTOTAL",
+    "TOTALDISCS",
+];
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Comment(pub Vec<String>);
 
+/// Where a `REM` belongs, see [`Cuna::scoped_comments()`](crate::Cuna::scoped_comments)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scope {
+    /// Before the first `FILE`, or a key of [`HEADER_KEYS`] anywhere
+    Header,
+    /// Between a `FILE` and its first `TRACK`, with the position of the `FILE`
+    File(usize),
+    /// Inside a `TRACK`, with the positions of the `FILE` and of the track in it
+    Track { file: usize, track: usize },
+}
+
+/// How [`Comment::set_genres()`] writes more than one genre
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum GenreStyle {
+    /// One `REM GENRE "Rock; Alternative"`, which tools reading a single genre show as it is
+    #[default]
+    Joined,
+    /// A `REM GENRE` line for each genre
+    Repeated,
+}
+
+/// The values of the standard `REM`s written by rippers, see [`Comment::metadata()`]
+///
+/// The comments are left as they are, so the sheet is still written back the same
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct RemMetadata {
+    /// The genres of `GENRE`, see [`Comment::genres()`]
+    pub genres: Vec<String>,
+    /// The value of `DATE` without quotes, like `2011` or `2011/10/19`
+    pub date: Option<String>,
+    /// The year at the start of `DATE`
+    pub year: Option<u32>,
+    /// The freedb disc ID of `DISCID`, 8 hexadecimal digits
+    pub discid: Option<u32>,
+    /// The value of `COMMENT` without quotes, usually the ripper and its version
+    pub comment: Option<String>,
+}
+
+/// The ReplayGain of an album or a track, as foobar2000 and CUETools write it in `REM`s
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplayGain {
+    /// The gain in dB
+    pub gain: Option<f64>,
+    /// The peak sample, 1.0 is full scale
+    pub peak: Option<f64>,
+}
+
+/// Builds the standard `REM` block (`GENRE`, `DATE`, `DISCID` and `COMMENT`) in the order EAC writes it
+///
+/// Values containing whitespace are quoted
+///
+/// ```rust
+/// use cuna::comment::RemBlock;
+/// let comment = RemBlock::new()
+///     .comment("ExactAudioCopy v0.99pb5")
+///     .genre("Pop")
+///     .date("2011")
+///     .build();
+/// assert_eq!(comment.0, ["GENRE Pop", "DATE 2011", r#"COMMENT "ExactAudioCopy v0.99pb5""#]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct RemBlock {
+    genre: Option<String>,
+    date: Option<String>,
+    discid: Option<String>,
+    comment: Option<String>,
+}
+
+impl RemBlock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Takes the standard values from existing comments
+    pub fn from_comment(comment: &Comment) -> Self {
+        let get = |key| comment.get(key).map(str::to_owned);
+        Self {
+            genre: get(GENRE),
+            date: get(DATE),
+            discid: get(DISCID),
+            comment: get(COMMENT),
+        }
+    }
+    pub fn genre(mut self, genre: impl Into<String>) -> Self {
+        self.genre = Some(genre.into());
+        self
+    }
+    pub fn date(mut self, date: impl Into<String>) -> Self {
+        self.date = Some(date.into());
+        self
+    }
+    pub fn discid(mut self, discid: impl Into<String>) -> Self {
+        self.discid = Some(discid.into());
+        self
+    }
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+    pub fn build(&self) -> Comment {
+        let values = [&self.genre, &self.date, &self.discid, &self.comment];
+        STANDARD_KEYS
+            .iter()
+            .zip(values.iter())
+            .filter_map(|(key, value)| value.as_ref().map(|v| rem(key, v)))
+            .collect()
+    }
+}
 impl Comment {
     pub fn new(s: &str) -> Self {
         s.lines()
-            .filter_map(|s| utils::keyword("REM")(s).ok().map(|(c, _)| c))
+            .filter_map(|s| match s.get(..4) {
+                Some(keyword) if keyword.eq_ignore_ascii_case("REM ") => Some(&s[4..]),
+                _ => None,
+            })
             .collect()
     }
     pub fn push(&mut self, s: String) {
         self.0.push(s)
     }
+    /// Returns the value of the first comment starting with key, case-insensitively, without quotes
+    ///
+    /// ```rust
+    /// use cuna::comment::Comment;
+    /// let comment: Comment = vec!["GENRE Pop", r#"COMMENT "ExactAudioCopy v0.99pb5""#].into_iter().collect();
+    /// assert_eq!(comment.get("genre"), Some("Pop"));
+    /// assert_eq!(comment.get("COMMENT"), Some("ExactAudioCopy v0.99pb5"));
+    /// assert_eq!(comment.get("DATE"), None);
+    /// ```
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.iter().find_map(|c| value_of(c, key))
+    }
+    /// Returns the genres of every `GENRE` comment, splitting values like `Rock; Alternative` at `;`
+    ///
+    /// Genres are trimmed and returned once in the order they appear
+    ///
+    /// ```rust
+    /// use cuna::comment::Comment;
+    /// let comment: Comment = vec![r#"GENRE "Rock; Alternative""#, "DATE 2011", "GENRE Rock", "GENRE Shoegaze"].into_iter().collect();
+    /// assert_eq!(comment.genres(), ["Rock", "Alternative", "Shoegaze"]);
+    /// ```
+    pub fn genres(&self) -> Vec<String> {
+        let mut genres: Vec<String> = Vec::new();
+        let values = self.0.iter().filter_map(|c| value_of(c, GENRE));
+        for genre in values.flat_map(|v| v.split(';')).map(str::trim) {
+            if !genre.is_empty() && !genres.iter().any(|g| g == genre) {
+                genres.push(genre.to_owned());
+            }
+        }
+        genres
+    }
+    /// Replaces the `GENRE` comments with genres written in style,
+    /// where the first `GENRE` was or before the other comments
+    ///
+    /// ```rust
+    /// use cuna::comment::{Comment, GenreStyle};
+    /// let mut comment: Comment = vec!["DATE 2011", "GENRE Pop", "GENRE Rock"].into_iter().collect();
+    /// comment.set_genres(&["Rock", "Alternative"], GenreStyle::Joined);
+    /// assert_eq!(comment.0, ["DATE 2011", r#"GENRE "Rock; Alternative""#]);
+    /// comment.set_genres(&["Rock", "Alternative"], GenreStyle::Repeated);
+    /// assert_eq!(comment.0, ["DATE 2011", "GENRE Rock", "GENRE Alternative"]);
+    /// ```
+    pub fn set_genres<S: AsRef<str>>(&mut self, genres: &[S], style: GenreStyle) {
+        let is_genre = |c: &String| split(c).0.eq_ignore_ascii_case(GENRE);
+        let at = self.0.iter().position(is_genre).unwrap_or(0);
+        self.0.retain(|c| !is_genre(c));
+        let genres = genres.iter().map(AsRef::as_ref);
+        let lines: Vec<_> = match style {
+            GenreStyle::Joined if genres.len() == 0 => Vec::new(),
+            GenreStyle::Joined => vec![rem(GENRE, &genres.collect::<Vec<_>>().join("; "))],
+            GenreStyle::Repeated => genres.map(|genre| rem(GENRE, genre)).collect(),
+        };
+        self.0.splice(at..at, lines);
+    }
+    /// Returns the value of a comment as a number, accepting quoted values and forms like `3/12`
+    ///
+    /// ```rust
+    /// use cuna::comment::Comment;
+    /// let comment: Comment = vec![r#"DATE "2011""#, "DISCNUMBER 1/2", "TRACKTOTAL twelve"].into_iter().collect();
+    /// assert_eq!(comment.get_number("DATE"), Some(2011));
+    /// assert_eq!(comment.get_number("DISCNUMBER"), Some(1));
+    /// assert_eq!(comment.get_number("TRACKTOTAL"), None);
+    /// ```
+    pub fn get_number(&self, key: &str) -> Option<u32> {
+        let value = self.get(key)?.trim();
+        let digits = value
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(value, |end| &value[..end]);
+        digits.parse().ok()
+    }
+    /// Returns the value of a comment as a decimal number followed by an optional unit,
+    /// with `.` or `,` as decimal separator, see [`parse_decimal()`]
+    ///
+    /// ```rust
+    /// use cuna::comment::Comment;
+    /// let comment: Comment = vec!["REPLAYGAIN_ALBUM_GAIN -7,11 dB", "REPLAYGAIN_ALBUM_PEAK 0.988525"].into_iter().collect();
+    /// assert_eq!(comment.get_decimal("REPLAYGAIN_ALBUM_GAIN"), Some(-7.11));
+    /// assert_eq!(comment.get_decimal("REPLAYGAIN_ALBUM_PEAK"), Some(0.988525));
+    /// ```
+    pub fn get_decimal(&self, key: &str) -> Option<f64> {
+        parse_decimal(self.get(key)?)
+    }
+    /// Replaces the comments with key by one with value written with decimals digits after a `.`
+    /// and followed by unit, where the first of them was or at the end
+    ///
+    /// ```rust
+    /// use cuna::comment::Comment;
+    /// let mut comment: Comment = vec!["REPLAYGAIN_ALBUM_GAIN -7,11 dB", "GENRE Pop"].into_iter().collect();
+    /// let gain = comment.get_decimal("REPLAYGAIN_ALBUM_GAIN").unwrap();
+    /// comment.set_decimal("REPLAYGAIN_ALBUM_GAIN", gain, 2, Some("dB"));
+    /// comment.set_decimal("REPLAYGAIN_ALBUM_PEAK", 0.988525, 6, None);
+    /// assert_eq!(comment.0, ["REPLAYGAIN_ALBUM_GAIN -7.11 dB", "GENRE Pop", "REPLAYGAIN_ALBUM_PEAK 0.988525"]);
+    /// ```
+    pub fn set_decimal(&mut self, key: &str, value: f64, decimals: usize, unit: Option<&str>) {
+        let mut line = format!("{} {:.*}", key, decimals, value);
+        if let Some(unit) = unit {
+            line.push(' ');
+            line.push_str(unit);
+        }
+        let is_key = |c: &String| split(c).0.eq_ignore_ascii_case(key);
+        let at = self.0.iter().position(is_key).unwrap_or(self.0.len());
+        self.0.retain(|c| !is_key(c));
+        self.0.insert(at, line);
+    }
+    /// Returns the values of `GENRE`, `DATE`, `DISCID` and `COMMENT`, the first of each key
+    ///
+    /// A value which does not parse, like a `DISCID` which is not hexadecimal, is None
+    ///
+    /// ```rust
+    /// use cuna::comment::Comment;
+    /// let comment: Comment = vec!["GENRE Pop", "DATE 2011/10/19", "DISCID 59063607", r#"COMMENT "ExactAudioCopy v0.99pb5""#]
+    ///     .into_iter()
+    ///     .collect();
+    /// let metadata = comment.metadata();
+    /// assert_eq!(metadata.genres, ["Pop"]);
+    /// assert_eq!(metadata.date.as_deref(), Some("2011/10/19"));
+    /// assert_eq!(metadata.year, Some(2011));
+    /// assert_eq!(metadata.discid, Some(0x59063607));
+    /// assert_eq!(metadata.comment.as_deref(), Some("ExactAudioCopy v0.99pb5"));
+    /// ```
+    pub fn metadata(&self) -> RemMetadata {
+        let discid = self.get(DISCID).map(str::trim).filter(|d| d.len() == 8);
+        RemMetadata {
+            genres: self.genres(),
+            date: self.get(DATE).map(str::to_owned),
+            year: self.get_number(DATE),
+            discid: discid.and_then(|d| u32::from_str_radix(d, 16).ok()),
+            comment: self.get(COMMENT).map(str::to_owned),
+        }
+    }
+    /// Returns the values of `REPLAYGAIN_ALBUM_GAIN` and `REPLAYGAIN_ALBUM_PEAK`
+    ///
+    /// ```rust
+    /// use cuna::comment::{Comment, ReplayGain};
+    /// let mut comment: Comment = vec!["REPLAYGAIN_ALBUM_GAIN -7,11 dB", "REPLAYGAIN_ALBUM_PEAK 0.988525"].into_iter().collect();
+    /// assert_eq!(comment.album_gain(), ReplayGain { gain: Some(-7.11), peak: Some(0.988525) });
+    /// comment.set_album_gain(ReplayGain { gain: Some(-6.5), peak: None });
+    /// assert_eq!(comment.0, ["REPLAYGAIN_ALBUM_GAIN -6.50 dB"]);
+    /// ```
+    pub fn album_gain(&self) -> ReplayGain {
+        self.replay_gain(REPLAYGAIN_ALBUM_GAIN, REPLAYGAIN_ALBUM_PEAK)
+    }
+    /// Returns the values of `REPLAYGAIN_TRACK_GAIN` and `REPLAYGAIN_TRACK_PEAK`
+    pub fn track_gain(&self) -> ReplayGain {
+        self.replay_gain(REPLAYGAIN_TRACK_GAIN, REPLAYGAIN_TRACK_PEAK)
+    }
+    /// Replaces the album ReplayGain, writing the gain with 2 decimals and the peak with 6 like foobar2000,
+    /// a value which is None is removed
+    pub fn set_album_gain(&mut self, replay_gain: ReplayGain) {
+        self.set_replay_gain(REPLAYGAIN_ALBUM_GAIN, REPLAYGAIN_ALBUM_PEAK, replay_gain)
+    }
+    /// Replaces the track ReplayGain like [`set_album_gain()`](Comment::set_album_gain)
+    pub fn set_track_gain(&mut self, replay_gain: ReplayGain) {
+        self.set_replay_gain(REPLAYGAIN_TRACK_GAIN, REPLAYGAIN_TRACK_PEAK, replay_gain)
+    }
+    fn replay_gain(&self, gain: &str, peak: &str) -> ReplayGain {
+        ReplayGain {
+            gain: self.get_decimal(gain),
+            peak: self.get_decimal(peak),
+        }
+    }
+    fn set_replay_gain(&mut self, gain: &str, peak: &str, replay_gain: ReplayGain) {
+        match replay_gain.gain {
+            Some(value) => self.set_decimal(gain, value, 2, Some("dB")),
+            None => self.remove(gain),
+        }
+        match replay_gain.peak {
+            Some(value) => self.set_decimal(peak, value, 6, None),
+            None => self.remove(peak),
+        }
+    }
+    /// Removes the comments with key
+    fn remove(&mut self, key: &str) {
+        self.0.retain(|c| !split(c).0.eq_ignore_ascii_case(key))
+    }
+    /// Returns the comments with the standard block first in the order EAC writes it,
+    /// followed by the other comments in their original order
+    ///
+    /// ```rust
+    /// use cuna::comment::Comment;
+    /// let comment: Comment = vec!["COMMENT EAC", "REPLAYGAIN_ALBUM_GAIN -7.11 dB", "GENRE Pop"].into_iter().collect();
+    /// assert_eq!(comment.standard_rem_block().0, ["GENRE Pop", "COMMENT EAC", "REPLAYGAIN_ALBUM_GAIN -7.11 dB"]);
+    /// ```
+    pub fn standard_rem_block(&self) -> Comment {
+        let rank = |c: &String| {
+            let (key, _) = split(c);
+            STANDARD_KEYS
+                .iter()
+                .position(|k| k.eq_ignore_ascii_case(key))
+                .unwrap_or(STANDARD_KEYS.len())
+        };
+        let mut comments = self.0.clone();
+        comments.sort_by_key(rank);
+        Self(comments)
+    }
+    pub fn iter(&self) -> core::slice::Iter<'_, String> {
+        self.0.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Removes every comment, keeping the allocated capacity
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
 }
 impl<S: Into<String>> FromIterator<S> for Comment {
     fn from_iter<T: IntoIterator<Item = S>>(iter: T) -> Self {
         Self(iter.into_iter().map(Into::into).collect())
     }
 }
+impl<S: Into<String>> Extend<S> for Comment {
+    fn extend<T: IntoIterator<Item = S>>(&mut self, iter: T) {
+        self.0.extend(iter.into_iter().map(Into::into))
+    }
+}
 impl IntoIterator for Comment {
     type Item = String;
     type IntoIter = <Vec<String> as IntoIterator>::IntoIter;
@@ -28,6 +387,14 @@ impl IntoIterator for Comment {
         self.0.into_iter()
     }
 }
+impl<'a> IntoIterator for &'a Comment {
+    type Item = &'a String;
+    type IntoIter = core::slice::Iter<'a, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
 impl Index<usize> for Comment {
     type Output = String;
 
@@ -36,3 +403,52 @@ impl Index<usize> for Comment {
         &self.0[index]
     }
 }
+
+/// Formats a comment, quoting values containing whitespace
+fn rem(key: &str, value: &str) -> String {
+    if value.contains(char::is_whitespace) {
+        format!(r#"{} "{}""#, key, value)
+    } else {
+        format!("{} {}", key, value)
+    }
+}
+/// Splits a comment into its key and the rest
+pub(crate) fn split(comment: &str) -> (&str, &str) {
+    let comment = comment.trim();
+    match comment.find(char::is_whitespace) {
+        Some(at) => (&comment[..at], comment[at..].trim_start()),
+        None => (comment, ""),
+    }
+}
+fn value_of<'a>(comment: &'a str, key: &str) -> Option<&'a str> {
+    match split(comment) {
+        (k, value) if k.eq_ignore_ascii_case(key) => Some(value.trim_matches('"')),
+        _ => None,
+    }
+}
+/// Parses the decimal number at the start of a value like `-7.11 dB`, whatever the locale of
+/// the tool which wrote it, so the decimal separator may be `.` or `,`
+///
+/// Text after the number, usually a unit, is ignored. Numbers with both separators, like `1,234.5`,
+/// are rejected rather than guessed
+///
+/// ```rust
+/// use cuna::comment::parse_decimal;
+/// assert_eq!(parse_decimal("-7,11 dB"), Some(-7.11));
+/// assert_eq!(parse_decimal("+0.5dB"), Some(0.5));
+/// assert_eq!(parse_decimal(" 1"), Some(1.0));
+/// assert_eq!(parse_decimal("1,234.5"), None);
+/// assert_eq!(parse_decimal("dB"), None);
+/// ```
+pub fn parse_decimal(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | ',')))
+        .unwrap_or(value.len());
+    value[..end].replace(',', ".").parse().ok()
+}
+/// Returns true if the comment is header-level metadata like `GENRE` or `DATE`
+pub fn is_header_key(comment: &str) -> bool {
+    let (key, _) = split(comment);
+    HEADER_KEYS.iter().any(|k| k.eq_ignore_ascii_case(key))
+}
@@ -0,0 +1,127 @@
+//! Scoring how complete the metadata of a cue sheet is
+use crate::Cuna;
+use std::fmt;
+
+/// A piece of metadata which may be missing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    Title,
+    Performer,
+    Isrc,
+    Date,
+    Genre,
+}
+/// A missing field, `track` is None if the field belongs to the whole sheet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Missing {
+    pub track: Option<u8>,
+    pub field: Field,
+}
+/// The result of [`Cuna::completeness()`](Cuna::completeness)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completeness {
+    /// Between 0.0 (nothing) and 1.0 (everything is present)
+    pub score: f32,
+    pub missing: Vec<Missing>,
+}
+
+impl Completeness {
+    /// Returns the fields missing in the whole sheet
+    pub fn missing_in_header(&self) -> impl Iterator<Item = Field> + '_ {
+        self.missing_in(None)
+    }
+    /// Returns the fields missing in the track with the given id
+    pub fn missing_in_track(&self, id: u8) -> impl Iterator<Item = Field> + '_ {
+        self.missing_in(Some(id))
+    }
+    /// Returns true if nothing is missing
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+    fn missing_in(&self, track: Option<u8>) -> impl Iterator<Item = Field> + '_ {
+        self.missing
+            .iter()
+            .filter(move |m| m.track == track)
+            .map(|m| m.field)
+    }
+}
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Title => "TITLE",
+            Self::Performer => "PERFORMER",
+            Self::Isrc => "ISRC",
+            Self::Date => "DATE",
+            Self::Genre => "GENRE",
+        };
+        f.write_str(s)
+    }
+}
+impl Cuna {
+    /// Scores the metadata of the sheet and lists what is missing
+    ///
+    /// The score is the average of six parts, each between 0.0 and 1.0:
+    /// album title, track titles, performers (a track without `PERFORMER` is covered by the album performer),
+    /// ISRCs, `REM DATE` and `REM GENRE`
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// use cuna::completeness::Field;
+    ///
+    /// let sheet = Cuna::new(r#"REM GENRE Pop
+    /// TITLE "Album"
+    /// FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO
+    ///     TITLE "Song"
+    ///     INDEX 01 00:00:00"#).unwrap();
+    /// let completeness = sheet.completeness();
+    /// assert!(completeness.score < 1.0);
+    /// assert_eq!(completeness.missing_in_header().collect::<Vec<_>>(), [Field::Performer, Field::Date]);
+    /// assert_eq!(completeness.missing_in_track(1).collect::<Vec<_>>(), [Field::Performer, Field::Isrc]);
+    /// # }
+    /// ```
+    pub fn completeness(&self) -> Completeness {
+        let mut missing = Vec::new();
+        let mut header = |present: bool, field| {
+            if !present {
+                missing.push(Missing { track: None, field });
+            }
+            present as u8 as f32
+        };
+        let album_title = header(!self.title().is_empty(), Field::Title);
+        header(!self.performer().is_empty(), Field::Performer);
+        let date = header(self.comments.get("DATE").is_some(), Field::Date);
+        let genre = header(self.comments.get("GENRE").is_some(), Field::Genre);
+
+        let (mut tracks, mut titles, mut performers, mut isrcs) = (0, 0, 0, 0);
+        for track in self.tracks() {
+            let mut check = |present: bool, field| {
+                if !present {
+                    missing.push(Missing {
+                        track: Some(track.id()),
+                        field,
+                    });
+                }
+                present as usize
+            };
+            tracks += 1;
+            titles += check(!track.title().is_empty(), Field::Title);
+            performers += check(
+                !track.performer().is_empty() || !self.performer().is_empty(),
+                Field::Performer,
+            );
+            isrcs += check(track.isrc().is_some(), Field::Isrc);
+        }
+        let ratio = |n: usize| {
+            if tracks == 0 {
+                1.0
+            } else {
+                n as f32 / tracks as f32
+            }
+        };
+        let score =
+            (album_title + ratio(titles) + ratio(performers) + ratio(isrcs) + date + genre) / 6.0;
+        Completeness { score, missing }
+    }
+}
@@ -0,0 +1,384 @@
+//! A lossless concrete syntax tree of cue sheets
+//!
+//! Every line of the source becomes a [`Node`] with byte spans for its indentation,
+//! keyword, arguments, trailing whitespace and line ending, so formatters and editors can
+//! work on the exact source structure.
+//! Building a [`Cst`] never fails, the commands are not checked;
+//! use [`Document`](crate::document::Document) or [`Cuna`](crate::Cuna) for the meaning of them.
+//!
+//! ```rust
+//! use cuna::cst::Cst;
+//! let cst = Cst::new("FILE \"a b.flac\" WAVE\n  TRACK 01 AUDIO\n");
+//! let track = &cst.nodes()[1];
+//! assert_eq!(cst.text(track.indent.clone()), "  ");
+//! assert_eq!(cst.text(track.keyword.as_ref().unwrap().span.clone()), "TRACK");
+//! let file = &cst.nodes()[0];
+//! assert_eq!(cst.value(&file.args[0]), "a b.flac");
+//! assert_eq!(cst.to_string(), "FILE \"a b.flac\" WAVE\n  TRACK 01 AUDIO\n");
+//! ```
+//!
+//! Edits such as [`Cst::set_track_title()`] only rewrite the affected lines and leave the rest
+//! of the source untouched, which keeps diffs of version-controlled sheets minimal.
+use crate::error::EditError;
+use crate::scan::closing_quote;
+use crate::time::TimeStamp;
+use crate::writer;
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+
+pub(crate) const BOM: char = '\u{feff}';
+
+/// The line ending of a [`Node`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    /// `\n`
+    Lf,
+    /// `\r\n`
+    CrLf,
+    /// `\r`
+    Cr,
+    /// The last line of a file without a line ending
+    None,
+}
+/// A range of bytes in the source of a [`Cst`]
+pub type Span = Range<usize>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// A run of characters other than whitespace
+    Word,
+    /// Characters between double quotes, the span includes the quotes.
+    /// `\"` and `""` are escaped quotes which do not end the token
+    Quoted,
+    /// A double quote without the closing one, the span runs to the end of the line
+    Unterminated,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+/// A line of a cue sheet
+///
+/// Blank lines have no keyword, and the spans of a node cover the whole line:
+/// `indent`, `keyword`, `args` separated by whitespace, `trailing` and `ending` in order
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Node {
+    /// The whole line including the line ending
+    pub span: Span,
+    pub indent: Span,
+    pub keyword: Option<Token>,
+    pub args: Vec<Token>,
+    pub trailing: Span,
+    pub ending: LineEnding,
+}
+/// The concrete syntax tree of a cue sheet, which owns the source
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Cst {
+    source: String,
+    nodes: Vec<Node>,
+}
+
+impl LineEnding {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+            Self::Cr => "\r",
+            Self::None => "",
+        }
+    }
+}
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+impl Token {
+    pub fn is_quoted(&self) -> bool {
+        self.kind != TokenKind::Word
+    }
+}
+impl Node {
+    /// The span of the command without the whitespace around it
+    pub fn content(&self) -> Span {
+        self.indent.end..self.trailing.start
+    }
+    pub fn is_blank(&self) -> bool {
+        self.keyword.is_none()
+    }
+}
+impl Cst {
+    /// Builds the syntax tree of s
+    pub fn new(s: &str) -> Self {
+        let offset = if s.starts_with(BOM) {
+            BOM.len_utf8()
+        } else {
+            0
+        };
+        let nodes = line_ranges(&s[offset..])
+            .map(|(range, ending)| {
+                let range = range.start + offset..range.end + offset;
+                node(s, range, ending)
+            })
+            .collect();
+        Self {
+            source: s.to_owned(),
+            nodes,
+        }
+    }
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+    /// Returns true if the source starts with a UTF-8 BOM
+    pub fn has_bom(&self) -> bool {
+        self.source.starts_with(BOM)
+    }
+    /// Returns the source text in span
+    ///
+    /// # Panics
+    ///
+    /// Panics if span is out of the source
+    pub fn text(&self, span: Span) -> &str {
+        &self.source[span]
+    }
+    /// Returns the text of a token without the quotes around it
+    pub fn value(&self, token: &Token) -> &str {
+        let text = self.text(token.span.clone());
+        match token.kind {
+            TokenKind::Word => text,
+            TokenKind::Quoted => &text[1..text.len() - 1],
+            TokenKind::Unterminated => &text[1..],
+        }
+    }
+    /// Returns the keyword of a node, or an empty str for blank lines
+    pub fn keyword(&self, node: &Node) -> &str {
+        node.keyword
+            .as_ref()
+            .map_or("", |keyword| self.text(keyword.span.clone()))
+    }
+}
+impl Cst {
+    /// Sets the `TITLE` of a `TRACK`, adding a `TITLE` line after the `TRACK` line if there is none
+    ///
+    /// The title is escaped like [`Sanitize::Escape`](crate::writer::Sanitize::Escape) does
+    ///
+    /// ```rust
+    /// use cuna::cst::Cst;
+    /// let mut cst = Cst::new("TRACK 01 AUDIO\r\n    INDEX 01 00:00:00\r\n");
+    /// cst.set_track_title(1, "Departures").unwrap();
+    /// assert_eq!(cst.source(), "TRACK 01 AUDIO\r\n    TITLE \"Departures\"\r\n    INDEX 01 00:00:00\r\n");
+    /// cst.set_track_title(1, "Departures (TV size)").unwrap();
+    /// assert_eq!(cst.nodes()[1].span, 16..50);
+    /// ```
+    pub fn set_track_title(&mut self, track: u8, title: &str) -> Result<(), EditError> {
+        let at = self
+            .find_track(track)
+            .ok_or(EditError::NoSuchTrack(track))?;
+        let title = format!("\"{}\"", writer::escape(title, true));
+        let existing = self.scope(at).find(|&i| self.is_command(i, "TITLE"));
+        match existing {
+            Some(i) => {
+                self.replace_args(i, &title);
+                Ok(())
+            }
+            None => {
+                let indent = match self.scope(at).find(|&i| !self.nodes[i].is_blank()) {
+                    Some(i) => self.text(self.nodes[i].indent.clone()).to_owned(),
+                    None => format!("{}  ", self.text(self.nodes[at].indent.clone())),
+                };
+                self.insert_line(at, &indent, &format!("TITLE {}", title));
+                Ok(())
+            }
+        }
+    }
+    /// Replaces the time of an `INDEX` of a `TRACK`
+    ///
+    /// ```rust
+    /// use cuna::cst::Cst;
+    /// use cuna::time::TimeStamp;
+    /// let mut cst = Cst::new("TRACK 01 AUDIO\n  index 01   00:00:00 \n");
+    /// cst.replace_index_time(1, 1, TimeStamp::new(0, 2, 0)).unwrap();
+    /// assert_eq!(cst.source(), "TRACK 01 AUDIO\n  index 01   00:02:00 \n");
+    /// ```
+    pub fn replace_index_time(
+        &mut self,
+        track: u8,
+        index: u8,
+        time: TimeStamp,
+    ) -> Result<(), EditError> {
+        let at = self
+            .find_track(track)
+            .ok_or(EditError::NoSuchTrack(track))?;
+        let node = self
+            .scope(at)
+            .find(|&i| self.is_command(i, "INDEX") && self.number_arg(i) == Some(index))
+            .filter(|&i| self.nodes[i].args.len() >= 2)
+            .ok_or(EditError::NoSuchIndex { track, index })?;
+        let span = self.nodes[node].args[1].span.clone();
+        self.splice(span, &time.to_string());
+        Ok(())
+    }
+    /// Inserts a command as a new line after the line at (starting from 0), with the same indentation
+    ///
+    /// ```rust
+    /// use cuna::cst::Cst;
+    /// let mut cst = Cst::new("FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO");
+    /// cst.insert_command_after(1, "ISRC JPU901100123").unwrap();
+    /// assert_eq!(cst.source(), "FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n  ISRC JPU901100123");
+    /// ```
+    pub fn insert_command_after(&mut self, at: usize, command: &str) -> Result<(), EditError> {
+        let node = self.nodes.get(at).ok_or(EditError::NoSuchLine(at))?;
+        let indent = self.text(node.indent.clone()).to_owned();
+        self.insert_line(at, &indent, command);
+        Ok(())
+    }
+    fn is_command(&self, at: usize, keyword: &str) -> bool {
+        self.keyword(&self.nodes[at]).eq_ignore_ascii_case(keyword)
+    }
+    fn number_arg(&self, at: usize) -> Option<u8> {
+        let arg = self.nodes[at].args.first()?;
+        self.value(arg).parse().ok()
+    }
+    fn find_track(&self, track: u8) -> Option<usize> {
+        (0..self.nodes.len())
+            .find(|&i| self.is_command(i, "TRACK") && self.number_arg(i) == Some(track))
+    }
+    /// The lines after a `TRACK` line up to the next `TRACK` or `FILE`
+    fn scope(&self, track: usize) -> impl Iterator<Item = usize> + '_ {
+        (track + 1..self.nodes.len())
+            .take_while(move |&i| !self.is_command(i, "TRACK") && !self.is_command(i, "FILE"))
+    }
+    fn replace_args(&mut self, at: usize, args: &str) {
+        let node = &self.nodes[at];
+        match (node.args.first(), node.args.last()) {
+            (Some(first), Some(last)) => {
+                let span = first.span.start..last.span.end;
+                self.splice(span, args)
+            }
+            _ => {
+                let end = node.content().end;
+                self.splice(end..end, &format!(" {}", args))
+            }
+        }
+    }
+    /// Inserts a line after the line at, the line ending is copied from the sheet
+    fn insert_line(&mut self, at: usize, indent: &str, content: &str) {
+        let node = &self.nodes[at];
+        let ending = match node.ending {
+            LineEnding::None => self
+                .nodes
+                .iter()
+                .map(|node| node.ending)
+                .find(|&ending| ending != LineEnding::None)
+                .unwrap_or(LineEnding::Lf),
+            ending => ending,
+        };
+        let end = node.span.end;
+        let line = match node.ending {
+            LineEnding::None => format!("{}{}{}", ending, indent, content),
+            _ => format!("{}{}{}", indent, content, ending),
+        };
+        self.splice(end..end, &line)
+    }
+    /// Replaces the text in span and rebuilds the nodes
+    fn splice(&mut self, span: Span, text: &str) {
+        self.source.replace_range(span, text);
+        self.nodes = Self::new(&self.source).nodes;
+    }
+}
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn node(s: &str, line: Span, ending: LineEnding) -> Node {
+    let text = &s[line.clone()];
+    let content_start = line.start + (text.len() - text.trim_start().len());
+    // blank lines are all indentation
+    let content_end = (line.start + text.trim_end().len()).max(content_start);
+    let mut tokens = tokens(s, content_start..content_end);
+    let keyword = tokens.next();
+    let args = tokens.collect();
+    Node {
+        span: line.start..line.end + ending.as_str().len(),
+        indent: line.start..content_start,
+        keyword,
+        args,
+        trailing: content_end..line.end,
+        ending,
+    }
+}
+
+fn tokens(s: &str, content: Span) -> impl Iterator<Item = Token> + '_ {
+    let mut at = content.start;
+    std::iter::from_fn(move || {
+        let rest = &s[at..content.end];
+        let start = at + (rest.len() - rest.trim_start().len());
+        let rest = &s[start..content.end];
+        let (kind, len) = if rest.is_empty() {
+            return None;
+        } else if let Some(quoted) = rest.strip_prefix('"') {
+            match closing_quote(quoted) {
+                Some(i) => (TokenKind::Quoted, i + 2),
+                None => (TokenKind::Unterminated, rest.len()),
+            }
+        } else {
+            (
+                TokenKind::Word,
+                rest.find(char::is_whitespace).unwrap_or(rest.len()),
+            )
+        };
+        at = start + len;
+        Some(Token {
+            kind,
+            span: start..at,
+        })
+    })
+}
+
+/// Replaces the escaped quotes `\"` and `""` of a quoted value with `"` and the escaped backslashes `\\` with `\`
+pub(crate) fn unescape(s: &str) -> Cow<'_, str> {
+    if !s.contains('"') && !s.contains(r"\\") {
+        return Cow::Borrowed(s);
+    }
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if (c == '\\' || c == '"') && chars.peek() == Some(&'"') {
+            chars.next();
+            unescaped.push('"');
+        } else if c == '\\' && chars.peek() == Some(&'\\') {
+            chars.next();
+            unescaped.push('\\');
+        } else {
+            unescaped.push(c);
+        }
+    }
+    Cow::Owned(unescaped)
+}
+
+/// Returns the ranges of the lines in s without line endings, and the line endings
+pub(crate) fn line_ranges(s: &str) -> impl Iterator<Item = (Range<usize>, LineEnding)> + '_ {
+    let mut start = 0;
+    std::iter::from_fn(move || {
+        let rest = &s[start..];
+        if rest.is_empty() {
+            return None;
+        }
+        let (len, ending) = match rest.find(['\r', '\n']) {
+            Some(i) if rest[i..].starts_with("\r\n") => (i, LineEnding::CrLf),
+            Some(i) if rest[i..].starts_with('\r') => (i, LineEnding::Cr),
+            Some(i) => (i, LineEnding::Lf),
+            None => (rest.len(), LineEnding::None),
+        };
+        let range = start..start + len;
+        start = range.end + ending.as_str().len();
+        Some((range, ending))
+    })
+}
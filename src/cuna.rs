@@ -1,27 +1,66 @@
-use crate::comment::Comment;
+use crate::comment::{Comment, RemMetadata, ReplayGain, Scope};
+#[cfg(feature = "parser")]
 use crate::error::Error;
+#[cfg(feature = "std")]
+use crate::error::ValidationIssues;
+#[cfg(feature = "parser")]
+use crate::error::{Warning, WarningKind};
+use crate::header::Catalog;
 use crate::header::Header;
-use crate::parser::Command;
+#[cfg(feature = "parser")]
+use crate::parser;
+#[cfg(feature = "parser")]
 use crate::parser::Parna;
+#[cfg(feature = "parser")]
+use crate::parser::ParseOptions;
+use crate::time::TimeStamp;
+use crate::track::Flag;
 use crate::track::Track;
 use crate::track::TrackInfo;
+#[cfg(feature = "parser")]
 use crate::trim_utf8_header;
+#[cfg(feature = "std")]
+use crate::writer::CueWriter;
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::fmt;
+use core::iter::Flatten;
+use core::ops::Index;
+use core::slice::Iter;
+#[cfg(feature = "parser")]
 use std::fs::File;
+#[cfg(feature = "parser")]
+use std::io;
+#[cfg(feature = "parser")]
 use std::io::BufRead;
+#[cfg(feature = "parser")]
 use std::io::BufReader;
-use std::iter::Flatten;
-use std::ops::Index;
+#[cfg(feature = "parser")]
 use std::path::Path;
-use std::slice::Iter;
+#[cfg(feature = "parser")]
 use std::str::FromStr;
 
 /// Represents a cue sheet
 ///
 /// See [`parser::Parser`](crate::parser::Parser) to deal with errors when parsing
+///
+/// # Empty sheets
+///
+/// A sheet without `FILE`s, like an empty input or one with only a header and `REM`s, is valid:
+/// it parses, passes [`validate()`](Cuna::validate) and [`from_parts()`](Cuna::from_parts)
+/// and is written back as it is. A `FILE` without `TRACK`s also parses and is written back,
+/// but `from_parts()` rejects it with [`ValidationError::EmptyFile`](crate::error::ValidationError::EmptyFile).
+/// The timeline, the exports and the other derived values of a sheet without tracks are empty
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cuna {
     pub header: Header,
     pub files: Vec<TrackInfo>,
+    /// `REM`s before the first `FILE` and the keys of [`HEADER_KEYS`](crate::comment::HEADER_KEYS)
+    /// wherever they appear, the other `REM`s belong to their `FILE` or `TRACK`
     pub comments: Comment,
 }
 
@@ -32,6 +71,7 @@ impl Cuna {
     /// let sheet = Cuna::new("REM a cue sheet").unwrap();
     /// ```
     #[inline(always)]
+    #[cfg(feature = "parser")]
     pub fn new(s: &str) -> Result<Self, Error> {
         s.parse()
     }
@@ -42,16 +82,58 @@ impl Cuna {
             comments,
         }
     }
+    /// Same as [`with_parts()`](Cuna::with_parts), but fails with every problem which would make
+    /// the sheet rejected when it is written or burned
+    ///
+    /// These are the problems of [`validate()`](Cuna::validate), `FILE`s without `TRACK`s,
+    /// tracks without `INDEX 00` or `INDEX 01`, and tracks or `INDEX`s out of order
+    ///
+    /// ```rust
+    /// use cuna::comment::Comment;
+    /// use cuna::error::ValidationError;
+    /// use cuna::header::Header;
+    /// use cuna::time::TimeStamp;
+    /// use cuna::track::{Track, TrackInfo};
+    /// use cuna::Cuna;
+    ///
+    /// let file = TrackInfo::new("a.flac".to_owned(), "WAVE".to_owned())
+    ///     .with_track(Track::new(1, "AUDIO".to_owned()).with_index(1, TimeStamp::new(0, 0, 0)))
+    ///     .with_track(Track::new(1, "AUDIO".to_owned()));
+    /// let issues = Cuna::from_parts(Header::default(), vec![file], Comment::default()).unwrap_err();
+    /// assert_eq!(
+    ///     issues.0,
+    ///     [ValidationError::TrackNotIncreasing(1), ValidationError::MissingIndex(1)]
+    /// );
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn from_parts(
+        header: Header,
+        files: Vec<TrackInfo>,
+        comments: Comment,
+    ) -> Result<Self, ValidationIssues> {
+        let sheet = Self::with_parts(header, files, comments);
+        let mut issues = sheet.validate_structure();
+        issues.extend(sheet.validate());
+        match issues.is_empty() {
+            true => Ok(sheet),
+            false => Err(ValidationIssues(issues)),
+        }
+    }
     /// Parses a file as a cue sheet
     ///
-    /// **Only UTF-8 encoding is supported (BOM header will be removed)**
+    /// **Only UTF-8, and UTF-16 with a BOM, are supported (the BOM is removed)**
+    ///
+    /// `Cuna::from_bytes()` of the `encoding` feature reads other encodings
+    #[cfg(feature = "parser")]
     pub fn from_file(file: &mut File) -> Result<Self, Error> {
         let mut buffer = BufReader::new(file);
         Self::from_buf_read(&mut buffer)
     }
     /// Opens a file and parses it as a cue sheet
     ///
-    /// **Only UTF-8 encoding is supported (BOM will be removed)**
+    /// **Only UTF-8, and UTF-16 with a BOM, are supported (the BOM is removed)**
+    ///
+    /// `Cuna::from_bytes()` of the `encoding` feature reads other encodings
     ///
     /// ```rust
     /// use cuna::Cuna;
@@ -64,27 +146,267 @@ impl Cuna {
     /// assert_eq!(cue[0].name, "EGOIST - Departures ～あなたにおくるアイの歌～.flac");
     /// assert_eq!(cue[0][0].performer(), &["EGOIST".to_owned()]);
     /// ```
+    #[cfg(feature = "parser")]
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
         let mut file = File::open(path)?;
         Self::from_file(&mut file)
     }
+    /// Reads and parses a cue sheet line by line, taking the reader by value
+    ///
+    /// Same as [`from_buf_read()`](Cuna::from_buf_read): the text is never loaded whole into a `String`,
+    /// only the current line is. A `&mut` of a reader is also a reader, to keep using it afterwards
+    ///
+    /// ```rust
+    /// use cuna::Cuna;
+    /// use std::io::BufReader;
+    ///
+    /// let cue = "TITLE \"Departures\"\nFILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00";
+    /// let sheet = Cuna::from_reader(BufReader::new(cue.as_bytes())).unwrap();
+    /// assert_eq!(sheet.title(), &["Departures".to_owned()]);
+    /// assert_eq!(sheet[0][0].id(), 1);
+    /// ```
+    #[cfg(feature = "parser")]
+    pub fn from_reader(mut reader: impl BufRead) -> Result<Self, Error> {
+        Self::from_buf_read(&mut reader)
+    }
+    #[cfg(feature = "parser")]
     pub fn from_buf_read(buf: &mut impl BufRead) -> Result<Self, Error> {
+        Self::from_buf_read_with(buf, &ParseOptions::default()).map(|(sheet, _)| sheet)
+    }
+    /// Reads and parses a cue sheet line by line with the given options,
+    /// returning the sheet with the warnings of the parser
+    ///
+    /// At most `max_line_length` bytes of a line are read before the line is rejected,
+    /// so a huge line is never loaded into memory. UTF-16 with a BOM is transcoded as it is read
+    #[cfg(feature = "parser")]
+    pub fn from_buf_read_with(
+        buf: &mut impl BufRead,
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<Warning>), Error> {
         let mut sheet = Self::default();
+        let mut warnings = Vec::new();
         let mut buffer = String::new();
-        let mut at = 1;
-        loop {
-            match buf.read_line(&mut buffer) {
-                Ok(0) => break Ok(sheet),
-                Ok(_) => {
-                    let err = |e| Error::new(e, at);
-                    let command = Command::new(trim_utf8_header(&buffer)).map_err(err)?;
-                    command.parse(&mut sheet).map_err(err)?;
+        let mut line_state = parser::LineState::default();
+        let mut splitter = LineSplitter::new(options.limits.max_line_length);
+        for at in 1.. {
+            match read_line(buf, &mut splitter, &mut buffer) {
+                Ok(false) => break,
+                Ok(true) if options.stops_at(&buffer, &sheet) => break,
+                Ok(true) => parser::parse_line_recovering(
+                    trim_utf8_header(&buffer),
+                    at,
+                    &mut sheet,
+                    options,
+                    &mut warnings,
+                    &mut line_state,
+                    None,
+                )?,
+                Err(e) => return Err(Error::new(e.into(), at)),
+            }
+        }
+        Ok((sheet, warnings))
+    }
+    /// Reads and parses a cue sheet line by line from an `AsyncBufRead`, requires the `tokio` feature
+    ///
+    /// Like [`from_buf_read()`](Cuna::from_buf_read), UTF-16 with a BOM is transcoded as it is read
+    ///
+    /// ```rust
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use cuna::Cuna;
+    /// let cue = "TITLE \"Departures\"\nFILE \"a.flac\" WAVE\n  TRACK 01 AUDIO";
+    /// let sheet = Cuna::from_async_reader(cue.as_bytes()).await.unwrap();
+    /// assert_eq!(sheet.title(), &["Departures".to_owned()]);
+    /// assert_eq!(sheet[0][0].id(), 1);
+    /// # }
+    /// ```
+    #[cfg(all(feature = "parser", feature = "tokio"))]
+    pub async fn from_async_reader<R>(reader: R) -> Result<Self, Error>
+    where
+        R: tokio::io::AsyncBufRead + Unpin,
+    {
+        Self::from_async_reader_with(reader, &ParseOptions::default())
+            .await
+            .map(|(sheet, _)| sheet)
+    }
+    /// Same as [`from_async_reader()`](Cuna::from_async_reader) with the given options,
+    /// returning the sheet with the warnings of the parser
+    ///
+    /// Like [`from_buf_read_with()`](Cuna::from_buf_read_with), at most `max_line_length` bytes
+    /// of a line are read before the line is rejected
+    #[cfg(all(feature = "parser", feature = "tokio"))]
+    pub async fn from_async_reader_with<R>(
+        mut reader: R,
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<Warning>), Error>
+    where
+        R: tokio::io::AsyncBufRead + Unpin,
+    {
+        use tokio::io::AsyncBufReadExt;
+        let mut sheet = Self::default();
+        let mut warnings = Vec::new();
+        let mut buffer = String::new();
+        let mut line_state = parser::LineState::default();
+        let mut splitter = LineSplitter::new(options.limits.max_line_length);
+        for at in 1.. {
+            let read = async {
+                loop {
+                    let chunk = reader.fill_buf().await?;
+                    if chunk.is_empty() {
+                        if splitter.line.is_empty() {
+                            return Ok(false);
+                        }
+                        break;
+                    }
+                    let (taken, ended) = splitter.take(chunk);
+                    reader.consume(taken);
+                    if ended {
+                        break;
+                    }
                 }
-                Err(e) => break Err(Error::new(e.into(), at)),
+                splitter.decode(&mut buffer).map(|_| true)
+            };
+            match read.await {
+                Ok(false) => break,
+                Ok(true) if options.stops_at(&buffer, &sheet) => break,
+                Ok(true) => parser::parse_line_recovering(
+                    trim_utf8_header(&buffer),
+                    at,
+                    &mut sheet,
+                    options,
+                    &mut warnings,
+                    &mut line_state,
+                    None,
+                )?,
+                Err(e) => return Err(Error::new(e.into(), at)),
             }
-            at += 1;
-            buffer.clear();
         }
+        Ok((sheet, warnings))
+    }
+    /// Parses an str as cue sheet with the given options, returning the sheet with the warnings
+    /// of the parser, like the lines dropped by a [`RecoveryPolicy`](crate::parser::RecoveryPolicy)
+    #[cfg(feature = "parser")]
+    pub fn parse_with(s: &str, options: &ParseOptions) -> Result<(Self, Vec<Warning>), Error> {
+        let mut sheet = Cuna::default();
+        let mut parser = Parna::new(trim_utf8_header(s)).with_options(options.clone());
+        parser.parse(&mut sheet)?;
+        Ok((sheet, parser.take_warnings()))
+    }
+    /// Parses only the header and the `REM`s before the first `FILE`, for scanners which need
+    /// the metadata of the album but not the tracks
+    ///
+    /// Parsing stops at the first `FILE` or `TRACK`, so errors after it are not found,
+    /// see [`Parna::parse_header()`](Parna::parse_header)
+    ///
+    /// ```rust
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::parse_header_only(r#"REM DATE 2011
+    /// PERFORMER "EGOIST"
+    /// TITLE "Departures"
+    /// CATALOG 4540774406606
+    /// FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO"#).unwrap();
+    /// assert_eq!(sheet.title(), &["Departures".to_owned()]);
+    /// assert_eq!(sheet.catalog(), Some(4540774406606));
+    /// assert_eq!(sheet.comments.get("DATE"), Some("2011"));
+    /// assert!(sheet.files().is_empty());
+    /// ```
+    #[cfg(feature = "parser")]
+    pub fn parse_header_only(s: &str) -> Result<Self, Error> {
+        Self::parse_header_only_with(s, &ParseOptions::default())
+    }
+    /// Same as [`parse_header_only()`](Cuna::parse_header_only) with the given options
+    #[cfg(feature = "parser")]
+    pub fn parse_header_only_with(s: &str, options: &ParseOptions) -> Result<Self, Error> {
+        let mut sheet = Cuna::default();
+        Parna::new(trim_utf8_header(s))
+            .with_options(options.clone())
+            .parse_header(&mut sheet)?;
+        Ok(sheet)
+    }
+    /// Parses bytes which should be UTF-8, or UTF-16 with a BOM, as a cue sheet,
+    /// replacing invalid bytes with U+FFFD
+    ///
+    /// Returns the sheet with a [`WarningKind::InvalidBytes`] at the line of each run of invalid bytes,
+    /// followed by the warnings of the parser. `Cuna::from_bytes()` of the `encoding` feature
+    /// reads sheets which are in another encoding rather than broken
+    ///
+    /// ```rust
+    /// use cuna::error::WarningKind;
+    /// use cuna::Cuna;
+    ///
+    /// let (sheet, warnings) = Cuna::from_bytes_lossy(b"REM DATE 2011\nTITLE \"Caf\xe9\"").unwrap();
+    /// assert_eq!(sheet.title(), &["Caf\u{fffd}".to_owned()]);
+    /// assert_eq!(warnings[0].kind(), &WarningKind::InvalidBytes { offset: 24, len: 1 });
+    /// assert_eq!(warnings[0].pos(), Some(2));
+    /// ```
+    #[cfg(feature = "parser")]
+    pub fn from_bytes_lossy(bytes: &[u8]) -> Result<(Self, Vec<Warning>), Error> {
+        Self::from_bytes_lossy_with(bytes, &ParseOptions::default())
+    }
+    /// Same as [`from_bytes_lossy()`](Cuna::from_bytes_lossy) with the given options
+    #[cfg(feature = "parser")]
+    pub fn from_bytes_lossy_with(
+        bytes: &[u8],
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<Warning>), Error> {
+        let (text, mut warnings) = match utf16_bom(bytes) {
+            Some(big_endian) => utf16_lossy(bytes, big_endian),
+            None => utf8_lossy(bytes),
+        };
+        let mut sheet = Cuna::default();
+        let mut parser = Parna::new(trim_utf8_header(&text)).with_options(options.clone());
+        parser.parse(&mut sheet)?;
+        warnings.append(&mut parser.take_warnings());
+        Ok((sheet, warnings))
+    }
+    /// Parses an str as cue sheet with the given options, skipping the lines with errors
+    ///
+    /// Returns the sheet built from the other lines with every error,
+    /// see [`Parna::parse_all_errors()`](Parna::parse_all_errors)
+    #[cfg(feature = "parser")]
+    pub fn parse_tolerant(s: &str, options: &ParseOptions) -> (Self, Vec<Error>) {
+        let mut sheet = Cuna::default();
+        let errors = Parna::new(trim_utf8_header(s))
+            .with_options(options.clone())
+            .parse_all_errors(&mut sheet);
+        (sheet, errors)
+    }
+    /// Empties the sheet so it can be parsed into again, keeping the allocated capacity
+    /// of the header, the `REM`s and the list of `FILE`s
+    ///
+    /// The `FILE`s themselves are dropped
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::parser::Parser;
+    /// use cuna::Cuna;
+    ///
+    /// let mut sheet = Cuna::default();
+    /// for cue in ["TITLE \"Departures\"\nREM DATE 2011", "TITLE \"Euterpe\""] {
+    ///     sheet.reset();
+    ///     Parser::new(cue).parse(&mut sheet).unwrap();
+    /// }
+    /// assert_eq!(sheet, Cuna::new("TITLE \"Euterpe\"").unwrap());
+    /// # }
+    /// ```
+    pub fn reset(&mut self) {
+        self.header.clear();
+        self.files.clear();
+        self.comments.clear();
+    }
+    /// Returns true if the sheet has no `TRACK`, even if it has a header, `REM`s or `FILE`s
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// assert!(Cuna::new("").unwrap().is_empty());
+    /// assert!(Cuna::new("TITLE \"Departures\"\nFILE \"a.flac\" WAVE").unwrap().is_empty());
+    /// assert!(!Cuna::new("FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO").unwrap().is_empty());
+    /// # }
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.files.iter().all(|file| file.tracks.is_empty())
     }
     pub fn header(&self) -> &Header {
         &self.header
@@ -96,20 +418,87 @@ impl Cuna {
         self.header.performer()
     }
     pub fn songwriter(&self) -> &Vec<String> {
-        self.header.songwriter().as_ref()
+        self.header.songwriter()
+    }
+    pub fn arranger(&self) -> &Vec<String> {
+        self.header.arranger()
+    }
+    pub fn composer(&self) -> &Vec<String> {
+        self.header.composer()
+    }
+    pub fn message(&self) -> &Vec<String> {
+        self.header.message()
     }
     pub fn catalog(&self) -> Option<u64> {
         self.header.catalog()
     }
+    /// Returns the values of the standard `REM`s of the sheet, see [`Comment::metadata()`]
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::new("REM GENRE \"J-Pop\"\nREM DATE 2011\nTITLE \"Departures\"").unwrap();
+    /// let metadata = sheet.metadata();
+    /// assert_eq!(metadata.genres, ["J-Pop"]);
+    /// assert_eq!(metadata.year, Some(2011));
+    /// assert_eq!(metadata.discid, None);
+    /// # }
+    /// ```
+    pub fn metadata(&self) -> RemMetadata {
+        self.comments.metadata()
+    }
+    /// Returns the ReplayGain of the album, see [`Comment::album_gain()`]
+    pub fn replay_gain(&self) -> ReplayGain {
+        self.comments.album_gain()
+    }
+    pub fn catalog_code(&self) -> Option<Catalog> {
+        self.header.catalog_code()
+    }
     pub fn files(&self) -> &Vec<TrackInfo> {
         &self.files
     }
     pub fn set_files(&mut self, files: Vec<TrackInfo>) -> Vec<TrackInfo> {
-        std::mem::replace(&mut self.files, files)
+        core::mem::replace(&mut self.files, files)
     }
     pub fn comments(&self) -> &Comment {
         &self.comments
     }
+    /// Returns the `REM`s of the header, of every `FILE` and of every `TRACK` with where they belong,
+    /// in the order they are written
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::comment::Scope;
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::new(r#"REM GENRE Pop
+    /// FILE "a.flac" WAVE
+    ///   REM SOURCE vinyl
+    ///   TRACK 01 AUDIO
+    ///     REM COMPOSER Ryo
+    ///     INDEX 01 00:00:00"#).unwrap();
+    /// let comments: Vec<_> = sheet.scoped_comments().collect();
+    /// assert_eq!(comments, [
+    ///     (Scope::Header, "GENRE Pop"),
+    ///     (Scope::File(0), "SOURCE vinyl"),
+    ///     (Scope::Track { file: 0, track: 0 }, "COMPOSER Ryo"),
+    /// ]);
+    /// # }
+    /// ```
+    pub fn scoped_comments(&self) -> impl Iterator<Item = (Scope, &str)> {
+        let header = self.comments.iter().map(|c| (Scope::Header, c.as_str()));
+        let files = self.files.iter().enumerate().flat_map(|(i, file)| {
+            let own = file
+                .comments
+                .iter()
+                .map(move |c| (Scope::File(i), c.as_str()));
+            let tracks = file.tracks.iter().enumerate().flat_map(move |(j, track)| {
+                let scope = Scope::Track { file: i, track: j };
+                track.comments.iter().map(move |c| (scope, c.as_str()))
+            });
+            own.chain(tracks)
+        });
+        header.chain(files)
+    }
     pub fn push_file(&mut self, track: TrackInfo) {
         self.files.push(track);
     }
@@ -131,17 +520,214 @@ impl Cuna {
     }
     /// Returns the last `TRACK` field which appears in the cue sheet
     pub fn last_track(&self) -> Option<&Track> {
-        self.last_file().map(TrackInfo::last_track).flatten()
+        self.last_file().and_then(TrackInfo::last_track)
     }
     /// The mutable version of [`Cuna::last_track()`](Cuna::last_track)
     pub fn last_track_mut(&mut self) -> Option<&mut Track> {
-        self.last_file_mut().map(TrackInfo::last_track_mut).flatten()
+        self.last_file_mut().and_then(TrackInfo::last_track_mut)
     }
     /// An iterator over the `TRACK`s in all the `FILE`s
-    pub fn tracks(&self) -> Flatten<Iter<TrackInfo>> {
+    pub fn tracks(&self) -> Flatten<Iter<'_, TrackInfo>> {
         self.files.iter().flatten()
     }
+    /// An iterator over the `AUDIO` tracks in all the `FILE`s
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::track::Flag;
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::new(r#"FILE "a.bin" BINARY
+    ///   TRACK 01 MODE1/2352
+    ///     INDEX 01 00:00:00
+    ///   TRACK 02 AUDIO
+    ///     FLAGS DCP PRE
+    ///     INDEX 01 30:00:00"#).unwrap();
+    /// assert_eq!(sheet.audio_tracks().map(|t| t.id()).collect::<Vec<_>>(), [2]);
+    /// assert_eq!(sheet.data_tracks().map(|t| t.id()).collect::<Vec<_>>(), [1]);
+    /// assert_eq!(sheet.tracks_with_flag(Flag::Pre).count(), 1);
+    /// assert_eq!(sheet.tracks_with_flag(Flag::FourChannel).count(), 0);
+    /// # }
+    /// ```
+    pub fn audio_tracks(&self) -> impl Iterator<Item = &Track> {
+        self.tracks().filter(|track| track.is_audio())
+    }
+    /// An iterator over the tracks which are not `AUDIO` in all the `FILE`s
+    pub fn data_tracks(&self) -> impl Iterator<Item = &Track> {
+        self.tracks().filter(|track| track.is_data())
+    }
+    /// An iterator over the tracks with the flag in all the `FILE`s
+    pub fn tracks_with_flag(&self, flag: Flag) -> impl Iterator<Item = &Track> {
+        self.tracks().filter(move |track| track.has_flag(flag))
+    }
+    /// Returns true if the sheets are the same, except that times may be at most tolerance frames apart,
+    /// e.g. when comparing sheets of different rippers which round differently
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// let eac = Cuna::new("FILE \"a.wav\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 04:15:44").unwrap();
+    /// let other = Cuna::new("FILE \"a.wav\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 04:15:45").unwrap();
+    /// assert_ne!(eac, other);
+    /// assert!(eac.approx_eq(&other, 1));
+    /// assert!(!eac.approx_eq(&other, 0));
+    /// # }
+    /// ```
+    pub fn approx_eq(&self, other: &Self, tolerance: u32) -> bool {
+        self.header == other.header
+            && self.comments == other.comments
+            && self.files.len() == other.files.len()
+            && self
+                .files
+                .iter()
+                .zip(&other.files)
+                .all(|(a, b)| a.approx_eq(b, tolerance))
+    }
+    /// Merges consecutive `FILE`s with the same name and format into one, keeping the order of `TRACK`s
+    ///
+    /// Returns the number of `FILE`s merged into the previous ones.
+    /// The `REM`s and unknown commands of a merged `FILE` are appended to those of the previous one,
+    /// the unknown commands are written before its first `TRACK` as they belong to the `FILE`
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// let mut sheet = Cuna::new(r#"FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO
+    ///     INDEX 01 00:00:00
+    /// FILE "a.flac" WAVE
+    ///   TRACK 02 AUDIO
+    ///     INDEX 01 04:15:44
+    /// FILE "b.flac" WAVE
+    ///   TRACK 03 AUDIO
+    ///     INDEX 01 00:00:00"#).unwrap();
+    /// assert_eq!(sheet.coalesce_files(), 1);
+    /// assert_eq!(sheet.files.len(), 2);
+    /// assert_eq!(sheet[0].tracks.len(), 2);
+    /// # }
+    /// ```
+    pub fn coalesce_files(&mut self) -> usize {
+        let before = self.files.len();
+        let mut files: Vec<TrackInfo> = Vec::with_capacity(before);
+        for file in self.files.drain(..) {
+            match files.last_mut() {
+                Some(last) if last.name == file.name && last.format == file.format => {
+                    last.comments.extend(file.comments);
+                    last.unknown.0.extend(file.unknown.0);
+                    last.tracks.extend(file.tracks)
+                }
+                _ => files.push(file),
+            }
+        }
+        self.files = files;
+        before - self.files.len()
+    }
+    /// Writes the cue sheet in cue format with the default [`CueWriter`](crate::writer::CueWriter)
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// let cue = r#"REM COMMENT ExactAudioCopy v0.99pb5
+    /// TITLE "Departures"
+    /// FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO
+    ///     INDEX 01 00:00:00
+    /// "#;
+    /// let sheet = Cuna::new(cue).unwrap();
+    /// let mut s = String::new();
+    /// sheet.write_to(&mut s).unwrap();
+    /// assert_eq!(s, cue);
+    /// assert_eq!(sheet.to_string(), cue);
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_to(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        CueWriter::new().write(self, w)
+    }
+    /// Writes the cue sheet to an `AsyncWrite` with the default [`CueWriter`](crate::writer::CueWriter),
+    /// requires the `tokio` feature
+    ///
+    /// ```rust
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::new("TITLE \"Departures\"").unwrap();
+    /// let mut buf = Vec::new();
+    /// sheet.write_to_async(&mut buf).await.unwrap();
+    /// assert_eq!(buf, b"TITLE \"Departures\"\n");
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub async fn write_to_async<W>(&self, w: &mut W) -> std::io::Result<()>
+    where
+        W: tokio::io::AsyncWrite + Unpin + ?Sized,
+    {
+        CueWriter::new().write_async(self, w).await
+    }
+    /// Returns a compact summary like `"<performer> — <title> (<n> tracks)"` for log messages
+    ///
+    /// The performer is omitted if missing and the title falls back to the first `FILE` or `Untitled`.
+    /// The sheet does not know how long the last track of a `FILE` is, see
+    /// [`one_liner_with_lengths()`](Cuna::one_liner_with_lengths) for the duration
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::new(r#"PERFORMER "EGOIST"
+    /// TITLE "Departures"
+    /// FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO
+    ///     INDEX 01 00:00:00
+    ///   TRACK 02 AUDIO
+    ///     INDEX 01 04:15:44"#).unwrap();
+    /// assert_eq!(sheet.one_liner(), "EGOIST — Departures (2 tracks)");
+    /// assert_eq!(Cuna::default().one_liner(), "Untitled (0 tracks)");
+    /// # }
+    /// ```
+    pub fn one_liner(&self) -> String {
+        self.one_liner_with_lengths(&[])
+    }
+    /// Same as [`one_liner()`](Cuna::one_liner), with the lengths of the `FILE`s in the order they appear.
+    /// The duration, `<mm:ss>` after the number of tracks, is their sum and is only written
+    /// if the length of every `FILE` is given
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::time::TimeStamp;
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::new("TITLE \"Departures\"\nFILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00").unwrap();
+    /// let lengths = [TimeStamp::new(4, 15, 44)];
+    /// assert_eq!(sheet.one_liner_with_lengths(&lengths), "Departures (1 track, 04:15)");
+    /// # }
+    /// ```
+    pub fn one_liner_with_lengths(&self, file_lengths: &[TimeStamp]) -> String {
+        let title = match (self.title().first(), self.first_file()) {
+            (Some(title), _) => title.as_str(),
+            (None, Some(file)) => file.name.as_str(),
+            (None, None) => "Untitled",
+        };
+        let mut s = match self.performer().first() {
+            Some(performer) => format!("{} — {}", performer, title),
+            None => title.to_owned(),
+        };
+        let tracks = self.tracks().count();
+        s.push_str(&format!(
+            " ({} track{}",
+            tracks,
+            if tracks == 1 { "" } else { "s" }
+        ));
+        if !self.files.is_empty() && file_lengths.len() >= self.files.len() {
+            let frames: u32 = file_lengths[..self.files.len()]
+                .iter()
+                .map(TimeStamp::as_frames)
+                .sum();
+            let seconds = TimeStamp::from_frames(frames).as_seconds();
+            s.push_str(&format!(", {:02}:{:02}", seconds / 60, seconds % 60));
+        }
+        s.push(')');
+        s
+    }
 }
+#[cfg(feature = "parser")]
 impl FromStr for Cuna {
     type Err = Error;
 
@@ -152,9 +738,14 @@ impl FromStr for Cuna {
     /// let sheet = Cuna::from_str("REM a cue sheet").unwrap();
     /// ```
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut sheet = Cuna::default();
-        Parna::new(crate::trim_utf8_header(s)).parse(&mut sheet)?;
-        Ok(sheet)
+        Self::parse_with(s, &ParseOptions::default()).map(|(sheet, _)| sheet)
+    }
+}
+#[cfg(feature = "std")]
+impl fmt::Display for Cuna {
+    /// Writes the cue sheet in cue format, see [`Cuna::write_to()`](Cuna::write_to)
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_to(formatter)
     }
 }
 impl Index<usize> for Cuna {
@@ -168,3 +759,214 @@ impl Index<usize> for Cuna {
         &self.files[index]
     }
 }
+
+/// Decodes bytes like [`String::from_utf8_lossy()`], with a warning for each run of invalid bytes
+#[cfg(feature = "parser")]
+fn utf8_lossy(bytes: &[u8]) -> (String, Vec<Warning>) {
+    let mut text = String::with_capacity(bytes.len());
+    let mut warnings = Vec::new();
+    let mut rest = bytes;
+    let mut line = 1;
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(valid) => break text.push_str(valid),
+            Err(e) => {
+                let (valid, invalid) = rest.split_at(e.valid_up_to());
+                let valid = std::str::from_utf8(valid).expect("checked by from_utf8");
+                line += valid.matches('\n').count();
+                text.push_str(valid);
+                text.push(char::REPLACEMENT_CHARACTER);
+                let len = e.error_len().unwrap_or(invalid.len());
+                let offset = bytes.len() - invalid.len();
+                warnings.push(Warning::new(
+                    WarningKind::InvalidBytes { offset, len },
+                    line,
+                ));
+                rest = &invalid[len..];
+            }
+        }
+    }
+    (text, warnings)
+}
+/// Returns whether the bytes start with a UTF-16 BOM, and if it is big endian
+#[cfg(feature = "parser")]
+fn utf16_bom(bytes: &[u8]) -> Option<bool> {
+    match bytes.get(..2) {
+        Some([0xff, 0xfe]) => Some(false),
+        Some([0xfe, 0xff]) => Some(true),
+        _ => None,
+    }
+}
+/// Same as [`utf8_lossy()`] for UTF-16 after a BOM, an unpaired surrogate or an odd byte at the end are invalid
+#[cfg(feature = "parser")]
+fn utf16_lossy(bytes: &[u8], big_endian: bool) -> (String, Vec<Warning>) {
+    let units = bytes[2..].chunks_exact(2).map(|unit| match big_endian {
+        true => u16::from_be_bytes([unit[0], unit[1]]),
+        false => u16::from_le_bytes([unit[0], unit[1]]),
+    });
+    let mut text = String::with_capacity(bytes.len());
+    let mut warnings = Vec::new();
+    let mut line = 1;
+    let mut offset = 2;
+    for c in char::decode_utf16(units) {
+        let (c, len) = match c {
+            Ok(c) => (c, c.len_utf16() * 2),
+            Err(_) => {
+                warnings.push(Warning::new(
+                    WarningKind::InvalidBytes { offset, len: 2 },
+                    line,
+                ));
+                (char::REPLACEMENT_CHARACTER, 2)
+            }
+        };
+        line += (c == '\n') as usize;
+        offset += len;
+        text.push(c);
+    }
+    if offset < bytes.len() {
+        warnings.push(Warning::new(
+            WarningKind::InvalidBytes { offset, len: 1 },
+            line,
+        ));
+        text.push(char::REPLACEMENT_CHARACTER);
+    }
+    (text, warnings)
+}
+/// Splits bytes read in chunks into lines ending at `\n`, `\r\n` or `\r`,
+/// for the readers of [`Cuna::from_buf_read_with()`] and `Cuna::from_async_reader_with()`
+///
+/// The input is UTF-8, or UTF-16 if it starts with a BOM
+#[cfg(feature = "parser")]
+#[derive(Debug, Default)]
+struct LineSplitter {
+    /// The bytes of the line which has not ended yet
+    line: Vec<u8>,
+    /// The last line ended at `\r`, so a `\n` right after it ends nothing
+    after_cr: bool,
+    /// The start of the input was read to look for a UTF-16 BOM
+    sniffed: bool,
+    /// UTF-16 after a BOM and if it is big endian, None for UTF-8
+    utf16: Option<bool>,
+    /// The current line was cut at the limit, maybe in the middle of a character
+    cut: bool,
+    max_line_length: usize,
+}
+#[cfg(feature = "parser")]
+impl LineSplitter {
+    fn new(max_line_length: usize) -> Self {
+        Self {
+            max_line_length,
+            ..Self::default()
+        }
+    }
+    /// Takes the bytes of the current line from the start of chunk,
+    /// returns how many are taken and if the line ended or was cut
+    fn take(&mut self, chunk: &[u8]) -> (usize, bool) {
+        if !self.sniffed {
+            match (&self.line[..], chunk.first()) {
+                (_, None) => return (0, false),
+                // neither byte starts UTF-8, so the input is UTF-16 or invalid
+                (&[], Some(&first @ (0xff | 0xfe))) => {
+                    self.line.push(first);
+                    return (1, false);
+                }
+                (&[first], Some(&second)) => {
+                    self.sniffed = true;
+                    if let Some(big_endian) = utf16_bom(&[first, second]) {
+                        self.utf16 = Some(big_endian);
+                        self.line.clear();
+                        return (1, false);
+                    }
+                }
+                _ => self.sniffed = true,
+            }
+        }
+        let unit = if self.utf16.is_some() { 2 } else { 1 };
+        // one more character than the limit, so a longer line is rejected
+        let max = self.max_line_length.saturating_add(1).saturating_mul(unit);
+        for (i, &byte) in chunk.iter().enumerate() {
+            self.line.push(byte);
+            if self.line.len() % unit != 0 {
+                continue;
+            }
+            let c = match (self.utf16, &self.line[self.line.len() - unit..]) {
+                (Some(true), &[a, b]) => u16::from_be_bytes([a, b]),
+                (Some(false), &[a, b]) => u16::from_le_bytes([a, b]),
+                (_, bytes) => u16::from(bytes[0]),
+            };
+            let after_cr = std::mem::take(&mut self.after_cr);
+            if c == u16::from(b'\n') && after_cr && self.line.len() == unit {
+                self.line.clear();
+            } else if c == u16::from(b'\n') || c == u16::from(b'\r') {
+                self.line.truncate(self.line.len() - unit);
+                self.after_cr = c == u16::from(b'\r');
+                return (i + 1, true);
+            } else if self.line.len() >= max {
+                self.cut = true;
+                return (i + 1, true);
+            }
+        }
+        (chunk.len(), false)
+    }
+    /// Decodes the current line into line and starts the next one
+    ///
+    /// A cut line is decoded lossily, it is still longer than the limit and rejected as such
+    fn decode(&mut self, line: &mut String) -> io::Result<()> {
+        let bytes = std::mem::take(&mut self.line);
+        let cut = std::mem::take(&mut self.cut);
+        match self.utf16 {
+            None if cut => *line = String::from_utf8_lossy(&bytes).into_owned(),
+            None => match String::from_utf8(bytes) {
+                Ok(text) => *line = text,
+                Err(_) => return Err(invalid_data("stream did not contain valid UTF-8")),
+            },
+            Some(big_endian) => {
+                let invalid = || invalid_data("stream did not contain valid UTF-16");
+                if bytes.len() % 2 != 0 {
+                    return Err(invalid());
+                }
+                let units = bytes.chunks_exact(2).map(|unit| match big_endian {
+                    true => u16::from_be_bytes([unit[0], unit[1]]),
+                    false => u16::from_le_bytes([unit[0], unit[1]]),
+                });
+                line.clear();
+                for c in char::decode_utf16(units) {
+                    match c {
+                        Ok(c) => line.push(c),
+                        Err(_) if cut => line.push(char::REPLACEMENT_CHARACTER),
+                        Err(_) => return Err(invalid()),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+/// Reads a line from buf into line without its line ending, returns false at the end of buf
+#[cfg(feature = "parser")]
+fn read_line(
+    buf: &mut impl BufRead,
+    splitter: &mut LineSplitter,
+    line: &mut String,
+) -> io::Result<bool> {
+    loop {
+        let chunk = buf.fill_buf()?;
+        if chunk.is_empty() {
+            if splitter.line.is_empty() {
+                return Ok(false);
+            }
+            break;
+        }
+        let (taken, ended) = splitter.take(chunk);
+        buf.consume(taken);
+        if ended {
+            break;
+        }
+    }
+    splitter.decode(line)?;
+    Ok(true)
+}
+#[cfg(feature = "parser")]
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
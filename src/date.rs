@@ -0,0 +1,126 @@
+//! The date of `REM DATE` as a typed value, requires the `date` feature
+//!
+//! Rippers write the year, like `2011`, and some taggers a full date, like `2011-10-19`
+//! or `2011/10/19`. [`Cuna::date()`] reads either, and the parser records a
+//! [`WarningKind::MalformedDate`] for a `REM DATE` which is neither instead of failing.
+//!
+//! ```rust
+//! # #[cfg(feature = "parser")] {
+//! use cuna::date::Date;
+//! use cuna::Cuna;
+//!
+//! let sheet = Cuna::new("REM DATE 2011/10/19\nTITLE \"Departures\"").unwrap();
+//! let date = sheet.date().unwrap();
+//! assert_eq!((date.year(), date.month(), date.day()), (2011, Some(10), Some(19)));
+//! assert_eq!(date.to_string(), "2011-10-19");
+//! assert_eq!("2011".parse::<Date>().unwrap().month(), None);
+//! # }
+//! ```
+use crate::comment::{self, Comment};
+use crate::error::{ParseError, WarningKind};
+use crate::Cuna;
+use std::fmt;
+use std::str::FromStr;
+
+/// A year, a month of a year or a day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    year: u16,
+    month: Option<u8>,
+    day: Option<u8>,
+}
+
+impl Date {
+    /// Returns the date, or None if the month or the day does not exist, or a day is given without month
+    pub fn new(year: u16, month: Option<u8>, day: Option<u8>) -> Option<Self> {
+        let valid = match (month, day) {
+            (None, None) => true,
+            (Some(month), None) => (1..=12).contains(&month),
+            (Some(month), Some(day)) => {
+                (1..=12).contains(&month) && day >= 1 && day <= days_in_month(year, month)
+            }
+            (None, Some(_)) => false,
+        };
+        if valid {
+            Some(Self { year, month, day })
+        } else {
+            None
+        }
+    }
+    pub const fn year(&self) -> u16 {
+        self.year
+    }
+    pub const fn month(&self) -> Option<u8> {
+        self.month
+    }
+    pub const fn day(&self) -> Option<u8> {
+        self.day
+    }
+}
+impl FromStr for Date {
+    type Err = ParseError;
+
+    /// Parses `YYYY`, `YYYY-MM` or `YYYY-MM-DD`, with `-`, `/` or `.` between the parts
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseError::syntax_error(s, "invalid date");
+        let mut parts = s.split(&['-', '/', '.'][..]);
+        let mut next = |len: usize| match parts.next() {
+            Some(part) if part.len() == len && part.bytes().all(|b| b.is_ascii_digit()) => {
+                part.parse().map(Some).map_err(|_| invalid())
+            }
+            Some(_) => Err(invalid()),
+            None => Ok(None),
+        };
+        let year = next(4)?.ok_or_else(invalid)?;
+        let month = next(2)?.map(|m| m as u8);
+        let day = next(2)?.map(|d| d as u8);
+        match parts.next() {
+            Some(_) => Err(invalid()),
+            None => Self::new(year, month, day).ok_or_else(invalid),
+        }
+    }
+}
+/// Writes `YYYY`, `YYYY-MM` or `YYYY-MM-DD`
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}", self.year)?;
+        if let Some(month) = self.month {
+            write!(f, "-{:02}", month)?;
+        }
+        if let Some(day) = self.day {
+            write!(f, "-{:02}", day)?;
+        }
+        Ok(())
+    }
+}
+
+impl Comment {
+    /// Returns the `DATE` as a [`Date`], None if it is missing and an error if it is malformed
+    pub fn date(&self) -> Option<Result<Date, ParseError>> {
+        self.get(comment::DATE).map(|date| date.trim().parse())
+    }
+}
+impl Cuna {
+    /// Returns the `REM DATE` of the sheet, None if it is missing or malformed
+    pub fn date(&self) -> Option<Date> {
+        self.comments.date()?.ok()
+    }
+}
+
+/// Returns a warning if rem is a `DATE` which is not a [`Date`]
+pub(crate) fn check(rem: &str) -> Option<WarningKind> {
+    let (key, value) = comment::split(rem);
+    let value = value.trim_matches('"');
+    match key.eq_ignore_ascii_case(comment::DATE) && value.parse::<Date>().is_err() {
+        true => Some(WarningKind::MalformedDate(value.to_owned())),
+        false => None,
+    }
+}
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
@@ -0,0 +1,82 @@
+//! Decoding cue sheets which are not UTF-8, requires the `encoding` feature
+//!
+//! A UTF-8 or UTF-16 BOM decides the encoding. Without one, the bytes are decoded as UTF-8
+//! and then as each encoding of a fallback list in order, and the first which decodes without
+//! errors is used. Almost any bytes are valid Windows-1252 and many GBK texts are valid Shift-JIS,
+//! so the likelier encodings go first and single byte ones last.
+//!
+//! ```rust
+//! use cuna::Cuna;
+//! use encoding_rs::SHIFT_JIS;
+//!
+//! let (bytes, _, _) = SHIFT_JIS.encode("FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"エウテルペ\"");
+//! let sheet = Cuna::from_bytes(&bytes).unwrap();
+//! assert_eq!(sheet[0][0].title(), &["エウテルペ".to_owned()]);
+//! ```
+#[cfg(feature = "parser")]
+use crate::error::Error;
+#[cfg(feature = "parser")]
+use crate::error::ParseError;
+#[cfg(feature = "parser")]
+use crate::error::Warning;
+#[cfg(feature = "parser")]
+use crate::parser::ParseOptions;
+#[cfg(feature = "parser")]
+use crate::Cuna;
+use encoding_rs::Encoding;
+use encoding_rs::{GBK_INIT, SHIFT_JIS_INIT, UTF_8, WINDOWS_1252_INIT};
+use std::borrow::Cow;
+
+/// The encodings tried by [`Cuna::from_bytes()`] after UTF-8: Shift-JIS, GBK and Windows-1252
+pub static FALLBACK: &[&Encoding] = &[&SHIFT_JIS_INIT, &GBK_INIT, &WINDOWS_1252_INIT];
+
+/// Decodes bytes with the encoding of their BOM, or UTF-8 or the first encoding of fallback
+/// which decodes them without errors, returning the text and the encoding
+///
+/// The BOM is removed. Returns None if no encoding fits
+///
+/// ```rust
+/// use cuna::decode::{decode, FALLBACK};
+/// use encoding_rs::{UTF_16LE, WINDOWS_1252};
+///
+/// assert_eq!(decode(b"TITLE \"Caf\xe9\"", FALLBACK), Some(("TITLE \"Café\"".into(), WINDOWS_1252)));
+/// assert_eq!(decode(b"\xff\xfeR\0E\0M\0", &[]), Some(("REM".into(), UTF_16LE)));
+/// assert_eq!(decode(b"\xe9", &[]), None);
+/// ```
+pub fn decode<'a>(
+    bytes: &'a [u8],
+    fallback: &[&'static Encoding],
+) -> Option<(Cow<'a, str>, &'static Encoding)> {
+    if let Some((encoding, bom)) = Encoding::for_bom(bytes) {
+        let text = encoding.decode_without_bom_handling_and_without_replacement(&bytes[bom..])?;
+        return Some((text, encoding));
+    }
+    std::iter::once(UTF_8)
+        .chain(fallback.iter().copied())
+        .find_map(|encoding| {
+            encoding
+                .decode_without_bom_handling_and_without_replacement(bytes)
+                .map(|text| (text, encoding))
+        })
+}
+
+#[cfg(feature = "parser")]
+impl Cuna {
+    /// Parses bytes as a cue sheet, detecting the encoding with [`FALLBACK`], see [`decode`](crate::decode)
+    ///
+    /// Fails with [`ParseError::UnknownEncoding`] if no encoding fits
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::from_bytes_with(bytes, FALLBACK, &ParseOptions::default()).map(|(sheet, _)| sheet)
+    }
+    /// Same as [`from_bytes()`](Cuna::from_bytes), but tries the given encodings after UTF-8
+    /// and parses with the given options, returning the sheet with the warnings of the parser
+    pub fn from_bytes_with(
+        bytes: &[u8],
+        fallback: &[&'static Encoding],
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<Warning>), Error> {
+        let (text, _) = decode(bytes, fallback)
+            .ok_or_else(|| Error::from_parse_error(ParseError::UnknownEncoding))?;
+        Self::parse_with(&text, options)
+    }
+}
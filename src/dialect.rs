@@ -0,0 +1,201 @@
+//! Dialects of cue sheets written by different tools
+//!
+//! A [`Dialect`] handles commands the parser doesn't know,
+//! adjusts the rules the parser enforces and chooses how sheets are written.
+//! Use [`Parser::with_dialect()`](crate::parser::Parna::with_dialect) to parse
+//! and [`CueWriter::for_dialect()`](crate::writer::CueWriter::for_dialect) to write.
+//!
+//! A [`CommandHandler`] is consulted before the dialect for a single parse, so an application
+//! can keep proprietary commands in its own state,
+//! see [`Parser::parse_with_handler()`](crate::parser::Parna::parse_with_handler)
+//!
+//! A [`Profile`] bundles a dialect with the tolerances of the parser and the layout of the writer
+//! which suit the sheets of a tool
+use crate::error::ParseError;
+use crate::parser::{CommandOrder, DuplicatePolicy, ParseOptions};
+use crate::writer::{CueWriter, Sanitize};
+use crate::Cuna;
+use std::fmt;
+use std::sync::Arc;
+
+/// What a [`Dialect`] did with a command unknown to the parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Extra {
+    /// The command was written to the sheet
+    Handled,
+    /// The command is valid in the dialect but dropped, a warning is recorded
+    Ignored,
+    /// The command is not known, parsing fails with `UnexpectedToken`
+    Unknown,
+}
+
+pub trait Dialect: fmt::Debug + Send + Sync {
+    fn name(&self) -> &str;
+    /// Called with the keyword and the arguments of a command the parser does not know
+    fn parse_extra(
+        &self,
+        _keyword: &str,
+        _args: &str,
+        _sheet: &mut Cuna,
+    ) -> Result<Extra, ParseError> {
+        Ok(Extra::Unknown)
+    }
+    /// Adjusts the options the parser is given
+    fn parse_options(&self, options: ParseOptions) -> ParseOptions {
+        options
+    }
+    /// Adjusts how sheets are written
+    fn writer(&self, writer: CueWriter) -> CueWriter {
+        writer
+    }
+}
+
+/// Handles commands unknown to the parser before the [`Dialect`] does
+///
+/// Unlike a dialect, a handler is borrowed mutably for one parse, so it may collect what it handles.
+/// Closures taking the same arguments are handlers
+pub trait CommandHandler {
+    /// Called with the keyword and the arguments of a command the parser does not know,
+    /// returning [`Extra::Unknown`] gives it to the dialect
+    fn handle(&mut self, keyword: &str, args: &str, sheet: &mut Cuna) -> Result<Extra, ParseError>;
+}
+impl<F> CommandHandler for F
+where
+    F: FnMut(&str, &str, &mut Cuna) -> Result<Extra, ParseError>,
+{
+    fn handle(&mut self, keyword: &str, args: &str, sheet: &mut Cuna) -> Result<Extra, ParseError> {
+        self(keyword, args, sheet)
+    }
+}
+
+/// Red Book rules: at most 99 `TRACK`s and 99 `FILE`s, and no commands the parser does not know
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Strict;
+/// Exact Audio Copy, which the defaults of the parser and the writer follow.
+/// XLD and foobar2000 write the same commands, so they have no dialect of their own:
+/// what they do differently is in their [`Profile`]s
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Eac;
+/// cdrdao, which accepts the CD-Text commands of its TOC files in cue sheets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Cdrdao;
+
+/// The known quirks of a tool, for parsing and writing without setting each option
+///
+/// The parser of a profile is as tolerant as the tool reading the sheet, and the writer
+/// writes what the tool reads back. Cue sheets have no escape for double quotes,
+/// so every profile replaces them with `'` ([`Sanitize::Replace`]) rather than escaping them.
+/// [`Tool::profile()`](crate::quirks::Tool::profile) gives the profile of a detected tool
+///
+/// ```rust
+/// # #[cfg(feature = "parser")] {
+/// use cuna::dialect::Profile;
+/// use cuna::Cuna;
+///
+/// let cue = "FILE \"a.mp3\" MP3\n  TRACK 01 AUDIO\n    TITLE \" Departures \"\n    ISRC JPSR01100001\n    ISRC JPSR01100002\n    INDEX 01 00:00:00";
+/// assert!(Cuna::new(cue).is_err());
+/// let mut sheet = Cuna::parse_with(cue, &Profile::MediaMonkey.parse_options()).unwrap().0;
+/// assert_eq!(sheet[0][0].title(), &["Departures".to_owned()]);
+/// assert_eq!(sheet[0][0].isrc(), Some("JPSR01100001"));
+/// sheet.header.push_title("\"Live\"".to_owned());
+/// let written = Profile::MediaMonkey.writer().format(&sheet);
+/// assert!(written.starts_with('\u{feff}'));
+/// assert!(written.contains("TITLE \"'Live'\""));
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Profile {
+    /// The defaults of the parser, which follow the sheets EAC writes
+    Eac,
+    /// XLD splits images with sheets which are often edited by hand or by taggers:
+    /// accepts the commands of a `TRACK` out of order and keeps the first of repeated ones
+    /// instead of failing
+    Xld,
+    /// Same as [`Xld`](Profile::Xld), also trimming the values, which foobar2000 reads as tags
+    Foobar2000,
+    /// Same as [`Foobar2000`](Profile::Foobar2000). Writes a BOM, as Windows programs read
+    /// text without one in the ANSI code page instead of UTF-8
+    MediaMonkey,
+    /// The [`Cdrdao`] dialect, accepting the CD-Text commands of its TOC files
+    Cdrdao,
+    /// Red Book rules: the [`Strict`] dialect, rejecting timestamps of 100 minutes or more
+    Strict,
+}
+
+impl Profile {
+    /// The dialect the profile parses and writes with
+    pub fn dialect(self) -> Arc<dyn Dialect> {
+        match self {
+            Self::Eac | Self::Xld | Self::Foobar2000 | Self::MediaMonkey => Arc::new(Eac),
+            Self::Cdrdao => Arc::new(Cdrdao),
+            Self::Strict => Arc::new(Strict),
+        }
+    }
+    /// Returns the options of the parser for the sheets of the tool
+    pub fn parse_options(self) -> ParseOptions {
+        let dialect = self.dialect();
+        let mut options = dialect.parse_options(ParseOptions::default());
+        options.dialect = Some(dialect);
+        if matches!(self, Self::Xld | Self::Foobar2000 | Self::MediaMonkey) {
+            options.ordering = CommandOrder::Permissive;
+            options.duplicates = DuplicatePolicy::KeepFirst;
+        }
+        match self {
+            Self::Foobar2000 | Self::MediaMonkey => options.trim_values = true,
+            Self::Strict => options.red_book_minutes = true,
+            Self::Eac | Self::Xld | Self::Cdrdao => {}
+        }
+        options
+    }
+    /// Returns a writer producing the layout of the tool
+    pub fn writer(self) -> CueWriter {
+        let writer = CueWriter::for_dialect(&*self.dialect()).sanitize(Sanitize::Replace);
+        match self {
+            Self::MediaMonkey => writer.bom(true),
+            _ => writer,
+        }
+    }
+}
+impl Cdrdao {
+    /// CD-Text commands accepted and ignored, `ARRANGER`, `COMPOSER` and `MESSAGE` are
+    /// commands of every sheet
+    pub const CD_TEXT: &'static [&'static str] = &[
+        "DISC_ID",
+        "UPC_EAN",
+        "GENRE",
+        "SIZE_INFO",
+        "TOC_INFO1",
+        "TOC_INFO2",
+    ];
+}
+impl Dialect for Strict {
+    fn name(&self) -> &str {
+        "Strict"
+    }
+    fn parse_options(&self, mut options: ParseOptions) -> ParseOptions {
+        options.limits.max_tracks = options.limits.max_tracks.min(99);
+        options.limits.max_files = options.limits.max_files.min(99);
+        options.keep_unknown = false;
+        options
+    }
+}
+impl Dialect for Eac {
+    fn name(&self) -> &str {
+        "EAC"
+    }
+}
+impl Dialect for Cdrdao {
+    fn name(&self) -> &str {
+        "cdrdao"
+    }
+    fn parse_extra(&self, keyword: &str, _: &str, _: &mut Cuna) -> Result<Extra, ParseError> {
+        if Self::CD_TEXT
+            .iter()
+            .any(|k| k.eq_ignore_ascii_case(keyword))
+        {
+            Ok(Extra::Ignored)
+        } else {
+            Ok(Extra::Unknown)
+        }
+    }
+}
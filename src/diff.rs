@@ -0,0 +1,98 @@
+//! What changed between two versions of a cue sheet
+//!
+//! `FILE`s are compared by position and tracks in the same `FILE` by number,
+//! so renaming a `FILE` is a [`FileChanged`](Change::FileChanged) while moving a track
+//! to another `FILE` is a removal and an addition.
+//!
+//! ```rust
+//! # #[cfg(feature = "parser")] {
+//! use cuna::diff::Change;
+//! use cuna::Cuna;
+//! let old = Cuna::new("FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00").unwrap();
+//! let new = Cuna::new("TITLE \"Departures\"\nFILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:01\n  TRACK 02 AUDIO\n    INDEX 01 04:15:44").unwrap();
+//! assert_eq!(
+//!     old.diff(&new),
+//!     [
+//!         Change::HeaderChanged,
+//!         Change::TrackChanged { file: 0, track: 1 },
+//!         Change::TrackAdded { file: 0, track: 2 },
+//!     ]
+//! );
+//! # }
+//! ```
+use crate::Cuna;
+
+/// A change from one version of a sheet to another, files are positions in [`Cuna::files`]
+/// and tracks are numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Change {
+    /// `TITLE`, `PERFORMER`, `SONGWRITER`, `CATALOG`, `CDTEXTFILE` or unknown commands of the header
+    HeaderChanged,
+    /// The `REM`s
+    CommentsChanged,
+    FileAdded(usize),
+    FileRemoved(usize),
+    /// The name, the format, the `REM`s or unknown commands of a `FILE` which is in both versions
+    FileChanged(usize),
+    TrackAdded {
+        file: usize,
+        track: u8,
+    },
+    TrackRemoved {
+        file: usize,
+        track: u8,
+    },
+    TrackChanged {
+        file: usize,
+        track: u8,
+    },
+}
+
+impl Cuna {
+    /// Returns what changed from self to new, in the order of the sheet with removals before additions
+    ///
+    /// The line numbers of unknown commands are not compared, so lines added above them are no change
+    pub fn diff(&self, new: &Cuna) -> Vec<Change> {
+        let mut changes = Vec::new();
+        if self.header != new.header {
+            changes.push(Change::HeaderChanged);
+        }
+        if self.comments != new.comments {
+            changes.push(Change::CommentsChanged);
+        }
+        for (file, (old, new)) in self.files.iter().zip(&new.files).enumerate() {
+            if old.name != new.name
+                || old.format != new.format
+                || old.comments != new.comments
+                || old.unknown != new.unknown
+            {
+                changes.push(Change::FileChanged(file));
+            }
+            for track in &old.tracks {
+                match new.tracks.iter().find(|t| t.id() == track.id()) {
+                    None => changes.push(Change::TrackRemoved {
+                        file,
+                        track: track.id(),
+                    }),
+                    Some(new) if new != track => changes.push(Change::TrackChanged {
+                        file,
+                        track: track.id(),
+                    }),
+                    Some(_) => {}
+                }
+            }
+            for track in &new.tracks {
+                if old.tracks.iter().all(|t| t.id() != track.id()) {
+                    changes.push(Change::TrackAdded {
+                        file,
+                        track: track.id(),
+                    });
+                }
+            }
+        }
+        let common = self.files.len().min(new.files.len());
+        changes.extend((common..self.files.len()).map(Change::FileRemoved));
+        changes.extend((common..new.files.len()).map(Change::FileAdded));
+        changes
+    }
+}
@@ -0,0 +1,348 @@
+//! Lossless cue sheets which reproduce the text they are parsed from
+//!
+//! A [`Document`] keeps every line with its indentation, trailing whitespace and line ending,
+//! so blank lines, the placement of `REM`s and the original casing survive a round trip.
+//! The parsed [`Cuna`] is kept alongside for reading the semantic model.
+use crate::comment;
+use crate::cst::line_ranges;
+use crate::cst::BOM;
+use crate::error::Error;
+use crate::error::Warning;
+use crate::parser;
+use crate::parser::ParseOptions;
+use crate::parser::RecoveryPolicy;
+use crate::track::TrackInfo;
+use crate::Cuna;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+pub use crate::cst::LineEnding;
+
+/// A line of a [`Document`] split into its content and the whitespace around it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Line {
+    indent: String,
+    content: String,
+    trailing: String,
+    ending: LineEnding,
+}
+/// A cue sheet together with its original text
+///
+/// ```rust
+/// use cuna::document::Document;
+/// let cue = "\u{feff}rem GENRE Pop\r\n\r\nFILE \"a.flac\" WAVE  \n\ttrack 01 AUDIO\n";
+/// let document = Document::parse(cue).unwrap();
+/// assert_eq!(document.to_string(), cue);
+/// assert_eq!(document.sheet().comments[0], "GENRE Pop");
+/// assert_eq!(document.lines()[3].indent(), "\t");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Document {
+    bom: bool,
+    lines: Vec<Line>,
+    sheet: Cuna,
+    warnings: Vec<Warning>,
+}
+
+impl Line {
+    fn new(line: &str, ending: LineEnding) -> Self {
+        let content = line.trim();
+        let indent_len = line.len() - line.trim_start().len();
+        Self {
+            indent: line[..indent_len].to_owned(),
+            content: content.to_owned(),
+            trailing: line[indent_len + content.len()..].to_owned(),
+            ending,
+        }
+    }
+    /// Whitespace before the command
+    pub fn indent(&self) -> &str {
+        &self.indent
+    }
+    /// The command without the whitespace around it, empty for blank lines
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+    /// Whitespace after the command
+    pub fn trailing(&self) -> &str {
+        &self.trailing
+    }
+    pub fn ending(&self) -> LineEnding {
+        self.ending
+    }
+    pub fn is_blank(&self) -> bool {
+        self.content.is_empty()
+    }
+}
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}{}",
+            self.indent, self.content, self.trailing, self.ending
+        )
+    }
+}
+impl Document {
+    /// Parses an str losslessly
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        Self::parse_with(s, &ParseOptions::default())
+    }
+    /// Parses an str losslessly with the given options
+    ///
+    /// With a [`RecoveryPolicy`] other than `Abort`, the lines it drops are left out of the
+    /// [`sheet()`](Document::sheet) but stay in the text
+    pub fn parse_with(s: &str, options: &ParseOptions) -> Result<Self, Error> {
+        let body = s.strip_prefix(BOM);
+        let mut document = Self {
+            bom: body.is_some(),
+            ..Self::default()
+        };
+        let mut line_state = parser::LineState::default();
+        for (at, line) in (1..).zip(split_lines(body.unwrap_or(s))) {
+            parser::parse_line_recovering(
+                &line.content,
+                at,
+                &mut document.sheet,
+                options,
+                &mut document.warnings,
+                &mut line_state,
+                None,
+            )?;
+            document.lines.push(line);
+        }
+        Ok(document)
+    }
+    /// The parsed cue sheet
+    pub fn sheet(&self) -> &Cuna {
+        &self.sheet
+    }
+    pub fn into_sheet(self) -> Cuna {
+        self.sheet
+    }
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+    /// Returns true if the text starts with a UTF-8 BOM
+    pub fn has_bom(&self) -> bool {
+        self.bom
+    }
+    /// Recoverable problems found when parsing
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+    /// Replaces the bytes in range of the text, as written by `to_string()`, with new_text
+    /// and parses the document again, see [`apply_edit_with()`](Document::apply_edit_with)
+    ///
+    /// ```rust
+    /// use cuna::document::Document;
+    ///
+    /// let cue = "TITLE \"Departures\"\nFILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"Euterpe\"\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    INDEX 01 04:15:44\n";
+    /// let mut document = Document::parse(cue).unwrap();
+    /// let at = cue.find("Euterpe").unwrap();
+    /// let reparsed = document.apply_edit(at..at + 7, "エウテルペ").unwrap();
+    /// assert_eq!(reparsed, 2..5);
+    /// assert_eq!(document.sheet()[0][0].title(), &["エウテルペ".to_owned()]);
+    /// assert_eq!(document.to_string(), cue.replace("Euterpe", "エウテルペ"));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if range is out of bounds or not on `char` boundaries
+    pub fn apply_edit(
+        &mut self,
+        range: Range<usize>,
+        new_text: &str,
+    ) -> Result<Range<usize>, Error> {
+        self.apply_edit_with(range, new_text, &ParseOptions::default())
+    }
+    /// Same as [`apply_edit()`](Document::apply_edit), parsing with the given options,
+    /// which should be the ones the document was parsed with
+    ///
+    /// When the edited lines are in a `TRACK` and neither the old nor the new lines have a `FILE`,
+    /// a `TRACK` or a `REM` with a header key, only the lines of that `TRACK` are parsed again.
+    /// Otherwise, or with a dialect or a [`RecoveryPolicy`] other than `Abort`, the whole text is.
+    /// Returns the positions in [`lines()`](Document::lines) which were parsed again.
+    ///
+    /// On error the document is left unchanged
+    pub fn apply_edit_with(
+        &mut self,
+        range: Range<usize>,
+        new_text: &str,
+        options: &ParseOptions,
+    ) -> Result<Range<usize>, Error> {
+        let bom = if self.bom { BOM.len_utf8() } else { 0 };
+        let mut starts = Vec::with_capacity(self.lines.len() + 1);
+        let mut start = bom;
+        for line in &self.lines {
+            starts.push(start);
+            start += line.to_string().len();
+        }
+        let containing = |at: usize| starts.iter().rposition(|&s| s <= at);
+        match (
+            range.start >= bom,
+            containing(range.start),
+            containing(range.end),
+        ) {
+            (true, Some(first), Some(last)) => {
+                let old: String = self.lines[first..=last]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect();
+                let mut edited = old.clone();
+                let offset = starts[first];
+                edited.replace_range(range.start - offset..range.end - offset, new_text);
+                self.reparse_track(first..last + 1, &edited, options)
+            }
+            _ => {
+                let mut text = self.to_string();
+                text.replace_range(range, new_text);
+                *self = Self::parse_with(&text, options)?;
+                Ok(0..self.lines.len())
+            }
+        }
+    }
+    /// Replaces the lines in edited with the lines of text, parsing again only their `TRACK` if possible
+    fn reparse_track(
+        &mut self,
+        edited: Range<usize>,
+        text: &str,
+        options: &ParseOptions,
+    ) -> Result<Range<usize>, Error> {
+        let new_lines: Vec<Line> = split_lines(text).collect();
+        let added = new_lines.len();
+        // a last line without ending would be joined to the next one
+        let joined = edited.end < self.lines.len()
+            && new_lines
+                .last()
+                .is_some_and(|l| l.ending() == LineEnding::None);
+        let incremental = options.dialect.is_none()
+            && options.aliases.is_empty()
+            && options.recovery == RecoveryPolicy::Abort
+            && self.lines.len() - edited.len() + added <= options.limits.max_lines
+            && !self.lines[edited.clone()].iter().any(is_structural)
+            && !joined
+            && !new_lines.iter().any(is_structural);
+        let mut lines = self.lines.clone();
+        lines.splice(edited.clone(), new_lines);
+        let found = if incremental {
+            self.find_track(edited.start)
+        } else {
+            None
+        };
+        let (track_line, file, track) = match found {
+            Some(found) => found,
+            None => {
+                let mut text = String::new();
+                if self.bom {
+                    text.push(BOM);
+                }
+                lines
+                    .iter()
+                    .for_each(|line| text.push_str(&line.to_string()));
+                *self = Self::parse_with(&text, options)?;
+                return Ok(0..self.lines.len());
+            }
+        };
+        let end = lines[track_line + 1..]
+            .iter()
+            .position(|line| is_keyword(line, "FILE") || is_keyword(line, "TRACK"))
+            .map_or(lines.len(), |at| track_line + 1 + at);
+        let old_end = end + edited.len() - added;
+        let mut scratch = Cuna::default();
+        let old_file = &self.sheet.files[file];
+        scratch.push_file(TrackInfo::new(
+            old_file.name.clone(),
+            old_file.format.clone(),
+        ));
+        let mut warnings = Vec::new();
+        let mut line_state = parser::LineState::default();
+        for (at, line) in (track_line + 1..).zip(&lines[track_line..end]) {
+            let content = line.content();
+            parser::parse_line(
+                content,
+                at,
+                &mut scratch,
+                options,
+                &mut warnings,
+                &mut line_state,
+            )?;
+        }
+        // line numbers after the track move by the number of added lines
+        let moved = |at: usize| (at + end).saturating_sub(old_end);
+        for file in &mut self.sheet.files {
+            let tracks = file.tracks.iter_mut().flat_map(|t| t.unknown.iter_mut());
+            for (at, _) in file.unknown.iter_mut().chain(tracks) {
+                if *at > old_end {
+                    *at = moved(*at);
+                }
+            }
+        }
+        let parsed = scratch.files.pop().and_then(|mut f| f.tracks.pop());
+        self.sheet.files[file].tracks[track] = parsed.expect("the lines start with `TRACK`");
+        let mut kept = Vec::with_capacity(self.warnings.len() + warnings.len());
+        for warning in self.warnings.drain(..) {
+            match warning.pos() {
+                Some(at) if at > old_end => {
+                    kept.push(Warning::new(warning.kind().clone(), moved(at)))
+                }
+                Some(at) if at > track_line => {}
+                _ => kept.push(warning),
+            }
+        }
+        kept.extend(warnings);
+        kept.sort_by_key(|w| w.pos());
+        self.warnings = kept;
+        self.lines = lines;
+        Ok(track_line..end)
+    }
+    /// Returns the position of the `TRACK` line the line at belongs to,
+    /// with the positions of the `FILE` and of the track in the sheet
+    fn find_track(&self, at: usize) -> Option<(usize, usize, usize)> {
+        let track_line = self.lines[..at]
+            .iter()
+            .rposition(|line| is_keyword(line, "FILE") || is_keyword(line, "TRACK"))
+            .filter(|&line| is_keyword(&self.lines[line], "TRACK"))?;
+        let before = &self.lines[..track_line];
+        let file_line = before.iter().rposition(|l| is_keyword(l, "FILE"))?;
+        let file = before.iter().filter(|l| is_keyword(l, "FILE")).count() - 1;
+        let track = before[file_line..]
+            .iter()
+            .filter(|l| is_keyword(l, "TRACK"))
+            .count();
+        let exists = self.sheet.files.get(file)?.tracks.len() > track;
+        exists.then_some((track_line, file, track))
+    }
+}
+impl FromStr for Document {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bom {
+            write!(f, "{}", BOM)?;
+        }
+        self.lines.iter().try_for_each(|line| write!(f, "{}", line))
+    }
+}
+
+fn is_keyword(line: &Line, keyword: &str) -> bool {
+    comment::split(line.content())
+        .0
+        .eq_ignore_ascii_case(keyword)
+}
+/// Returns true if changing the line may change more than its `TRACK`
+fn is_structural(line: &Line) -> bool {
+    let (keyword, rest) = comment::split(line.content());
+    keyword.eq_ignore_ascii_case("FILE")
+        || keyword.eq_ignore_ascii_case("TRACK")
+        || keyword.eq_ignore_ascii_case("REM") && comment::is_header_key(rest)
+}
+fn split_lines(s: &str) -> impl Iterator<Item = Line> + '_ {
+    line_ranges(s).map(move |(range, ending)| Line::new(&s[range], ending))
+}
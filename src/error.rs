@@ -1,38 +1,201 @@
-use std::fmt;
+#[cfg(feature = "std")]
+use crate::parser::RecoveryPolicy;
+use crate::time::Component;
+#[cfg(feature = "std")]
+use crate::time::TimeStamp;
+#[cfg(feature = "alloc")]
+use alloc::format;
+#[cfg(feature = "alloc")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "alloc")]
+use core::mem::discriminant;
+#[cfg(feature = "alloc")]
+use core::num::ParseIntError;
+#[cfg(feature = "std")]
 use std::io;
-use std::mem::discriminant;
-use std::num::ParseIntError;
-use thiserror::Error;
 
-#[derive(Debug, Error, PartialEq, Eq, Hash, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum InvalidArgument {
-    #[error("Invalid timestamp")]
     InvalidTimestamp,
-    #[error("Missing arguments")]
+    /// A component of a timestamp is invalid, column starts from 1 at the beginning of the timestamp
+    MalformedTimestamp {
+        component: Component,
+        column: usize,
+    },
     MissingArgument,
-    #[error("Invalid id")]
     InvalidId,
+    /// An `ISRC` is not 12 characters like `JPSR01100001`,
+    /// see [`ParseOptions::strict_isrc`](crate::parser::ParseOptions::strict_isrc)
+    MalformedIsrc,
 }
-#[derive(Debug, Error)]
+/// A limit of [`Limits`](crate::parser::Limits) which was exceeded, with the maximum allowed
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Limit {
+    LineLength(usize),
+    Lines(usize),
+    Files(usize),
+    Tracks(usize),
+}
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
 pub enum ParseError {
     /// There is something wrong in the cue sheet
-    #[error("SyntaxError: {0}")]
     SyntaxError(String),
-    #[error("UnexpetedToken: {0}")]
     UnexpectedToken(String),
-    #[error(transparent)]
-    InvalidArgument(#[from] InvalidArgument),
+    InvalidArgument(InvalidArgument),
+    /// The input is larger than allowed
+    LimitExceeded(Limit),
     /// Fails to read a file
-    #[error("IoError: {0}")]
-    IoError(#[from] io::Error),
+    #[cfg(feature = "std")]
+    IoError(io::Error),
+    /// The bytes are not text in any of the tried encodings, see [`decode`](crate::decode)
+    UnknownEncoding,
+}
+/// A problem found by [`Cuna::validate()`](crate::Cuna::validate) which strict players refuse,
+/// or by [`Cuna::validate_codes()`](crate::Cuna::validate_codes) in `CATALOG` and `ISRC`
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum ValidationError {
+    /// A track has `INDEX 00` but no `INDEX 01`, see [`FixPolicy`](crate::validation::FixPolicy)
+    MissingStartIndex(u8),
+    /// An `AUDIO` track followed by a data track has `POSTGAP`,
+    /// which belongs after the last audio track of the session
+    PostgapBeforeData(u8),
+    /// The check digit of `CATALOG` is wrong, see [`Cuna::validate_codes()`](crate::Cuna::validate_codes)
+    InvalidCatalog,
+    /// `CATALOG` is from a country none of the `ISRC`s are from
+    CatalogCountryMismatch,
+    /// An `ISRC` is not 12 characters like `JPU901100123`
+    MalformedIsrc(u8),
+    /// An `ISRC` is the same as the one of an earlier track
+    DuplicateIsrc(u8),
+    /// The country and registrant of an `ISRC` differ from those of most tracks
+    IsrcRegistrantMismatch(u8),
+    /// An `ISRC` was registered after the year of `REM DATE`
+    IsrcAfterDate(u8),
+    /// The `FILE` at this position, starting from 1, has no `TRACK`
+    EmptyFile(usize),
+    /// A track has neither `INDEX 00` nor `INDEX 01`
+    MissingIndex(u8),
+    /// A track is not numbered higher than the one before it
+    TrackNotIncreasing(u8),
+    /// The `INDEX`s of a track are not in order of number and time,
+    /// or it starts before the previous track in the same `FILE`
+    IndexNotIncreasing(u8),
+}
+#[cfg(feature = "alloc")]
+/// The problems which keep [`Cuna::from_parts()`](crate::Cuna::from_parts) from building a sheet
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ValidationIssues(pub Vec<ValidationError>);
+/// An edit of a [`Cst`](crate::cst::Cst) which can not be applied
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum EditError {
+    NoSuchTrack(u8),
+    NoSuchIndex { track: u8, index: u8 },
+    NoSuchLine(usize),
+}
+/// A TOC which [`Cuna::from_toc()`](crate::Cuna::from_toc) can not turn into a cue sheet
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum TocError {
+    NoTracks,
+    /// A track would be numbered 0 or more than 99
+    InvalidTrackNumber(u8),
+    /// A track does not start before the next one or the lead-out
+    NotIncreasing(u8),
+}
+#[cfg(feature = "alloc")]
+/// A value rejected by [`Sanitize::Reject`](crate::writer::Sanitize::Reject)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InvalidValue {
+    /// The keyword of the command, like `TITLE`
+    pub keyword: &'static str,
+    pub value: String,
+    /// The first character which would make the sheet invalid
+    pub character: char,
+}
+/// Why [`CueWriter::encode()`](crate::writer::CueWriter::encode) failed
+#[cfg(feature = "encoding")]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum EncodeError {
+    /// A character of the cue sheet which can not be written in the chosen encoding
+    Unmappable {
+        character: char,
+        /// The name of the encoding, like `Shift_JIS`
+        encoding: &'static str,
+        /// The line of the written sheet, starting from 1
+        line: usize,
+        /// The column in characters, starting from 1
+        column: usize,
+    },
+    /// A value rejected by [`Sanitize::Reject`](crate::writer::Sanitize::Reject)
+    InvalidValue(InvalidValue),
+}
+#[cfg(feature = "std")]
+/// A problem in the cue sheet which does not stop parsing
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WarningKind {
+    /// A header-level `REM` appears after the first `FILE`, it is kept in the header
+    MisplacedHeaderRem(String),
+    /// A command known to the [`Dialect`](crate::dialect::Dialect) was dropped
+    IgnoredCommand(String),
+    /// A line has an error and what the policy says was dropped, see [`RecoveryPolicy`]
+    Recovered {
+        error: String,
+        policy: RecoveryPolicy,
+    },
+    /// A `MM:SS.mmm` or `H:MM:SS` timestamp was converted and rounded to a frame, see
+    /// [`ParseOptions::decimal_seconds`](crate::parser::ParseOptions::decimal_seconds)
+    /// and [`ParseOptions::hms_timestamps`](crate::parser::ParseOptions::hms_timestamps)
+    DecimalTimestamp {
+        timestamp: String,
+        rounded: TimeStamp,
+    },
+    /// A command of a `TRACK` is after one it should be before, see
+    /// [`CommandOrder::Permissive`](crate::parser::CommandOrder::Permissive)
+    OutOfOrder {
+        keyword: &'static str,
+        after: &'static str,
+    },
+    /// A command which may appear once appeared again and was dropped, see
+    /// [`DuplicatePolicy::KeepFirstAndWarn`](crate::parser::DuplicatePolicy::KeepFirstAndWarn)
+    DuplicateCommand(String),
+    /// len bytes at offset, counted from the start of the input, are not UTF-8
+    /// and were replaced with U+FFFD, see [`Cuna::from_bytes_lossy()`](crate::Cuna::from_bytes_lossy)
+    InvalidBytes { offset: usize, len: usize },
+    /// A comment after a command was stripped, see
+    /// [`ParseOptions::inline_comments`](crate::parser::ParseOptions::inline_comments)
+    InlineComment(String),
+    /// A `REM DATE` is not a year or a date, only recorded with the `date` feature,
+    /// see [`date`](crate::date)
+    MalformedDate(String),
+}
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Warning {
+    kind: WarningKind,
+    at: Option<usize>,
 }
-#[derive(Debug, Error)]
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
 pub struct Error {
-    #[source]
     error: ParseError,
     at: Option<usize>,
+    context: Option<ErrorContext>,
+}
+#[cfg(feature = "alloc")]
+/// The `FILE` and `TRACK` an [`Error`] happened in
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ErrorContext {
+    /// The name of the `FILE`
+    pub file: String,
+    /// The number of the `TRACK`, None if the error is before the first `TRACK` of the `FILE`
+    /// or in a `TRACK` line
+    pub track: Option<u8>,
 }
 
+#[cfg(feature = "alloc")]
 impl ParseError {
     pub fn syntax_error(content: impl fmt::Display, description: impl fmt::Display) -> Self {
         Self::err_msg(format!("{}: {}", content, description))
@@ -44,28 +207,105 @@ impl ParseError {
         Self::SyntaxError(msg.to_string())
     }
 }
+impl fmt::Display for InvalidArgument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTimestamp => f.write_str("Invalid timestamp"),
+            Self::MalformedTimestamp { component, column } => write!(
+                f,
+                "Invalid timestamp: invalid {} at column {}",
+                component, column
+            ),
+            Self::MissingArgument => f.write_str("Missing arguments"),
+            Self::InvalidId => f.write_str("Invalid id"),
+            Self::MalformedIsrc => f.write_str("Malformed ISRC"),
+        }
+    }
+}
+impl core::error::Error for InvalidArgument {}
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LineLength(n) => write!(f, "line longer than {} bytes", n),
+            Self::Lines(n) => write!(f, "more than {} lines", n),
+            Self::Files(n) => write!(f, "more than {} `FILE`s", n),
+            Self::Tracks(n) => write!(f, "more than {} `TRACK`s", n),
+        }
+    }
+}
+impl core::error::Error for Limit {}
+#[cfg(feature = "alloc")]
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SyntaxError(msg) => write!(f, "SyntaxError: {}", msg),
+            Self::UnexpectedToken(msg) => write!(f, "UnexpetedToken: {}", msg),
+            Self::InvalidArgument(e) => e.fmt(f),
+            Self::LimitExceeded(limit) => write!(f, "LimitExceeded: {}", limit),
+            #[cfg(feature = "std")]
+            Self::IoError(e) => write!(f, "IoError: {}", e),
+            Self::UnknownEncoding => f.write_str("UnknownEncoding: the encoding is not supported"),
+        }
+    }
+}
+#[cfg(feature = "alloc")]
+impl core::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Self::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+#[cfg(feature = "alloc")]
+impl From<InvalidArgument> for ParseError {
+    fn from(e: InvalidArgument) -> Self {
+        Self::InvalidArgument(e)
+    }
+}
+#[cfg(feature = "std")]
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self {
+        Self::IoError(e)
+    }
+}
+#[cfg(feature = "alloc")]
 impl From<ParseIntError> for ParseError {
     fn from(e: ParseIntError) -> Self {
         Self::err_msg(e)
     }
 }
+#[cfg(feature = "alloc")]
 impl PartialEq for ParseError {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::InvalidArgument(t), Self::InvalidArgument(t2)) => t == t2,
+            (Self::LimitExceeded(l), Self::LimitExceeded(l2)) => l == l2,
             _ => discriminant(self) == discriminant(other),
         }
     }
 }
+#[cfg(feature = "alloc")]
 impl Error {
     pub const fn new(error: ParseError, at: usize) -> Self {
         Self {
             error,
             at: Some(at),
+            context: None,
         }
     }
     pub const fn from_parse_error(error: ParseError) -> Self {
-        Self { error, at: None }
+        Self {
+            error,
+            at: None,
+            context: None,
+        }
+    }
+    /// Consumes the error and returns it with the `FILE` and `TRACK` it happened in
+    pub fn with_context(mut self, context: ErrorContext) -> Self {
+        self.context = Some(context);
+        self
     }
     pub const fn kind(&self) -> &ParseError {
         &self.error
@@ -73,22 +313,264 @@ impl Error {
     pub const fn pos(&self) -> Option<usize> {
         self.at
     }
+    /// Returns the `FILE` and `TRACK` the error happened in, if it happened after a `FILE`
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// let err = Cuna::new("FILE \"x.flac\" WAVE\nTRACK 03 AUDIO\nINDEX 01 00:00:99").unwrap_err();
+    /// let context = err.context().unwrap();
+    /// assert_eq!((context.file.as_str(), context.track), ("x.flac", Some(3)));
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "Invalid timestamp: invalid frames at column 7 at line 3 while parsing FILE 'x.flac' TRACK 03"
+    /// );
+    /// # }
+    /// ```
+    pub const fn context(&self) -> Option<&ErrorContext> {
+        self.context.as_ref()
+    }
 }
+#[cfg(feature = "alloc")]
 impl fmt::Display for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.at {
-            Some(l) => write!(formatter, "{} at line {}", self.error, l),
-            None => write!(formatter, "{}", self.error),
+            Some(l) => write!(formatter, "{} at line {}", self.error, l)?,
+            None => write!(formatter, "{}", self.error)?,
+        }
+        match &self.context {
+            Some(context) => write!(formatter, " while parsing {}", context),
+            None => Ok(()),
+        }
+    }
+}
+#[cfg(feature = "alloc")]
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FILE '{}'", self.file)?;
+        match self.track {
+            Some(track) => write!(f, " TRACK {:02}", track),
+            None => Ok(()),
         }
     }
 }
+#[cfg(feature = "alloc")]
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+#[cfg(feature = "alloc")]
 impl<E: Into<ParseError>> From<E> for Error {
     fn from(e: E) -> Self {
         Self::from_parse_error(e.into())
     }
 }
+#[cfg(feature = "alloc")]
 impl PartialEq for Error {
     fn eq(&self, other: &Self) -> bool {
         self.kind() == other.kind()
     }
 }
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingStartIndex(track) => {
+                write!(f, "`TRACK {:02}` has `INDEX 00` but no `INDEX 01`", track)
+            }
+            Self::PostgapBeforeData(track) => {
+                write!(
+                    f,
+                    "`TRACK {:02}` has `POSTGAP` but a data track follows it",
+                    track
+                )
+            }
+            Self::InvalidCatalog => write!(f, "the check digit of `CATALOG` is wrong"),
+            Self::CatalogCountryMismatch => {
+                write!(
+                    f,
+                    "`CATALOG` is from a country none of the `ISRC`s are from"
+                )
+            }
+            Self::MalformedIsrc(track) => write!(f, "`ISRC` of `TRACK {:02}` is malformed", track),
+            Self::DuplicateIsrc(track) => {
+                write!(
+                    f,
+                    "`ISRC` of `TRACK {:02}` is used by an earlier track",
+                    track
+                )
+            }
+            Self::IsrcRegistrantMismatch(track) => write!(
+                f,
+                "`ISRC` of `TRACK {:02}` has another registrant than most tracks",
+                track
+            ),
+            Self::IsrcAfterDate(track) => write!(
+                f,
+                "`ISRC` of `TRACK {:02}` was registered after `REM DATE`",
+                track
+            ),
+            Self::EmptyFile(file) => write!(f, "`FILE` {} has no `TRACK`", file),
+            Self::MissingIndex(track) => {
+                write!(
+                    f,
+                    "`TRACK {:02}` has neither `INDEX 00` nor `INDEX 01`",
+                    track
+                )
+            }
+            Self::TrackNotIncreasing(track) => write!(
+                f,
+                "`TRACK {:02}` is not numbered higher than the track before it",
+                track
+            ),
+            Self::IndexNotIncreasing(track) => {
+                write!(f, "the `INDEX`s of `TRACK {:02}` are out of order", track)
+            }
+        }
+    }
+}
+impl core::error::Error for ValidationError {}
+#[cfg(feature = "alloc")]
+impl fmt::Display for ValidationIssues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, issue) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            issue.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+#[cfg(feature = "alloc")]
+impl core::error::Error for ValidationIssues {}
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSuchTrack(track) => write!(f, "no `TRACK {:02}`", track),
+            Self::NoSuchIndex { track, index } => {
+                write!(f, "no `INDEX {:02}` in `TRACK {:02}`", index, track)
+            }
+            Self::NoSuchLine(at) => write!(f, "no line {}", at),
+        }
+    }
+}
+impl core::error::Error for EditError {}
+#[cfg(feature = "encoding")]
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unmappable {
+                character,
+                encoding,
+                line,
+                column,
+            } => write!(
+                f,
+                "{:?} (U+{:04X}) can not be encoded in {} at line {}, column {}",
+                character, *character as u32, encoding, line, column
+            ),
+            Self::InvalidValue(invalid) => invalid.fmt(f),
+        }
+    }
+}
+#[cfg(feature = "encoding")]
+impl core::error::Error for EncodeError {}
+impl fmt::Display for TocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoTracks => f.write_str("the TOC has no tracks"),
+            Self::InvalidTrackNumber(track) => write!(f, "invalid track number {}", track),
+            Self::NotIncreasing(track) => {
+                write!(f, "track {} does not start before the next one", track)
+            }
+        }
+    }
+}
+impl core::error::Error for TocError {}
+#[cfg(feature = "alloc")]
+impl fmt::Display for InvalidValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} can not be written in {} {:?}",
+            self.character, self.keyword, self.value
+        )
+    }
+}
+#[cfg(feature = "alloc")]
+impl core::error::Error for InvalidValue {}
+#[cfg(feature = "std")]
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MisplacedHeaderRem(rem) => {
+                write!(
+                    f,
+                    "MisplacedHeaderRem: `REM {}` should be before `FILE`",
+                    rem
+                )
+            }
+            Self::IgnoredCommand(keyword) => {
+                write!(
+                    f,
+                    "IgnoredCommand: `{}` is not supported and ignored",
+                    keyword
+                )
+            }
+            Self::Recovered { error, policy } => {
+                let dropped = match policy {
+                    RecoveryPolicy::SkipTrack => "the `TRACK`",
+                    RecoveryPolicy::SkipFile => "the `FILE`",
+                    _ => "the line",
+                };
+                write!(f, "Recovered: {} is dropped, {}", dropped, error)
+            }
+            Self::DecimalTimestamp { timestamp, rounded } => write!(
+                f,
+                "DecimalTimestamp: `{}` is not `MM:SS:FF` and rounded to `{}`",
+                timestamp, rounded
+            ),
+            Self::OutOfOrder { keyword, after } => {
+                write!(f, "OutOfOrder: `{}` should be before `{}`", keyword, after)
+            }
+            Self::DuplicateCommand(command) => {
+                write!(f, "DuplicateCommand: `{}` is dropped", command)
+            }
+            Self::InvalidBytes { offset, len } => write!(
+                f,
+                "InvalidBytes: {} bytes at offset {} are not UTF-8 and replaced",
+                len, offset
+            ),
+            Self::InlineComment(comment) => {
+                write!(f, "InlineComment: `{}` is not part of the command", comment)
+            }
+            Self::MalformedDate(date) => {
+                write!(f, "MalformedDate: `{}` is not a year or a date", date)
+            }
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl core::error::Error for WarningKind {}
+#[cfg(feature = "std")]
+impl Warning {
+    pub const fn new(kind: WarningKind, at: usize) -> Self {
+        Self { kind, at: Some(at) }
+    }
+    pub const fn kind(&self) -> &WarningKind {
+        &self.kind
+    }
+    pub const fn pos(&self) -> Option<usize> {
+        self.at
+    }
+}
+#[cfg(feature = "std")]
+impl fmt::Display for Warning {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.at {
+            Some(l) => write!(formatter, "{} at line {}", self.kind, l),
+            None => write!(formatter, "{}", self.kind),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! Subsystems which are not stable yet, requires the `unstable` feature
+//!
+//! New large subsystems land here first so they can be used and changed quickly.
+//! Nothing in this module is covered by semver: items may change or be removed in any release,
+//! and are moved out to a stable module once their API settles. See [`stability`](crate::stability).
+//!
+//! It currently holds [`merge()`], which joins the sheets of several discs into one.
+use crate::transform::Pipeline;
+use crate::Cuna;
+
+/// Joins cue sheets into one, e.g. the discs of a set burned as a single disc
+///
+/// The header and the comments are taken from the first sheet, the `FILE`s of every sheet
+/// are appended in order and the tracks are renumbered from 1.
+/// Returns None if there are no sheets or more than 99 tracks in total
+///
+/// ```rust
+/// # #[cfg(feature = "parser")] {
+/// use cuna::experimental::merge;
+/// use cuna::Cuna;
+///
+/// let disc = |name: &str| Cuna::new(&format!("TITLE \"Set\"\nFILE \"{}\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00", name)).unwrap();
+/// let merged = merge(vec![disc("1.flac"), disc("2.flac")]).unwrap();
+/// assert_eq!(merged.files().len(), 2);
+/// assert_eq!(merged[1][0].id(), 2);
+/// assert_eq!(merged.title(), &["Set".to_owned()]);
+/// # }
+/// ```
+pub fn merge(sheets: impl IntoIterator<Item = Cuna>) -> Option<Cuna> {
+    let mut sheets = sheets.into_iter();
+    let mut merged = sheets.next()?;
+    for sheet in sheets {
+        merged.files.extend(sheet.files);
+    }
+    if merged.tracks().count() > 99 {
+        return None;
+    }
+    merged.transform(&Pipeline::new().renumber());
+    Some(merged)
+}
@@ -0,0 +1,9 @@
+//! Exporters to formats other than cue sheets
+
+pub mod chapters;
+pub mod csv;
+pub mod gst;
+pub mod m3u;
+pub mod rows;
+#[cfg(feature = "xml")]
+pub mod xml;
@@ -0,0 +1,106 @@
+//! Chapter lists for podcast and Ogg tooling
+//!
+//! Chapters are taken from the tracks in order, starting at `INDEX 01` (or the first `INDEX`).
+//! Timestamps are relative to the `FILE` a track belongs to, so these are meant for single-`FILE` sheets.
+use crate::time::TimeStamp;
+use crate::track::Track;
+use crate::Cuna;
+use std::fmt;
+use std::time::Duration;
+
+/// A chapter mark derived from a `TRACK`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Chapter {
+    pub start: TimeStamp,
+    pub title: String,
+}
+
+impl Chapter {
+    fn from_track(track: &Track) -> Option<Self> {
+        let title = match track.title().first() {
+            Some(title) => title.clone(),
+            None => format!("Track {:02}", track.id()),
+        };
+        Some(Self {
+            start: track.start()?,
+            title,
+        })
+    }
+}
+
+/// Returns the chapters of a cue sheet, tracks without `INDEX` are skipped
+pub fn chapters(sheet: &Cuna) -> Vec<Chapter> {
+    sheet.tracks().filter_map(Chapter::from_track).collect()
+}
+/// Writes chapters in the Podlove simple chapters format (`HH:MM:SS.mmm Title`)
+///
+/// ```rust
+/// # #[cfg(feature = "parser")] {
+/// use cuna::Cuna;
+/// use cuna::export::chapters;
+///
+/// let sheet = Cuna::new(r#"FILE "a.flac" WAVE
+///   TRACK 01 AUDIO
+///     TITLE "Intro"
+///     INDEX 01 00:00:00
+///   TRACK 02 AUDIO
+///     INDEX 01 61:02:30"#).unwrap();
+/// assert_eq!(chapters::podlove(&sheet), "00:00:00.000 Intro\n01:01:02.400 Track 02\n");
+/// # }
+/// ```
+pub fn write_podlove(sheet: &Cuna, w: &mut impl fmt::Write) -> fmt::Result {
+    for chapter in chapters(sheet) {
+        writeln!(w, "{} {}", Hms(chapter.start), chapter.title)?;
+    }
+    Ok(())
+}
+/// Returns chapters in the Podlove simple chapters format
+pub fn podlove(sheet: &Cuna) -> String {
+    let mut s = String::new();
+    write_podlove(sheet, &mut s).expect("writing to a String never fails");
+    s
+}
+/// Writes chapters as Ogg/Opus chapter comments (`CHAPTER001=...` and `CHAPTER001NAME=...`)
+///
+/// ```rust
+/// # #[cfg(feature = "parser")] {
+/// use cuna::Cuna;
+/// use cuna::export::chapters;
+///
+/// let sheet = Cuna::new(r#"FILE "a.opus" MP3
+///   TRACK 01 AUDIO
+///     TITLE "Intro"
+///     INDEX 01 00:01:00"#).unwrap();
+/// assert_eq!(chapters::ogg(&sheet), "CHAPTER001=00:00:01.000\nCHAPTER001NAME=Intro\n");
+/// # }
+/// ```
+pub fn write_ogg(sheet: &Cuna, w: &mut impl fmt::Write) -> fmt::Result {
+    for (i, chapter) in chapters(sheet).into_iter().enumerate() {
+        writeln!(w, "CHAPTER{:03}={}", i + 1, Hms(chapter.start))?;
+        writeln!(w, "CHAPTER{:03}NAME={}", i + 1, chapter.title)?;
+    }
+    Ok(())
+}
+/// Returns chapters as Ogg/Opus chapter comments
+pub fn ogg(sheet: &Cuna) -> String {
+    let mut s = String::new();
+    write_ogg(sheet, &mut s).expect("writing to a String never fails");
+    s
+}
+
+/// Displays a TimeStamp as `HH:MM:SS.mmm`
+struct Hms(TimeStamp);
+
+impl fmt::Display for Hms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = Duration::from(self.0).as_millis();
+        write!(
+            f,
+            "{:02}:{:02}:{:02}.{:03}",
+            millis / 3_600_000,
+            millis / 60_000 % 60,
+            millis / 1000 % 60,
+            millis % 1000
+        )
+    }
+}
@@ -0,0 +1,71 @@
+//! Track listings as CSV
+use crate::Cuna;
+use std::io;
+
+const HEADER: &[&str] = &["number", "title", "performer", "isrc", "start", "duration"];
+
+impl Cuna {
+    /// Writes one CSV row per track: number, title, performer, ISRC, start and duration
+    ///
+    /// Times are `MM:SS:FF`, the duration of the last track of each `FILE` is left empty
+    /// (see [`timeline`](crate::timeline)), the album performer is used for tracks without `PERFORMER`,
+    /// and fields are quoted as described in RFC 4180
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::new(r#"PERFORMER "EGOIST"
+    /// FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO
+    ///     TITLE "Departures, TV Edit"
+    ///     INDEX 01 00:00:00
+    ///   TRACK 02 AUDIO
+    ///     INDEX 01 04:15:44"#).unwrap();
+    /// let mut csv = Vec::new();
+    /// sheet.to_csv(&mut csv).unwrap();
+    /// assert_eq!(String::from_utf8(csv).unwrap(), "number,title,performer,isrc,start,duration\r
+    /// 1,\"Departures, TV Edit\",EGOIST,,00:00:00,04:15:44\r
+    /// 2,,EGOIST,,04:15:44,\r
+    /// ");
+    /// # }
+    /// ```
+    pub fn to_csv(&self, mut w: impl io::Write) -> io::Result<()> {
+        write_row(&mut w, HEADER.iter().copied())?;
+        let album_performer = self.performer().first().map(String::as_str);
+        for span in self.timeline() {
+            let track = &self.files[span.file].tracks[span.track];
+            let time = |t: Option<_>| t.map(|t| format!("{}", t)).unwrap_or_default();
+            let (start, duration) = (time(span.start), time(span.length));
+            let number = track.id().to_string();
+            let row = [
+                number.as_str(),
+                track.title().first().map_or("", String::as_str),
+                track
+                    .performer()
+                    .first()
+                    .map(String::as_str)
+                    .or(album_performer)
+                    .unwrap_or(""),
+                track.isrc().unwrap_or(""),
+                &start,
+                &duration,
+            ];
+            write_row(&mut w, row.iter().copied())?;
+        }
+        Ok(())
+    }
+}
+
+fn write_row<'a>(w: &mut impl io::Write, fields: impl Iterator<Item = &'a str>) -> io::Result<()> {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            w.write_all(b",")?;
+        }
+        if field.contains(&[',', '"', '\r', '\n'][..]) {
+            write!(w, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            w.write_all(field.as_bytes())?;
+        }
+    }
+    w.write_all(b"\r\n")
+}
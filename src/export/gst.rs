@@ -0,0 +1,121 @@
+//! A table of contents shaped like a GStreamer `GstToc`
+//!
+//! Each `FILE` becomes an edition entry and each `TRACK` a chapter entry in it,
+//! so mapping to `gstreamer::Toc` is one `TocEntry::new()` and `set_start_stop_times()` per entry
+use crate::Cuna;
+use std::time::Duration;
+
+/// Mirrors `GstTocEntryType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntryKind {
+    Edition,
+    Chapter,
+}
+/// Tags of an entry, named after the GStreamer tags they map to
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Tags {
+    /// `GST_TAG_TITLE`
+    pub title: Option<String>,
+    /// `GST_TAG_ARTIST`
+    pub artist: Option<String>,
+    /// `GST_TAG_ISRC`
+    pub isrc: Option<String>,
+    /// `GST_TAG_TRACK_NUMBER`
+    pub track_number: Option<u32>,
+}
+/// Mirrors `GstTocEntry`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TocEntry {
+    pub kind: EntryKind,
+    pub uid: String,
+    pub start: Option<Duration>,
+    /// None for the last chapter of a `FILE`, whose end is not in the cue sheet
+    pub stop: Option<Duration>,
+    pub tags: Tags,
+    pub children: Vec<TocEntry>,
+}
+/// Mirrors a `GstToc` with global scope
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Toc {
+    pub tags: Tags,
+    pub entries: Vec<TocEntry>,
+}
+
+impl TocEntry {
+    /// Returns start and stop in nanoseconds as `gst_toc_entry_set_start_stop_times()` takes them, -1 if unknown
+    pub fn start_stop_ns(&self) -> (i64, i64) {
+        let ns = |d: Option<Duration>| d.map_or(-1, |d| d.as_nanos() as i64);
+        (ns(self.start), ns(self.stop))
+    }
+}
+/// Builds the table of contents of a cue sheet
+///
+/// ```rust
+/// # #[cfg(feature = "parser")] {
+/// use cuna::Cuna;
+/// use cuna::export::gst::{self, EntryKind};
+/// use std::time::Duration;
+///
+/// let sheet = Cuna::new(r#"PERFORMER "EGOIST"
+/// FILE "a.flac" WAVE
+///   TRACK 01 AUDIO
+///     TITLE "Departures"
+///     INDEX 01 00:00:00
+///   TRACK 02 AUDIO
+///     INDEX 01 04:15:00"#).unwrap();
+/// let toc = gst::toc(&sheet);
+/// let edition = &toc.entries[0];
+/// assert_eq!(edition.kind, EntryKind::Edition);
+/// assert_eq!(edition.children[0].stop, Some(Duration::from_secs(255)));
+/// assert_eq!(edition.children[0].tags.artist.as_deref(), Some("EGOIST"));
+/// assert_eq!(edition.children[1].start_stop_ns(), (255_000_000_000, -1));
+/// # }
+/// ```
+pub fn toc(sheet: &Cuna) -> Toc {
+    let album_artist = sheet.performer().first();
+    let entries = sheet
+        .files()
+        .iter()
+        .enumerate()
+        .map(|(i, file)| {
+            let starts: Vec<_> = file.tracks.iter().map(|tk| tk.start()).collect();
+            let children = file
+                .tracks
+                .iter()
+                .enumerate()
+                .map(|(j, track)| TocEntry {
+                    kind: EntryKind::Chapter,
+                    uid: format!("{}/{:02}", i, track.id()),
+                    start: starts[j].map(Duration::from),
+                    stop: starts.get(j + 1).copied().flatten().map(Duration::from),
+                    tags: Tags {
+                        title: track.title().first().cloned(),
+                        artist: track.performer().first().or(album_artist).cloned(),
+                        isrc: track.isrc().map(str::to_owned),
+                        track_number: Some(track.id().into()),
+                    },
+                    children: Vec::new(),
+                })
+                .collect();
+            TocEntry {
+                kind: EntryKind::Edition,
+                uid: i.to_string(),
+                start: None,
+                stop: None,
+                tags: Tags {
+                    title: Some(file.name.clone()),
+                    ..Tags::default()
+                },
+                children,
+            }
+        })
+        .collect();
+    Toc {
+        tags: Tags {
+            title: sheet.title().first().cloned(),
+            artist: album_artist.cloned(),
+            ..Tags::default()
+        },
+        entries,
+    }
+}
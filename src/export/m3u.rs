@@ -0,0 +1,93 @@
+//! Extended M3U playlists
+//!
+//! Each `TRACK` becomes an `#EXTINF` entry pointing to its `FILE`, with the duration from the
+//! [`timeline`](crate::timeline). The output is UTF-8, so it can be saved as `.m3u8` as is.
+use crate::time::TimeStamp;
+use crate::Cuna;
+use std::fmt;
+use std::time::Duration;
+
+impl Cuna {
+    /// Writes an extended M3U playlist, the duration of the last track of each `FILE` is `-1` (unknown)
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::new(r#"PERFORMER "EGOIST"
+    /// TITLE "Departures"
+    /// FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO
+    ///     TITLE "Departures"
+    ///     INDEX 01 00:00:00
+    ///   TRACK 02 AUDIO
+    ///     PERFORMER "supercell"
+    ///     INDEX 01 04:15:44"#).unwrap();
+    /// assert_eq!(sheet.to_m3u(), "#EXTM3U
+    /// #PLAYLIST:Departures
+    /// #EXTINF:256,EGOIST - Departures
+    /// a.flac
+    /// #EXTINF:-1,supercell - Track 02
+    /// a.flac
+    /// ");
+    /// # }
+    /// ```
+    pub fn write_m3u(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        self.write_m3u_with_lengths(&[], w)
+    }
+    /// Same as [`Cuna::write_m3u()`](Cuna::write_m3u), with the lengths of the `FILE`s in the order they appear
+    /// so the last tracks have a duration too
+    pub fn write_m3u_with_lengths(
+        &self,
+        file_lengths: &[TimeStamp],
+        w: &mut impl fmt::Write,
+    ) -> fmt::Result {
+        writeln!(w, "#EXTM3U")?;
+        if let Some(title) = self.title().first() {
+            writeln!(w, "#PLAYLIST:{}", OneLine(title))?;
+        }
+        let album_performer = self.performer().first();
+        for span in self.timeline_with_lengths(file_lengths) {
+            let file = &self.files[span.file];
+            let track = &file.tracks[span.track];
+            let seconds = match span.length {
+                // rounded to the nearest second
+                Some(length) => ((Duration::from(length).as_millis() + 500) / 1000) as i64,
+                None => -1,
+            };
+            write!(w, "#EXTINF:{},", seconds)?;
+            if let Some(performer) = track.performer().first().or(album_performer) {
+                write!(w, "{} - ", OneLine(performer))?;
+            }
+            match track.title().first() {
+                Some(title) => writeln!(w, "{}", OneLine(title))?,
+                None => writeln!(w, "Track {:02}", track.id())?,
+            }
+            writeln!(w, "{}", OneLine(&file.name))?;
+        }
+        Ok(())
+    }
+    /// Returns an extended M3U playlist, see [`Cuna::write_m3u()`](Cuna::write_m3u)
+    pub fn to_m3u(&self) -> String {
+        let mut s = String::new();
+        self.write_m3u(&mut s)
+            .expect("writing to a String never fails");
+        s
+    }
+}
+
+/// Displays a str with line breaks and other control characters as spaces
+struct OneLine<'a>(&'a str);
+
+impl fmt::Display for OneLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0
+            .split(char::is_control)
+            .enumerate()
+            .try_for_each(|(i, part)| {
+                if i > 0 {
+                    f.write_str(" ")?;
+                }
+                f.write_str(part)
+            })
+    }
+}
@@ -0,0 +1,314 @@
+//! Cue sheets as flat rows of a disc, its tracks and their indices, for relational databases
+//!
+//! The columns of each table are listed in `COLUMNS` and only change like the public API does.
+//! Times are numbers of frames (1/75 second), and tracks are keyed by their position in the sheet
+//! starting from 1, since the numbers of `TRACK`s may repeat in broken sheets.
+//! With the `sqlite` feature, [`SCHEMA`] creates the tables and [`Rows::insert()`] fills them
+//! with [rusqlite](https://docs.rs/rusqlite).
+//!
+//! ```rust
+//! # #[cfg(feature = "parser")] {
+//! use cuna::Cuna;
+//! let sheet = Cuna::new(r#"REM GENRE Pop
+//! PERFORMER "EGOIST"
+//! FILE "a.flac" WAVE
+//!   TRACK 01 AUDIO
+//!     TITLE "Departures"
+//!     INDEX 01 00:00:00
+//!   TRACK 02 AUDIO
+//!     INDEX 00 04:13:44
+//!     INDEX 01 04:15:44"#).unwrap();
+//! let rows = sheet.to_rows();
+//! assert_eq!(rows.disc.genre.as_deref(), Some("Pop"));
+//! assert_eq!(rows.tracks[0].title.as_deref(), Some("Departures"));
+//! assert_eq!(rows.tracks[0].length, Some(19169));
+//! assert_eq!(rows.indices.len(), 3);
+//! assert_eq!((rows.indices[1].track, rows.indices[1].number), (2, 0));
+//! # }
+//! ```
+use crate::comment;
+use crate::Cuna;
+
+/// The row of the disc, which the `REM`s and the header describe
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DiscRow {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub songwriter: Option<String>,
+    pub catalog: Option<u64>,
+    pub cdtextfile: Option<String>,
+    pub genre: Option<String>,
+    pub date: Option<String>,
+    pub discid: Option<String>,
+    pub comment: Option<String>,
+}
+/// The row of a track
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct TrackRow {
+    /// The position of the track in the sheet, starting from 1
+    pub position: usize,
+    pub number: u8,
+    /// The name of the `FILE` of the track
+    pub file: String,
+    /// The format of the `FILE`, like `WAVE`
+    pub file_format: String,
+    /// The format of the track, like `AUDIO`
+    pub format: String,
+    pub title: Option<String>,
+    /// The `PERFORMER` of the track, not of the disc
+    pub performer: Option<String>,
+    pub songwriter: Option<String>,
+    pub isrc: Option<String>,
+    /// The flags separated by spaces
+    pub flags: Option<String>,
+    pub pregap: Option<u32>,
+    pub postgap: Option<u32>,
+    /// Where the track starts in its `FILE`, see [`Cuna::timeline()`](crate::Cuna::timeline)
+    pub start: Option<u32>,
+    /// None for the last track of each `FILE`
+    pub length: Option<u32>,
+}
+/// The row of an `INDEX`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct IndexRow {
+    /// The [`position`](TrackRow::position) of the track
+    pub track: usize,
+    pub number: u8,
+    pub time: u32,
+}
+/// The rows of a cue sheet, see [`rows`](crate::export::rows)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Rows {
+    pub disc: DiscRow,
+    pub tracks: Vec<TrackRow>,
+    pub indices: Vec<IndexRow>,
+}
+
+/// Creates the tables `discs`, `tracks` and `indices` if they do not exist,
+/// `tracks` and `indices` refer to the `id` of `discs` with `disc`
+pub const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS discs (
+    id INTEGER PRIMARY KEY,
+    title TEXT,
+    performer TEXT,
+    songwriter TEXT,
+    catalog INTEGER,
+    cdtextfile TEXT,
+    genre TEXT,
+    date TEXT,
+    discid TEXT,
+    comment TEXT
+);
+CREATE TABLE IF NOT EXISTS tracks (
+    disc INTEGER NOT NULL REFERENCES discs (id),
+    position INTEGER NOT NULL,
+    number INTEGER NOT NULL,
+    file TEXT NOT NULL,
+    file_format TEXT NOT NULL,
+    format TEXT NOT NULL,
+    title TEXT,
+    performer TEXT,
+    songwriter TEXT,
+    isrc TEXT,
+    flags TEXT,
+    pregap INTEGER,
+    postgap INTEGER,
+    start INTEGER,
+    length INTEGER,
+    PRIMARY KEY (disc, position)
+);
+CREATE TABLE IF NOT EXISTS indices (
+    disc INTEGER NOT NULL REFERENCES discs (id),
+    track INTEGER NOT NULL,
+    number INTEGER NOT NULL,
+    time INTEGER NOT NULL
+);
+";
+
+impl DiscRow {
+    pub const COLUMNS: &'static [&'static str] = &[
+        "title",
+        "performer",
+        "songwriter",
+        "catalog",
+        "cdtextfile",
+        "genre",
+        "date",
+        "discid",
+        "comment",
+    ];
+}
+impl TrackRow {
+    pub const COLUMNS: &'static [&'static str] = &[
+        "position",
+        "number",
+        "file",
+        "file_format",
+        "format",
+        "title",
+        "performer",
+        "songwriter",
+        "isrc",
+        "flags",
+        "pregap",
+        "postgap",
+        "start",
+        "length",
+    ];
+}
+impl IndexRow {
+    pub const COLUMNS: &'static [&'static str] = &["track", "number", "time"];
+}
+
+impl Cuna {
+    /// Flattens the sheet into rows, see [`rows`](crate::export::rows)
+    ///
+    /// Only the first of repeated `TITLE`, `PERFORMER` and `SONGWRITER` values is kept
+    pub fn to_rows(&self) -> Rows {
+        let first = |values: &[String]| values.first().cloned();
+        let rem = |key| self.comments.get(key).map(str::to_owned);
+        let disc = DiscRow {
+            title: first(self.title()),
+            performer: first(self.performer()),
+            songwriter: first(self.songwriter()),
+            catalog: self.catalog(),
+            cdtextfile: self.header.cdtextfile.clone(),
+            genre: rem(comment::GENRE),
+            date: rem(comment::DATE),
+            discid: rem(comment::DISCID),
+            comment: rem(comment::COMMENT),
+        };
+        let mut rows = Rows {
+            disc,
+            ..Rows::default()
+        };
+        for (i, span) in self.timeline().into_iter().enumerate() {
+            let file = &self.files[span.file];
+            let track = &file.tracks[span.track];
+            let position = i + 1;
+            let flags: Vec<_> = track.flags_trimmed().collect();
+            rows.tracks.push(TrackRow {
+                position,
+                number: track.id(),
+                file: file.name.clone(),
+                file_format: file.format.clone(),
+                format: track.format.clone(),
+                title: first(track.title()),
+                performer: first(track.performer()),
+                songwriter: first(track.songwriter()),
+                isrc: track.isrc().map(str::to_owned),
+                flags: Some(flags.join(" ")).filter(|flags| !flags.is_empty()),
+                pregap: track.pregap().map(|t| t.as_frames()),
+                postgap: track.postgap().map(|t| t.as_frames()),
+                start: span.start.map(|t| t.as_frames()),
+                length: span.length.map(|t| t.as_frames()),
+            });
+            rows.indices
+                .extend(track.index.iter().map(|index| IndexRow {
+                    track: position,
+                    number: index.id(),
+                    time: index.begin_time.as_frames(),
+                }));
+        }
+        rows
+    }
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::*;
+    use rusqlite::{Connection, ToSql};
+
+    fn insert_into(table: &str, columns: &[&str]) -> String {
+        let placeholders = vec!["?"; columns.len() + 1].join(", ");
+        format!(
+            "INSERT INTO {} (disc, {}) VALUES ({})",
+            table,
+            columns.join(", "),
+            placeholders
+        )
+    }
+
+    impl DiscRow {
+        /// Returns the values in the order of [`COLUMNS`](DiscRow::COLUMNS) to bind to a statement
+        pub fn params(&self) -> [&dyn ToSql; 9] {
+            [
+                &self.title,
+                &self.performer,
+                &self.songwriter,
+                &self.catalog,
+                &self.cdtextfile,
+                &self.genre,
+                &self.date,
+                &self.discid,
+                &self.comment,
+            ]
+        }
+    }
+    impl TrackRow {
+        /// Returns the values in the order of [`COLUMNS`](TrackRow::COLUMNS) to bind to a statement
+        pub fn params(&self) -> [&dyn ToSql; 14] {
+            [
+                &self.position,
+                &self.number,
+                &self.file,
+                &self.file_format,
+                &self.format,
+                &self.title,
+                &self.performer,
+                &self.songwriter,
+                &self.isrc,
+                &self.flags,
+                &self.pregap,
+                &self.postgap,
+                &self.start,
+                &self.length,
+            ]
+        }
+    }
+    impl IndexRow {
+        /// Returns the values in the order of [`COLUMNS`](IndexRow::COLUMNS) to bind to a statement
+        pub fn params(&self) -> [&dyn ToSql; 3] {
+            [&self.track, &self.number, &self.time]
+        }
+    }
+    impl Rows {
+        /// Inserts the rows into the tables of [`SCHEMA`], returning the `id` of the disc
+        ///
+        /// ```rust
+        /// use cuna::export::rows::SCHEMA;
+        /// use cuna::Cuna;
+        /// use rusqlite::Connection;
+        ///
+        /// let conn = Connection::open_in_memory().unwrap();
+        /// conn.execute_batch(SCHEMA).unwrap();
+        /// let sheet = Cuna::new("FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00").unwrap();
+        /// let disc = sheet.to_rows().insert(&conn).unwrap();
+        /// let file: String = conn
+        ///     .query_row("SELECT file FROM tracks WHERE disc = ?", [disc], |row| row.get(0))
+        ///     .unwrap();
+        /// assert_eq!(file, "a.flac");
+        /// ```
+        pub fn insert(&self, conn: &Connection) -> rusqlite::Result<i64> {
+            let columns = DiscRow::COLUMNS.join(", ");
+            let placeholders = vec!["?"; DiscRow::COLUMNS.len()].join(", ");
+            conn.execute(
+                &format!("INSERT INTO discs ({}) VALUES ({})", columns, placeholders),
+                &self.disc.params()[..],
+            )?;
+            let disc = conn.last_insert_rowid();
+            let mut insert = conn.prepare(&insert_into("tracks", TrackRow::COLUMNS))?;
+            for track in &self.tracks {
+                let mut params: Vec<&dyn ToSql> = vec![&disc];
+                params.extend_from_slice(&track.params());
+                insert.execute(&params[..])?;
+            }
+            let mut insert = conn.prepare(&insert_into("indices", IndexRow::COLUMNS))?;
+            for index in &self.indices {
+                let mut params: Vec<&dyn ToSql> = vec![&disc];
+                params.extend_from_slice(&index.params());
+                insert.execute(&params[..])?;
+            }
+            Ok(disc)
+        }
+    }
+}
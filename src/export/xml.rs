@@ -0,0 +1,177 @@
+//! Cue sheets as XML, requires the `xml` feature
+//!
+//! The document mirrors the sheet:
+//!
+//! ``` xml
+//! <?xml version="1.0" encoding="UTF-8"?>
+//! <cuesheet>
+//!   <header>
+//!     <title>...</title>
+//!     <performer>...</performer>
+//!     <songwriter>...</songwriter>
+//!     <catalog>...</catalog>
+//!     <cdtextfile>...</cdtextfile>
+//!   </header>
+//!   <rem>...</rem>
+//!   <file name="..." format="WAVE">
+//!     <rem>...</rem>
+//!     <track number="01" format="AUDIO">
+//!       <title>...</title>
+//!       <rem>...</rem>
+//!       <pregap>00:00:00</pregap>
+//!       <index number="01">00:00:00</index>
+//!       <flag>DCP</flag>
+//!     </track>
+//!   </file>
+//! </cuesheet>
+//! ```
+//!
+//! Elements of missing values are omitted and repeated commands become repeated elements.
+//! Characters which are not allowed in XML 1.0 are dropped.
+use crate::track::Track;
+use crate::Cuna;
+use std::fmt;
+use std::fmt::Write;
+
+impl Cuna {
+    /// Writes the cue sheet as an XML document
+    pub fn write_xml(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(w, "<cuesheet>")?;
+        writeln!(w, "  <header>")?;
+        let header = &self.header;
+        elements(w, 2, "title", &header.title)?;
+        elements(w, 2, "performer", &header.performer)?;
+        elements(w, 2, "songwriter", &header.songwriter)?;
+        elements(w, 2, "arranger", &header.arranger)?;
+        elements(w, 2, "composer", &header.composer)?;
+        elements(w, 2, "message", &header.message)?;
+        if let Some(catalog) = header.catalog_code() {
+            element(w, 2, "catalog", catalog.as_str())?;
+        }
+        if let Some(cdtextfile) = header.cdtextfile() {
+            element(w, 2, "cdtextfile", cdtextfile)?;
+        }
+        writeln!(w, "  </header>")?;
+        elements(w, 1, "rem", &self.comments)?;
+        for file in &self.files {
+            writeln!(
+                w,
+                r#"  <file name="{}" format="{}">"#,
+                Escaped(&file.name),
+                Escaped(&file.format)
+            )?;
+            elements(w, 2, "rem", file.comments())?;
+            for track in file {
+                write_track(w, track)?;
+            }
+            writeln!(w, "  </file>")?;
+        }
+        writeln!(w, "</cuesheet>")
+    }
+    /// Returns the cue sheet as an XML document
+    ///
+    /// ```rust
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::new(r#"TITLE "Departures & Co"
+    /// FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO
+    ///     INDEX 01 00:00:00"#).unwrap();
+    /// assert_eq!(sheet.to_xml(), r#"<?xml version="1.0" encoding="UTF-8"?>
+    /// <cuesheet>
+    ///   <header>
+    ///     <title>Departures &amp; Co</title>
+    ///   </header>
+    ///   <file name="a.flac" format="WAVE">
+    ///     <track number="01" format="AUDIO">
+    ///       <index number="01">00:00:00</index>
+    ///     </track>
+    ///   </file>
+    /// </cuesheet>
+    /// "#);
+    /// ```
+    pub fn to_xml(&self) -> String {
+        let mut s = String::new();
+        self.write_xml(&mut s)
+            .expect("writing to a String never fails");
+        s
+    }
+}
+
+fn write_track(w: &mut impl fmt::Write, track: &Track) -> fmt::Result {
+    writeln!(
+        w,
+        r#"    <track number="{:02}" format="{}">"#,
+        track.id(),
+        Escaped(track.format())
+    )?;
+    elements(w, 3, "title", track.title())?;
+    elements(w, 3, "performer", track.performer())?;
+    elements(w, 3, "songwriter", track.songwriter())?;
+    elements(w, 3, "arranger", track.arranger())?;
+    elements(w, 3, "composer", track.composer())?;
+    elements(w, 3, "message", track.message())?;
+    elements(w, 3, "rem", track.comments())?;
+    if let Some(isrc) = track.isrc() {
+        element(w, 3, "isrc", isrc)?;
+    }
+    elements(w, 3, "flag", track.flags())?;
+    if let Some(pregap) = track.pregap() {
+        element(w, 3, "pregap", &pregap.to_string())?;
+    }
+    for index in &track.index {
+        writeln!(
+            w,
+            r#"      <index number="{}">{}</index>"#,
+            index.number(),
+            index.begin_time()
+        )?;
+    }
+    if let Some(postgap) = track.postgap() {
+        element(w, 3, "postgap", &postgap.to_string())?;
+    }
+    writeln!(w, "    </track>")
+}
+
+fn element(w: &mut impl fmt::Write, depth: usize, name: &str, value: &str) -> fmt::Result {
+    writeln!(
+        w,
+        "{:indent$}<{name}>{}</{name}>",
+        "",
+        Escaped(value),
+        indent = depth * 2,
+        name = name
+    )
+}
+
+fn elements<'a>(
+    w: &mut impl fmt::Write,
+    depth: usize,
+    name: &str,
+    values: impl IntoIterator<Item = &'a String>,
+) -> fmt::Result {
+    values
+        .into_iter()
+        .try_for_each(|value| element(w, depth, name, value))
+}
+
+/// Escapes text for both element content and attribute values
+struct Escaped<'a>(&'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '&' => f.write_str("&amp;")?,
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '"' => f.write_str("&quot;")?,
+                '\'' => f.write_str("&apos;")?,
+                '\t' | '\n' | '\r' => f.write_char(c)?,
+                c if c < ' ' || c == '\u{fffe}' || c == '\u{ffff}' => {}
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
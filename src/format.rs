@@ -0,0 +1,46 @@
+//! A canonical layout of cue sheets, like `rustfmt` for cue files
+//!
+//! The canonical layout is the one of [`CueWriter::new()`](crate::writer::CueWriter::new):
+//! uppercase keywords, quoted values, 2 spaces of indentation for each level and numbers padded to 2 digits.
+//! File types, track modes and flags are uppercased as well, since they are case-insensitive keywords.
+//! Quoted values are kept as they are, so the meaning of the sheet never changes.
+#[cfg(feature = "parser")]
+use crate::error::Error;
+use crate::writer::CueWriter;
+use crate::Cuna;
+
+/// Parses a cue sheet and writes it in the canonical layout, requires the `parser` feature
+///
+/// Formatting is idempotent, canonicalizing the output again returns the same string
+///
+/// ```rust
+/// let cue = "file \"a.flac\" wave\ntrack 01 audio\n\tflags dcp\n\tindex 01 00:00:00";
+/// assert_eq!(cuna::format::canonicalize(cue).unwrap(), r#"FILE "a.flac" WAVE
+///   TRACK 01 AUDIO
+///     FLAGS DCP
+///     INDEX 01 00:00:00
+/// "#);
+/// ```
+#[cfg(feature = "parser")]
+pub fn canonicalize(s: &str) -> Result<String, Error> {
+    Ok(Cuna::new(s)?.format_canonical())
+}
+
+impl Cuna {
+    /// Returns the cue sheet in the canonical layout, see [`format`](crate::format)
+    pub fn format_canonical(&self) -> String {
+        let mut sheet = self.clone();
+        for file in &mut sheet.files {
+            file.format.make_ascii_uppercase();
+            for track in &mut file.tracks {
+                track.format.make_ascii_uppercase();
+                track.flags.retain(|flag| !flag.is_empty());
+                track
+                    .flags
+                    .iter_mut()
+                    .for_each(|flag| flag.make_ascii_uppercase());
+            }
+        }
+        CueWriter::new().format(&sheet)
+    }
+}
@@ -1,12 +1,137 @@
 use crate::error::ParseError;
+use crate::track::UnknownLines;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use core::str::FromStr;
 
+/// The commands before the first `FILE`
+///
+/// The `REM`s before the first `FILE` are not part of the header but of the sheet, in
+/// [`Cuna::comments`](crate::Cuna::comments), along with the header keys written later.
+/// [`Comment::standard_rem_block()`](crate::comment::Comment::standard_rem_block) orders them
+/// like EAC, which the writer keeps
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Header {
     pub title: Vec<String>,
     pub performer: Vec<String>,
     pub songwriter: Vec<String>,
-    pub(crate) catalog: Option<u64>,
+    /// The CD-Text `ARRANGER`s, written by cdrdao
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub arranger: Vec<String>,
+    /// The CD-Text `COMPOSER`s, written by cdrdao
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub composer: Vec<String>,
+    /// The CD-Text `MESSAGE`s, written by cdrdao
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub message: Vec<String>,
+    pub(crate) catalog: Option<Catalog>,
     pub cdtextfile: Option<String>,
+    /// Lines with commands the parser does not know before the first `FILE`,
+    /// see [`ParseOptions::keep_unknown`](crate::parser::ParseOptions::keep_unknown)
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "UnknownLines::is_empty")
+    )]
+    pub unknown: UnknownLines,
+}
+/// The UPC/EAN code of `CATALOG`, 13 digits which may start with zeros
+///
+/// With the `serde` feature it is (de)serialized as the 13-digit string, keeping the zeros
+///
+/// ```rust
+/// use cuna::header::Catalog;
+/// let catalog: Catalog = "0075678164125".parse().unwrap();
+/// assert_eq!(catalog.value(), 75678164125);
+/// assert_eq!(catalog.to_string(), "0075678164125");
+/// assert!(catalog.is_valid_ean());
+/// assert_eq!(Catalog::from_value(75678164125), Some(catalog));
+/// assert!("75678164125".parse::<Catalog>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "String", try_from = "String")
+)]
+pub struct Catalog([u8; 13]);
+
+impl Catalog {
+    /// Returns the catalog of a number, padded with zeros to 13 digits,
+    /// or None if it has more than 13 digits
+    pub fn from_value(value: u64) -> Option<Self> {
+        format!("{:013}", value).parse().ok()
+    }
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.0).expect("a catalog is ASCII digits")
+    }
+    /// The digits as a number, without the leading zeros
+    pub fn value(&self) -> u64 {
+        self.0
+            .iter()
+            .fold(0, |value, d| value * 10 + u64::from(d - b'0'))
+    }
+    /// Returns true if the last digit is the EAN-13 check digit of the others
+    pub fn is_valid_ean(&self) -> bool {
+        let sum: u32 = self.0[..12]
+            .iter()
+            .enumerate()
+            .map(|(i, d)| u32::from(d - b'0') * if i % 2 == 0 { 1 } else { 3 })
+            .sum();
+        (10 - sum % 10) % 10 == u32::from(self.0[12] - b'0')
+    }
+}
+impl FromStr for Catalog {
+    type Err = ParseError;
+
+    /// Parses exactly 13 ASCII digits
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match <[u8; 13]>::try_from(s.as_bytes()) {
+            Ok(digits) if digits.iter().all(u8::is_ascii_digit) => Ok(Self(digits)),
+            _ => Err(ParseError::syntax_error(s, "invalid catalog")),
+        }
+    }
+}
+impl TryFrom<u64> for Catalog {
+    type Error = ParseError;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        Self::from_value(value).ok_or_else(|| ParseError::syntax_error(value, "invalid catalog"))
+    }
+}
+impl TryFrom<String> for Catalog {
+    type Error = ParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+impl From<Catalog> for String {
+    fn from(catalog: Catalog) -> Self {
+        catalog.as_str().into()
+    }
+}
+impl From<Catalog> for u64 {
+    fn from(catalog: Catalog) -> Self {
+        catalog.value()
+    }
+}
+impl fmt::Display for Catalog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 impl Header {
@@ -37,16 +162,104 @@ impl Header {
     pub fn push_songwriter(&mut self, songwriter: String) {
         self.songwriter.push(songwriter)
     }
+    pub fn arranger(&self) -> &Vec<String> {
+        &self.arranger
+    }
+    pub fn arranger_mut(&mut self) -> &mut Vec<String> {
+        &mut self.arranger
+    }
+    pub fn push_arranger(&mut self, arranger: String) {
+        self.arranger.push(arranger)
+    }
+    pub fn composer(&self) -> &Vec<String> {
+        &self.composer
+    }
+    pub fn composer_mut(&mut self) -> &mut Vec<String> {
+        &mut self.composer
+    }
+    pub fn push_composer(&mut self, composer: String) {
+        self.composer.push(composer)
+    }
+    pub fn message(&self) -> &Vec<String> {
+        &self.message
+    }
+    pub fn message_mut(&mut self) -> &mut Vec<String> {
+        &mut self.message
+    }
+    pub fn push_message(&mut self, message: String) {
+        self.message.push(message)
+    }
+    /// Returns the `CATALOG` as a number, see [`catalog_code()`](Header::catalog_code)
+    /// for its leading zeros
     pub fn catalog(&self) -> Option<u64> {
+        self.catalog.map(|c| c.value())
+    }
+    pub fn catalog_code(&self) -> Option<Catalog> {
         self.catalog
     }
+    /// Removes every command, keeping the allocated capacity of the lists
+    pub fn clear(&mut self) {
+        self.title.clear();
+        self.performer.clear();
+        self.songwriter.clear();
+        self.arranger.clear();
+        self.composer.clear();
+        self.message.clear();
+        self.catalog = None;
+        self.cdtextfile = None;
+        self.unknown.clear();
+    }
+    /// Sets the `CATALOG` from a number of 13 digits, use [`set_catalog_code()`](Header::set_catalog_code)
+    /// for a code starting with zeros
     pub fn set_catalog(&mut self, catalog: u64) -> Result<Option<u64>, ParseError> {
         if len(catalog) == 13 {
-            Ok(self.catalog.replace(catalog))
+            let old = self.catalog();
+            self.catalog = Catalog::from_value(catalog);
+            Ok(old)
         } else {
             Err(ParseError::syntax_error(catalog, "invalid catalog"))
         }
     }
+    pub fn set_catalog_code(&mut self, catalog: Catalog) -> Option<Catalog> {
+        self.catalog.replace(catalog)
+    }
+    /// Consumes the header and returns it with a new `TITLE`, for building a header in one expression
+    ///
+    /// ```rust
+    /// use cuna::header::Header;
+    /// let header = Header::default()
+    ///     .with_title("Departures")
+    ///     .with_performer("EGOIST")
+    ///     .with_catalog(4547366179934)
+    ///     .unwrap();
+    /// assert_eq!(header.performer(), &["EGOIST".to_owned()]);
+    /// assert_eq!(header.catalog(), Some(4547366179934));
+    /// ```
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.push_title(title.into());
+        self
+    }
+    /// Consumes the header and returns it with a new `PERFORMER`
+    pub fn with_performer(mut self, performer: impl Into<String>) -> Self {
+        self.push_performer(performer.into());
+        self
+    }
+    /// Consumes the header and returns it with a new `SONGWRITER`
+    pub fn with_songwriter(mut self, songwriter: impl Into<String>) -> Self {
+        self.push_songwriter(songwriter.into());
+        self
+    }
+    /// Consumes the header and returns it with the `CATALOG` replaced,
+    /// fails like [`Header::set_catalog()`](Header::set_catalog)
+    pub fn with_catalog(mut self, catalog: u64) -> Result<Self, ParseError> {
+        self.set_catalog(catalog)?;
+        Ok(self)
+    }
+    /// Consumes the header and returns it with the `CDTEXTFILE` replaced
+    pub fn with_cdtextfile(mut self, cdtextfile: impl Into<String>) -> Self {
+        self.cdtextfile = Some(cdtextfile.into());
+        self
+    }
     pub fn cdtextfile(&self) -> Option<&str> {
         self.cdtextfile.as_deref()
     }
@@ -57,5 +270,5 @@ impl Header {
 
 #[inline]
 fn len(d: u64) -> usize {
-    (d as f32).log10() as usize + 1
+    d.checked_ilog10().map_or(1, |n| n as usize + 1)
 }
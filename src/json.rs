@@ -0,0 +1,61 @@
+//! Converting cue sheets from and to JSON, requires the `json` feature
+//!
+//! The JSON shape follows the structure of [`Cuna`], timestamps are `MM:SS:FF` strings,
+//! `catalog` is the 13-digit string with its leading zeros
+//! and `catalog`, `cdtextfile`, `isrc`, `pregap` and `postgap` are `null` when missing.
+//! The `unknown` lines of the header, files and tracks are left out when there are none:
+//!
+//! ```json
+//! {
+//!   "header": {
+//!     "title": ["Departures"],
+//!     "performer": ["EGOIST"],
+//!     "songwriter": [],
+//!     "catalog": "4540774409198",
+//!     "cdtextfile": null
+//!   },
+//!   "files": [{
+//!     "name": "a.flac",
+//!     "format": "WAVE",
+//!     "tracks": [{
+//!       "id": 1,
+//!       "format": "AUDIO",
+//!       "index": [{ "id": 1, "begin_time": "00:00:00" }],
+//!       "pregap": null,
+//!       "postgap": null,
+//!       "title": ["Departures"],
+//!       "performer": [],
+//!       "songwriter": [],
+//!       "isrc": null,
+//!       "flags": []
+//!     }]
+//!   }],
+//!   "comments": ["GENRE Pop"]
+//! }
+//! ```
+//!
+//! Fields are only ever added to this shape in minor versions
+use crate::Cuna;
+
+impl Cuna {
+    /// Serializes the cue sheet as compact JSON
+    ///
+    /// ```rust
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::new("REM GENRE Pop").unwrap();
+    /// let json = sheet.to_json().unwrap();
+    /// assert!(json.ends_with(r#""comments":["GENRE Pop"]}"#));
+    /// assert_eq!(Cuna::from_json(&json).unwrap(), sheet);
+    /// ```
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+    /// Serializes the cue sheet as indented JSON
+    pub fn to_json_pretty(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+    /// Deserializes a cue sheet from JSON
+    pub fn from_json(s: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(s)
+    }
+}
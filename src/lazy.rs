@@ -0,0 +1,201 @@
+//! Parsing the tracks of a sheet only when they are needed
+//!
+//! [`LazySheet::new()`] reads the sheet once to find where the header, each `FILE` and each `TRACK`
+//! are, without parsing their commands. [`LazySheet::track()`] then parses the lines of one track,
+//! so a game image with 99 tracks costs one track when only one is needed.
+//!
+//! ```rust
+//! use cuna::lazy::LazySheet;
+//! use cuna::time::TimeStamp;
+//!
+//! let cue = r#"TITLE "Departures"
+//! FILE "a.bin" BINARY
+//!   TRACK 01 MODE1/2352
+//!     INDEX 01 00:00:00
+//!   TRACK 02 AUDIO
+//!     TITLE "Euterpe"
+//!     INDEX 00 30:00:00
+//!     INDEX 01 30:02:00"#;
+//! let sheet = LazySheet::new(cue).unwrap();
+//! assert_eq!(sheet.tracks().len(), 2);
+//! let at = sheet.find_track(2).unwrap();
+//! let (track, warnings) = sheet.track(at).unwrap();
+//! assert_eq!(track.title(), &["Euterpe".to_owned()]);
+//! assert_eq!(track.start(), Some(TimeStamp::new(30, 2, 0)));
+//! assert!(warnings.is_empty());
+//! assert_eq!(sheet.header().unwrap().0.title(), &["Departures".to_owned()]);
+//! ```
+use crate::comment;
+use crate::cst::line_ranges;
+use crate::error::{Error, Limit, ParseError, Warning};
+use crate::parser::{self, ParseOptions};
+use crate::track::{Track, TrackInfo};
+use crate::trim_utf8_header;
+use crate::Cuna;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Lines of the source found by [`LazySheet::new()`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Section {
+    /// The bytes of [`LazySheet::source()`], from the start of the first line
+    /// to the start of the next section
+    pub range: Range<usize>,
+    /// The number of the first line, starting from 1
+    pub line: usize,
+    /// The position of the `FILE` of a `TRACK`, None for the header, `FILE`s
+    /// and a `TRACK` before any `FILE`
+    pub file: Option<usize>,
+    /// The number of a `TRACK`, None for the header, `FILE`s and a `TRACK` whose number is invalid
+    pub number: Option<u8>,
+}
+/// A cue sheet whose `FILE`s and `TRACK`s are parsed on demand
+#[derive(Debug, Clone)]
+pub struct LazySheet<'a> {
+    source: &'a str,
+    options: ParseOptions,
+    header: Section,
+    /// A `FILE` line and the lines before its first `TRACK`
+    files: Vec<Section>,
+    tracks: Vec<Section>,
+}
+
+impl<'a> LazySheet<'a> {
+    /// Finds the sections of a sheet, see [`lazy`](crate::lazy)
+    pub fn new(s: &'a str) -> Result<Self, Error> {
+        Self::with_options(s, ParseOptions::default())
+    }
+    /// Same as [`new()`](LazySheet::new), parsing with the given options later
+    ///
+    /// The [`Limits`](crate::parser::Limits) are checked on the whole sheet here.
+    /// Errors in commands are only found when their section is parsed
+    pub fn with_options(s: &'a str, options: ParseOptions) -> Result<Self, Error> {
+        let source = trim_utf8_header(s);
+        let limits = options.limits;
+        let mut sheet = Self {
+            source,
+            options,
+            header: Section {
+                range: 0..source.len(),
+                line: 1,
+                file: None,
+                number: None,
+            },
+            files: Vec::new(),
+            tracks: Vec::new(),
+        };
+        for (at, (range, _)) in (1..).zip(line_ranges(source)) {
+            let line = &source[range.clone()];
+            limits.check_line(line, at).map_err(|e| Error::new(e, at))?;
+            let (keyword, _) = comment::split(line);
+            let is_file = keyword.eq_ignore_ascii_case("FILE");
+            let file = if is_file {
+                None
+            } else {
+                sheet.files.len().checked_sub(1)
+            };
+            let section = if is_file {
+                if sheet.files.len() >= limits.max_files {
+                    let e = ParseError::LimitExceeded(Limit::Files(limits.max_files));
+                    return Err(Error::new(e, at));
+                }
+                &mut sheet.files
+            } else if keyword.eq_ignore_ascii_case("TRACK") {
+                if sheet.tracks.len() >= limits.max_tracks {
+                    let e = ParseError::LimitExceeded(Limit::Tracks(limits.max_tracks));
+                    return Err(Error::new(e, at));
+                }
+                &mut sheet.tracks
+            } else {
+                continue;
+            };
+            section.push(Section {
+                range: range.start..source.len(),
+                line: at,
+                file,
+                number: Track::from_str(line.trim()).ok().map(|t| t.id()),
+            });
+        }
+        sheet.close_sections();
+        Ok(sheet)
+    }
+    /// The text the sections point into, without a UTF-8 BOM
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+    /// The lines before the first `FILE` or `TRACK`
+    pub fn header_section(&self) -> &Section {
+        &self.header
+    }
+    pub fn files(&self) -> &[Section] {
+        &self.files
+    }
+    pub fn tracks(&self) -> &[Section] {
+        &self.tracks
+    }
+    /// Returns the position in [`tracks()`](LazySheet::tracks) of the first `TRACK` numbered number
+    pub fn find_track(&self, number: u8) -> Option<usize> {
+        self.tracks.iter().position(|t| t.number == Some(number))
+    }
+    /// Parses the header and the `REM`s before the first `FILE`, with the warnings of their lines
+    pub fn header(&self) -> Result<(Cuna, Vec<Warning>), Error> {
+        let mut sheet = Cuna::default();
+        let warnings = self.parse_section(&self.header, &mut sheet)?;
+        Ok((sheet, warnings))
+    }
+    /// Parses the `FILE` at position at, without its `TRACK`s, with the warnings of its lines
+    ///
+    /// # Panics
+    ///
+    /// Panics if at is out of bounds
+    pub fn file(&self, at: usize) -> Result<(TrackInfo, Vec<Warning>), Error> {
+        let mut sheet = Cuna::default();
+        let warnings = self.parse_section(&self.files[at], &mut sheet)?;
+        let file = sheet.files.pop().expect("a section starts with `FILE`");
+        Ok((file, warnings))
+    }
+    /// Parses the `TRACK` at position at, with the warnings of its lines
+    ///
+    /// `REM`s with the header keys of [`HEADER_KEYS`](crate::comment::HEADER_KEYS) belong
+    /// to the sheet and are dropped with a warning. Like the other sections, the lines are parsed without
+    /// [`ParseOptions::recovery`], so an error in the track is returned
+    ///
+    /// # Panics
+    ///
+    /// Panics if at is out of bounds
+    pub fn track(&self, at: usize) -> Result<(Track, Vec<Warning>), Error> {
+        let section = &self.tracks[at];
+        let mut sheet = Cuna::default();
+        if section.file.is_some() {
+            sheet.push_file(TrackInfo::new(String::new(), String::new()));
+        }
+        let warnings = self.parse_section(section, &mut sheet)?;
+        let track = sheet.last_file_mut().and_then(|file| file.tracks.pop());
+        Ok((track.expect("a section starts with `TRACK`"), warnings))
+    }
+    /// Ends every section where the next one starts
+    fn close_sections(&mut self) {
+        let Self {
+            header,
+            files,
+            tracks,
+            ..
+        } = self;
+        let mut sections: Vec<_> = std::iter::once(header).chain(files).chain(tracks).collect();
+        sections.sort_by_key(|s| s.range.start);
+        for i in 1..sections.len() {
+            sections[i - 1].range.end = sections[i].range.start;
+        }
+    }
+    fn parse_section(&self, section: &Section, sheet: &mut Cuna) -> Result<Vec<Warning>, Error> {
+        let text = &self.source[section.range.clone()];
+        let mut warnings = Vec::new();
+        let mut line_state = parser::LineState::default();
+        for (at, (range, _)) in (section.line..).zip(line_ranges(text)) {
+            let line = &text[range];
+            let options = &self.options;
+            parser::parse_line(line, at, sheet, options, &mut warnings, &mut line_state)?;
+        }
+        Ok(warnings)
+    }
+}
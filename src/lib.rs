@@ -1,15 +1,127 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod anonymize;
+#[cfg(feature = "alloc")]
 pub mod comment;
+#[cfg(feature = "std")]
+pub mod completeness;
+#[cfg(feature = "std")]
+pub mod cst;
+#[cfg(feature = "alloc")]
 pub mod cuna;
+#[cfg(feature = "date")]
+pub mod date;
+#[cfg(feature = "encoding")]
+pub mod decode;
+#[cfg(feature = "std")]
+pub mod dialect;
+#[cfg(feature = "std")]
+pub mod diff;
+#[cfg(feature = "parser")]
+pub mod document;
 pub mod error;
+#[cfg(feature = "unstable")]
+pub mod experimental;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "std")]
+pub mod format;
+#[cfg(feature = "alloc")]
 pub mod header;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "parser")]
+pub mod lazy;
+#[cfg(feature = "std")]
+pub mod message;
+#[cfg(feature = "std")]
+pub mod mojibake;
+#[cfg(feature = "std")]
 pub mod parser;
+#[cfg(feature = "std")]
+pub mod partition;
+#[cfg(feature = "std")]
+pub mod paths;
+#[cfg(feature = "std")]
+pub mod quirks;
+pub mod scan;
+#[cfg(feature = "std")]
+pub mod split;
+pub mod stability;
 pub mod time;
+#[cfg(feature = "std")]
+pub mod timeline;
+#[cfg(feature = "std")]
+pub mod toc;
+#[cfg(feature = "toml")]
+pub mod toml;
+#[cfg(feature = "alloc")]
 pub mod track;
+#[cfg(feature = "std")]
+pub mod transform;
+#[cfg(feature = "parser")]
 pub mod utils;
+#[cfg(feature = "std")]
+pub mod validation;
+#[cfg(feature = "parser")]
+pub mod visit;
+#[cfg(feature = "watch")]
+pub mod watch;
+#[cfg(feature = "std")]
+pub mod writer;
+#[cfg(feature = "yaml")]
+pub mod yaml;
 
+#[cfg(feature = "alloc")]
 pub use crate::cuna::Cuna;
+#[cfg(feature = "alloc")]
 pub use crate::cuna::Cuna as CueSheet;
 
+#[cfg(feature = "parser")]
+use crate::error::Error;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+/// Parses an str as a cue sheet with the default options, a BOM is removed
+///
+/// Same as [`Cuna::new()`](Cuna::new), see [`Cuna::parse_with()`](Cuna::parse_with) for more options
+///
+/// ```rust
+/// let sheet = cuna::parse("TITLE \"Departures\"").unwrap();
+/// assert_eq!(sheet.title(), &["Departures".to_owned()]);
+/// ```
+#[cfg(feature = "parser")]
+pub fn parse(s: &str) -> Result<Cuna, Error> {
+    Cuna::new(s)
+}
+/// Opens a UTF-8 or UTF-16 file and parses it as a cue sheet with the default options
+///
+/// Same as [`Cuna::open()`](Cuna::open)
+#[cfg(feature = "parser")]
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Cuna, Error> {
+    Cuna::open(path)
+}
+/// Writes a cue sheet to a file with the default [`CueWriter`](writer::CueWriter), replacing the file if it exists
+///
+/// ```rust
+/// # #[cfg(feature = "parser")] {
+/// # let path = std::env::temp_dir().join("cuna-write-doctest.cue");
+/// let sheet = cuna::parse("TITLE \"Departures\"").unwrap();
+/// cuna::write(&sheet, &path).unwrap();
+/// assert_eq!(cuna::parse_file(&path).unwrap(), sheet);
+/// # std::fs::remove_file(&path).unwrap();
+/// # }
+/// ```
+#[cfg(feature = "std")]
+pub fn write(sheet: &Cuna, path: impl AsRef<Path>) -> io::Result<()> {
+    std::fs::write(path, writer::CueWriter::new().format(sheet))
+}
+
 /// Returns a str without UTF-8 bom
 ///
 /// ```rust
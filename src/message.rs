@@ -0,0 +1,315 @@
+//! Message ids and parameters of errors and warnings, for translating them
+//!
+//! Every error and warning implements [`Describe`], which returns a [`Message`] with a stable id like
+//! `parse.syntax_error` and its parameters. A [`Catalog`] maps ids to templates where `{name}` is
+//! replaced by the parameter `name`, and [`localize()`] falls back to `Display` for missing ids.
+//!
+//! ```rust
+//! # #[cfg(feature = "parser")] {
+//! use cuna::message::{localize, Describe};
+//! use std::collections::HashMap;
+//!
+//! let err = cuna::parse("FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:80").unwrap_err();
+//! let message = err.message();
+//! assert_eq!(message.id, "argument.malformed_timestamp");
+//! assert_eq!(message.param("component"), Some("frames"));
+//!
+//! let mut catalog = HashMap::new();
+//! catalog.insert("argument.malformed_timestamp", "Zeitstempel ungültig ({component}) in Zeile {line}");
+//! assert_eq!(localize(&err, &catalog), "Zeitstempel ungültig (frames) in Zeile 3");
+//! assert_eq!(localize(&err, &HashMap::<&str, &str>::new()), err.to_string());
+//! # }
+//! ```
+use crate::error::EditError;
+#[cfg(feature = "encoding")]
+use crate::error::EncodeError;
+use crate::error::Error;
+use crate::error::InvalidArgument;
+use crate::error::InvalidValue;
+use crate::error::Limit;
+use crate::error::ParseError;
+use crate::error::TocError;
+use crate::error::ValidationError;
+use crate::error::Warning;
+use crate::error::WarningKind;
+use crate::parser::RecoveryPolicy;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The id of a message with its parameters
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Message {
+    pub id: &'static str,
+    pub params: Vec<(&'static str, String)>,
+}
+/// Errors and warnings which can be described by a [`Message`]
+pub trait Describe {
+    fn message(&self) -> Message;
+}
+/// Templates of messages by id
+pub trait Catalog {
+    /// Returns the template of a message, or None to use the default message
+    fn template(&self, id: &str) -> Option<Cow<'_, str>>;
+}
+
+impl Message {
+    pub fn new(id: &'static str) -> Self {
+        Self {
+            id,
+            params: Vec::new(),
+        }
+    }
+    /// Adds a parameter
+    pub fn with(mut self, name: &'static str, value: impl fmt::Display) -> Self {
+        self.params.push((name, value.to_string()));
+        self
+    }
+    /// Returns the value of a parameter
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| value.as_str())
+    }
+    /// Replaces `{name}` in a template with the parameter `name`, unknown names are kept
+    pub fn fill(&self, template: &str) -> String {
+        let mut filled = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            filled.push_str(&rest[..start]);
+            let name = &rest[start + 1..];
+            match name
+                .find('}')
+                .and_then(|end| Some((self.param(&name[..end])?, end)))
+            {
+                Some((value, end)) => {
+                    filled.push_str(value);
+                    rest = &name[end + 1..];
+                }
+                None => {
+                    filled.push('{');
+                    rest = name;
+                }
+            }
+        }
+        filled.push_str(rest);
+        filled
+    }
+}
+/// Returns the message of an error or a warning from a catalog, or its `Display` if the catalog has no template
+pub fn localize<T: Describe + fmt::Display + ?Sized>(item: &T, catalog: &impl Catalog) -> String {
+    let message = item.message();
+    match catalog.template(message.id) {
+        Some(template) => message.fill(&template),
+        None => item.to_string(),
+    }
+}
+
+impl<K, V> Catalog for HashMap<K, V>
+where
+    K: std::borrow::Borrow<str> + std::hash::Hash + Eq,
+    V: AsRef<str>,
+{
+    fn template(&self, id: &str) -> Option<Cow<'_, str>> {
+        self.get(id)
+            .map(|template| Cow::Borrowed(template.as_ref()))
+    }
+}
+impl<F: Fn(&str) -> Option<String>> Catalog for F {
+    fn template(&self, id: &str) -> Option<Cow<'_, str>> {
+        self(id).map(Cow::Owned)
+    }
+}
+
+impl Describe for InvalidArgument {
+    fn message(&self) -> Message {
+        match self {
+            Self::InvalidTimestamp => Message::new("argument.invalid_timestamp"),
+            Self::MalformedTimestamp { component, column } => {
+                Message::new("argument.malformed_timestamp")
+                    .with("component", component)
+                    .with("column", column)
+            }
+            Self::MissingArgument => Message::new("argument.missing_argument"),
+            Self::InvalidId => Message::new("argument.invalid_id"),
+            Self::MalformedIsrc => Message::new("argument.malformed_isrc"),
+        }
+    }
+}
+impl Describe for Limit {
+    fn message(&self) -> Message {
+        let (id, max) = match self {
+            Self::LineLength(n) => ("limit.line_length", n),
+            Self::Lines(n) => ("limit.lines", n),
+            Self::Files(n) => ("limit.files", n),
+            Self::Tracks(n) => ("limit.tracks", n),
+        };
+        Message::new(id).with("max", max)
+    }
+}
+impl Describe for ParseError {
+    fn message(&self) -> Message {
+        match self {
+            Self::SyntaxError(msg) => Message::new("parse.syntax_error").with("detail", msg),
+            Self::UnexpectedToken(msg) => {
+                Message::new("parse.unexpected_token").with("detail", msg)
+            }
+            Self::InvalidArgument(e) => e.message(),
+            Self::LimitExceeded(limit) => limit.message(),
+            Self::IoError(e) => Message::new("parse.io_error").with("detail", e),
+            Self::UnknownEncoding => Message::new("parse.unknown_encoding"),
+        }
+    }
+}
+impl Describe for Error {
+    /// The message of [`kind()`](Error::kind), with a `line` parameter if the line is known,
+    /// and `file` and `track` parameters if the [`context()`](Error::context) is
+    fn message(&self) -> Message {
+        let mut message = self.kind().message();
+        if let Some(line) = self.pos() {
+            message = message.with("line", line);
+        }
+        if let Some(context) = self.context() {
+            message = message.with("file", &context.file);
+            if let Some(track) = context.track {
+                message = message.with("track", track);
+            }
+        }
+        message
+    }
+}
+impl Describe for ValidationError {
+    fn message(&self) -> Message {
+        match self {
+            Self::MissingStartIndex(track) => {
+                Message::new("validation.missing_start_index").with("track", track)
+            }
+            Self::PostgapBeforeData(track) => {
+                Message::new("validation.postgap_before_data").with("track", track)
+            }
+            Self::InvalidCatalog => Message::new("validation.invalid_catalog"),
+            Self::CatalogCountryMismatch => Message::new("validation.catalog_country_mismatch"),
+            Self::MalformedIsrc(track) => {
+                Message::new("validation.malformed_isrc").with("track", track)
+            }
+            Self::DuplicateIsrc(track) => {
+                Message::new("validation.duplicate_isrc").with("track", track)
+            }
+            Self::IsrcRegistrantMismatch(track) => {
+                Message::new("validation.isrc_registrant_mismatch").with("track", track)
+            }
+            Self::IsrcAfterDate(track) => {
+                Message::new("validation.isrc_after_date").with("track", track)
+            }
+            Self::EmptyFile(file) => Message::new("validation.empty_file").with("file", file),
+            Self::MissingIndex(track) => {
+                Message::new("validation.missing_index").with("track", track)
+            }
+            Self::TrackNotIncreasing(track) => {
+                Message::new("validation.track_not_increasing").with("track", track)
+            }
+            Self::IndexNotIncreasing(track) => {
+                Message::new("validation.index_not_increasing").with("track", track)
+            }
+        }
+    }
+}
+impl Describe for EditError {
+    fn message(&self) -> Message {
+        match self {
+            Self::NoSuchTrack(track) => Message::new("edit.no_such_track").with("track", track),
+            Self::NoSuchIndex { track, index } => Message::new("edit.no_such_index")
+                .with("track", track)
+                .with("index", index),
+            Self::NoSuchLine(at) => Message::new("edit.no_such_line").with("line", at),
+        }
+    }
+}
+#[cfg(feature = "encoding")]
+impl Describe for EncodeError {
+    fn message(&self) -> Message {
+        match self {
+            Self::Unmappable {
+                character,
+                encoding,
+                line,
+                column,
+            } => Message::new("write.unencodable")
+                .with("character", character)
+                .with("encoding", encoding)
+                .with("line", line)
+                .with("column", column),
+            Self::InvalidValue(invalid) => invalid.message(),
+        }
+    }
+}
+impl Describe for TocError {
+    fn message(&self) -> Message {
+        match self {
+            Self::NoTracks => Message::new("toc.no_tracks"),
+            Self::InvalidTrackNumber(track) => {
+                Message::new("toc.invalid_track_number").with("track", track)
+            }
+            Self::NotIncreasing(track) => Message::new("toc.not_increasing").with("track", track),
+        }
+    }
+}
+impl Describe for InvalidValue {
+    fn message(&self) -> Message {
+        Message::new("write.invalid_value")
+            .with("keyword", self.keyword)
+            .with("value", &self.value)
+            .with("character", self.character.escape_debug())
+    }
+}
+impl Describe for WarningKind {
+    fn message(&self) -> Message {
+        match self {
+            Self::MisplacedHeaderRem(rem) => {
+                Message::new("warning.misplaced_header_rem").with("rem", rem)
+            }
+            Self::IgnoredCommand(keyword) => {
+                Message::new("warning.ignored_command").with("keyword", keyword)
+            }
+            Self::Recovered { error, policy } => {
+                let dropped = match policy {
+                    RecoveryPolicy::SkipTrack => "track",
+                    RecoveryPolicy::SkipFile => "file",
+                    _ => "line",
+                };
+                Message::new("warning.recovered")
+                    .with("dropped", dropped)
+                    .with("detail", error)
+            }
+            Self::DecimalTimestamp { timestamp, rounded } => {
+                Message::new("warning.decimal_timestamp")
+                    .with("timestamp", timestamp)
+                    .with("rounded", rounded)
+            }
+            Self::OutOfOrder { keyword, after } => Message::new("warning.out_of_order")
+                .with("keyword", keyword)
+                .with("after", after),
+            Self::DuplicateCommand(command) => {
+                Message::new("warning.duplicate_command").with("command", command)
+            }
+            Self::InvalidBytes { offset, len } => Message::new("warning.invalid_bytes")
+                .with("offset", offset)
+                .with("len", len),
+            Self::InlineComment(comment) => {
+                Message::new("warning.inline_comment").with("comment", comment)
+            }
+            Self::MalformedDate(date) => Message::new("warning.malformed_date").with("date", date),
+        }
+    }
+}
+impl Describe for Warning {
+    /// The message of [`kind()`](Warning::kind), with a `line` parameter if the line is known
+    fn message(&self) -> Message {
+        let message = self.kind().message();
+        match self.pos() {
+            Some(line) => message.with("line", line),
+            None => message,
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Detecting text which was decoded with the wrong encoding
+//!
+//! Rippers and taggers often read UTF-8 or Shift-JIS bytes as Latin-1 / Windows-1252, turning `Café`
+//! into `CafÃ©`. [`Cuna::mojibake()`] finds such titles, performers and songwriters and returns
+//! [`Suggestion`]s with a confidence instead of changing the sheet, so they can be reviewed first.
+//!
+//! ```rust
+//! # #[cfg(feature = "parser")] {
+//! use cuna::mojibake::{Field, Mojibake};
+//! use cuna::Cuna;
+//!
+//! let mut sheet = Cuna::new("PERFORMER \"BeyoncÃ©\"\nTITLE \"Dangerously in Love\"").unwrap();
+//! let suggestions = sheet.mojibake();
+//! assert_eq!(suggestions.len(), 1);
+//! assert_eq!(suggestions[0].field, Field::Performer);
+//! assert_eq!(suggestions[0].kind, Mojibake::Utf8AsLatin1);
+//! assert_eq!(suggestions[0].repaired.as_deref(), Some("Beyoncé"));
+//! assert!(suggestions[0].apply(&mut sheet));
+//! assert_eq!(sheet.performer(), &["Beyoncé".to_owned()]);
+//! # }
+//! ```
+use crate::Cuna;
+
+/// How a text was garbled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mojibake {
+    /// UTF-8 decoded as Latin-1 or Windows-1252
+    Utf8AsLatin1,
+    /// Shift-JIS decoded as Latin-1 or Windows-1252, only detected with the `encoding` feature
+    ShiftJisAsLatin1,
+    /// The text has U+FFFD replacement characters, e.g. Shift-JIS decoded as UTF-8,
+    /// and can not be repaired
+    Replaced,
+}
+/// The field a [`Suggestion`] is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    Title,
+    Performer,
+    Songwriter,
+}
+/// The result of checking a text with [`detect()`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repair {
+    pub kind: Mojibake,
+    /// The repaired text, None if it can not be repaired
+    pub repaired: Option<String>,
+    /// Between 0.0 and 1.0, how likely the text is garbled
+    pub confidence: f32,
+}
+/// A text of a cue sheet which is likely garbled, `track` is None if it belongs to the whole sheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub track: Option<u8>,
+    pub field: Field,
+    /// The position of the text among the values of the field, e.g. 1 for the second `TITLE`
+    pub position: usize,
+    pub original: String,
+    pub kind: Mojibake,
+    pub repaired: Option<String>,
+    pub confidence: f32,
+}
+
+/// Windows-1252 characters which are not at the same code point as the byte, from `0x80` to `0x9f`
+const WINDOWS_1252: [(char, u8); 27] = [
+    ('€', 0x80),
+    ('‚', 0x82),
+    ('ƒ', 0x83),
+    ('„', 0x84),
+    ('…', 0x85),
+    ('†', 0x86),
+    ('‡', 0x87),
+    ('ˆ', 0x88),
+    ('‰', 0x89),
+    ('Š', 0x8a),
+    ('‹', 0x8b),
+    ('Œ', 0x8c),
+    ('Ž', 0x8e),
+    ('‘', 0x91),
+    ('’', 0x92),
+    ('“', 0x93),
+    ('”', 0x94),
+    ('•', 0x95),
+    ('–', 0x96),
+    ('—', 0x97),
+    ('˜', 0x98),
+    ('™', 0x99),
+    ('š', 0x9a),
+    ('›', 0x9b),
+    ('œ', 0x9c),
+    ('ž', 0x9e),
+    ('Ÿ', 0x9f),
+];
+
+/// Checks whether a text looks garbled, returns None if it does not
+///
+/// ```rust
+/// use cuna::mojibake::{detect, Mojibake};
+/// let repair = detect("ã‚¨ã‚¦ãƒ†ãƒ«ãƒš").unwrap();
+/// assert_eq!(repair.kind, Mojibake::Utf8AsLatin1);
+/// assert_eq!(repair.repaired.as_deref(), Some("エウテルペ"));
+/// assert!(repair.confidence > 0.9);
+/// assert_eq!(detect("Café"), None);
+/// ```
+pub fn detect(s: &str) -> Option<Repair> {
+    if s.contains('\u{fffd}') {
+        return Some(Repair {
+            kind: Mojibake::Replaced,
+            repaired: None,
+            confidence: 1.0,
+        });
+    }
+    if s.is_ascii() {
+        return None;
+    }
+    let bytes = latin1_bytes(s)?;
+    if let Ok(repaired) = String::from_utf8(bytes.clone()) {
+        // any valid multi-byte sequence is unlikely by chance,
+        // characters beyond U+07FF (3 or more bytes) even less so
+        let confidence = if repaired.chars().any(|c| c as u32 > 0x7ff) {
+            0.95
+        } else {
+            0.8
+        };
+        return Some(Repair {
+            kind: Mojibake::Utf8AsLatin1,
+            repaired: Some(repaired),
+            confidence,
+        });
+    }
+    shift_jis(&bytes)
+}
+#[cfg(feature = "encoding")]
+fn shift_jis(bytes: &[u8]) -> Option<Repair> {
+    let repaired =
+        encoding_rs::SHIFT_JIS.decode_without_bom_handling_and_without_replacement(bytes)?;
+    let non_ascii = repaired.chars().filter(|c| !c.is_ascii()).count();
+    let japanese = repaired.chars().filter(|&c| is_japanese(c)).count();
+    // Shift-JIS decodes most byte pairs, so require the result to be mostly Japanese
+    if non_ascii == 0 || japanese * 2 < non_ascii {
+        return None;
+    }
+    Some(Repair {
+        kind: Mojibake::ShiftJisAsLatin1,
+        repaired: Some(repaired.into_owned()),
+        confidence: 0.9 * japanese as f32 / non_ascii as f32,
+    })
+}
+#[cfg(not(feature = "encoding"))]
+fn shift_jis(_: &[u8]) -> Option<Repair> {
+    None
+}
+#[cfg(feature = "encoding")]
+fn is_japanese(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' // hiragana and katakana
+        | '\u{4e00}'..='\u{9fff}' // CJK unified ideographs
+        | '\u{ff66}'..='\u{ff9f}' // halfwidth katakana
+        | '\u{3000}'..='\u{303f}' // CJK punctuation
+        | '\u{ff01}'..='\u{ff5e}' // fullwidth forms
+    )
+}
+/// Encodes a text as Windows-1252, or None if a character is not in it
+fn latin1_bytes(s: &str) -> Option<Vec<u8>> {
+    s.chars()
+        .map(|c| match c as u32 {
+            n @ 0..=0xff => Some(n as u8),
+            _ => WINDOWS_1252
+                .iter()
+                .find(|(w, _)| *w == c)
+                .map(|(_, byte)| *byte),
+        })
+        .collect()
+}
+impl Suggestion {
+    /// Replaces the original text with the repaired one, returns false if there is no repair
+    /// or the sheet does not have the original text anymore
+    pub fn apply(&self, sheet: &mut Cuna) -> bool {
+        let repaired = match &self.repaired {
+            Some(repaired) => repaired,
+            None => return false,
+        };
+        let values = match self.track {
+            None => match self.field {
+                Field::Title => sheet.header.title_mut(),
+                Field::Performer => sheet.header.performer_mut(),
+                Field::Songwriter => sheet.header.songwriter_mut(),
+            },
+            Some(id) => {
+                let track = sheet
+                    .files
+                    .iter_mut()
+                    .flat_map(|file| &mut file.tracks)
+                    .find(|track| track.id() == id);
+                match (track, self.field) {
+                    (Some(track), Field::Title) => &mut track.title,
+                    (Some(track), Field::Performer) => &mut track.performer,
+                    (Some(track), Field::Songwriter) => &mut track.songwriter,
+                    (None, _) => return false,
+                }
+            }
+        };
+        match values.get_mut(self.position) {
+            Some(value) if *value == self.original => {
+                *value = repaired.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+impl Cuna {
+    /// Returns the titles, performers and songwriters which look garbled, see [`mojibake`](crate::mojibake)
+    pub fn mojibake(&self) -> Vec<Suggestion> {
+        let header = &self.header;
+        let mut suggestions = Vec::new();
+        check(&mut suggestions, None, Field::Title, header.title());
+        check(&mut suggestions, None, Field::Performer, header.performer());
+        check(
+            &mut suggestions,
+            None,
+            Field::Songwriter,
+            header.songwriter(),
+        );
+        for track in self.tracks() {
+            let id = Some(track.id());
+            check(&mut suggestions, id, Field::Title, track.title());
+            check(&mut suggestions, id, Field::Performer, track.performer());
+            check(&mut suggestions, id, Field::Songwriter, track.songwriter());
+        }
+        suggestions
+    }
+}
+fn check(suggestions: &mut Vec<Suggestion>, track: Option<u8>, field: Field, values: &[String]) {
+    for (position, value) in values.iter().enumerate() {
+        if let Some(repair) = detect(value) {
+            suggestions.push(Suggestion {
+                track,
+                field,
+                position,
+                original: value.clone(),
+                kind: repair.kind,
+                repaired: repair.repaired,
+                confidence: repair.confidence,
+            });
+        }
+    }
+}
@@ -1,33 +1,70 @@
+use crate::comment;
+use crate::cst::unescape;
+#[cfg(feature = "parser")]
+use crate::cst::BOM;
+#[cfg(feature = "parser")]
+use crate::dialect::CommandHandler;
+use crate::dialect::Dialect;
+#[cfg(feature = "parser")]
+use crate::dialect::Extra;
+#[cfg(feature = "parser")]
 use crate::error::Error;
+#[cfg(feature = "parser")]
+use crate::error::ErrorContext;
 use crate::error::InvalidArgument;
+#[cfg(feature = "parser")]
+use crate::error::Limit;
 use crate::error::ParseError;
+#[cfg(feature = "parser")]
+use crate::error::Warning;
+use crate::error::WarningKind;
+use crate::header::Catalog;
+#[cfg(feature = "parser")]
+use crate::scan::closing_quote;
+use crate::time::Component;
+use crate::time::Rounding;
+#[cfg(feature = "parser")]
+use crate::time::TimeFormat;
 use crate::time::TimeStamp;
 use crate::track::Index;
+use crate::track::Isrc;
 use crate::track::Track;
 use crate::track::TrackInfo;
+#[cfg(feature = "parser")]
 use crate::utils;
+#[cfg(feature = "parser")]
+use crate::visit::CueVisitor;
 use crate::Cuna;
 use std::fmt;
+#[cfg(feature = "parser")]
+use std::io;
+#[cfg(feature = "parser")]
 use std::iter::Enumerate;
-use std::str::Lines;
+use std::sync::Arc;
 
-pub type Parser<'a> = Parna<Enumerate<Lines<'a>>>;
+#[cfg(feature = "parser")]
+pub type Parser<'a> = Parna<Enumerate<CueLines<'a>>>;
 
 macro_rules! fail {
     (token $token: expr) => {
-        return Err($crate::error::ParseError::unexpected_token($token));
+        return Err($crate::error::ParseError::unexpected_token($token))
     };
     (syntax $cmd: expr, $msg: expr) => {
-        return Err($crate::error::ParseError::syntax_error($cmd, $msg));
+        return Err($crate::error::ParseError::syntax_error($cmd, $msg))
     };
 }
 
+/// A line of a cue sheet, values are borrowed from it without the quotes
+/// and keep escaped quotes like `\"` until they are added to a [`Cuna`](crate::Cuna)
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Command<'a> {
     Rem(&'a str),
     Title(&'a str),
     Performer(&'a str),
     Songwriter(&'a str),
+    Arranger(&'a str),
+    Composer(&'a str),
+    Message(&'a str),
     Catalog(u64),
     Cdtextfile(&'a str),
     File(&'a str, &'a str),
@@ -39,10 +76,310 @@ pub enum Command<'a> {
     Flags(&'a str),
     Empty,
 }
+/// Upper bounds on the size of the input, exceeding any of them is a
+/// [`ParseError::LimitExceeded`](crate::error::ParseError::LimitExceeded)
+///
+/// Parsing is line by line and never recursive, so with these limits the memory used
+/// is bounded by `max_line_length` for the current line plus the size of the [`Cuna`](crate::Cuna) built so far.
+///
+/// The defaults are far above what real cue sheets need:
+/// 16 KiB per line, 65536 lines, 1024 `FILE`s and 1024 `TRACK`s.
+/// [`Limits::untrusted()`] is tighter, for input from users
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Limits {
+    /// Maximum length of a line in bytes, without the line ending
+    pub max_line_length: usize,
+    pub max_lines: usize,
+    pub max_files: usize,
+    /// Maximum number of `TRACK`s in all the `FILE`s
+    pub max_tracks: usize,
+}
+/// Options of a [`Parser`]
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    pub limits: Limits,
+    /// Consulted with commands the parser does not know
+    pub dialect: Option<Arc<dyn Dialect>>,
+    /// Removes whitespace around the arguments, e.g. `TITLE " Departures "` is parsed as `Departures`
+    pub trim_values: bool,
+    /// What to drop when a line has an error
+    pub recovery: RecoveryPolicy,
+    /// Accepts `MM:SS.mmm` and `H:MM:SS.mmm` timestamps in `INDEX`, `PREGAP` and `POSTGAP`,
+    /// rounded to a frame, with a [`WarningKind::DecimalTimestamp`](crate::error::WarningKind::DecimalTimestamp).
+    /// They are errors if None, which is the default
+    pub decimal_seconds: Option<Rounding>,
+    /// Reads `H:MM:SS` timestamps as hours, minutes and seconds instead of `MM:SS:FF`,
+    /// for sheets made from video chapters, with a
+    /// [`WarningKind::DecimalTimestamp`](crate::error::WarningKind::DecimalTimestamp).
+    /// Fractions like `1:02:03.500` need [`decimal_seconds`](ParseOptions::decimal_seconds) for the rounding
+    pub hms_timestamps: bool,
+    /// Keeps the lines with commands the parser and the dialect do not know, like `SESSION`,
+    /// in the `unknown` of the header, `FILE` or `TRACK` they appear in, and writes them back
+    /// at their lines. This is the default, they are errors if false
+    pub keep_unknown: bool,
+    /// Whether `PREGAP` after `INDEX` and `INDEX` after `POSTGAP` are errors
+    pub ordering: CommandOrder,
+    /// What to do with a second `CATALOG`, or a second `PREGAP`, `POSTGAP`, `ISRC` or `FLAGS` in a `TRACK`
+    pub duplicates: DuplicatePolicy,
+    /// Rejects timestamps of 100 minutes or more, which do not fit the `MM` of the Red Book.
+    /// They are accepted up to [`TimeStamp::MAX_MINUTES`] if false, which is the default
+    pub red_book_minutes: bool,
+    /// Keywords to read as others, like `("ARTIST", "PERFORMER")`, tried after [`ALIASES`].
+    /// Keywords may have more than one word, and the replacement may add words like `REM COMMENT`
+    pub aliases: Vec<(String, String)>,
+    /// Strips comments like `; ripped 2003` or `// note` after a command, outside quotes
+    /// and after whitespace, keeps each as a `REM` of the header, `FILE` or `TRACK` of the command
+    /// and records it as a [`WarningKind::InlineComment`](crate::error::WarningKind::InlineComment).
+    /// `REM` lines are left as they are. They are part of the arguments if false, which is the default
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::error::WarningKind;
+    /// use cuna::parser::{ParseOptions, Parser};
+    /// use cuna::Cuna;
+    ///
+    /// let cue = "TITLE \"Departures; Live\" ; ripped 2003\nREM COMMENT a; b";
+    /// let options = ParseOptions { inline_comments: true, ..ParseOptions::default() };
+    /// let mut parser = Parser::new(cue).with_options(options);
+    /// let mut sheet = Cuna::default();
+    /// parser.parse(&mut sheet).unwrap();
+    /// assert_eq!(sheet.title(), &["Departures; Live".to_owned()]);
+    /// assert_eq!(sheet.comments[0], "ripped 2003");
+    /// assert_eq!(sheet.comments[1], "COMMENT a; b");
+    /// assert_eq!(parser.warnings()[0].kind(), &WarningKind::InlineComment("ripped 2003".to_owned()));
+    /// # }
+    /// ```
+    pub inline_comments: bool,
+    /// Stops parsing once a track or a `FILE` is complete, for players which only need
+    /// the metadata of what is playing. The rest of the sheet is not read, so its errors are not found.
+    /// Followed by [`Parser::parse()`](Parna::parse), [`Cuna::parse_with()`](crate::Cuna::parse_with)
+    /// and the readers of [`Cuna`](crate::Cuna), None by default
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::parser::{ParseOptions, StopAfter};
+    /// use cuna::Cuna;
+    ///
+    /// let cue = "FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"Departures\"\n  TRACK 02 AUDIO\n    INDEX 01 ??";
+    /// let options = ParseOptions {
+    ///     stop_after: Some(StopAfter::Track(1)),
+    ///     ..ParseOptions::default()
+    /// };
+    /// let (sheet, _) = Cuna::parse_with(cue, &options).unwrap();
+    /// assert_eq!(sheet.tracks().count(), 1);
+    /// assert_eq!(sheet[0][0].title(), &["Departures".to_owned()]);
+    /// # }
+    /// ```
+    pub stop_after: Option<StopAfter>,
+    /// Rejects an `ISRC` which is not an [`Isrc`](crate::track::Isrc) with
+    /// [`InvalidArgument::MalformedIsrc`], instead of leaving it to [`Cuna::validate_codes()`](crate::Cuna::validate_codes).
+    /// Whitespace around it is allowed, false by default
+    pub strict_isrc: bool,
+}
+/// Where [`ParseOptions::stop_after`] stops parsing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StopAfter {
+    /// At the `TRACK` or `FILE` after the track with this number
+    Track(u8),
+    /// At the `FILE` after the one at this position, starting from 0
+    File(usize),
+}
+/// Misspelled keywords which other tools write, with the commands they are read as
+///
+/// ```rust
+/// # #[cfg(feature = "parser")] {
+/// use cuna::Cuna;
+/// let sheet = Cuna::new("PERFORMERS \"EGOIST\"\nSONG WRITER \"ryo\"\nCOMMENT \"ExactAudioCopy\"").unwrap();
+/// assert_eq!(sheet.performer(), &["EGOIST".to_owned()]);
+/// assert_eq!(sheet.songwriter(), &["ryo".to_owned()]);
+/// assert_eq!(sheet.comments[0], "COMMENT \"ExactAudioCopy\"");
+/// # }
+/// ```
+pub const ALIASES: &[(&str, &str)] = &[
+    ("FLAG", "FLAGS"),
+    ("PERFORMERS", "PERFORMER"),
+    ("SONGWRITERS", "SONGWRITER"),
+    ("SONG WRITER", "SONGWRITER"),
+    ("COMMENT", "REM COMMENT"),
+];
+/// What to do with a command which may appear once, like `CATALOG` or the `ISRC` of a track,
+/// when it appears again
+///
+/// ```rust
+/// # #[cfg(feature = "parser")] {
+/// use cuna::parser::{DuplicatePolicy, ParseOptions};
+/// use cuna::Cuna;
+///
+/// let cue = "CATALOG 4547366179934\nCATALOG 4988002632014";
+/// assert!(Cuna::new(cue).is_err());
+/// let parse = |duplicates| {
+///     let options = ParseOptions { duplicates, ..ParseOptions::default() };
+///     Cuna::parse_with(cue, &options).unwrap().0.catalog()
+/// };
+/// assert_eq!(parse(DuplicatePolicy::KeepFirst), Some(4547366179934));
+/// assert_eq!(parse(DuplicatePolicy::KeepLast), Some(4988002632014));
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DuplicatePolicy {
+    /// Fail, which is the default
+    #[default]
+    Error,
+    /// Keep the first value and drop the others without a warning
+    KeepFirst,
+    /// Keep the last value
+    KeepLast,
+    /// Keep the first value and the others in the warnings, each command dropped as a
+    /// [`WarningKind::DuplicateCommand`](crate::error::WarningKind::DuplicateCommand)
+    KeepFirstAndWarn,
+}
+/// How strictly the order of the commands in a `TRACK` is enforced
+///
+/// ```rust
+/// # #[cfg(feature = "parser")] {
+/// use cuna::parser::{CommandOrder, ParseOptions, Parser};
+/// use cuna::Cuna;
+///
+/// let cue = "FILE \"a.flac\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nPREGAP 00:02:00";
+/// assert!(Cuna::new(cue).is_err());
+/// let options = ParseOptions {
+///     ordering: CommandOrder::Permissive,
+///     ..ParseOptions::default()
+/// };
+/// let mut parser = Parser::new(cue).with_options(options);
+/// let mut sheet = Cuna::default();
+/// parser.parse(&mut sheet).unwrap();
+/// assert_eq!(sheet[0][0].pregap().unwrap().to_string(), "00:02:00");
+/// assert_eq!(parser.warnings()[0].pos(), Some(4));
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CommandOrder {
+    /// `PREGAP` must be before `INDEX` and `INDEX` before `POSTGAP`, which is the default
+    #[default]
+    Strict,
+    /// Accept the commands in any order with a
+    /// [`WarningKind::OutOfOrder`](crate::error::WarningKind::OutOfOrder)
+    Permissive,
+}
+/// How much of a cue sheet to drop when a line has an error, instead of failing
+///
+/// Every dropped error is recorded as a [`WarningKind::Recovered`](crate::error::WarningKind::Recovered).
+/// Exceeding the [`Limits`] always fails, and an error in a `FILE` line drops the lines
+/// until the next `FILE` unless the policy is [`SkipLine`](RecoveryPolicy::SkipLine),
+/// since its tracks would end up in the previous `FILE`
+///
+/// ```rust
+/// # #[cfg(feature = "parser")] {
+/// use cuna::parser::{ParseOptions, RecoveryPolicy};
+/// use cuna::Cuna;
+///
+/// let cue = r#"FILE "a.flac" WAVE
+///   TRACK 01 AUDIO
+///     INDEX 01 00:00:00
+///   TRACK 02 AUDIO
+///     INDEX 01 04:15:90
+///     TITLE "Broken"
+///   TRACK 03 AUDIO
+///     INDEX 01 08:31:00"#;
+/// let options = ParseOptions {
+///     recovery: RecoveryPolicy::SkipTrack,
+///     ..ParseOptions::default()
+/// };
+/// let (sheet, warnings) = Cuna::parse_with(cue, &options).unwrap();
+/// let ids: Vec<_> = sheet.tracks().map(|track| track.id()).collect();
+/// assert_eq!(ids, [1, 3]);
+/// assert_eq!(warnings[0].pos(), Some(5));
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RecoveryPolicy {
+    /// Fail at the first error, which is the default
+    #[default]
+    Abort,
+    /// Drop the line
+    SkipLine,
+    /// Drop the `TRACK` the line belongs to, or only the line if it is not in a `TRACK`
+    SkipTrack,
+    /// Drop the `FILE` the line belongs to, or only the line if it is not in a `FILE`
+    SkipFile,
+}
+/// Parses a cue sheet from chunks of UTF-8 as they arrive, e.g. from a network stream
+///
+/// Chunks may end anywhere, even inside a line or a character: a line is parsed once its `\n`
+/// or `\r` arrives, and the last one by [`finish()`](PushParser::finish). Lines are split like
+/// [`Parser::new()`](Parna::new) does and [`Limits`] bound the partial line kept in memory.
+/// After an error the sheet is incomplete and the parser should be dropped
+///
+/// ```rust
+/// use cuna::parser::PushParser;
+///
+/// let cue = "TITLE \"エウテルペ\"\r\nFILE \"a.flac\" WAVE\r\n  TRACK 01 AUDIO\r\n    INDEX 01 00:00:00";
+/// let mut parser = PushParser::new();
+/// for chunk in cue.as_bytes().chunks(5) {
+///     parser.feed(chunk).unwrap();
+/// }
+/// assert_eq!(parser.sheet().files().len(), 1);
+/// let sheet = parser.finish().unwrap();
+/// assert_eq!(sheet.title(), &["エウテルペ".to_owned()]);
+/// assert_eq!(sheet[0][0].start().unwrap().to_string(), "00:00:00");
+/// ```
+#[cfg(feature = "parser")]
+#[derive(Debug, Clone, Default)]
+pub struct PushParser {
+    options: ParseOptions,
+    sheet: Cuna,
+    /// The bytes of the line which has not ended yet
+    buffer: Vec<u8>,
+    /// The number of lines parsed
+    lines: usize,
+    warnings: Vec<Warning>,
+    line_state: LineState,
+    /// The last line ended at `\r`, so a `\n` right after it ends nothing
+    after_cr: bool,
+}
+/// The lines of a str ending at `\n`, `\r\n` or `\r`, without their endings,
+/// see [`Parser::new()`](Parna::new)
+///
+/// ```rust
+/// use cuna::parser::CueLines;
+/// let lines: Vec<_> = CueLines::new("REM a\r\nREM b\rREM c\n\nREM d").collect();
+/// assert_eq!(lines, ["REM a", "REM b", "REM c", "", "REM d"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CueLines<'a>(&'a str);
+/// The lines of a parser made by [`Parser::from_line_results()`](Parna::from_line_results)
+#[cfg(feature = "parser")]
 #[derive(Debug, Clone)]
-pub struct Parna<I>(I);
+pub struct LineResults<I>(Enumerate<I>);
+#[cfg(feature = "parser")]
+#[derive(Debug, Clone)]
+pub struct Parna<I: Iterator> {
+    lines: I,
+    /// The rest of a line after a `\r`, which ends a command like a line ending
+    rest: Option<I::Item>,
+    /// The iterator returned an error which ends the input
+    done: bool,
+    options: ParseOptions,
+    warnings: Vec<Warning>,
+    line_state: LineState,
+}
+/// What the parser keeps between the lines of a sheet
+#[cfg(feature = "parser")]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LineState {
+    /// Dropping lines until the next `TRACK` or `FILE` after an error
+    skipping: Option<RecoveryPolicy>,
+    /// The number of files counted in `tracks`, all but the last one
+    counted_files: usize,
+    /// The number of tracks in the counted files
+    tracks: usize,
+    /// [`ParseOptions::stop_after`] was reached and no more lines are parsed
+    stopped: bool,
+}
 
 impl<'a> Command<'a> {
+    #[cfg(feature = "parser")]
     pub fn new(s: &'a str) -> Result<Self, ParseError> {
         let s = match s.trim() {
             "" => return Ok(Self::Empty),
@@ -57,6 +394,9 @@ impl<'a> Command<'a> {
             "title" => Ok(Self::Title(trimq(content))),
             "performer" => Ok(Self::Performer(trimq(content))),
             "songwriter" => Ok(Self::Songwriter(trimq(content))),
+            "arranger" => Ok(Self::Arranger(trimq(content))),
+            "composer" => Ok(Self::Composer(trimq(content))),
+            "message" => Ok(Self::Message(trimq(content))),
             "catalog" => match utils::number(13)(content) {
                 Ok((_, catalog)) => Ok(Self::Catalog(catalog)),
                 Err(_) => fail!(syntax content, "invaild catalog"),
@@ -64,7 +404,7 @@ impl<'a> Command<'a> {
             "cdtextfile" => Ok(Self::Cdtextfile(trimq(content))),
             "file" => match utils::quote_opt(content) {
                 Ok(("", _)) | Err(_) => Err(InvalidArgument::MissingArgument.into()),
-                Ok((format, path)) => Ok(Self::File(trimq(path), format.trim())),
+                Ok((format, path)) => Ok(Self::File(path, format.trim())),
             },
             "track" => match utils::token(content) {
                 Ok((format, id)) => Ok(Self::Track(parse_id(id)?, format)),
@@ -77,35 +417,105 @@ impl<'a> Command<'a> {
             "pregap" => Ok(Self::Pregap(trimq(content))),
             "postgap" => Ok(Self::Postgap(trimq(content))),
             "isrc" => Ok(Self::Isrc(trimq(content))),
-            "flag" => Ok(Self::Flags(trimq(content))),
+            "flags" | "flag" => Ok(Self::Flags(trimq(content))),
             _ => Err(ParseError::unexpected_token(command)),
         }
     }
+    /// Returns the command with whitespace around its arguments removed
+    pub fn trimmed(self) -> Self {
+        match self {
+            Self::Title(s) => Self::Title(s.trim()),
+            Self::Performer(s) => Self::Performer(s.trim()),
+            Self::Songwriter(s) => Self::Songwriter(s.trim()),
+            Self::Arranger(s) => Self::Arranger(s.trim()),
+            Self::Composer(s) => Self::Composer(s.trim()),
+            Self::Message(s) => Self::Message(s.trim()),
+            Self::Cdtextfile(s) => Self::Cdtextfile(s.trim()),
+            Self::File(name, format) => Self::File(name.trim(), format.trim()),
+            Self::Track(id, format) => Self::Track(id, format.trim()),
+            Self::Pregap(s) => Self::Pregap(s.trim()),
+            Self::Postgap(s) => Self::Postgap(s.trim()),
+            Self::Isrc(s) => Self::Isrc(s.trim()),
+            Self::Flags(s) => Self::Flags(s.trim()),
+            command => command,
+        }
+    }
     pub fn parse(&self, sheet: &mut Cuna) -> Result<(), ParseError> {
+        self.parse_with_warnings(sheet, &ParseOptions::default(), &mut Vec::new())
+    }
+    /// Same as [`Command::parse()`](Command::parse) following the options,
+    /// recoverable problems are pushed to warnings
+    pub(crate) fn parse_with_warnings(
+        &self,
+        sheet: &mut Cuna,
+        options: &ParseOptions,
+        warnings: &mut Vec<WarningKind>,
+    ) -> Result<(), ParseError> {
+        if options.red_book_minutes {
+            self.check_red_book_minutes()?;
+        }
+        let permissive = options.ordering == CommandOrder::Permissive;
+        let duplicates = options.duplicates;
         match *self {
             Self::Empty => {}
-            Self::Rem(s) => sheet.comments.push(s.to_owned()),
+            Self::Rem(s) if comment::is_header_key(s) => {
+                if !sheet.files.is_empty() {
+                    warnings.push(WarningKind::MisplacedHeaderRem(s.to_owned()));
+                }
+                #[cfg(feature = "date")]
+                warnings.extend(crate::date::check(s));
+                sheet.comments.push(s.to_owned())
+            }
+            Self::Rem(s) => match sheet.files.last_mut() {
+                Some(file) => match file.tracks.last_mut() {
+                    Some(tk) => tk.comments.push(s.to_owned()),
+                    None => file.comments.push(s.to_owned()),
+                },
+                None => sheet.comments.push(s.to_owned()),
+            },
             Self::Title(s) => match sheet.last_track_mut() {
-                Some(tk) => tk.push_title(s.to_owned()),
-                None => sheet.header.push_title(s.to_owned()),
+                Some(tk) => tk.push_title(unescape(s).into_owned()),
+                None => sheet.header.push_title(unescape(s).into_owned()),
             },
             Self::Performer(s) => match sheet.last_track_mut() {
-                Some(tk) => tk.push_performer(s.to_owned()),
-                _ => sheet.header.push_performer(s.to_owned()),
+                Some(tk) => tk.push_performer(unescape(s).into_owned()),
+                _ => sheet.header.push_performer(unescape(s).into_owned()),
             },
             Self::Songwriter(s) => match sheet.last_track_mut() {
-                Some(tk) => tk.push_songwriter(s.to_owned()),
-                _ => sheet.header.push_songwriter(s.to_owned()),
+                Some(tk) => tk.push_songwriter(unescape(s).into_owned()),
+                _ => sheet.header.push_songwriter(unescape(s).into_owned()),
+            },
+            Self::Arranger(s) => match sheet.last_track_mut() {
+                Some(tk) => tk.push_arranger(unescape(s).into_owned()),
+                _ => sheet.header.push_arranger(unescape(s).into_owned()),
+            },
+            Self::Composer(s) => match sheet.last_track_mut() {
+                Some(tk) => tk.push_composer(unescape(s).into_owned()),
+                _ => sheet.header.push_composer(unescape(s).into_owned()),
+            },
+            Self::Message(s) => match sheet.last_track_mut() {
+                Some(tk) => tk.push_message(unescape(s).into_owned()),
+                _ => sheet.header.push_message(unescape(s).into_owned()),
             },
             Self::Catalog(s) => match sheet.header.catalog {
-                None => sheet.header.catalog = Some(s),
-                _ => fail!(syntax self, "multiple `CATALOG` commands is not allowed"),
+                None => sheet.header.catalog = Catalog::from_value(s),
+                _ if duplicates == DuplicatePolicy::Error => {
+                    fail!(syntax self, "multiple `CATALOG` commands is not allowed")
+                }
+                _ => {
+                    if self.replaces_duplicate(duplicates, warnings) {
+                        sheet.header.catalog = Catalog::from_value(s);
+                    }
+                }
             },
             Self::Cdtextfile(s) => {
-                sheet.header.set_cdtextfile(s.to_owned());
+                sheet.header.set_cdtextfile(unescape(s).into_owned());
             }
             Self::File(name, format) => {
-                sheet.push_file(TrackInfo::new(name.to_owned(), format.to_owned()));
+                sheet.push_file(TrackInfo::new(
+                    unescape(name).into_owned(),
+                    format.to_owned(),
+                ));
             }
             Self::Track(id, format) => match sheet.last_file_mut() {
                 Some(tk) => tk.push_track(Track::new_unchecked(id, format.to_owned())),
@@ -115,15 +525,34 @@ impl<'a> Command<'a> {
                 Some(tk) if tk.postgap.is_none() => {
                     tk.push_index(Index::new_unchecked(id, timestamp))
                 }
+                Some(tk) if permissive => {
+                    warnings.push(WarningKind::OutOfOrder {
+                        keyword: "INDEX",
+                        after: "POSTGAP",
+                    });
+                    tk.push_index(Index::new_unchecked(id, timestamp))
+                }
                 Some(_) => fail!(syntax self, "Command `INDEX` should be before `POSTGAP`"),
                 None => fail!(token "INDEX"),
             },
             Self::Pregap(timestamp) => match sheet.last_track_mut() {
-                Some(tk) if tk.index.is_empty() && tk.pregap.is_none() => {
-                    tk.set_pregep(timestamp.parse()?);
+                Some(tk) if tk.pregap.is_some() && duplicates == DuplicatePolicy::Error => {
+                    fail!(syntax self, "Multiple `PREGAP` commands are not allowed in one `TRACK` scope")
                 }
                 Some(tk) if tk.pregap.is_some() => {
-                    fail!(syntax self, "Multiple `PREGAP` commands are not allowed in one `TRACK` scope")
+                    if self.replaces_duplicate(duplicates, warnings) {
+                        tk.set_pregep(timestamp.parse()?);
+                    }
+                }
+                Some(tk) if tk.index.is_empty() => {
+                    tk.set_pregep(timestamp.parse()?);
+                }
+                Some(tk) if permissive => {
+                    warnings.push(WarningKind::OutOfOrder {
+                        keyword: "PREGAP",
+                        after: "INDEX",
+                    });
+                    tk.set_pregep(timestamp.parse()?);
                 }
                 Some(_) => fail!(syntax self, "Command `PREGAP` should be before `INDEX`"),
                 _ => fail!(token "PREGAP"),
@@ -132,30 +561,207 @@ impl<'a> Command<'a> {
                 Some(tk) if tk.postgap.is_none() => {
                     tk.set_postgep(timestamp.parse()?);
                 }
-                Some(_) => {
+                Some(_) if duplicates == DuplicatePolicy::Error => {
                     fail!(syntax self, "Multiple `POSTGAP` commands are not allowed in one `TRACK` scope")
                 }
+                Some(tk) => {
+                    if self.replaces_duplicate(duplicates, warnings) {
+                        tk.set_postgep(timestamp.parse()?);
+                    }
+                }
                 None => fail!(token "POSTGAP"),
             },
+            Self::Isrc(s) if options.strict_isrc && s.trim().parse::<Isrc>().is_err() => {
+                return Err(InvalidArgument::MalformedIsrc.into())
+            }
             Self::Isrc(s) => match sheet.last_track_mut() {
                 Some(tk) if tk.isrc.is_none() => {
                     tk.set_isrc(s.to_owned());
                 }
-                Some(_) => {
+                Some(_) if duplicates == DuplicatePolicy::Error => {
                     fail!(syntax self, "Multiple `ISRC` commands are not allowed in one `TRACK` scope")
                 }
+                Some(tk) => {
+                    if self.replaces_duplicate(duplicates, warnings) {
+                        tk.set_isrc(s.to_owned());
+                    }
+                }
                 None => fail!(token "ISRC"),
             },
             Self::Flags(s) => match sheet.last_track_mut() {
                 Some(tk) if tk.flags.is_empty() => tk.push_flags(s.split(' ')),
-                Some(_) => {
+                Some(_) if duplicates == DuplicatePolicy::Error => {
                     fail!(syntax self, "Multiple `FLAGS` commands are not allowed in one `TRACK` scope")
                 }
+                Some(tk) => {
+                    if self.replaces_duplicate(duplicates, warnings) {
+                        tk.flags.clear();
+                        tk.push_flags(s.split(' '));
+                    }
+                }
                 None => fail!(token "FLAGS"),
             },
         }
         Ok(())
     }
+    /// Fails if a timestamp of the command has more minutes than [`TimeStamp::RED_BOOK_MAX_MINUTES`]
+    fn check_red_book_minutes(&self) -> Result<(), InvalidArgument> {
+        let timestamp: TimeStamp = match *self {
+            Self::Index(_, timestamp) => timestamp,
+            Self::Pregap(s) | Self::Postgap(s) => s.parse()?,
+            _ => return Ok(()),
+        };
+        match timestamp.minutes() > TimeStamp::RED_BOOK_MAX_MINUTES {
+            true => Err(InvalidArgument::MalformedTimestamp {
+                component: Component::Minutes,
+                column: 1,
+            }),
+            false => Ok(()),
+        }
+    }
+    /// Returns true if the command replaces the value it duplicates, warning if the policy says so
+    fn replaces_duplicate(&self, policy: DuplicatePolicy, warnings: &mut Vec<WarningKind>) -> bool {
+        if policy == DuplicatePolicy::KeepFirstAndWarn {
+            warnings.push(WarningKind::DuplicateCommand(self.to_string()));
+        }
+        policy == DuplicatePolicy::KeepLast
+    }
+}
+impl Limits {
+    pub const DEFAULT_MAX_LINE_LENGTH: usize = 16 * 1024;
+    pub const DEFAULT_MAX_LINES: usize = 65536;
+    pub const DEFAULT_MAX_FILES: usize = 1024;
+    pub const DEFAULT_MAX_TRACKS: usize = 1024;
+
+    /// Returns limits for input from users, like uploads to a server, which one disc never exceeds:
+    /// 4 KiB per line, 4096 lines, 99 `FILE`s and 99 `TRACK`s
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::error::{Limit, ParseError};
+    /// use cuna::parser::{Limits, ParseOptions};
+    /// use cuna::Cuna;
+    ///
+    /// let options = ParseOptions {
+    ///     limits: Limits::untrusted(),
+    ///     ..ParseOptions::default()
+    /// };
+    /// let cue = format!("FILE \"a.wav\" WAVE\n{}", "  TRACK 01 AUDIO\n".repeat(100));
+    /// let err = Cuna::parse_with(&cue, &options).unwrap_err();
+    /// assert_eq!(err.kind(), &ParseError::LimitExceeded(Limit::Tracks(99)));
+    /// # }
+    /// ```
+    pub const fn untrusted() -> Self {
+        Self {
+            max_line_length: 4 * 1024,
+            max_lines: 4096,
+            max_files: 99,
+            max_tracks: 99,
+        }
+    }
+    /// Returns limits which never stop parsing, only use them for trusted input
+    pub const fn none() -> Self {
+        Self {
+            max_line_length: usize::MAX,
+            max_lines: usize::MAX,
+            max_files: usize::MAX,
+            max_tracks: usize::MAX,
+        }
+    }
+    #[cfg(feature = "parser")]
+    pub(crate) fn check_line(&self, line: &str, at: usize) -> Result<(), ParseError> {
+        if at > self.max_lines {
+            Err(ParseError::LimitExceeded(Limit::Lines(self.max_lines)))
+        } else if line.trim_end_matches(&['\r', '\n'][..]).len() > self.max_line_length {
+            Err(ParseError::LimitExceeded(Limit::LineLength(
+                self.max_line_length,
+            )))
+        } else {
+            Ok(())
+        }
+    }
+    #[cfg(feature = "parser")]
+    fn check_sheet(
+        &self,
+        command: &Command,
+        sheet: &Cuna,
+        line_state: &mut LineState,
+    ) -> Result<(), ParseError> {
+        match command {
+            Command::File(..) if sheet.files.len() > self.max_files => {
+                Err(ParseError::LimitExceeded(Limit::Files(self.max_files)))
+            }
+            Command::Track(..) if line_state.count_tracks(sheet) > self.max_tracks => {
+                Err(ParseError::LimitExceeded(Limit::Tracks(self.max_tracks)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+#[cfg(feature = "parser")]
+impl LineState {
+    /// Returns true if parsing stops before line, and for every line after it
+    fn stops_at(&mut self, options: &ParseOptions, line: &str, state: &Cuna) -> bool {
+        self.stopped = self.stopped || options.stops_at(line, state);
+        self.stopped
+    }
+    /// Counts the tracks in sheet, only going through the files added since the last count
+    fn count_tracks(&mut self, sheet: &Cuna) -> usize {
+        let counted = sheet.files.len().saturating_sub(1);
+        let tracks = |files: &[TrackInfo]| files.iter().map(|f| f.tracks.len()).sum::<usize>();
+        // recovery may have dropped a counted file
+        self.tracks = match counted.checked_sub(self.counted_files) {
+            Some(_) => self.tracks + tracks(&sheet.files[self.counted_files..counted]),
+            None => tracks(&sheet.files[..counted]),
+        };
+        self.counted_files = counted;
+        self.tracks + sheet.last_file().map_or(0, |f| f.tracks.len())
+    }
+}
+#[cfg(feature = "parser")]
+impl ParseOptions {
+    /// Returns true if parsing stops before line, see [`ParseOptions::stop_after`]
+    pub(crate) fn stops_at(&self, line: &str, state: &Cuna) -> bool {
+        let (keyword, _) = comment::split(line.trim_start_matches(BOM));
+        let is = |k: &str| keyword.eq_ignore_ascii_case(k);
+        match self.stop_after {
+            Some(StopAfter::Track(id)) => {
+                (is("TRACK") || is("FILE")) && state.last_track().is_some_and(|t| t.id() == id)
+            }
+            Some(StopAfter::File(at)) => is("FILE") && state.files.len() > at,
+            None => false,
+        }
+    }
+}
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            limits: Limits::default(),
+            dialect: None,
+            trim_values: false,
+            recovery: RecoveryPolicy::default(),
+            decimal_seconds: None,
+            hms_timestamps: false,
+            keep_unknown: true,
+            ordering: CommandOrder::default(),
+            duplicates: DuplicatePolicy::default(),
+            red_book_minutes: false,
+            aliases: Vec::new(),
+            inline_comments: false,
+            stop_after: None,
+            strict_isrc: false,
+        }
+    }
+}
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_line_length: Self::DEFAULT_MAX_LINE_LENGTH,
+            max_lines: Self::DEFAULT_MAX_LINES,
+            max_files: Self::DEFAULT_MAX_FILES,
+            max_tracks: Self::DEFAULT_MAX_TRACKS,
+        }
+    }
 }
 impl fmt::Display for Command<'_> {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -164,37 +770,153 @@ impl fmt::Display for Command<'_> {
             Self::Title(c) => write!(formatter, r#"TITLE "{}""#, c),
             Self::Performer(c) => write!(formatter, r#"PERFORMER "{}""#, c),
             Self::Songwriter(c) => write!(formatter, r#"SONGWRITER "{}""#, c),
-            Self::Catalog(c) => write!(formatter, "CATALOG {}", c),
+            Self::Arranger(c) => write!(formatter, r#"ARRANGER "{}""#, c),
+            Self::Composer(c) => write!(formatter, r#"COMPOSER "{}""#, c),
+            Self::Message(c) => write!(formatter, r#"MESSAGE "{}""#, c),
+            Self::Catalog(c) => write!(formatter, "CATALOG {:013}", c),
             Self::Cdtextfile(c) => write!(formatter, r#"CDTEXTFILE "{}""#, c),
             Self::File(name, tp) => write!(formatter, r#"FILE "{}" {}"#, name, tp),
-            Self::Track(id, format) => write!(formatter, "TRACK {} {}", id, format),
-            Self::Index(id, timestamp) => write!(formatter, "INDEX {} {}", id, timestamp),
+            Self::Track(id, format) => write!(formatter, "TRACK {:02} {}", id, format),
+            Self::Index(id, timestamp) => write!(formatter, "INDEX {:02} {}", id, timestamp),
             Self::Pregap(c) => write!(formatter, "PREGAP {}", c),
             Self::Postgap(c) => write!(formatter, "POSTGAP {}", c),
             Self::Isrc(c) => write!(formatter, "ISRC {}", c),
-            Self::Flags(c) => write!(formatter, "FLAG {}", c),
+            Self::Flags(c) => write!(formatter, "FLAGS {}", c),
             Self::Empty => Ok(()),
         }
     }
 }
-impl<'a> Parna<Enumerate<Lines<'a>>> {
+#[cfg(feature = "parser")]
+impl<'a> Parna<Enumerate<CueLines<'a>>> {
     /// Returns a new Parser
+    ///
+    /// Lines end at `\n`, `\r\n` or `\r`, so the line numbers and
+    /// [`max_lines`](Limits::max_lines) count the lines of any of them
     pub fn new(s: &'a str) -> Self {
-        Self(s.lines().enumerate())
+        Self::with_iter(CueLines::new(s).enumerate())
+    }
+    /// Returns a parser of owned lines which may fail to be read, like the ones of
+    /// [`BufRead::lines()`](std::io::BufRead::lines), a decompressor or an archive
+    ///
+    /// An `io::Error` stops parsing with the number of the line which could not be read.
+    /// A UTF-8 BOM at the start of the first line is removed
+    ///
+    /// ```rust
+    /// use cuna::error::ParseError;
+    /// use cuna::parser::Parser;
+    /// use cuna::Cuna;
+    /// use std::io::{self, BufRead};
+    ///
+    /// let cue = "\u{feff}TITLE \"Departures\"\nFILE \"a.flac\" WAVE\n  TRACK 01 AUDIO";
+    /// let mut parser = Parser::from_line_results(cue.as_bytes().lines());
+    /// let mut sheet = Cuna::default();
+    /// parser.parse(&mut sheet).unwrap();
+    /// assert_eq!(sheet.title(), &["Departures".to_owned()]);
+    ///
+    /// let broken = vec![Ok("TITLE \"Departures\"".to_owned()), Err(io::ErrorKind::UnexpectedEof.into())];
+    /// let err = Parser::from_line_results(broken.into_iter()).parse(&mut Cuna::default()).unwrap_err();
+    /// assert!(matches!(err.kind(), ParseError::IoError(_)));
+    /// assert_eq!(err.pos(), Some(2));
+    /// ```
+    pub fn from_line_results<I>(lines: I) -> Parna<LineResults<I>>
+    where
+        I: Iterator<Item = io::Result<String>>,
+    {
+        Parna {
+            lines: LineResults(lines.enumerate()),
+            rest: None,
+            done: false,
+            options: ParseOptions::default(),
+            warnings: Vec::new(),
+            line_state: LineState::default(),
+        }
     }
 }
+#[cfg(feature = "parser")]
 impl <'a, I: Iterator<Item = &'a str>> Parna<Enumerate<I>> {
     pub fn from_lines(lines: I) -> Self {
-        Self(lines.enumerate())
+        Self::with_iter(lines.enumerate())
     }
     #[deprecated]
     pub fn set_lines(&mut self, lines: I) {
-        self.0 = lines.enumerate();
+        self.lines = lines.enumerate();
     }
 }
+#[cfg(feature = "parser")]
+impl<I: Iterator> Parna<I> {
+    /// Returns the warnings recorded so far
+    ///
+    /// ```rust
+    /// use cuna::parser::Parser;
+    /// use cuna::Cuna;
+    ///
+    /// let mut parser = Parser::new("FILE \"a.flac\" WAVE\nREM DATE 2011");
+    /// let mut sheet = Cuna::default();
+    /// parser.parse(&mut sheet).unwrap();
+    /// assert_eq!(sheet.comments[0], "DATE 2011");
+    /// assert_eq!(parser.warnings()[0].pos(), Some(2));
+    /// ```
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+    /// Takes the warnings recorded so far out of the parser
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+    /// Sets the options used by the parser
+    ///
+    /// ```rust
+    /// use cuna::error::{Limit, ParseError};
+    /// use cuna::parser::{ParseOptions, Parser};
+    /// use cuna::Cuna;
+    ///
+    /// let mut options = ParseOptions::default();
+    /// options.limits.max_files = 1;
+    /// let mut parser = Parser::new("FILE \"a.wav\" WAVE\nFILE \"b.wav\" WAVE").with_options(options);
+    /// let err = parser.parse(&mut Cuna::default()).unwrap_err();
+    /// assert_eq!(err.kind(), &ParseError::LimitExceeded(Limit::Files(1)));
+    /// assert_eq!(err.pos(), Some(2));
+    /// ```
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+    /// Sets the dialect of a tool, which may adjust the options set before
+    ///
+    /// ```rust
+    /// use cuna::dialect::Cdrdao;
+    /// use cuna::parser::Parser;
+    /// use cuna::Cuna;
+    ///
+    /// let cue = "DISC_ID \"XY12345\"\nFILE \"a.wav\" WAVE\n  TRACK 01 AUDIO";
+    /// assert_eq!(Cuna::new(cue).unwrap().header.unknown.len(), 1);
+    /// let mut parser = Parser::new(cue).with_dialect(Cdrdao);
+    /// let mut sheet = Cuna::default();
+    /// parser.parse(&mut sheet).unwrap();
+    /// assert!(sheet.header.unknown.is_empty());
+    /// assert_eq!(parser.warnings().len(), 1);
+    /// ```
+    pub fn with_dialect(mut self, dialect: impl Dialect + 'static) -> Self {
+        let mut options = dialect.parse_options(std::mem::take(&mut self.options));
+        options.dialect = Some(Arc::new(dialect));
+        self.options = options;
+        self
+    }
+}
+#[cfg(feature = "parser")]
 impl<'a, I: Iterator<Item = (usize, &'a str)>> Parna<I> {
     pub fn with_iter(it: I) -> Self {
-        Self(it)
+        Self {
+            lines: it,
+            rest: None,
+            done: false,
+            options: ParseOptions::default(),
+            warnings: Vec::new(),
+            line_state: LineState::default(),
+        }
     }
     /// Parses one line and writes to state
     pub fn parse_next_line(&mut self, state: &mut Cuna) -> Result<(), Error> {
@@ -204,12 +926,22 @@ impl<'a, I: Iterator<Item = (usize, &'a str)>> Parna<I> {
     ///
     /// Each line will be parsed and written to state until an Error is returned
     pub fn parse_next_n_lines(&mut self, n: usize, state: &mut Cuna) -> Result<(), Error> {
-        for (at, line) in self.0.by_ref().take(n) {
-            let to_error = |e| Error::new(e, at + 1);
-            Command::new(line)
-                .map_err(to_error)?
-                .parse(state)
-                .map_err(to_error)?;
+        if self.line_state.stopped {
+            return Ok(());
+        }
+        for (at, line) in self.lines.by_ref().take(n) {
+            if self.line_state.stops_at(&self.options, line, state) {
+                break;
+            }
+            parse_line_recovering(
+                line,
+                at + 1,
+                state,
+                &self.options,
+                &mut self.warnings,
+                &mut self.line_state,
+                None,
+            )?;
         }
         Ok(())
     }
@@ -217,16 +949,212 @@ impl<'a, I: Iterator<Item = (usize, &'a str)>> Parna<I> {
     ///
     /// Each line will be parsed and written to state until an Error is returned
     pub fn parse(&mut self, state: &mut Cuna) -> Result<(), Error> {
-        for (at, line) in self.0.by_ref() {
-            let to_error = |e| Error::new(e, at + 1);
-            Command::new(line)
-                .map_err(to_error)?
-                .parse(state)
-                .map_err(to_error)?;
+        self.parse_handled(state, None)
+    }
+    /// Same as [`parse()`](Self::parse), giving the commands the parser does not know to handler
+    /// before the dialect
+    ///
+    /// ```rust
+    /// use cuna::dialect::Extra;
+    /// use cuna::parser::Parser;
+    /// use cuna::Cuna;
+    ///
+    /// let mut graphics = Vec::new();
+    /// let mut handler = |keyword: &str, args: &str, _: &mut Cuna| {
+    ///     Ok(match keyword {
+    ///         "CDG" => {
+    ///             graphics.push(args.to_owned());
+    ///             Extra::Handled
+    ///         }
+    ///         _ => Extra::Unknown,
+    ///     })
+    /// };
+    /// let mut parser = Parser::new("FILE \"a.bin\" BINARY\nCDG \"a.cdg\"\nTRACK 01 AUDIO");
+    /// let mut sheet = Cuna::default();
+    /// parser.parse_with_handler(&mut sheet, &mut handler).unwrap();
+    /// assert_eq!(sheet.files()[0].tracks.len(), 1);
+    /// assert_eq!(graphics, ["\"a.cdg\""]);
+    /// ```
+    pub fn parse_with_handler(
+        &mut self,
+        state: &mut Cuna,
+        handler: &mut impl CommandHandler,
+    ) -> Result<(), Error> {
+        self.parse_handled(state, Some(handler))
+    }
+    /// Parses the lines before the first `FILE` or `TRACK` and writes to state, leaving the rest unread
+    ///
+    /// Only the header and the `REM`s before the first `FILE` are parsed, so header keys like `REM DATE`
+    /// written after it are missed. The `FILE` or `TRACK` line is consumed without being parsed
+    ///
+    /// ```rust
+    /// use cuna::parser::Parser;
+    /// use cuna::Cuna;
+    ///
+    /// let mut parser = Parser::new("TITLE \"Departures\"\nFILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n  INDEX 01 ??");
+    /// let mut sheet = Cuna::default();
+    /// parser.parse_header(&mut sheet).unwrap();
+    /// assert_eq!(sheet.title(), &["Departures".to_owned()]);
+    /// assert!(sheet.files().is_empty());
+    /// assert_eq!(parser.current_line(), Some("  TRACK 01 AUDIO"));
+    /// ```
+    pub fn parse_header(&mut self, state: &mut Cuna) -> Result<(), Error> {
+        for (at, line) in self.lines.by_ref() {
+            let (keyword, _) = comment::split(line.trim_start_matches(BOM));
+            if keyword.eq_ignore_ascii_case("FILE") || keyword.eq_ignore_ascii_case("TRACK") {
+                break;
+            }
+            parse_line_recovering(
+                line,
+                at + 1,
+                state,
+                &self.options,
+                &mut self.warnings,
+                &mut self.line_state,
+                None,
+            )?;
+        }
+        Ok(())
+    }
+    fn parse_handled(
+        &mut self,
+        state: &mut Cuna,
+        mut handler: Option<&mut (dyn CommandHandler + '_)>,
+    ) -> Result<(), Error> {
+        if self.line_state.stopped {
+            return Ok(());
+        }
+        for (at, line) in self.lines.by_ref() {
+            if self.line_state.stops_at(&self.options, line, state) {
+                break;
+            }
+            parse_line_recovering(
+                line,
+                at + 1,
+                state,
+                &self.options,
+                &mut self.warnings,
+                &mut self.line_state,
+                handler.as_deref_mut(),
+            )?;
+        }
+        Ok(())
+    }
+    /// Gives the commands of the remaining lines to visitor, see [`visit`](crate::visit)
+    ///
+    /// The lines are read like the [`Iterator`] of commands does, stopping at the first error
+    pub fn drive(&mut self, visitor: &mut impl CueVisitor) -> Result<(), Error> {
+        for command in self {
+            let (at, command) = command?;
+            visitor.on_command(at, &command);
         }
         Ok(())
     }
+    /// Parses all the lines and writes to state, skipping the lines with errors instead of stopping at the first one
+    ///
+    /// Returns every error in the order of lines. Exceeding the [`Limits`] still stops parsing
+    /// since they bound the memory used, so such an error is always the last one
+    ///
+    /// ```rust
+    /// use cuna::parser::Parser;
+    /// use cuna::Cuna;
+    ///
+    /// let mut parser = Parser::new("TITLE \"Departures\"\nINDEX 01 00:00:00\nFILE \"a.flac\" WAVE\nTRACK 1 AUDIO");
+    /// let mut sheet = Cuna::default();
+    /// let errors = parser.parse_all_errors(&mut sheet);
+    /// assert_eq!(errors.iter().map(|e| e.pos()).collect::<Vec<_>>(), [Some(2), Some(4)]);
+    /// assert_eq!(sheet.title(), &["Departures".to_owned()]);
+    /// assert_eq!(sheet.files().len(), 1);
+    /// ```
+    pub fn parse_all_errors(&mut self, state: &mut Cuna) -> Vec<Error> {
+        let mut errors = Vec::new();
+        for (at, line) in self.lines.by_ref() {
+            let warnings = &mut self.warnings;
+            if let Err(e) = parse_line(
+                line,
+                at + 1,
+                state,
+                &self.options,
+                warnings,
+                &mut self.line_state,
+            ) {
+                let stop = matches!(e.kind(), ParseError::LimitExceeded(_));
+                errors.push(e);
+                if stop {
+                    break;
+                }
+            }
+        }
+        errors
+    }
 }
+/// Reads the commands one by one without building a [`Cuna`]
+///
+/// Each item is a command and the number of its line, starting from 1. Blank lines are skipped,
+/// and a line with an error is an `Err` after which the next lines are read.
+/// Only the [`limits`](ParseOptions::limits) and [`trim_values`](ParseOptions::trim_values)
+/// of the options are used, since aliases and dialects may change the text of a line.
+/// Exceeding the limits ends the commands
+///
+/// ```rust
+/// use cuna::parser::{Command, Parser};
+///
+/// let cue = "TITLE \"Departures\"\nFILE \"a.flac\" WAVE\n\n  TRACK 01 AUDIO\n    INDEX 01 ??";
+/// let commands: Vec<_> = Parser::new(cue).collect();
+/// assert_eq!(commands.len(), 4);
+/// assert_eq!(commands[1].as_ref().unwrap(), &(2, Command::File("a.flac", "WAVE")));
+/// assert_eq!(commands[3].as_ref().unwrap_err().pos(), Some(5));
+/// let tracks = Parser::new(cue)
+///     .filter_map(Result::ok)
+///     .filter(|(_, command)| matches!(command, Command::Track(..)))
+///     .count();
+/// assert_eq!(tracks, 1);
+/// ```
+#[cfg(feature = "parser")]
+impl<'a, I: Iterator<Item = (usize, &'a str)>> Iterator for Parna<I> {
+    type Item = Result<(usize, Command<'a>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        loop {
+            let (at, line) = match self.rest.take() {
+                Some((at, rest)) => (at + 1, rest),
+                None => {
+                    let (at, line) = self.lines.next()?;
+                    let at = at + 1;
+                    let line = if at == 1 {
+                        line.trim_start_matches(BOM)
+                    } else {
+                        line
+                    };
+                    if let Err(e) = self.options.limits.check_line(line, at) {
+                        self.done = true;
+                        return Some(Err(Error::new(e, at)));
+                    }
+                    (at, line)
+                }
+            };
+            // a `\r` always ends a command, like in parse_line()
+            let line = match line.split_once('\r') {
+                Some((line, rest)) => {
+                    self.rest = Some((at - 1, rest));
+                    line
+                }
+                None => line,
+            };
+            let command = match Command::new(line) {
+                Ok(Command::Empty) => continue,
+                Ok(command) if self.options.trim_values => command.trimmed(),
+                Ok(command) => command,
+                Err(e) => return Some(Err(Error::new(e, at))),
+            };
+            return Some(Ok((at, command)));
+        }
+    }
+}
+#[cfg(feature = "parser")]
 impl<'a, I: Iterator<Item = (usize, &'a str)> + Clone> Parna<I> {
     /// Returns the current line to be parsed
     /// ```rust
@@ -236,17 +1164,483 @@ impl<'a, I: Iterator<Item = (usize, &'a str)> + Clone> Parna<I> {
     /// assert_eq!(parser.current_line(), Some(line));
     /// ```
     pub fn current_line(&self) -> Option<&'a str> {
-        self.0.clone().next().map(|(_, s)| s)
+        self.lines.clone().next().map(|(_, s)| s)
+    }
+}
+
+#[cfg(feature = "parser")]
+impl<I: Iterator<Item = io::Result<String>>> Iterator for LineResults<I> {
+    type Item = (usize, io::Result<String>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+#[cfg(feature = "parser")]
+impl<I: Iterator<Item = io::Result<String>>> Parna<LineResults<I>> {
+    /// Parses all the lines and writes to state, see [`Parser::from_line_results()`](Parna::from_line_results)
+    ///
+    /// Each line will be parsed and written to state until an Error is returned
+    pub fn parse(&mut self, state: &mut Cuna) -> Result<(), Error> {
+        if self.line_state.stopped {
+            return Ok(());
+        }
+        for (at, line) in self.lines.0.by_ref() {
+            let at = at + 1;
+            let line = line.map_err(|e| Error::new(e.into(), at))?;
+            let line = if at == 1 {
+                line.trim_start_matches(BOM)
+            } else {
+                &line
+            };
+            if self.line_state.stops_at(&self.options, line, state) {
+                break;
+            }
+            parse_line_recovering(
+                line,
+                at,
+                state,
+                &self.options,
+                &mut self.warnings,
+                &mut self.line_state,
+                None,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> CueLines<'a> {
+    pub fn new(s: &'a str) -> Self {
+        Self(s)
+    }
+}
+impl<'a> Iterator for CueLines<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let end = self.0.find(['\r', '\n']).unwrap_or(self.0.len());
+        let line = &self.0[..end];
+        let ending = match &self.0.as_bytes()[end..] {
+            [b'\r', b'\n', ..] => 2,
+            [] => 0,
+            _ => 1,
+        };
+        self.0 = &self.0[end + ending..];
+        Some(line)
+    }
+}
+
+#[cfg(feature = "parser")]
+impl PushParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the options used by the parser
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+    /// Parses the lines which end in bytes, keeping the rest until more bytes arrive
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let mut searched = self.buffer.len();
+        self.buffer.extend_from_slice(bytes);
+        let mut start = 0;
+        while let Some(end) = self.buffer[searched..]
+            .iter()
+            .position(|&b| b == b'\n' || b == b'\r')
+        {
+            let end = searched + end;
+            let cr = self.buffer[end] == b'\r';
+            // the `\n` of a `\r\n`, which may come in the next chunk
+            if !(end == start && self.after_cr && !cr) {
+                self.parse_line(start, end)?;
+            }
+            self.after_cr = cr;
+            start = end + 1;
+            searched = start;
+        }
+        self.buffer.drain(..start);
+        let at = self.lines + 1;
+        // the line ending may still be missing
+        let max = self.options.limits.max_line_length.saturating_add(1);
+        if self.buffer.len() > max {
+            let limit = Limit::LineLength(self.options.limits.max_line_length);
+            return Err(Error::new(ParseError::LimitExceeded(limit), at));
+        }
+        Ok(())
+    }
+    /// Parses the last line, which has no line ending, and returns the sheet
+    pub fn finish(mut self) -> Result<Cuna, Error> {
+        if !self.buffer.is_empty() {
+            self.parse_line(0, self.buffer.len())?;
+        }
+        Ok(self.sheet)
+    }
+    /// The sheet built from the lines parsed so far
+    pub fn sheet(&self) -> &Cuna {
+        &self.sheet
+    }
+    /// Returns the warnings recorded so far
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+    /// Parses the line in buffer[start..end], without its line ending
+    fn parse_line(&mut self, start: usize, end: usize) -> Result<(), Error> {
+        self.lines += 1;
+        let at = self.lines;
+        let bytes = &self.buffer[start..end];
+        let line = std::str::from_utf8(bytes).map_err(|_| {
+            let e = io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            );
+            Error::new(e.into(), at)
+        })?;
+        let line = if at == 1 {
+            line.trim_start_matches(BOM)
+        } else {
+            line
+        };
+        parse_line_recovering(
+            line,
+            at,
+            &mut self.sheet,
+            &self.options,
+            &mut self.warnings,
+            &mut self.line_state,
+            None,
+        )
     }
 }
 
+#[cfg(feature = "parser")]
+/// Same as [`parse_line()`], dropping what [`ParseOptions::recovery`] says on errors
+///
+/// line_state is kept between the lines, starting from the default
+pub(crate) fn parse_line_recovering(
+    line: &str,
+    at: usize,
+    state: &mut Cuna,
+    options: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+    line_state: &mut LineState,
+    handler: Option<&mut (dyn CommandHandler + '_)>,
+) -> Result<(), Error> {
+    let (keyword, _) = comment::split(line.trim_start());
+    let is = |k: &str| keyword.eq_ignore_ascii_case(k);
+    let skipping = &mut line_state.skipping;
+    match *skipping {
+        Some(RecoveryPolicy::SkipTrack) if is("TRACK") || is("FILE") => *skipping = None,
+        Some(RecoveryPolicy::SkipFile) if is("FILE") => *skipping = None,
+        Some(_) => return Ok(()),
+        None => {}
+    }
+    let error = match parse_line_handled(line, at, state, options, warnings, line_state, handler) {
+        Ok(()) => return Ok(()),
+        Err(e) if matches!(e.kind(), ParseError::LimitExceeded(_)) => return Err(e),
+        Err(e) => e,
+    };
+    let skipping = &mut line_state.skipping;
+    let policy = match options.recovery {
+        RecoveryPolicy::Abort => return Err(error),
+        RecoveryPolicy::SkipLine => RecoveryPolicy::SkipLine,
+        _ if is("FILE") => {
+            *skipping = Some(RecoveryPolicy::SkipFile);
+            RecoveryPolicy::SkipFile
+        }
+        // the tracks before a broken `TRACK` line are fine
+        RecoveryPolicy::SkipTrack if is("TRACK") => {
+            *skipping = Some(RecoveryPolicy::SkipTrack);
+            RecoveryPolicy::SkipTrack
+        }
+        RecoveryPolicy::SkipTrack => match state.last_file_mut().and_then(|f| f.tracks.pop()) {
+            Some(_) => {
+                *skipping = Some(RecoveryPolicy::SkipTrack);
+                RecoveryPolicy::SkipTrack
+            }
+            None => RecoveryPolicy::SkipLine,
+        },
+        RecoveryPolicy::SkipFile if !state.files.is_empty() => {
+            state.files.pop();
+            *skipping = Some(RecoveryPolicy::SkipFile);
+            RecoveryPolicy::SkipFile
+        }
+        RecoveryPolicy::SkipFile => RecoveryPolicy::SkipLine,
+    };
+    let kind = WarningKind::Recovered {
+        error: error.kind().to_string(),
+        policy,
+    };
+    warnings.push(Warning::new(kind, at));
+    Ok(())
+}
+#[cfg(feature = "parser")]
+/// Parses the line at `at` (starting from 1) and writes to state
+///
+/// line_state is kept between the lines, starting from the default
+pub(crate) fn parse_line(
+    line: &str,
+    at: usize,
+    state: &mut Cuna,
+    options: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+    line_state: &mut LineState,
+) -> Result<(), Error> {
+    parse_line_handled(line, at, state, options, warnings, line_state, None)
+}
+#[cfg(feature = "parser")]
+/// Same as [`parse_line()`], consulting handler first for commands the parser does not know
+fn parse_line_handled(
+    line: &str,
+    at: usize,
+    state: &mut Cuna,
+    options: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+    line_state: &mut LineState,
+    mut handler: Option<&mut (dyn CommandHandler + '_)>,
+) -> Result<(), Error> {
+    // the lines of a caller may still have a `\r`, which always ends a command
+    if line.contains('\r') {
+        return line.split('\r').try_for_each(|line| {
+            let handler = handler.as_deref_mut();
+            parse_line_handled(line, at, state, options, warnings, line_state, handler)
+        });
+    }
+    parse_command_line(line, at, state, options, warnings, line_state, handler).map_err(|e| {
+        match scope(line, state) {
+            Some(context) => e.with_context(context),
+            None => e,
+        }
+    })
+}
+/// Returns the `FILE` and `TRACK` a line which is not parsed yet belongs to
+#[cfg(feature = "parser")]
+fn scope(line: &str, state: &Cuna) -> Option<ErrorContext> {
+    let (keyword, _) = comment::split(line.trim_start());
+    let is = |k: &str| keyword.eq_ignore_ascii_case(k);
+    let file = state.last_file().filter(|_| !is("FILE"))?;
+    Some(ErrorContext {
+        file: file.name.clone(),
+        track: file.tracks.last().filter(|_| !is("TRACK")).map(Track::id),
+    })
+}
+#[cfg(feature = "parser")]
+fn parse_command_line(
+    line: &str,
+    at: usize,
+    state: &mut Cuna,
+    options: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+    line_state: &mut LineState,
+    handler: Option<&mut (dyn CommandHandler + '_)>,
+) -> Result<(), Error> {
+    options
+        .limits
+        .check_line(line, at)
+        .map_err(|e| Error::new(e, at))?;
+    let inline = match options.inline_comments {
+        true => split_inline_comment(line),
+        false => None,
+    };
+    let (line, inline) = match inline {
+        Some((command, comment)) => {
+            let kind = WarningKind::InlineComment(comment.to_owned());
+            warnings.push(Warning::new(kind, at));
+            (command, Some(comment))
+        }
+        None => (line, None),
+    };
+    parse_command(line, at, state, options, warnings, line_state, handler)?;
+    // kept as a `REM` of the header, `FILE` or `TRACK` the command belongs to, so it is written back
+    if let Some(comment) = inline.filter(|comment| !comment.is_empty()) {
+        let comments = match state.last_file_mut() {
+            Some(file) => match file.tracks.last_mut() {
+                Some(track) => &mut track.comments,
+                None => &mut file.comments,
+            },
+            None => &mut state.comments,
+        };
+        comments.push(comment.to_owned());
+    }
+    Ok(())
+}
+/// Parses a line without an inline comment, see [`parse_command_line()`]
+#[cfg(feature = "parser")]
+fn parse_command(
+    line: &str,
+    at: usize,
+    state: &mut Cuna,
+    options: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+    line_state: &mut LineState,
+    handler: Option<&mut (dyn CommandHandler + '_)>,
+) -> Result<(), Error> {
+    let to_error = |e| Error::new(e, at);
+    let aliased = resolve_alias(line, &options.aliases);
+    let line = aliased.as_deref().unwrap_or(line);
+    let converted;
+    let line = match convert_timestamp(line, options) {
+        Some((line, kind)) => {
+            warnings.push(Warning::new(kind, at));
+            converted = line;
+            converted.as_str()
+        }
+        None => line,
+    };
+    let mut kinds = Vec::new();
+    let command = match (Command::new(line), &options.dialect) {
+        (Err(ParseError::UnexpectedToken(_)), dialect)
+            if dialect.is_some() || handler.is_some() || options.keep_unknown =>
+        {
+            let (keyword, args) = comment::split(line);
+            let extra = match handler {
+                Some(handler) => handler.handle(keyword, args, state).map_err(to_error)?,
+                None => Extra::Unknown,
+            };
+            let extra = match (extra, dialect) {
+                (Extra::Unknown, Some(dialect)) => dialect
+                    .parse_extra(keyword, args, state)
+                    .map_err(to_error)?,
+                (extra, _) => extra,
+            };
+            match extra {
+                Extra::Handled => return Ok(()),
+                Extra::Ignored => {
+                    let kind = WarningKind::IgnoredCommand(keyword.to_owned());
+                    warnings.push(Warning::new(kind, at));
+                    return Ok(());
+                }
+                Extra::Unknown if options.keep_unknown => {
+                    let unknown = match state.last_file_mut() {
+                        Some(file) => match file.tracks.last_mut() {
+                            Some(track) => &mut track.unknown,
+                            None => &mut file.unknown,
+                        },
+                        None => &mut state.header.unknown,
+                    };
+                    unknown.push(at, line.trim().to_owned());
+                    return Ok(());
+                }
+                Extra::Unknown => return Err(to_error(ParseError::unexpected_token(keyword))),
+            }
+        }
+        (command, _) => command.map_err(to_error)?,
+    };
+    let command = match options.trim_values {
+        true => command.trimmed(),
+        false => command,
+    };
+    command
+        .parse_with_warnings(state, options, &mut kinds)
+        .map_err(to_error)?;
+    options
+        .limits
+        .check_sheet(&command, state, line_state)
+        .map_err(to_error)?;
+    warnings.extend(kinds.into_iter().map(|kind| Warning::new(kind, at)));
+    Ok(())
+}
+/// Splits a line into the command and a comment after `;` or `//`, see [`ParseOptions::inline_comments`]
+#[cfg(feature = "parser")]
+fn split_inline_comment(line: &str) -> Option<(&str, &str)> {
+    if comment::split(line).0.eq_ignore_ascii_case("REM") {
+        return None;
+    }
+    let mut i = 0;
+    while let Some(rest) = line.get(i..).filter(|rest| !rest.is_empty()) {
+        if let Some(quoted) = rest.strip_prefix('"') {
+            i += 1 + closing_quote(quoted).map_or(quoted.len(), |end| end + 1);
+            continue;
+        }
+        let after_space = i == 0 || line[..i].ends_with(char::is_whitespace);
+        let marker = [";", "//"].iter().find(|m| rest.starts_with(*m));
+        if let Some(marker) = marker.filter(|_| after_space) {
+            return Some((line[..i].trim_end(), rest[marker.len()..].trim()));
+        }
+        i += rest.chars().next().map_or(1, char::len_utf8);
+    }
+    None
+}
+/// Rewrites a line starting with a keyword of [`ALIASES`] or aliases with the command it is read as
+#[cfg(feature = "parser")]
+fn resolve_alias(line: &str, aliases: &[(String, String)]) -> Option<String> {
+    let builtin = ALIASES.iter().map(|&(alias, keyword)| (alias, keyword));
+    let custom = aliases
+        .iter()
+        .map(|(alias, keyword)| (&alias[..], &keyword[..]));
+    let mut aliases = builtin
+        .chain(custom)
+        .filter(|(alias, _)| !alias.trim().is_empty());
+    aliases.find_map(|(alias, keyword)| {
+        strip_keyword(line, alias).map(|rest| format!("{} {}", keyword, rest))
+    })
+}
+/// Returns the rest of the line if it starts with the words of keyword, case-insensitively
+#[cfg(feature = "parser")]
+fn strip_keyword<'a>(line: &'a str, keyword: &str) -> Option<&'a str> {
+    let mut rest = line.trim_start();
+    for word in keyword.split_whitespace() {
+        if !rest.get(..word.len())?.eq_ignore_ascii_case(word) {
+            return None;
+        }
+        rest = &rest[word.len()..];
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        rest = rest.trim_start();
+    }
+    Some(rest)
+}
+/// Rewrites a `MM:SS.mmm` or `H:MM:SS` timestamp at the end of an `INDEX`, `PREGAP` or `POSTGAP` line
+/// as `MM:SS:FF`, as the options allow
+#[cfg(feature = "parser")]
+fn convert_timestamp(line: &str, options: &ParseOptions) -> Option<(String, WarningKind)> {
+    if options.decimal_seconds.is_none() && !options.hms_timestamps {
+        return None;
+    }
+    let line = line.trim();
+    let (keyword, _) = comment::split(line);
+    if !["INDEX", "PREGAP", "POSTGAP"]
+        .iter()
+        .any(|k| k.eq_ignore_ascii_case(keyword))
+    {
+        return None;
+    }
+    let start = line.rfind(char::is_whitespace)? + 1;
+    let timestamp = &line[start..];
+    let rounding = options.decimal_seconds;
+    let hms = match options.hms_timestamps {
+        true if rounding.is_some() || !timestamp.contains('.') => {
+            TimeStamp::from_hms(timestamp, rounding.unwrap_or_default())
+        }
+        _ => None,
+    };
+    let rounded = hms.or_else(|| match TimeStamp::parse_lenient(timestamp, rounding?)? {
+        (_, TimeFormat::Msf) => None,
+        (timestamp, _) => Some(timestamp),
+    })?;
+    let kind = WarningKind::DecimalTimestamp {
+        timestamp: timestamp.to_owned(),
+        rounded,
+    };
+    Some((format!("{}{}", &line[..start], rounded), kind))
+}
+#[cfg(feature = "parser")]
 #[inline(always)]
 fn parse_id(s: &str) -> Result<u8, InvalidArgument> {
     Ok(utils::number(2)(s)
         .map_err(|_| InvalidArgument::InvalidId)?
         .1)
 }
+#[cfg(feature = "parser")]
 #[inline(always)]
 fn trimq(s: &str) -> &str {
-    s.trim_matches('"')
+    match utils::quote(s) {
+        Ok(("", value)) => value,
+        _ => s.trim_matches('"'),
+    }
 }
@@ -0,0 +1,135 @@
+//! Splitting a sheet with too many tracks into several valid sheets
+//!
+//! A cue sheet holds at most 99 tracks, so a sheet generated from a longer list, with repeated
+//! or out of order numbers, can not be written as is. [`Cuna::partition()`] cuts its tracks
+//! into parts of at most 99 tracks, or of at most a given length, and numbers the tracks
+//! of each part from 01. Each part keeps the header, the `REM`s and the `FILE`s of its tracks,
+//! whose `INDEX`s still point into the same `FILE`s.
+//!
+//! ```rust
+//! use cuna::partition::PartitionOptions;
+//! use cuna::time::TimeStamp;
+//! use cuna::track::{Track, TrackInfo};
+//! use cuna::Cuna;
+//!
+//! let mut file = TrackInfo::new("mix.flac".to_owned(), "WAVE".to_owned());
+//! for i in 0..150 {
+//!     let start = TimeStamp::from_frames(i * 60 * 75);
+//!     file.push_track(Track::new(1, "AUDIO".to_owned()).with_index(1, start));
+//! }
+//! let mut sheet = Cuna::default();
+//! sheet.push_file(file);
+//!
+//! let partition = sheet.partition(&PartitionOptions::default());
+//! assert_eq!(partition.sheets.len(), 2);
+//! assert_eq!(partition.sheets[1].tracks().count(), 51);
+//! let placement = partition.mapping[120];
+//! assert_eq!((placement.sheet, placement.number), (1, 22));
+//! ```
+use crate::time::TimeStamp;
+use crate::track::TrackInfo;
+use crate::Cuna;
+
+/// How large the parts of [`Cuna::partition()`] may be
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PartitionOptions {
+    /// The most tracks of a part, between 1 and 99, which is the default
+    pub max_tracks: u8,
+    /// The longest a part may be, None by default
+    ///
+    /// Tracks of unknown length, like the last one of a `FILE`, count as empty,
+    /// and a track longer than this is a part on its own
+    pub max_length: Option<TimeStamp>,
+}
+/// Where a track of the original sheet went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Placement {
+    /// The number of the track in the original sheet
+    pub original: u8,
+    /// The position of the part in [`Partition::sheets`]
+    pub sheet: usize,
+    /// The number of the track in its part
+    pub number: u8,
+}
+/// The result of [`Cuna::partition()`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Partition {
+    pub sheets: Vec<Cuna>,
+    /// The placement of each track, in the order of the original sheet
+    pub mapping: Vec<Placement>,
+}
+
+impl Default for PartitionOptions {
+    fn default() -> Self {
+        Self {
+            max_tracks: 99,
+            max_length: None,
+        }
+    }
+}
+impl PartitionOptions {
+    /// Parts of at most max_length, and of at most 99 tracks
+    pub const fn max_length(max_length: TimeStamp) -> Self {
+        Self {
+            max_tracks: 99,
+            max_length: Some(max_length),
+        }
+    }
+}
+
+impl Cuna {
+    /// Cuts the tracks into sheets of at most [`max_tracks`](PartitionOptions::max_tracks) tracks
+    /// and [`max_length`](PartitionOptions::max_length), see [`partition`](crate::partition)
+    ///
+    /// A sheet without tracks is returned as the only part
+    pub fn partition(&self, options: &PartitionOptions) -> Partition {
+        let max_tracks = options.max_tracks.clamp(1, 99);
+        let max_length = options.max_length.map_or(u32::MAX, |t| t.as_frames());
+        let mut partition = Partition::default();
+        let mut part: Option<(Cuna, Option<usize>)> = None;
+        let mut length = 0u32;
+        let mut number = 0;
+        for span in self.timeline() {
+            let track_length = span.length.map_or(0, |t| t.as_frames());
+            let full = number == max_tracks || length.saturating_add(track_length) > max_length;
+            if part.is_none() || full && number > 0 {
+                partition.sheets.extend(part.take().map(|(sheet, _)| sheet));
+                let sheet =
+                    Cuna::with_parts(self.header.clone(), Vec::new(), self.comments.clone());
+                part = Some((sheet, None));
+                length = 0;
+                number = 0;
+            }
+            let (sheet, last_file) = part.as_mut().expect("a part is started");
+            if *last_file != Some(span.file) {
+                let file = &self.files[span.file];
+                sheet.push_file(TrackInfo {
+                    name: file.name.clone(),
+                    format: file.format.clone(),
+                    tracks: Vec::new(),
+                    comments: file.comments.clone(),
+                    unknown: file.unknown.clone(),
+                });
+                *last_file = Some(span.file);
+            }
+            let mut track = self.files[span.file].tracks[span.track].clone();
+            number += 1;
+            length = length.saturating_add(track_length);
+            track.id = number;
+            sheet
+                .last_file_mut()
+                .expect("the FILE is pushed")
+                .push_track(track);
+            partition.mapping.push(Placement {
+                original: span.id,
+                sheet: partition.sheets.len(),
+                number,
+            });
+        }
+        match part {
+            Some((sheet, _)) => partition.sheets.push(sheet),
+            None => partition.sheets.push(self.clone()),
+        }
+        partition
+    }
+}
@@ -0,0 +1,149 @@
+//! Rewriting the paths of `FILE`s when a cue sheet moves to another directory
+//!
+//! Paths are handled as text without touching the file system: both `/` and `\` separate components,
+//! `.` and `..` are resolved, and directories are compared ignoring ASCII case since rips often move
+//! between Windows and other systems. A rewritten path keeps the separator of the original,
+//! or of the old directory if it has none.
+//! Absolute paths, like `/music/a.flac` or `C:\music\a.flac`, are left as they are.
+//!
+//! ```rust
+//! use cuna::paths::rebase;
+//! assert_eq!(rebase("a.flac", "/music/Album", "/music/Album/cue"), "../a.flac");
+//! assert_eq!(
+//!     rebase(r"..\Disc 1\a.flac", r"C:\Music\Album\cue", r"c:\music\album"),
+//!     r"Disc 1\a.flac"
+//! );
+//! assert_eq!(rebase("/music/a.flac", "/music", "/backup"), "/music/a.flac");
+//! ```
+use crate::Cuna;
+use std::path::Path;
+
+/// A path split into its root (`""` for `/`, `C:` for a drive) and normalized components
+struct Parts<'a> {
+    root: Option<&'a str>,
+    components: Vec<&'a str>,
+}
+
+impl<'a> Parts<'a> {
+    fn new(path: &'a str) -> Self {
+        let (root, rest) = if path.starts_with(['/', '\\']) {
+            (Some(""), &path[1..])
+        } else if is_drive(path) {
+            (Some(&path[..2]), path[2..].trim_start_matches(['/', '\\']))
+        } else {
+            (None, path)
+        };
+        let mut parts = Self {
+            root,
+            components: Vec::new(),
+        };
+        parts.extend(rest);
+        parts
+    }
+    fn extend(&mut self, path: &'a str) {
+        for component in path.split(['/', '\\']) {
+            match component {
+                "" | "." => {}
+                ".." => match self.components.last() {
+                    Some(&last) if last != ".." => {
+                        self.components.pop();
+                    }
+                    // nothing is above the root
+                    _ if self.root.is_some() => {}
+                    _ => self.components.push(".."),
+                },
+                _ => self.components.push(component),
+            }
+        }
+    }
+    fn join(&self, separator: char) -> String {
+        let mut s = String::new();
+        if let Some(root) = self.root {
+            s.push_str(root);
+            s.push(separator);
+        }
+        for (i, component) in self.components.iter().enumerate() {
+            if i > 0 {
+                s.push(separator);
+            }
+            s.push_str(component);
+        }
+        s
+    }
+}
+
+fn is_drive(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+/// Returns the separator a path uses, or None if it has none or uses both
+fn separator(path: &str) -> Option<char> {
+    match (path.contains('/'), path.contains('\\')) {
+        (true, false) => Some('/'),
+        (false, true) => Some('\\'),
+        _ => None,
+    }
+}
+fn same_root(a: Option<&str>, b: Option<&str>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Rewrites path, which is relative to from_dir, to be relative to to_dir, see [`paths`](crate::paths)
+///
+/// If no relative path leads from to_dir to the file, e.g. they are on different drives,
+/// the path is returned joined to from_dir
+pub fn rebase(path: &str, from_dir: impl AsRef<Path>, to_dir: impl AsRef<Path>) -> String {
+    if Parts::new(path).root.is_some() {
+        return path.to_owned();
+    }
+    let from_dir = from_dir.as_ref().to_string_lossy();
+    let to_dir = to_dir.as_ref().to_string_lossy();
+    let separator = separator(path)
+        .or_else(|| separator(&from_dir))
+        .unwrap_or('/');
+    let mut target = Parts::new(&from_dir);
+    target.extend(path);
+    let base = Parts::new(&to_dir);
+    let common = target
+        .components
+        .iter()
+        .zip(&base.components)
+        .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+        .count();
+    // `..` left in to_dir can only be undone by knowing the name of the directory it leaves
+    if !same_root(target.root, base.root) || base.components[common..].contains(&"..") {
+        return target.join(separator);
+    }
+    let mut components = vec![".."; base.components.len() - common];
+    components.extend_from_slice(&target.components[common..]);
+    Parts {
+        root: None,
+        components,
+    }
+    .join(separator)
+}
+
+impl Cuna {
+    /// Returns a clone whose `FILE`s are rewritten with [`rebase()`] for a sheet moved from from_dir to to_dir
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::new("FILE \"Disc 1/a.flac\" WAVE").unwrap();
+    /// let moved = sheet.rebase_paths("/music/Album", "/music/Album/cue");
+    /// assert_eq!(moved[0].name, "../Disc 1/a.flac");
+    /// assert_eq!(sheet[0].name, "Disc 1/a.flac");
+    /// # }
+    /// ```
+    pub fn rebase_paths(&self, from_dir: impl AsRef<Path>, to_dir: impl AsRef<Path>) -> Self {
+        let mut sheet = self.clone();
+        for file in &mut sheet.files {
+            file.name = rebase(&file.name, from_dir.as_ref(), to_dir.as_ref());
+        }
+        sheet
+    }
+}
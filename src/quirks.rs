@@ -0,0 +1,250 @@
+//! Recognizing the tool which wrote a sheet and what it does differently
+//!
+//! [`Cuna::quirks()`] looks for fingerprints of rippers, like the `REM COMMENT` EAC writes,
+//! and for known quirks, like gaps written as `INDEX 00`. [`Document::quirks()`] also reads
+//! the layout of the text, which a parsed sheet no longer has. The [`Quirks`] pick the [`Dialect`],
+//! the [`CueWriter`] and the [`FixPolicy`] which suit the sheet.
+//!
+//! The detection is a heuristic: a sheet edited by hand or by a tagger may look like another tool's.
+//!
+//! ```rust
+//! # #[cfg(feature = "parser")] {
+//! use cuna::quirks::{Quirk, Tool};
+//! use cuna::Cuna;
+//!
+//! let sheet = Cuna::new(r#"REM COMMENT "ExactAudioCopy v1.6"
+//! FILE "a.flac" WAVE
+//!   TRACK 01 AUDIO
+//!     INDEX 01 00:00:00
+//!   TRACK 02 AUDIO
+//!     INDEX 00 04:13:50
+//!     INDEX 01 04:15:44"#).unwrap();
+//! let quirks = sheet.quirks();
+//! assert_eq!(quirks.tool, Tool::Eac);
+//! assert_eq!(quirks.quirks, [Quirk::Index00Gaps, Quirk::WaveForCompressed]);
+//! assert_eq!(quirks.dialect().unwrap().name(), "EAC");
+//! # }
+//! ```
+use crate::comment;
+#[cfg(feature = "parser")]
+use crate::dialect::Cdrdao;
+use crate::dialect::{Dialect, Profile};
+#[cfg(feature = "parser")]
+use crate::document::Document;
+use crate::track::FileFormat;
+use crate::track::IndexNumber;
+use crate::track::TrackInfo;
+use crate::validation::FixPolicy;
+use crate::writer::CueWriter;
+use crate::Cuna;
+use std::sync::Arc;
+
+/// A tool which writes cue sheets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tool {
+    /// Exact Audio Copy
+    Eac,
+    /// X Lossless Decoder
+    Xld,
+    Foobar2000,
+    Cdrdao,
+    /// No fingerprint was found
+    Unknown,
+}
+/// Something a tool is known to write differently from other tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Quirk {
+    /// Gaps are in the file, from `INDEX 00` to `INDEX 01`, as EAC writes them by default
+    Index00Gaps,
+    /// Gaps are `PREGAP`s which are not in the file
+    PregapCommands,
+    /// Some tracks have `INDEX 00` without `INDEX 01`, see [`Cuna::repair()`](Cuna::repair)
+    MissingStartIndex,
+    /// The first track starts after `00:00:00`: audio hidden before it, or a gap left in the file
+    HiddenTrackOne,
+    /// `FILE`s like FLAC or MP3 are declared `WAVE`, which EAC does after compressing
+    WaveForCompressed,
+    /// `REM GENRE` and the other header keys are written after the first `FILE`,
+    /// only found by [`Document::quirks()`]
+    LateHeaderRems,
+    /// `TRACK`s are not indented, only found by [`Document::quirks()`]
+    Unindented,
+    /// Lines are indented with tabs, only found by [`Document::quirks()`]
+    TabIndent,
+}
+/// The result of [`Cuna::quirks()`] and [`Document::quirks()`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Quirks {
+    pub tool: Tool,
+    /// The quirks found, in the order of [`Quirk`]
+    pub quirks: Vec<Quirk>,
+}
+
+/// Text of `REM COMMENT` naming each tool, compared case-insensitively
+const SIGNATURES: &[(&str, Tool)] = &[
+    ("exactaudiocopy", Tool::Eac),
+    ("x lossless decoder", Tool::Xld),
+    ("xld", Tool::Xld),
+    ("foobar2000", Tool::Foobar2000),
+    ("cdrdao", Tool::Cdrdao),
+];
+/// Extensions of compressed audio which a `WAVE` `FILE` should not have
+const COMPRESSED: &[&str] = &[
+    "flac", "ape", "wv", "tak", "tta", "mp3", "m4a", "ogg", "opus",
+];
+
+impl Tool {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Eac => "EAC",
+            Self::Xld => "XLD",
+            Self::Foobar2000 => "foobar2000",
+            Self::Cdrdao => "cdrdao",
+            Self::Unknown => "unknown",
+        }
+    }
+    /// The [`Profile`] for the sheets of the tool, None if it is unknown
+    pub fn profile(self) -> Option<Profile> {
+        match self {
+            Self::Eac => Some(Profile::Eac),
+            Self::Xld => Some(Profile::Xld),
+            Self::Foobar2000 => Some(Profile::Foobar2000),
+            Self::Cdrdao => Some(Profile::Cdrdao),
+            Self::Unknown => None,
+        }
+    }
+    /// The [`dialect`](crate::dialect) of the [`profile`](Tool::profile) of the tool,
+    /// None if it is unknown
+    pub fn dialect(self) -> Option<Arc<dyn Dialect>> {
+        self.profile().map(Profile::dialect)
+    }
+}
+impl Quirks {
+    pub fn contains(&self, quirk: Quirk) -> bool {
+        self.quirks.contains(&quirk)
+    }
+    /// The [`dialect`](crate::dialect) of the tool, None if it is unknown
+    pub fn dialect(&self) -> Option<Arc<dyn Dialect>> {
+        self.tool.dialect()
+    }
+    /// Returns the writer of the [`Profile`] of the tool which keeps the layout of the sheet
+    pub fn writer(&self) -> CueWriter {
+        let writer = self
+            .tool
+            .profile()
+            .map_or_else(CueWriter::new, Profile::writer);
+        if self.contains(Quirk::Unindented) {
+            writer.indent(0)
+        } else {
+            writer
+        }
+    }
+    /// The policy for [`Cuna::repair()`](Cuna::repair): tracks missing `INDEX 01` of a sheet
+    /// using `PREGAP`s start after the gap, otherwise at `INDEX 00`
+    pub fn fix_policy(&self) -> FixPolicy {
+        if self.contains(Quirk::PregapCommands) {
+            FixPolicy::UsePregapLength
+        } else {
+            FixPolicy::CopyPregapIndex
+        }
+    }
+    fn push(&mut self, quirk: Quirk) {
+        if !self.contains(quirk) {
+            self.quirks.push(quirk);
+            self.quirks.sort_by_key(|q| *q as u8);
+        }
+    }
+}
+
+impl Cuna {
+    /// Guesses the tool which wrote the sheet and finds its quirks, see [`quirks`](crate::quirks)
+    pub fn quirks(&self) -> Quirks {
+        let tool = self
+            .comments
+            .iter()
+            .filter_map(|c| match comment::split(c) {
+                (key, value) if key.eq_ignore_ascii_case(comment::COMMENT) => Some(value),
+                _ => None,
+            })
+            .find_map(signature)
+            .unwrap_or(Tool::Unknown);
+        let mut quirks = Quirks {
+            tool,
+            quirks: Vec::new(),
+        };
+        for track in self.tracks() {
+            let index00 = track.get_index(IndexNumber::PREGAP).is_some();
+            let start = track.get_index(IndexNumber::START).is_some();
+            if index00 && start {
+                quirks.push(Quirk::Index00Gaps);
+            }
+            if track.pregap().is_some() {
+                quirks.push(Quirk::PregapCommands);
+            }
+            if index00 && !start {
+                quirks.push(Quirk::MissingStartIndex);
+            }
+        }
+        let first_start = self.first_file().and_then(|f| f.tracks.first()?.start());
+        if first_start.is_some_and(|t| t.as_frames() > 0) {
+            quirks.push(Quirk::HiddenTrackOne);
+        }
+        if self.files.iter().any(is_wave_for_compressed) {
+            quirks.push(Quirk::WaveForCompressed);
+        }
+        quirks
+    }
+}
+#[cfg(feature = "parser")]
+impl Document {
+    /// Same as [`Cuna::quirks()`], also reading the layout of the text and the commands
+    /// the parser does not keep
+    ///
+    /// ```rust
+    /// use cuna::document::Document;
+    /// use cuna::quirks::{Quirk, Tool};
+    ///
+    /// let document = Document::parse("FILE \"a.wav\" WAVE\nTRACK 01 AUDIO\n\tINDEX 01 00:00:00\nREM DATE 2011").unwrap();
+    /// let quirks = document.quirks();
+    /// assert_eq!(quirks.tool, Tool::Unknown);
+    /// assert_eq!(quirks.quirks, [Quirk::LateHeaderRems, Quirk::Unindented, Quirk::TabIndent]);
+    /// ```
+    pub fn quirks(&self) -> Quirks {
+        let mut quirks = self.sheet().quirks();
+        let mut in_file = false;
+        for line in self.lines() {
+            let (keyword, args) = comment::split(line.content());
+            if keyword.eq_ignore_ascii_case("FILE") {
+                in_file = true;
+            } else if keyword.eq_ignore_ascii_case("TRACK") && line.indent().is_empty() {
+                quirks.push(Quirk::Unindented);
+            } else if keyword.eq_ignore_ascii_case("REM") && in_file && comment::is_header_key(args)
+            {
+                quirks.push(Quirk::LateHeaderRems);
+            }
+            if line.indent().contains('\t') {
+                quirks.push(Quirk::TabIndent);
+            }
+            let cd_text = Cdrdao::CD_TEXT
+                .iter()
+                .any(|k| k.eq_ignore_ascii_case(keyword));
+            if quirks.tool == Tool::Unknown && cd_text {
+                quirks.tool = Tool::Cdrdao;
+            }
+        }
+        quirks
+    }
+}
+
+fn signature(comment: &str) -> Option<Tool> {
+    let comment = comment.to_ascii_lowercase();
+    SIGNATURES
+        .iter()
+        .find(|(signature, _)| comment.contains(signature))
+        .map(|(_, tool)| *tool)
+}
+fn is_wave_for_compressed(file: &TrackInfo) -> bool {
+    let extension = file.name.rsplit_once('.').map_or("", |(_, e)| e);
+    file.file_format() == FileFormat::Wave
+        && COMPRESSED.iter().any(|e| e.eq_ignore_ascii_case(extension))
+}
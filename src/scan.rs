@@ -0,0 +1,228 @@
+//! Scanning cue sheets without building a [`Cuna`](crate::Cuna)
+//!
+//! Everything here borrows from the source and never allocates, so a player with little memory
+//! can find where tracks start without parsing the whole sheet. Values are returned without quotes
+//! but with escaped quotes as they are, and lines which can not be understood are skipped.
+//!
+//! This module and [`time`](crate::time) are all that is built without the `std` and `alloc` features,
+//! when the crate is `no_std`.
+//!
+//! ```rust
+//! use cuna::scan;
+//! use cuna::time::TimeStamp;
+//!
+//! let cue = "FILE \"a.flac\" WAVE\r\n  TRACK 01 AUDIO\r\n    INDEX 01 00:00:00\r\n  TRACK 02 AUDIO\r\n    INDEX 01 04:15:44";
+//! let mut lines = scan::lines(cue);
+//! let file = lines.next().unwrap();
+//! assert!(file.is("file"));
+//! assert_eq!(file.args().collect::<Vec<_>>(), ["a.flac", "WAVE"]);
+//!
+//! let second = scan::track_starts(cue).nth(1).unwrap();
+//! assert_eq!((second.file, second.track), ("a.flac", 2));
+//! assert_eq!(second.start, TimeStamp::new(4, 15, 44));
+//! ```
+use crate::time::TimeStamp;
+
+/// A line which is not blank
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Line<'a> {
+    /// The number of the line, starting from 1
+    pub number: usize,
+    pub keyword: &'a str,
+    /// The text after the keyword, without surrounding whitespace
+    pub rest: &'a str,
+}
+/// An iterator over the lines which are not blank, see [`lines()`]
+#[derive(Debug, Clone)]
+pub struct Lines<'a> {
+    source: &'a str,
+    number: usize,
+}
+/// An iterator over the arguments of a [`Line`]
+#[derive(Debug, Clone)]
+pub struct Args<'a> {
+    rest: &'a str,
+}
+/// Where a track starts, see [`track_starts()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TrackStart<'a> {
+    /// The name of the `FILE` of the track
+    pub file: &'a str,
+    pub track: u8,
+    /// `INDEX 01`, or the first `INDEX` if the track has no `INDEX 01`
+    pub start: TimeStamp,
+}
+/// An iterator over where tracks start, see [`track_starts()`]
+#[derive(Debug, Clone)]
+pub struct TrackStarts<'a> {
+    lines: Lines<'a>,
+    file: &'a str,
+    /// The current track with its first `INDEX`, None once it is returned
+    pending: Option<(u8, Option<TimeStamp>)>,
+}
+
+/// Returns the lines of s which are not blank, ending at `\n`, `\r\n` or `\r`
+pub fn lines(s: &str) -> Lines<'_> {
+    Lines {
+        source: s.trim_start_matches('\u{feff}'),
+        number: 0,
+    }
+}
+/// Returns where every track with an `INDEX` starts, in the order of the sheet
+pub fn track_starts(s: &str) -> TrackStarts<'_> {
+    TrackStarts {
+        lines: lines(s),
+        file: "",
+        pending: None,
+    }
+}
+
+impl<'a> Line<'a> {
+    /// Returns true if the keyword is keyword, case-insensitively
+    pub fn is(&self, keyword: &str) -> bool {
+        self.keyword.eq_ignore_ascii_case(keyword)
+    }
+    /// Returns the arguments, quoted ones without the quotes
+    pub fn args(&self) -> Args<'a> {
+        Args { rest: self.rest }
+    }
+}
+impl<'a> Iterator for Lines<'a> {
+    type Item = Line<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.source.is_empty() {
+            let end = self.source.find(['\r', '\n']).unwrap_or(self.source.len());
+            let line = &self.source[..end];
+            let ending = match &self.source.as_bytes()[end..] {
+                [b'\r', b'\n', ..] => 2,
+                [] => 0,
+                _ => 1,
+            };
+            self.source = &self.source[end + ending..];
+            self.number += 1;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (keyword, rest) =
+                line.split_at(line.find(char::is_whitespace).unwrap_or(line.len()));
+            return Some(Line {
+                number: self.number,
+                keyword,
+                rest: rest.trim_start(),
+            });
+        }
+        None
+    }
+}
+impl<'a> Iterator for Args<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+        let (arg, next) = match rest.strip_prefix('"') {
+            Some(quoted) => match closing_quote(quoted) {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            },
+            None => {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+        self.rest = next;
+        Some(arg)
+    }
+}
+impl<'a> TrackStarts<'a> {
+    fn take_pending(&mut self) -> Option<TrackStart<'a>> {
+        match self.pending.take() {
+            Some((track, Some(start))) => Some(TrackStart {
+                file: self.file,
+                track,
+                start,
+            }),
+            _ => None,
+        }
+    }
+}
+impl<'a> Iterator for TrackStarts<'a> {
+    type Item = TrackStart<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(line) = self.lines.next() {
+            let mut args = line.args();
+            if line.is("FILE") {
+                let pending = self.take_pending();
+                self.file = args.next().unwrap_or_default();
+                if pending.is_some() {
+                    return pending;
+                }
+            } else if line.is("TRACK") {
+                let pending = self.take_pending();
+                self.pending = args.next().and_then(number).map(|id| (id as u8, None));
+                if pending.is_some() {
+                    return pending;
+                }
+            } else if line.is("INDEX") {
+                let id = args.next().and_then(number);
+                let time = args.next().and_then(msf);
+                let (track, first) = match &mut self.pending {
+                    Some(pending) => pending,
+                    None => continue,
+                };
+                match (id, time) {
+                    (Some(1), Some(start)) => {
+                        let track = *track;
+                        self.pending = None;
+                        return Some(TrackStart {
+                            file: self.file,
+                            track,
+                            start,
+                        });
+                    }
+                    (Some(_), Some(time)) if first.is_none() => *first = Some(time),
+                    _ => {}
+                }
+            }
+        }
+        self.take_pending()
+    }
+}
+
+/// Parses a number of at most 2 digits
+fn number(s: &str) -> Option<u32> {
+    match s.len() {
+        1 | 2 if s.bytes().all(|b| b.is_ascii_digit()) => s.parse().ok(),
+        _ => None,
+    }
+}
+fn msf(s: &str) -> Option<TimeStamp> {
+    let mut parts = s.split(':');
+    let minutes = parts.next()?;
+    let (seconds, frames) = (parts.next()?, parts.next()?);
+    if parts.next().is_some() || minutes.is_empty() || !minutes.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    TimeStamp::from_msf_opt(minutes.parse().ok()?, number(seconds)?, number(frames)?)
+}
+/// Returns the position of the double quote ending a quoted value, s starts after the opening one
+///
+/// `\"`, `""` and `\\` are escapes, so a value ending with an escaped backslash is closed
+pub(crate) fn closing_quote(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'\\', Some(b'"')) | (b'\\', Some(b'\\')) | (b'"', Some(b'"')) => i += 2,
+            (b'"', _) => return Some(i),
+            _ => i += 1,
+        }
+    }
+    None
+}
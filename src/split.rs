@@ -0,0 +1,192 @@
+//! Checking that a directory of per-track files was split from the image a cue sheet describes
+use crate::time::TimeStamp;
+use crate::timeline::PostgapPolicy;
+use crate::Cuna;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Extensions of the files [`Cuna::verify_split()`](crate::Cuna::verify_split) looks at
+pub const AUDIO_EXTENSIONS: &[&str] = &["flac", "wav", "wave"];
+
+/// The comparison of a track with the file split from it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TrackCheck {
+    pub id: u8,
+    pub path: PathBuf,
+    /// Length computed from the cue sheet, see [`timeline`](crate::timeline)
+    pub expected: Option<TimeStamp>,
+    /// Length of the file, None if it could not be read
+    pub actual: Option<TimeStamp>,
+}
+/// The result of [`Cuna::verify_split()`](crate::Cuna::verify_split)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SplitReport {
+    pub tracks: Vec<TrackCheck>,
+    /// Tracks without a file
+    pub missing: Vec<u8>,
+    /// Files without a track
+    pub extra: Vec<PathBuf>,
+    tolerance: u32,
+}
+
+impl TrackCheck {
+    /// Returns `actual - expected` in frames, or None if either is unknown
+    pub fn drift(&self) -> Option<i64> {
+        let (expected, actual) = (self.expected?, self.actual?);
+        Some(actual.as_frames() as i64 - expected.as_frames() as i64)
+    }
+}
+impl SplitReport {
+    /// Returns the tracks whose length differs by more than the tolerance
+    pub fn drifted(&self) -> impl Iterator<Item = &TrackCheck> {
+        let tolerance = self.tolerance as i64;
+        self.tracks
+            .iter()
+            .filter(move |c| c.drift().is_some_and(|d| d.abs() > tolerance))
+    }
+    /// Returns true if every track has a file, there are no other files
+    /// and no known length differs by more than the tolerance
+    pub fn is_match(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.drifted().next().is_none()
+    }
+}
+impl Cuna {
+    /// Compares the length of each track with the audio files in dir, within tolerance frames
+    ///
+    /// Files with an extension in [`AUDIO_EXTENSIONS`] are sorted by name and matched to the tracks in order.
+    /// The lengths of WAVE and FLAC files are read from their headers, use
+    /// [`Cuna::verify_split_with()`](Cuna::verify_split_with) for other formats.
+    ///
+    /// file_lengths are the lengths of the `FILE`s of the sheet in the order they appear,
+    /// the last track of a `FILE` without one has no expected length and is never flagged,
+    /// see [`Cuna::timeline_with_lengths()`](Cuna::timeline_with_lengths)
+    pub fn verify_split(
+        &self,
+        dir: impl AsRef<Path>,
+        tolerance: u32,
+        file_lengths: &[TimeStamp],
+    ) -> io::Result<SplitReport> {
+        self.verify_split_with(dir, tolerance, file_lengths, |path| {
+            probe_duration(path).ok().flatten()
+        })
+    }
+    /// Same as [`Cuna::verify_split()`](Cuna::verify_split), probe returns the length of a file
+    pub fn verify_split_with(
+        &self,
+        dir: impl AsRef<Path>,
+        tolerance: u32,
+        file_lengths: &[TimeStamp],
+        probe: impl Fn(&Path) -> Option<Duration>,
+    ) -> io::Result<SplitReport> {
+        self.verify_split_with_policy(dir, tolerance, file_lengths, PostgapPolicy::Ignore, probe)
+    }
+    /// Same as [`Cuna::verify_split_with()`](Cuna::verify_split_with), for files which
+    /// include the `POSTGAP` of their track if the policy is [`PostgapPolicy::Append`]
+    pub fn verify_split_with_policy(
+        &self,
+        dir: impl AsRef<Path>,
+        tolerance: u32,
+        file_lengths: &[TimeStamp],
+        postgap: PostgapPolicy,
+        probe: impl Fn(&Path) -> Option<Duration>,
+    ) -> io::Result<SplitReport> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let is_audio = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| AUDIO_EXTENSIONS.iter().any(|a| a.eq_ignore_ascii_case(e)));
+            if is_audio && path.is_file() {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        let mut paths = paths.into_iter();
+        let mut report = SplitReport {
+            tolerance,
+            ..SplitReport::default()
+        };
+        for span in self.timeline_with_policy(file_lengths, postgap) {
+            match paths.next() {
+                Some(path) => report.tracks.push(TrackCheck {
+                    id: span.id,
+                    actual: probe(&path).map(TimeStamp::from_duration_rounded),
+                    expected: span.length,
+                    path,
+                }),
+                None => report.missing.push(span.id),
+            }
+        }
+        report.extra = paths.collect();
+        Ok(report)
+    }
+}
+
+/// Reads the length of a WAVE or FLAC file from its header, returns None for other formats
+pub fn probe_duration(path: impl AsRef<Path>) -> io::Result<Option<Duration>> {
+    let mut file = File::open(path)?;
+    let mut magic = [0; 4];
+    file.read_exact(&mut magic)?;
+    match &magic {
+        b"RIFF" => probe_wave(&mut file),
+        b"fLaC" => probe_flac(&mut file),
+        _ => Ok(None),
+    }
+}
+
+fn probe_wave(file: &mut File) -> io::Result<Option<Duration>> {
+    let mut header = [0; 8];
+    file.read_exact(&mut header[..8])?;
+    if &header[4..8] != b"WAVE" {
+        return Ok(None);
+    }
+    let mut byte_rate = None;
+    loop {
+        if file.read(&mut header)? < 8 {
+            return Ok(None);
+        }
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        match &header[..4] {
+            b"fmt " => {
+                let mut fmt = [0; 12];
+                file.read_exact(&mut fmt)?;
+                byte_rate = Some(u32::from_le_bytes([fmt[8], fmt[9], fmt[10], fmt[11]]));
+                file.seek(SeekFrom::Current(size as i64 - 12 + (size & 1) as i64))?;
+            }
+            b"data" => {
+                return Ok(byte_rate
+                    .filter(|r| *r > 0)
+                    .map(|r| Duration::from_secs_f64(size as f64 / r as f64)))
+            }
+            _ => {
+                file.seek(SeekFrom::Current(size as i64 + (size & 1) as i64))?;
+            }
+        }
+    }
+}
+fn probe_flac(file: &mut File) -> io::Result<Option<Duration>> {
+    // STREAMINFO is always the first metadata block
+    let mut block = [0; 4 + 18];
+    file.read_exact(&mut block)?;
+    if block[0] & 0x7f != 0 {
+        return Ok(None);
+    }
+    let info = &block[4..];
+    let sample_rate = (info[10] as u32) << 12 | (info[11] as u32) << 4 | (info[12] as u32) >> 4;
+    let samples = (info[13] as u64 & 0x0f) << 32
+        | u32::from_be_bytes([info[14], info[15], info[16], info[17]]) as u64;
+    if sample_rate == 0 || samples == 0 {
+        return Ok(None);
+    }
+    Ok(Some(Duration::from_secs_f64(
+        samples as f64 / sample_rate as f64,
+    )))
+}
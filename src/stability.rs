@@ -0,0 +1,81 @@
+//! The stability of each public module
+//!
+//! [`Stable`](Stability::Stable) modules follow semver: their public API, recorded in
+//! `tests/public-api.txt` and checked by `tests/api.rs`, only changes incompatibly with the minor version
+//! while the crate is 0.x. [`Experimental`](Stability::Experimental) modules live under
+//! [`experimental`](crate::experimental), need the `unstable` feature and may change in any release.
+//!
+//! ```rust
+//! use cuna::stability::{self, Stability};
+//! assert_eq!(stability::of("writer"), Some(Stability::Stable));
+//! assert_eq!(stability::of("experimental"), Some(Stability::Experimental));
+//! ```
+//!
+//! # Order and determinism
+//!
+//! The following is guaranteed by every stable module and changes only like the public API does,
+//! so hashes and caches of written sheets stay valid:
+//! - A [`Cuna`](crate::Cuna) keeps `FILE`s, `TRACK`s, `INDEX`es, `REM`s, `FLAGS` and repeated
+//!   `TITLE`, `PERFORMER` and `SONGWRITER` values in the order of the input, without sorting
+//!   or removing duplicates. Only methods documented to reorder, like
+//!   [`Comment::standard_rem_block()`](crate::comment::Comment::standard_rem_block), do.
+//! - Iterators like [`Cuna::tracks()`](crate::Cuna::tracks) return items in that order.
+//! - Writing and serializing, with [`CueWriter`](crate::writer::CueWriter) or serde, depend only on
+//!   the sheet and the options: the same input gives byte-identical output on every run and platform.
+//!   No hash maps or other unordered collections are involved.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stability {
+    Stable,
+    Experimental,
+}
+
+/// Every public module of the crate root with its stability
+pub const MODULES: &[(&str, Stability)] = &[
+    ("anonymize", Stability::Stable),
+    ("comment", Stability::Stable),
+    ("completeness", Stability::Stable),
+    ("cst", Stability::Stable),
+    ("cuna", Stability::Stable),
+    ("date", Stability::Stable),
+    ("decode", Stability::Stable),
+    ("dialect", Stability::Stable),
+    ("diff", Stability::Stable),
+    ("document", Stability::Stable),
+    ("error", Stability::Stable),
+    ("experimental", Stability::Experimental),
+    ("export", Stability::Stable),
+    ("format", Stability::Stable),
+    ("header", Stability::Stable),
+    ("json", Stability::Stable),
+    ("lazy", Stability::Stable),
+    ("message", Stability::Stable),
+    ("mojibake", Stability::Stable),
+    ("parser", Stability::Stable),
+    ("partition", Stability::Stable),
+    ("paths", Stability::Stable),
+    ("quirks", Stability::Stable),
+    ("scan", Stability::Stable),
+    ("split", Stability::Stable),
+    ("stability", Stability::Stable),
+    ("time", Stability::Stable),
+    ("timeline", Stability::Stable),
+    ("toc", Stability::Stable),
+    ("toml", Stability::Stable),
+    ("track", Stability::Stable),
+    ("transform", Stability::Stable),
+    ("utils", Stability::Stable),
+    ("validation", Stability::Stable),
+    ("visit", Stability::Stable),
+    ("watch", Stability::Stable),
+    ("writer", Stability::Stable),
+    ("yaml", Stability::Stable),
+];
+
+/// Returns the stability of a module of the crate root, or None if there is no such module
+pub fn of(module: &str) -> Option<Stability> {
+    MODULES
+        .iter()
+        .find(|(name, _)| *name == module)
+        .map(|(_, stability)| *stability)
+}
@@ -1,14 +1,54 @@
 use crate::error::InvalidArgument;
-use crate::utils::number;
-use nom::bytes::complete::tag;
-use nom::character::complete::digit1;
-use nom::combinator::map;
-use nom::sequence::terminated;
-use nom::sequence::tuple;
-use std::fmt;
-use std::str::FromStr;
-use std::time::Duration;
+use core::convert::TryFrom;
+use core::fmt;
+use core::str::FromStr;
+use core::time::Duration;
 
+/// A component of a `MM:SS:FF` timestamp
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Copy)]
+pub enum Component {
+    Minutes,
+    Seconds,
+    Frames,
+}
+/// How a time between two frames is turned into a frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Rounding {
+    /// The nearest frame, halfway rounds up, which is the default
+    #[default]
+    Nearest,
+    /// The frame before
+    Down,
+    /// The frame after
+    Up,
+}
+/// A form of timestamps, see [`TimeStamp::parse_lenient()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeFormat {
+    /// `MM:SS:FF`, the form of cue sheets
+    Msf,
+    /// `MM:SS.mmm`, see [`TimeStamp::from_decimal_seconds()`]
+    DecimalSeconds,
+    /// `H:MM:SS` or `H:MM:SS.mmm`, see [`TimeStamp::from_hms()`]
+    Hms,
+}
+/// How [`TimeStamp::human()`] shows a time to people, in whole seconds with the frames dropped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum HumanFormat {
+    /// `MM:SS`, with more digits of minutes from 100 minutes like `104:31`, which is the default
+    #[default]
+    MinutesSeconds,
+    /// `HH:MM:SS`
+    HoursMinutesSeconds,
+    /// `MM:SS` under an hour, `H:MM:SS` from an hour
+    Auto,
+}
+/// A TimeStamp displayed in a [`HumanFormat`], returned by [`TimeStamp::human()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HumanTime {
+    time: TimeStamp,
+    format: HumanFormat,
+}
 #[derive(Debug, PartialEq, Eq, Clone, Default, Hash, Copy)]
 pub struct TimeStamp {
     seconds: u32,
@@ -16,16 +56,24 @@ pub struct TimeStamp {
 }
 
 impl TimeStamp {
+    /// The most minutes a timestamp of a cue sheet may have, which 3 digits allow
+    /// for 90-minute and overburned discs
+    pub const MAX_MINUTES: u32 = 999;
+    /// The most minutes the `MM` of a Red Book timestamp allows
+    pub const RED_BOOK_MAX_MINUTES: u32 = 99;
     /// Constructs a new TimeStamp with minutes, seconds and frames
     ///
     /// # Panics
     ///
     /// Panics if seconds >= 60 or frames >= 75
-    pub fn new(minutes: u32, seconds: u32, frames: u32) -> Self {
-        Self::from_msf_opt(minutes, seconds, frames).expect("Invalid time")
+    pub const fn new(minutes: u32, seconds: u32, frames: u32) -> Self {
+        match Self::from_msf_opt(minutes, seconds, frames) {
+            Some(timestamp) => timestamp,
+            None => panic!("Invalid time"),
+        }
     }
     /// Constructs a new TimeStamp with minutes, seconds and frames, or returns None if seconds >= 60 or frames >= 75
-    pub fn from_msf_opt(minutes: u32, seconds: u32, frames: u32) -> Option<Self> {
+    pub const fn from_msf_opt(minutes: u32, seconds: u32, frames: u32) -> Option<Self> {
         if seconds >= 60 || frames >= 75 {
             None
         } else {
@@ -61,9 +109,126 @@ impl TimeStamp {
     pub const fn from_frames(frms: u32) -> Self {
         Self::from_msf(0, 0, frms)
     }
+    /// Constructs a new TimeStamp with the frame nearest to a Duration
+    ///
+    /// Unlike `From<Duration>`, which truncates to milliseconds first, this rounds to the nearest frame
+    /// ``` rust
+    /// use cuna::time::TimeStamp;
+    /// use std::time::Duration;
+    /// let ts = TimeStamp::from_duration_rounded(Duration::from_nanos(1_026_666_667));
+    /// assert_eq!(ts, TimeStamp::new(0, 1, 2));
+    /// ```
+    pub fn from_duration_rounded(duration: Duration) -> Self {
+        let frames = (duration.as_nanos() * 75 + 500_000_000) / 1_000_000_000;
+        Self::from_frames(frames as u32)
+    }
+    /// Parses a `MM:SS.mmm` timestamp with decimal seconds, as chapter lists have,
+    /// or returns None if s is not one. The fraction may have 1 to 9 digits
+    ///
+    /// ``` rust
+    /// use cuna::time::{Rounding, TimeStamp};
+    /// assert_eq!(TimeStamp::from_decimal_seconds("04:15.500", Rounding::Nearest), Some(TimeStamp::new(4, 15, 38)));
+    /// assert_eq!(TimeStamp::from_decimal_seconds("04:15.500", Rounding::Down), Some(TimeStamp::new(4, 15, 37)));
+    /// assert_eq!(TimeStamp::from_decimal_seconds("00:59.999", Rounding::Up), Some(TimeStamp::new(1, 0, 0)));
+    /// assert_eq!(TimeStamp::from_decimal_seconds("04:15:44", Rounding::Nearest), None);
+    /// ```
+    pub fn from_decimal_seconds(s: &str, rounding: Rounding) -> Option<Self> {
+        let (minutes, rest) = split_digits(s).filter(|(_, rest)| rest.starts_with(':'))?;
+        let (seconds, rest) = split_digits(&rest[1..])
+            .filter(|(seconds, rest)| seconds.len() == 2 && rest.starts_with('.'))?;
+        let seconds: u64 = seconds.parse().ok().filter(|&seconds| seconds < 60)?;
+        let minutes: u32 = minutes.parse().ok()?;
+        let fraction = Some(&rest[1..]).filter(|fraction| !fraction.is_empty())?;
+        Self::from_fraction(minutes as u64 * 60 + seconds, fraction, rounding)
+    }
+    /// Parses a `H:MM:SS` timestamp of hours, minutes and seconds, which video chapters have,
+    /// or returns None if s is not one. Seconds may have a fraction of 1 to 9 digits, rounded as
+    /// [`from_decimal_seconds()`](TimeStamp::from_decimal_seconds) does
+    ///
+    /// `1:02:03` is also a `MM:SS:FF` timestamp, so this is only right when the form is known
+    ///
+    /// ``` rust
+    /// use cuna::time::{Rounding, TimeStamp};
+    /// assert_eq!(TimeStamp::from_hms("1:02:03", Rounding::Nearest), Some(TimeStamp::new(62, 3, 0)));
+    /// assert_eq!(TimeStamp::from_hms("0:03:25.5", Rounding::Down), Some(TimeStamp::new(3, 25, 37)));
+    /// assert_eq!(TimeStamp::from_hms("1:62:03", Rounding::Nearest), None);
+    /// ```
+    pub fn from_hms(s: &str, rounding: Rounding) -> Option<Self> {
+        let mut parts = s.splitn(3, ':');
+        let hours = parts.next().filter(|h| (1..=2).contains(&h.len()))?;
+        let minutes = parts.next().filter(|m| m.len() == 2)?;
+        let (seconds, fraction) = split_digits(parts.next()?)?;
+        let fraction = match fraction {
+            "" => "",
+            fraction => fraction.strip_prefix('.').filter(|f| !f.is_empty())?,
+        };
+        let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if seconds.len() != 2 || !all_digits(hours) || !all_digits(minutes) {
+            return None;
+        }
+        let (hours, minutes, seconds): (u64, u64, u64) = (
+            hours.parse().ok()?,
+            minutes.parse().ok()?,
+            seconds.parse().ok()?,
+        );
+        if minutes >= 60 || seconds >= 60 || hours * 60 + minutes > Self::MAX_MINUTES as u64 {
+            return None;
+        }
+        Self::from_fraction((hours * 60 + minutes) * 60 + seconds, fraction, rounding)
+    }
+    /// Parses a timestamp in any [`TimeFormat`], returning the form it is in
+    ///
+    /// `MM:SS:FF` is tried first, so `1:02:03` is never read as hours, see [`from_hms()`](TimeStamp::from_hms)
+    ///
+    /// ``` rust
+    /// use cuna::time::{Rounding, TimeFormat, TimeStamp};
+    /// let parse = |s| TimeStamp::parse_lenient(s, Rounding::Nearest);
+    /// assert_eq!(parse("03:25:37"), Some((TimeStamp::new(3, 25, 37), TimeFormat::Msf)));
+    /// assert_eq!(parse("03:25.500"), Some((TimeStamp::new(3, 25, 38), TimeFormat::DecimalSeconds)));
+    /// assert_eq!(parse("1:02:03.000"), Some((TimeStamp::new(62, 3, 0), TimeFormat::Hms)));
+    /// ```
+    pub fn parse_lenient(s: &str, rounding: Rounding) -> Option<(Self, TimeFormat)> {
+        if let Ok(timestamp) = s.parse() {
+            return Some((timestamp, TimeFormat::Msf));
+        }
+        if let Some(timestamp) = Self::from_decimal_seconds(s, rounding) {
+            return Some((timestamp, TimeFormat::DecimalSeconds));
+        }
+        Self::from_hms(s, rounding).map(|timestamp| (timestamp, TimeFormat::Hms))
+    }
+    /// Rounds whole seconds and the digits of a fraction of a second to a frame
+    fn from_fraction(seconds: u64, fraction: &str, rounding: Rounding) -> Option<Self> {
+        if fraction.len() > 9 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let scale = 10u64.pow(fraction.len() as u32);
+        let scaled = fraction.parse::<u64>().unwrap_or(0) * 75;
+        let frames = match rounding {
+            Rounding::Nearest => (scaled + scale / 2) / scale,
+            Rounding::Down => scaled / scale,
+            Rounding::Up => scaled.div_ceil(scale),
+        };
+        u32::try_from(seconds * 75 + frames)
+            .ok()
+            .map(Self::from_frames)
+    }
     pub const fn minutes(&self) -> u32 {
         self.seconds / 60
     }
+    /// Displays the time in a form for reports or generated `REM`s rather than for `INDEX`
+    ///
+    /// ``` rust
+    /// use cuna::time::{HumanFormat, TimeStamp};
+    /// let time = TimeStamp::new(4, 31, 74);
+    /// assert_eq!(time.human(HumanFormat::MinutesSeconds).to_string(), "04:31");
+    /// assert_eq!(time.human(HumanFormat::HoursMinutesSeconds).to_string(), "00:04:31");
+    /// let long = TimeStamp::new(75, 2, 0);
+    /// assert_eq!(long.human(HumanFormat::MinutesSeconds).to_string(), "75:02");
+    /// assert_eq!(long.human(HumanFormat::Auto).to_string(), "1:15:02");
+    /// ```
+    pub const fn human(self, format: HumanFormat) -> HumanTime {
+        HumanTime { time: self, format }
+    }
     pub const fn seconds(&self) -> u32 {
         self.seconds % 60
     }
@@ -95,21 +260,76 @@ impl TimeStamp {
     pub const fn as_frames(&self) -> u32 {
         self.as_seconds() * 75 + self.frames()
     }
+    /// Returns true if self and other are at most tolerance frames apart
+    ///
+    /// ``` rust
+    /// use cuna::time::TimeStamp;
+    /// let ts = TimeStamp::new(4, 15, 44);
+    /// assert!(ts.approx_eq(&TimeStamp::new(4, 15, 42), 2));
+    /// assert!(!ts.approx_eq(&TimeStamp::new(4, 16, 0), 2));
+    /// ```
+    pub const fn approx_eq(&self, other: &Self, tolerance: u32) -> bool {
+        self.as_frames().abs_diff(other.as_frames()) <= tolerance
+    }
+    /// Returns `self - other`, or None if other is later than self
+    ///
+    /// ``` rust
+    /// use cuna::time::TimeStamp;
+    /// let ts = TimeStamp::new(4, 15, 44);
+    /// assert_eq!(ts.checked_sub(TimeStamp::new(0, 15, 50)), Some(TimeStamp::new(3, 59, 69)));
+    /// assert_eq!(TimeStamp::default().checked_sub(ts), None);
+    /// ```
+    pub const fn checked_sub(self, other: Self) -> Option<Self> {
+        match self.as_frames().checked_sub(other.as_frames()) {
+            Some(frames) => Some(Self::from_frames(frames)),
+            None => None,
+        }
+    }
 }
 impl FromStr for TimeStamp {
     type Err = InvalidArgument;
 
+    /// Parses a `MM:SS:FF` timestamp, see [`utils::msf`](crate::utils::msf) to use it in a parser
+    ///
+    /// Minutes may have 1 to 3 digits, see [`MAX_MINUTES`](TimeStamp::MAX_MINUTES)
+    ///
+    /// ```rust
+    /// use cuna::error::InvalidArgument;
+    /// use cuna::time::{Component, TimeStamp};
+    /// assert_eq!(
+    ///     "04:15:80".parse::<TimeStamp>(),
+    ///     Err(InvalidArgument::MalformedTimestamp { component: Component::Frames, column: 7 })
+    /// );
+    /// assert_eq!("100:23:45".parse::<TimeStamp>().unwrap().to_string(), "100:23:45");
+    /// assert!("1000:00:00".parse::<TimeStamp>().is_err());
+    /// ```
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (_, (minutes, seconds, frames)) = tuple((
-            terminated(map(digit1, |d: &str| d.parse().unwrap()), tag(":")),
-            terminated(number(2), tag(":")),
-            number(2),
-        ))(s)
-        .map_err(|_| InvalidArgument::InvalidTimestamp)?;
-        Ok(
-            Self::from_msf_opt(minutes, seconds, frames)
-                .ok_or(InvalidArgument::InvalidTimestamp)?,
-        )
+        let malformed = |component, i: &str| InvalidArgument::MalformedTimestamp {
+            component,
+            column: s.len() - i.len() + 1,
+        };
+        let (minutes, i) = split_digits(s)
+            .filter(|(minutes, i)| minutes.len() <= 3 && i.starts_with(':'))
+            .and_then(|(minutes, i)| Some((minutes.parse().ok()?, &i[1..])))
+            .ok_or_else(|| malformed(Component::Minutes, s))?;
+        let (seconds, f) = split_digits(i)
+            .filter(|(seconds, f)| seconds.len() == 2 && f.starts_with(':'))
+            .map(|(seconds, f)| (seconds.parse().unwrap_or(u32::MAX), &f[1..]))
+            .filter(|(seconds, _)| *seconds < 60)
+            .ok_or_else(|| malformed(Component::Seconds, i))?;
+        let frames = split_digits(f)
+            .filter(|(frames, rest)| frames.len() == 2 && rest.is_empty())
+            .map(|(frames, _)| frames.parse().unwrap_or(u32::MAX))
+            .filter(|frames| *frames < 75)
+            .ok_or_else(|| malformed(Component::Frames, f))?;
+        Ok(Self::from_msf(minutes, seconds, frames))
+    }
+}
+/// Splits leading ASCII digits, or returns None if there is none
+fn split_digits(s: &str) -> Option<(&str, &str)> {
+    match s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()) {
+        0 => None,
+        i => Some(s.split_at(i)),
     }
 }
 impl fmt::Display for TimeStamp {
@@ -123,6 +343,44 @@ impl fmt::Display for TimeStamp {
         )
     }
 }
+impl fmt::Display for HumanTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.time.as_seconds();
+        let (hours, minutes) = (seconds / 3600, seconds / 60 % 60);
+        match self.format {
+            HumanFormat::MinutesSeconds => write!(f, "{:02}:{:02}", seconds / 60, seconds % 60),
+            HumanFormat::HoursMinutesSeconds => {
+                write!(f, "{:02}:{:02}:{:02}", hours, minutes, seconds % 60)
+            }
+            HumanFormat::Auto if hours == 0 => write!(f, "{:02}:{:02}", minutes, seconds % 60),
+            HumanFormat::Auto => write!(f, "{}:{:02}:{:02}", hours, minutes, seconds % 60),
+        }
+    }
+}
+impl fmt::Display for Component {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Minutes => "minutes",
+            Self::Seconds => "seconds",
+            Self::Frames => "frames",
+        };
+        f.write_str(s)
+    }
+}
+#[cfg(feature = "serde")]
+impl serde::Serialize for TimeStamp {
+    /// Serializes as a `MM:SS:FF` string
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for TimeStamp {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <alloc::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
 impl From<TimeStamp> for Duration {
     fn from(ti: TimeStamp) -> Duration {
         Duration::from_secs(ti.seconds as u64)
@@ -0,0 +1,114 @@
+//! Where tracks start and how long they are
+//!
+//! A track starts at `INDEX 01` (or its first `INDEX`) and lasts until the next track of the same `FILE` starts,
+//! so its length includes the pregap of the next track, which is how EAC splits images by default.
+//! The length of the last track of a `FILE` is only known if the length of the `FILE` is given.
+//!
+//! `POSTGAP` is silence which is not in the `FILE`, usually after the last track.
+//! Whether it counts towards the length of its track depends on the [`PostgapPolicy`]
+use crate::time::TimeStamp;
+use crate::Cuna;
+
+/// Whether the `POSTGAP` of a track is part of its length
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PostgapPolicy {
+    /// The length is only what is in the `FILE`, which is the default
+    #[default]
+    Ignore,
+    /// The silence is appended to the track, as a splitter writing it out would do
+    Append,
+}
+
+/// A track placed on the timeline of its `FILE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// Position of the `FILE` in [`Cuna::files`](crate::Cuna::files)
+    pub file: usize,
+    /// Position of the `TRACK` in [`TrackInfo::tracks`](crate::track::TrackInfo::tracks)
+    pub track: usize,
+    pub id: u8,
+    /// None if the track has no `INDEX`
+    pub start: Option<TimeStamp>,
+    pub length: Option<TimeStamp>,
+}
+
+impl Cuna {
+    /// Returns the start and the length of every track, the last track of each `FILE` has an unknown length
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// use cuna::time::TimeStamp;
+    /// let sheet = Cuna::new(r#"FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO
+    ///     INDEX 01 00:00:00
+    ///   TRACK 02 AUDIO
+    ///     INDEX 00 04:12:21
+    ///     INDEX 01 04:15:09"#).unwrap();
+    /// let timeline = sheet.timeline();
+    /// assert_eq!(timeline[0].length, Some(TimeStamp::new(4, 15, 9)));
+    /// assert_eq!(timeline[1].start, Some(TimeStamp::new(4, 15, 9)));
+    /// assert_eq!(timeline[1].length, None);
+    /// # }
+    /// ```
+    pub fn timeline(&self) -> Vec<Span> {
+        self.timeline_with_lengths(&[])
+    }
+    /// Same as [`Cuna::timeline()`](Cuna::timeline), with the lengths of the `FILE`s in the order they appear
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// use cuna::time::TimeStamp;
+    /// let sheet = Cuna::new("FILE \"a.flac\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:02:00").unwrap();
+    /// let timeline = sheet.timeline_with_lengths(&[TimeStamp::new(3, 0, 0)]);
+    /// assert_eq!(timeline[0].length, Some(TimeStamp::new(2, 58, 0)));
+    /// # }
+    /// ```
+    pub fn timeline_with_lengths(&self, file_lengths: &[TimeStamp]) -> Vec<Span> {
+        self.timeline_with_policy(file_lengths, PostgapPolicy::Ignore)
+    }
+    /// Same as [`Cuna::timeline_with_lengths()`](Cuna::timeline_with_lengths), counting `POSTGAP` as the policy says
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// use cuna::time::TimeStamp;
+    /// use cuna::timeline::PostgapPolicy;
+    /// let sheet = Cuna::new("FILE \"a.flac\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nPOSTGAP 00:02:00").unwrap();
+    /// let lengths = [TimeStamp::new(3, 0, 0)];
+    /// let timeline = sheet.timeline_with_policy(&lengths, PostgapPolicy::Append);
+    /// assert_eq!(timeline[0].length, Some(TimeStamp::new(3, 2, 0)));
+    /// # }
+    /// ```
+    pub fn timeline_with_policy(
+        &self,
+        file_lengths: &[TimeStamp],
+        postgap: PostgapPolicy,
+    ) -> Vec<Span> {
+        let mut spans = Vec::new();
+        for (file, info) in self.files.iter().enumerate() {
+            let starts: Vec<_> = info.tracks.iter().map(|tk| tk.start()).collect();
+            for (track, tk) in info.tracks.iter().enumerate() {
+                let end = match starts.get(track + 1) {
+                    Some(next) => *next,
+                    None => file_lengths.get(file).copied(),
+                };
+                let start = starts[track];
+                let mut length = start.zip(end).and_then(|(s, e)| e.checked_sub(s));
+                if let (PostgapPolicy::Append, Some(gap)) = (postgap, tk.postgap()) {
+                    length =
+                        length.map(|l| TimeStamp::from_frames(l.as_frames() + gap.as_frames()));
+                }
+                spans.push(Span {
+                    file,
+                    track,
+                    id: tk.id(),
+                    start,
+                    length,
+                })
+            }
+        }
+        spans
+    }
+}
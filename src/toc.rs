@@ -0,0 +1,262 @@
+//! Tables of contents read from discs: checking a cue sheet against one, or generating one from it
+//!
+//! Positions are LBAs: frames from the start of track 1, without the 2 seconds (150 frames) of lead-in.
+//! The position of a track in the cue sheet is its `INDEX 01` plus the start of its `FILE`
+//! and every `PREGAP` and `POSTGAP` before it. The first `FILE` starts at 0,
+//! the following ones only if the lengths of the `FILE`s before them are given.
+//!
+//! ```rust
+//! # #[cfg(feature = "parser")] {
+//! use cuna::toc::Toc;
+//! use cuna::Cuna;
+//!
+//! let sheet = Cuna::new(r#"FILE "a.flac" WAVE
+//!   TRACK 01 AUDIO
+//!     INDEX 01 00:00:00
+//!   TRACK 02 AUDIO
+//!     INDEX 01 04:15:44"#).unwrap();
+//! let report = sheet.verify_against_toc(&Toc::new(vec![0, 19169], 36379));
+//! assert_eq!(report.is_match(), Some(true));
+//! let shifted = sheet.verify_against_toc(&Toc::new(vec![30, 19199, 25000], 36409));
+//! assert_eq!(report.common_offset(), Some(0));
+//! assert_eq!(shifted.common_offset(), Some(-30));
+//! assert_eq!(shifted.missing, [3]);
+//! # }
+//! ```
+use crate::error::TocError;
+use crate::time::TimeStamp;
+use crate::track::Index;
+use crate::track::Track;
+use crate::track::TrackInfo;
+use crate::Cuna;
+use std::convert::TryFrom;
+
+/// A position on a disc in frames, see [`toc`](crate::toc)
+pub type Lba = u32;
+
+/// A table of contents read from a disc
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Toc {
+    /// The number of the first track, usually 1
+    pub first_track: u8,
+    /// The LBA each track starts at, in order
+    pub starts: Vec<Lba>,
+    /// The LBA of the lead-out, where the last track ends
+    pub leadout: Lba,
+}
+/// What to put in a cue sheet generated by [`Cuna::from_toc()`](Cuna::from_toc) besides the positions
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TocMetadata {
+    /// The name of the image `FILE`, `CDImage.wav` by default
+    pub file: String,
+    /// The format of the `FILE`, `WAVE` by default
+    pub format: String,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    /// Titles of the tracks in order, tracks after the last title have none
+    pub track_titles: Vec<String>,
+}
+/// The comparison of a track of the cue sheet with the disc
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TocCheck {
+    pub id: u8,
+    /// The LBA computed from the cue sheet, None if it is unknown
+    pub cue: Option<Lba>,
+    pub disc: Lba,
+}
+/// The result of [`Cuna::verify_against_toc()`](Cuna::verify_against_toc)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct TocReport {
+    /// Tracks which are both in the cue sheet and on the disc
+    pub tracks: Vec<TocCheck>,
+    /// Tracks on the disc which are not in the cue sheet
+    pub missing: Vec<u8>,
+    /// Tracks in the cue sheet which are not on the disc
+    pub extra: Vec<u8>,
+    /// `cue - disc` of the lead-out in frames, None if the length of the last `FILE` is unknown
+    pub leadout_offset: Option<i64>,
+}
+
+impl Toc {
+    /// Constructs a TOC whose first track is track 1
+    pub fn new(starts: Vec<Lba>, leadout: Lba) -> Self {
+        Self {
+            first_track: 1,
+            starts,
+            leadout,
+        }
+    }
+    /// Returns the LBA of a track, or None if it is not on the disc
+    pub fn start(&self, id: u8) -> Option<Lba> {
+        let i = id.checked_sub(self.first_track)?;
+        self.starts.get(i as usize).copied()
+    }
+}
+impl Default for TocMetadata {
+    fn default() -> Self {
+        Self {
+            file: "CDImage.wav".to_owned(),
+            format: "WAVE".to_owned(),
+            title: None,
+            performer: None,
+            track_titles: Vec::new(),
+        }
+    }
+}
+impl TocCheck {
+    /// Returns `cue - disc` in frames, or None if the position in the cue sheet is unknown
+    pub fn offset(&self) -> Option<i64> {
+        Some(self.cue? as i64 - self.disc as i64)
+    }
+}
+impl TocReport {
+    /// Returns the tracks whose known position differs from the disc
+    pub fn mismatched(&self) -> impl Iterator<Item = &TocCheck> {
+        self.tracks
+            .iter()
+            .filter(|c| c.offset().is_some_and(|o| o != 0))
+    }
+    /// Returns the offset shared by every track whose position is known,
+    /// or None if they differ or no position is known
+    ///
+    /// A common offset other than 0 usually means the sheet is for another pressing of the disc
+    pub fn common_offset(&self) -> Option<i64> {
+        let mut offsets = self.tracks.iter().filter_map(TocCheck::offset);
+        let first = offsets.next()?;
+        offsets.all(|o| o == first).then_some(first)
+    }
+    /// Returns true if the sheet and the disc have the same tracks at the same positions,
+    /// and the same lead-out if it is known. None if they do not differ but the position
+    /// of a track in the sheet is unknown
+    pub fn is_match(&self) -> Option<bool> {
+        let differs = !self.missing.is_empty()
+            || !self.extra.is_empty()
+            || self.mismatched().next().is_some()
+            || self.leadout_offset.is_some_and(|o| o != 0);
+        if differs {
+            Some(false)
+        } else if self.tracks.iter().any(|c| c.cue.is_none()) {
+            None
+        } else {
+            Some(true)
+        }
+    }
+}
+impl Cuna {
+    /// Generates a cue sheet for an image of a whole disc from the positions of its tracks,
+    /// e.g. before looking up the metadata of the disc
+    ///
+    /// Every track is `AUDIO` with an `INDEX 01` at its LBA, and the first track is track 1.
+    /// The lead-out is only checked to be after the last track
+    ///
+    /// ```rust
+    /// use cuna::toc::TocMetadata;
+    /// use cuna::Cuna;
+    ///
+    /// let metadata = TocMetadata {
+    ///     title: Some("Departures".to_owned()),
+    ///     track_titles: vec!["Departures".to_owned()],
+    ///     ..TocMetadata::default()
+    /// };
+    /// let sheet = Cuna::from_toc_points(&[0, 19169], 36379, &metadata).unwrap();
+    /// assert_eq!(sheet.to_string(), r#"TITLE "Departures"
+    /// FILE "CDImage.wav" WAVE
+    ///   TRACK 01 AUDIO
+    ///     TITLE "Departures"
+    ///     INDEX 01 00:00:00
+    ///   TRACK 02 AUDIO
+    ///     INDEX 01 04:15:44
+    /// "#);
+    /// ```
+    pub fn from_toc_points(
+        starts: &[Lba],
+        leadout: Lba,
+        metadata: &TocMetadata,
+    ) -> Result<Self, TocError> {
+        Self::from_toc(&Toc::new(starts.to_vec(), leadout), metadata)
+    }
+    /// Same as [`Cuna::from_toc_points()`](Cuna::from_toc_points), numbering the tracks from `toc.first_track`
+    pub fn from_toc(toc: &Toc, metadata: &TocMetadata) -> Result<Self, TocError> {
+        let last = match toc.starts.len() {
+            0 => return Err(TocError::NoTracks),
+            n => toc.first_track as usize + n - 1,
+        };
+        if toc.first_track == 0 {
+            return Err(TocError::InvalidTrackNumber(0));
+        }
+        if last > 99 {
+            return Err(TocError::InvalidTrackNumber(
+                last.min(u8::MAX as usize) as u8
+            ));
+        }
+        let ends = toc.starts.iter().skip(1).chain(Some(&toc.leadout));
+        let mut file = TrackInfo::new(metadata.file.clone(), metadata.format.clone());
+        for (i, (&start, &end)) in toc.starts.iter().zip(ends).enumerate() {
+            // at most 99 tracks, checked above
+            let id = toc.first_track + i as u8;
+            if end <= start {
+                return Err(TocError::NotIncreasing(id));
+            }
+            let mut track = Track::new_unchecked(id, "AUDIO".to_owned());
+            if let Some(title) = metadata.track_titles.get(i) {
+                track.push_title(title.clone());
+            }
+            track.push_index(Index::new(1, TimeStamp::from_frames(start)));
+            file.push_track(track);
+        }
+        let mut sheet = Self::default();
+        if let Some(performer) = &metadata.performer {
+            sheet.header.push_performer(performer.clone());
+        }
+        if let Some(title) = &metadata.title {
+            sheet.header.push_title(title.clone());
+        }
+        sheet.push_file(file);
+        Ok(sheet)
+    }
+    /// Compares the positions of the tracks with a TOC, see [`toc`](crate::toc)
+    pub fn verify_against_toc(&self, toc: &Toc) -> TocReport {
+        self.verify_against_toc_with_lengths(toc, &[])
+    }
+    /// Same as [`Cuna::verify_against_toc()`](Cuna::verify_against_toc), with the lengths of the `FILE`s
+    /// in the order they appear, which places the tracks of the following `FILE`s and the lead-out
+    pub fn verify_against_toc_with_lengths(
+        &self,
+        toc: &Toc,
+        file_lengths: &[TimeStamp],
+    ) -> TocReport {
+        let mut report = TocReport::default();
+        let mut file_start = Some(0);
+        let mut gaps = 0;
+        for (file, info) in self.files.iter().enumerate() {
+            for track in &info.tracks {
+                gaps += track.pregap().map_or(0, TimeStamp::as_frames);
+                let cue = match (file_start, track.start()) {
+                    (Some(file_start), Some(start)) => Some(file_start + gaps + start.as_frames()),
+                    _ => None,
+                };
+                gaps += track.postgap().map_or(0, TimeStamp::as_frames);
+                match toc.start(track.id()) {
+                    Some(disc) => report.tracks.push(TocCheck {
+                        id: track.id(),
+                        cue,
+                        disc,
+                    }),
+                    None => report.extra.push(track.id()),
+                }
+            }
+            file_start = match (file_start, file_lengths.get(file)) {
+                (Some(start), Some(length)) => Some(start + length.as_frames()),
+                _ => None,
+            };
+        }
+        if !self.files.is_empty() {
+            report.leadout_offset = file_start.map(|end| (end + gaps) as i64 - toc.leadout as i64);
+        }
+        report.missing = (0..toc.starts.len())
+            .map_while(|i| toc.first_track.checked_add(u8::try_from(i).ok()?))
+            .filter(|id| !report.tracks.iter().any(|c| c.id == *id))
+            .collect();
+        report
+    }
+}
@@ -0,0 +1,24 @@
+//! Converting cue sheets from and to TOML, requires the `toml` feature
+//!
+//! [`Cuna::to_toml()`] and [`Cuna::from_toml()`] use the same shape as the JSON conversion,
+//! except that missing values are omitted
+use crate::Cuna;
+
+impl Cuna {
+    /// Serializes the cue sheet as TOML
+    ///
+    /// ```rust
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::new("REM GENRE Pop\nFILE \"a.flac\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00").unwrap();
+    /// let toml = sheet.to_toml().unwrap();
+    /// assert!(toml.contains(r#"begin_time = "00:00:00""#));
+    /// assert_eq!(Cuna::from_toml(&toml).unwrap(), sheet);
+    /// ```
+    pub fn to_toml(&self) -> Result<String, ::toml::ser::Error> {
+        ::toml::to_string(self)
+    }
+    /// Deserializes a cue sheet from TOML
+    pub fn from_toml(s: &str) -> Result<Self, ::toml::de::Error> {
+        ::toml::from_str(s)
+    }
+}
@@ -1,23 +1,152 @@
+use crate::comment::Comment;
+use crate::comment::ReplayGain;
 use crate::error::InvalidArgument;
 use crate::time::TimeStamp;
+#[cfg(feature = "parser")]
 use crate::utils;
-use nom::bytes::complete::tag_no_case as tag;
+use alloc::borrow::ToOwned;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::iter::FromIterator;
+use core::ops;
+use core::str::FromStr;
+#[cfg(feature = "parser")]
 use nom::combinator::map;
+#[cfg(feature = "parser")]
 use nom::combinator::map_res;
+#[cfg(feature = "parser")]
 use nom::combinator::rest;
+#[cfg(feature = "parser")]
 use nom::sequence::delimited;
+#[cfg(feature = "parser")]
 use nom::sequence::tuple;
-use std::ops;
-use std::str::FromStr;
 
 pub use self::TrackInfo as File;
 
+/// The number of an `INDEX` command
+///
+/// `INDEX 00` marks the beginning of the pregap, `INDEX 01` marks the start of the track
+/// and 02 to 99 are subindexes
+///
+/// ```rust
+/// use cuna::track::IndexNumber;
+/// assert_eq!(IndexNumber::PREGAP.to_string(), "00");
+/// assert_eq!(IndexNumber::START.get(), 1);
+/// assert_eq!(IndexNumber::sub(3).to_string(), "03");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IndexNumber(u8);
+/// A flag of the `FLAGS` command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Flag {
+    /// `DCP`, digital copy permitted
+    Dcp,
+    /// `4CH`, four channel audio
+    FourChannel,
+    /// `PRE`, pre-emphasis enabled
+    Pre,
+    /// `SCMS`, serial copy management system
+    Scms,
+}
+/// The flags of a `FLAGS` command as a set, see [`Track::track_flags()`]
+///
+/// Each known flag is in the set at most once, and flags the [`Flag`]s do not have
+/// are kept as written so they are not lost
+///
+/// ```rust
+/// use cuna::track::{Flag, TrackFlags};
+/// let mut flags = TrackFlags::from_names(["pre", "DCP", "PRE", "XYZ"]);
+/// assert!(flags.contains(Flag::Pre));
+/// assert!(!flags.insert(Flag::Dcp));
+/// assert!(flags.insert(Flag::FourChannel));
+/// assert_eq!(flags.unknown(), ["XYZ"]);
+/// assert_eq!(flags.to_string(), "DCP 4CH PRE XYZ");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct TrackFlags {
+    bits: u8,
+    unknown: Vec<String>,
+}
+/// An International Standard Recording Code, the argument of `ISRC`
+///
+/// It is written as 12 characters, `CCXXXYYNNNNN`: the country code, the registrant,
+/// the last 2 digits of the year and the designation
+///
+/// ```rust
+/// use cuna::track::Isrc;
+/// let isrc: Isrc = "JPSR01100001".parse().unwrap();
+/// assert_eq!(isrc.country(), "JP");
+/// assert_eq!(isrc.registrant(), "SR0");
+/// assert_eq!(isrc.year(), 2011);
+/// assert_eq!(isrc.designation(), 1);
+/// assert_eq!(format!("{:#}", isrc), "JP-SR0-11-00001");
+/// assert!("JP-SR0-11-00001".parse::<Isrc>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Isrc([u8; 12]);
+/// The datatype of a `TRACK`, see [`Track::mode()`]
+///
+/// ```rust
+/// use cuna::track::TrackMode;
+/// assert_eq!(TrackMode::from_name("mode1/2048"), TrackMode::Mode1_2048);
+/// assert_eq!(TrackMode::Mode1_2048.sector_size(), Some(2048));
+/// assert_eq!(TrackMode::from_name("MODE3"), TrackMode::Other("MODE3".to_owned()));
+/// assert_eq!(TrackMode::Cdi2352.to_string(), "CDI/2352");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TrackMode {
+    /// `AUDIO`, 2352 bytes of audio per sector
+    Audio,
+    /// `CDG`, audio with karaoke graphics, 2448 bytes per sector
+    Cdg,
+    /// `MODE1/2048`, CD-ROM data without the error correction
+    Mode1_2048,
+    /// `MODE1/2352`, raw CD-ROM data
+    Mode1_2352,
+    /// `MODE2/2336`, CD-ROM XA data without the sync and header
+    Mode2_2336,
+    /// `MODE2/2352`, raw CD-ROM XA data
+    Mode2_2352,
+    /// `CDI/2336`, CD-i data without the sync and header
+    Cdi2336,
+    /// `CDI/2352`, raw CD-i data
+    Cdi2352,
+    /// Any other datatype, as written
+    Other(String),
+}
+/// The type of a `FILE`, see [`TrackInfo::file_format()`]
+///
+/// ```rust
+/// use cuna::track::FileFormat;
+/// assert_eq!(FileFormat::from_name("binary"), FileFormat::Binary);
+/// assert!(FileFormat::Motorola.is_binary());
+/// assert_eq!(FileFormat::from_name("FLAC"), FileFormat::Other("FLAC".to_owned()));
+/// assert_eq!(FileFormat::Aiff.to_string(), "AIFF");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FileFormat {
+    /// `WAVE`, also written by rippers for compressed audio like FLAC
+    Wave,
+    Mp3,
+    Aiff,
+    /// `BINARY`, little-endian data
+    Binary,
+    /// `MOTOROLA`, big-endian data
+    Motorola,
+    /// Any other type, as written
+    Other(String),
+}
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Index {
     pub(crate) id: u8, // index id must between 1 and 99
     pub begin_time: TimeStamp,
 }
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Track {
     pub(crate) id: u8, // track-id must between 1 and 99
     pub format: String,
@@ -27,44 +156,419 @@ pub struct Track {
     pub title: Vec<String>,
     pub performer: Vec<String>,
     pub songwriter: Vec<String>,
+    /// The CD-Text `ARRANGER`s, written by cdrdao
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub arranger: Vec<String>,
+    /// The CD-Text `COMPOSER`s, written by cdrdao
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub composer: Vec<String>,
+    /// The CD-Text `MESSAGE`s, written by cdrdao
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub message: Vec<String>,
+    /// The `ISRC` as written, which may be malformed unless
+    /// [`ParseOptions::strict_isrc`](crate::parser::ParseOptions::strict_isrc) is set:
+    /// [`parsed_isrc()`](Track::parsed_isrc) gives the [`Isrc`] and
+    /// [`Cuna::validate_codes()`](crate::Cuna::validate_codes) reports the malformed ones
     pub isrc: Option<String>,
     pub flags: Vec<String>,
+    /// `REM`s between this `TRACK` and the next `TRACK` or `FILE`, except the header keys
+    /// of [`HEADER_KEYS`](crate::comment::HEADER_KEYS) which always belong to the sheet
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Comment::is_empty")
+    )]
+    pub comments: Comment,
+    /// Lines with commands the parser does not know,
+    /// see [`ParseOptions::keep_unknown`](crate::parser::ParseOptions::keep_unknown)
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "UnknownLines::is_empty")
+    )]
+    pub unknown: UnknownLines,
 }
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TrackInfo {
     pub name: String,
     pub format: String,
     pub tracks: Vec<Track>,
+    /// `REM`s between `FILE` and the first `TRACK`, except the header keys
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Comment::is_empty")
+    )]
+    pub comments: Comment,
+    /// Lines with commands the parser does not know between `FILE` and the first `TRACK`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "UnknownLines::is_empty")
+    )]
+    pub unknown: UnknownLines,
+}
+
+impl IndexNumber {
+    /// `INDEX 00`, the beginning of the pregap
+    pub const PREGAP: Self = Self(0);
+    /// `INDEX 01`, the start of the track
+    pub const START: Self = Self(1);
+
+    /// Constructs a subindex number
+    ///
+    /// # Panics
+    ///
+    /// Panics if n < 2 or n > 99
+    pub const fn sub(n: u8) -> Self {
+        assert!(n >= 2 && n <= 99, "subindex must be between 2 and 99");
+        Self(n)
+    }
+    /// Constructs an IndexNumber, or returns None if n > 99
+    pub const fn new_opt(n: u8) -> Option<Self> {
+        if n <= 99 {
+            Some(Self(n))
+        } else {
+            None
+        }
+    }
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+    pub const fn is_pregap(self) -> bool {
+        self.0 == 0
+    }
+    pub const fn is_start(self) -> bool {
+        self.0 == 1
+    }
+    pub const fn is_sub(self) -> bool {
+        self.0 >= 2
+    }
+}
+impl fmt::Display for IndexNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}", self.0)
+    }
+}
+impl From<IndexNumber> for u8 {
+    fn from(n: IndexNumber) -> Self {
+        n.0
+    }
+}
+impl TryFrom<u8> for IndexNumber {
+    type Error = InvalidArgument;
+
+    fn try_from(n: u8) -> Result<Self, Self::Error> {
+        Self::new_opt(n).ok_or(InvalidArgument::InvalidId)
+    }
+}
+impl Flag {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Dcp => "DCP",
+            Self::FourChannel => "4CH",
+            Self::Pre => "PRE",
+            Self::Scms => "SCMS",
+        }
+    }
+    /// Returns the flag with the name, case-insensitively, or None if the name is unknown
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|flag| flag.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+    const ALL: [Self; 4] = [Self::Dcp, Self::FourChannel, Self::Pre, Self::Scms];
+
+    const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+impl TrackFlags {
+    pub const fn new() -> Self {
+        Self {
+            bits: 0,
+            unknown: Vec::new(),
+        }
+    }
+    /// Returns the set of the flags with the names, skipping empty names and repeated ones
+    pub fn from_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut flags = Self::new();
+        for name in names.into_iter().map(str::trim).filter(|n| !n.is_empty()) {
+            match Flag::from_name(name) {
+                Some(flag) => {
+                    flags.insert(flag);
+                }
+                None if flags.unknown.iter().any(|u| u.eq_ignore_ascii_case(name)) => {}
+                None => flags.unknown.push(name.to_owned()),
+            }
+        }
+        flags
+    }
+    pub const fn contains(&self, flag: Flag) -> bool {
+        self.bits & flag.bit() != 0
+    }
+    /// Adds the flag, returns false if it was already in the set
+    pub fn insert(&mut self, flag: Flag) -> bool {
+        let inserted = !self.contains(flag);
+        self.bits |= flag.bit();
+        inserted
+    }
+    /// Removes the flag, returns false if it was not in the set
+    pub fn remove(&mut self, flag: Flag) -> bool {
+        let removed = self.contains(flag);
+        self.bits &= !flag.bit();
+        removed
+    }
+    /// The known flags in the set, in the order `DCP`, `4CH`, `PRE`, `SCMS`
+    pub fn iter(&self) -> impl Iterator<Item = Flag> + '_ {
+        Flag::ALL.iter().copied().filter(move |&f| self.contains(f))
+    }
+    /// The flags which are not [`Flag`]s, as written
+    pub fn unknown(&self) -> &[String] {
+        &self.unknown
+    }
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0 && self.unknown.is_empty()
+    }
+    /// Returns the names of the flags, the known ones first
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        let unknown = self.unknown.iter().map(String::as_str);
+        self.iter()
+            .map(|flag| -> &str { flag.as_str() })
+            .chain(unknown)
+    }
+}
+impl fmt::Display for TrackFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, name) in self.names().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(name)?;
+        }
+        Ok(())
+    }
+}
+impl fmt::Display for Flag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+impl TrackMode {
+    const KNOWN: [Self; 8] = [
+        Self::Audio,
+        Self::Cdg,
+        Self::Mode1_2048,
+        Self::Mode1_2352,
+        Self::Mode2_2336,
+        Self::Mode2_2352,
+        Self::Cdi2336,
+        Self::Cdi2352,
+    ];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Audio => "AUDIO",
+            Self::Cdg => "CDG",
+            Self::Mode1_2048 => "MODE1/2048",
+            Self::Mode1_2352 => "MODE1/2352",
+            Self::Mode2_2336 => "MODE2/2336",
+            Self::Mode2_2352 => "MODE2/2352",
+            Self::Cdi2336 => "CDI/2336",
+            Self::Cdi2352 => "CDI/2352",
+            Self::Other(name) => name,
+        }
+    }
+    /// Returns the datatype with the name, case-insensitively, or [`Other`](TrackMode::Other)
+    /// with the name trimmed if it is unknown
+    pub fn from_name(name: &str) -> Self {
+        let name = name.trim();
+        Self::KNOWN
+            .iter()
+            .find(|mode| mode.as_str().eq_ignore_ascii_case(name))
+            .cloned()
+            .unwrap_or_else(|| Self::Other(name.to_owned()))
+    }
+    /// The number of bytes of a sector in the `FILE`, None for [`Other`](TrackMode::Other)
+    pub fn sector_size(&self) -> Option<u16> {
+        match self {
+            Self::Audio | Self::Mode1_2352 | Self::Mode2_2352 | Self::Cdi2352 => Some(2352),
+            Self::Cdg => Some(2448),
+            Self::Mode1_2048 => Some(2048),
+            Self::Mode2_2336 | Self::Cdi2336 => Some(2336),
+            Self::Other(_) => None,
+        }
+    }
+    pub fn is_audio(&self) -> bool {
+        *self == Self::Audio
+    }
+}
+impl Isrc {
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.0).expect("an ISRC is ASCII")
+    }
+    /// The 2 letters of the country
+    pub fn country(&self) -> &str {
+        &self.as_str()[..2]
+    }
+    /// The 3 letters or digits of the registrant
+    pub fn registrant(&self) -> &str {
+        &self.as_str()[2..5]
+    }
+    /// The year of reference, ISRCs started in 1986 so `86` to `99` are 1986 to 1999
+    pub fn year(&self) -> u16 {
+        let year: u16 = self.as_str()[5..7].parse().unwrap_or_default();
+        if year >= 86 {
+            1900 + year
+        } else {
+            2000 + year
+        }
+    }
+    /// The number given by the registrant, up to 99999
+    pub fn designation(&self) -> u32 {
+        self.as_str()[7..].parse().unwrap_or_default()
+    }
 }
+impl FromStr for Isrc {
+    type Err = InvalidArgument;
 
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code =
+            <[u8; 12]>::try_from(s.as_bytes()).map_err(|_| InvalidArgument::MalformedIsrc)?;
+        let valid = code[..2].iter().all(u8::is_ascii_uppercase)
+            && code[2..5]
+                .iter()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+            && code[5..].iter().all(u8::is_ascii_digit);
+        match valid {
+            true => Ok(Self(code)),
+            false => Err(InvalidArgument::MalformedIsrc),
+        }
+    }
+}
+/// `{:#}` writes the parts separated by `-`, like `JP-SR0-11-00001`, which is not valid in a cue sheet
+impl fmt::Display for Isrc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = self.as_str();
+        match f.alternate() {
+            true => write!(f, "{}-{}-{}-{}", &s[..2], &s[2..5], &s[5..7], &s[7..]),
+            false => f.write_str(s),
+        }
+    }
+}
+impl FileFormat {
+    const KNOWN: [Self; 5] = [
+        Self::Wave,
+        Self::Mp3,
+        Self::Aiff,
+        Self::Binary,
+        Self::Motorola,
+    ];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Wave => "WAVE",
+            Self::Mp3 => "MP3",
+            Self::Aiff => "AIFF",
+            Self::Binary => "BINARY",
+            Self::Motorola => "MOTOROLA",
+            Self::Other(name) => name,
+        }
+    }
+    /// Returns the type with the name, case-insensitively, or [`Other`](FileFormat::Other)
+    /// with the name trimmed if it is unknown
+    pub fn from_name(name: &str) -> Self {
+        let name = name.trim();
+        Self::KNOWN
+            .iter()
+            .find(|format| format.as_str().eq_ignore_ascii_case(name))
+            .cloned()
+            .unwrap_or_else(|| Self::Other(name.to_owned()))
+    }
+    /// Returns true for the raw data of `BINARY` and `MOTOROLA`
+    pub fn is_binary(&self) -> bool {
+        matches!(self, Self::Binary | Self::Motorola)
+    }
+    /// Returns true for the audio of `WAVE`, `MP3` and `AIFF`
+    pub fn is_audio(&self) -> bool {
+        matches!(self, Self::Wave | Self::Mp3 | Self::Aiff)
+    }
+}
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+impl fmt::Display for TrackMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
 impl Index {
     pub(crate) const fn new_unchecked(id: u8, begin_time: TimeStamp) -> Self {
         Self { id, begin_time }
     }
-    pub fn new(id: u8, begin_time: TimeStamp) -> Self {
-        Self::new_opt(id, begin_time).expect("index-id must be between 1 and 99")
+    /// Constructs a new Index, usable in constants
+    ///
+    /// ```rust
+    /// use cuna::time::TimeStamp;
+    /// use cuna::track::Index;
+    /// const CHAPTERS: [Index; 2] = [
+    ///     Index::new(1, TimeStamp::new(0, 0, 0)),
+    ///     Index::new(1, TimeStamp::new(4, 15, 44)),
+    /// ];
+    /// assert_eq!(CHAPTERS[1].begin_time().seconds(), 15);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if id > 99
+    pub const fn new(id: u8, begin_time: TimeStamp) -> Self {
+        match Self::new_opt(id, begin_time) {
+            Some(index) => index,
+            None => panic!("index-id must be between 1 and 99"),
+        }
     }
-    pub fn new_opt(id: u8, begin_time: TimeStamp) -> Option<Self> {
+    pub const fn new_opt(id: u8, begin_time: TimeStamp) -> Option<Self> {
         if id <= 99 {
             Some(Self::new_unchecked(id, begin_time))
         } else {
             None
         }
     }
-    pub fn id(&self) -> u8 {
+    /// Constructs a new Index with a typed number
+    pub const fn with_number(number: IndexNumber, begin_time: TimeStamp) -> Self {
+        Self::new_unchecked(number.0, begin_time)
+    }
+    pub const fn id(&self) -> u8 {
         self.id
     }
-    pub fn begin_time(&self) -> &TimeStamp {
+    pub const fn number(&self) -> IndexNumber {
+        IndexNumber(self.id)
+    }
+    pub const fn begin_time(&self) -> &TimeStamp {
         &self.begin_time
     }
+    /// Returns true if the numbers are the same and the times are at most tolerance frames apart
+    pub const fn approx_eq(&self, other: &Self, tolerance: u32) -> bool {
+        self.id == other.id && self.begin_time.approx_eq(&other.begin_time, tolerance)
+    }
 }
+#[cfg(feature = "parser")]
 impl FromStr for Index {
     type Err = InvalidArgument;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (_, index) = map(
             tuple((
-                delimited(utils::keyword("INDEX"), utils::number(2), tag(" ")),
+                delimited(utils::keyword("INDEX"), utils::number(2), utils::separator),
                 map_res(rest, TimeStamp::from_str),
             )),
             |(id, begin_time)| Self::new_unchecked(id, begin_time),
@@ -74,11 +578,23 @@ impl FromStr for Index {
     }
 }
 impl Track {
-    pub(crate) fn new_unchecked(id: u8, format: String) -> Self {
+    pub(crate) const fn new_unchecked(id: u8, format: String) -> Self {
         Self {
             id,
             format,
-            ..Self::default()
+            index: Vec::new(),
+            pregap: None,
+            postgap: None,
+            title: Vec::new(),
+            performer: Vec::new(),
+            songwriter: Vec::new(),
+            arranger: Vec::new(),
+            composer: Vec::new(),
+            message: Vec::new(),
+            isrc: None,
+            flags: Vec::new(),
+            comments: Comment(Vec::new()),
+            unknown: UnknownLines(Vec::new()),
         }
     }
     /// Constructs a new Track
@@ -89,23 +605,46 @@ impl Track {
     pub fn new(id: u8, format: String) -> Self {
         Self::new_opt(id, format).expect("track-id must be between 1 and 99")
     }
+    /// Constructs a new Track, or returns None if id > 99
+    ///
+    /// This is not a `const fn` since `format` would be dropped at compile time when id is invalid,
+    /// check the id with the const [`Track::is_valid_id()`](Track::is_valid_id) instead
     pub fn new_opt(id: u8, format: String) -> Option<Self> {
-        if id <= 99 {
+        if Self::is_valid_id(id) {
             Some(Self::new_unchecked(id, format))
         } else {
             None
         }
     }
-    pub fn id(&self) -> u8 {
+    /// Returns true if id can be the number of a `TRACK`
+    pub const fn is_valid_id(id: u8) -> bool {
+        id <= 99
+    }
+    pub const fn id(&self) -> u8 {
         self.id
     }
     pub fn format(&self) -> &str {
         &self.format
     }
-    pub fn pregap(&self) -> Option<&TimeStamp> {
+    /// Returns the datatype of [`format`](Track::format)
+    ///
+    /// ```rust
+    /// use cuna::track::{Track, TrackMode};
+    /// let mut track = Track::new(1, "MODE2/2352".to_owned());
+    /// assert_eq!(track.mode().sector_size(), Some(2352));
+    /// track.set_mode(TrackMode::Audio);
+    /// assert_eq!(track.format(), "AUDIO");
+    /// ```
+    pub fn mode(&self) -> TrackMode {
+        TrackMode::from_name(&self.format)
+    }
+    pub fn set_mode(&mut self, mode: TrackMode) {
+        self.format = mode.to_string();
+    }
+    pub const fn pregap(&self) -> Option<&TimeStamp> {
         self.pregap.as_ref()
     }
-    pub fn postgap(&self) -> Option<&TimeStamp> {
+    pub const fn postgap(&self) -> Option<&TimeStamp> {
         self.postgap.as_ref()
     }
     pub fn title(&self) -> &Vec<String> {
@@ -126,9 +665,46 @@ impl Track {
     pub fn push_songwriter(&mut self, songwriter: String) {
         self.songwriter.push(songwriter)
     }
+    pub fn arranger(&self) -> &Vec<String> {
+        &self.arranger
+    }
+    pub fn push_arranger(&mut self, arranger: String) {
+        self.arranger.push(arranger)
+    }
+    pub fn composer(&self) -> &Vec<String> {
+        &self.composer
+    }
+    pub fn push_composer(&mut self, composer: String) {
+        self.composer.push(composer)
+    }
+    pub fn message(&self) -> &Vec<String> {
+        &self.message
+    }
+    pub fn push_message(&mut self, message: String) {
+        self.message.push(message)
+    }
     pub fn push_index(&mut self, index: Index) {
         self.index.push(index)
     }
+    /// Returns the first `INDEX` with the given number
+    ///
+    /// ```rust
+    /// use cuna::track::{Index, IndexNumber, Track};
+    /// use cuna::time::TimeStamp;
+    /// let mut track = Track::new(1, "AUDIO".to_owned());
+    /// track.push_index(Index::with_number(IndexNumber::START, TimeStamp::new(0, 2, 0)));
+    /// assert_eq!(track.get_index(IndexNumber::START).unwrap().begin_time(), &TimeStamp::new(0, 2, 0));
+    /// assert!(track.get_index(IndexNumber::PREGAP).is_none());
+    /// ```
+    pub fn get_index(&self, number: IndexNumber) -> Option<&Index> {
+        self.index.iter().find(|i| i.number() == number)
+    }
+    /// Returns the time the track starts at, which is `INDEX 01` or the first `INDEX` if there is no `INDEX 01`
+    pub fn start(&self) -> Option<TimeStamp> {
+        self.get_index(IndexNumber::START)
+            .or_else(|| self.index.first())
+            .map(|i| i.begin_time)
+    }
     pub fn set_pregep(&mut self, pregap: TimeStamp) -> Option<TimeStamp> {
         self.pregap.replace(pregap)
     }
@@ -138,6 +714,17 @@ impl Track {
     pub fn isrc(&self) -> Option<&str> {
         self.isrc.as_deref()
     }
+    /// Returns the `ISRC` as an [`Isrc`], or an error if it is malformed
+    ///
+    /// ```rust
+    /// use cuna::track::Track;
+    /// let track = Track::new(1, "AUDIO".to_owned()).with_isrc(" JPSR01100001 ");
+    /// assert_eq!(track.parsed_isrc().unwrap().unwrap().registrant(), "SR0");
+    /// assert!(track.with_isrc("JPSR011").parsed_isrc().unwrap().is_err());
+    /// ```
+    pub fn parsed_isrc(&self) -> Option<Result<Isrc, InvalidArgument>> {
+        self.isrc_trimmed().map(str::parse)
+    }
     pub fn set_isrc(&mut self, isrc: String) -> Option<String> {
         self.isrc.replace(isrc)
     }
@@ -147,6 +734,35 @@ impl Track {
     pub fn push_flag(&mut self, flag: String) {
         self.flags.push(flag)
     }
+    /// Returns the flags as a set, without repeated ones
+    ///
+    /// ```rust
+    /// use cuna::track::{Flag, Track};
+    /// let mut track = Track::new(1, "AUDIO".to_owned()).with_flag("DCP").with_flag("dcp");
+    /// let mut flags = track.track_flags();
+    /// flags.insert(Flag::Pre);
+    /// track.set_track_flags(&flags);
+    /// assert_eq!(track.flags(), &["DCP", "PRE"]);
+    /// ```
+    pub fn track_flags(&self) -> TrackFlags {
+        TrackFlags::from_names(self.flags.iter().map(String::as_str))
+    }
+    /// Replaces the flags with the ones of the set
+    pub fn set_track_flags(&mut self, flags: &TrackFlags) {
+        self.flags = flags.names().map(ToOwned::to_owned).collect();
+    }
+    /// Returns true if the track has the flag
+    pub fn has_flag(&self, flag: Flag) -> bool {
+        self.flags.iter().any(|s| Flag::from_name(s) == Some(flag))
+    }
+    /// Returns true if the datatype of the track is `AUDIO`
+    pub fn is_audio(&self) -> bool {
+        self.format.trim().eq_ignore_ascii_case("AUDIO")
+    }
+    /// Returns true if the track is not `AUDIO`, like `MODE1/2352`
+    pub fn is_data(&self) -> bool {
+        !self.is_audio()
+    }
     pub fn push_flags<F, S>(&mut self, flags: F)
     where
         F: IntoIterator<Item = S>,
@@ -154,12 +770,276 @@ impl Track {
     {
         self.flags.extend(flags.into_iter().map(Into::into))
     }
+    /// Returns the `REM`s of the track
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::new(r#"REM GENRE Pop
+    /// FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO
+    ///     REM REPLAYGAIN_TRACK_GAIN -7.11 dB
+    ///     INDEX 01 00:00:00"#).unwrap();
+    /// assert_eq!(sheet[0][0].comments().get("REPLAYGAIN_TRACK_GAIN"), Some("-7.11 dB"));
+    /// assert_eq!(sheet.comments().len(), 1);
+    /// # }
+    /// ```
+    pub fn comments(&self) -> &Comment {
+        &self.comments
+    }
+    /// The mutable version of [`Track::comments()`](Track::comments)
+    pub fn comments_mut(&mut self) -> &mut Comment {
+        &mut self.comments
+    }
+    /// Returns the ReplayGain of the `REM REPLAYGAIN_TRACK_GAIN` and `REM REPLAYGAIN_TRACK_PEAK` of the track
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::new(r#"REM REPLAYGAIN_ALBUM_GAIN -7.11 dB
+    /// FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO
+    ///     REM REPLAYGAIN_TRACK_GAIN -6.02 dB
+    ///     REM REPLAYGAIN_TRACK_PEAK 0.977203
+    ///     INDEX 01 00:00:00"#).unwrap();
+    /// assert_eq!(sheet[0][0].replay_gain().gain, Some(-6.02));
+    /// assert_eq!(sheet[0][0].replay_gain().peak, Some(0.977203));
+    /// assert_eq!(sheet.replay_gain().gain, Some(-7.11));
+    /// # }
+    /// ```
+    pub fn replay_gain(&self) -> ReplayGain {
+        self.comments.track_gain()
+    }
+    /// Consumes the track and returns it with a new `INDEX`, for building a track in one expression
+    ///
+    /// ```rust
+    /// use cuna::time::TimeStamp;
+    /// use cuna::track::Track;
+    /// let track = Track::new(2, "AUDIO".to_owned())
+    ///     .with_title("Departures")
+    ///     .with_performer("EGOIST")
+    ///     .with_index(0, TimeStamp::new(4, 12, 21))
+    ///     .with_index(1, TimeStamp::new(4, 15, 9));
+    /// assert_eq!(track.title(), &["Departures".to_owned()]);
+    /// assert_eq!(track.start(), Some(TimeStamp::new(4, 15, 9)));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if id > 99
+    pub fn with_index(mut self, id: u8, begin_time: TimeStamp) -> Self {
+        self.push_index(Index::new(id, begin_time));
+        self
+    }
+    /// Consumes the track and returns it with a new `TITLE`
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.push_title(title.into());
+        self
+    }
+    /// Consumes the track and returns it with a new `PERFORMER`
+    pub fn with_performer(mut self, performer: impl Into<String>) -> Self {
+        self.push_performer(performer.into());
+        self
+    }
+    /// Consumes the track and returns it with a new `SONGWRITER`
+    pub fn with_songwriter(mut self, songwriter: impl Into<String>) -> Self {
+        self.push_songwriter(songwriter.into());
+        self
+    }
+    /// Consumes the track and returns it with the `PREGAP` replaced
+    pub fn with_pregap(mut self, pregap: TimeStamp) -> Self {
+        self.pregap = Some(pregap);
+        self
+    }
+    /// Consumes the track and returns it with the `POSTGAP` replaced
+    pub fn with_postgap(mut self, postgap: TimeStamp) -> Self {
+        self.postgap = Some(postgap);
+        self
+    }
+    /// Consumes the track and returns it with the `ISRC` replaced
+    pub fn with_isrc(mut self, isrc: impl Into<String>) -> Self {
+        self.isrc = Some(isrc.into());
+        self
+    }
+    /// Consumes the track and returns it with a new flag
+    pub fn with_flag(mut self, flag: impl Into<String>) -> Self {
+        self.push_flag(flag.into());
+        self
+    }
+    /// Returns the format with surrounding whitespace removed
+    pub fn format_trimmed(&self) -> &str {
+        self.format.trim()
+    }
+    /// Returns the titles with surrounding whitespace removed
+    pub fn title_trimmed(&self) -> impl Iterator<Item = &str> {
+        self.title.iter().map(|s| s.trim())
+    }
+    /// Returns the performers with surrounding whitespace removed
+    pub fn performer_trimmed(&self) -> impl Iterator<Item = &str> {
+        self.performer.iter().map(|s| s.trim())
+    }
+    /// Returns the songwriters with surrounding whitespace removed
+    pub fn songwriter_trimmed(&self) -> impl Iterator<Item = &str> {
+        self.songwriter.iter().map(|s| s.trim())
+    }
+    /// Returns the ISRC with surrounding whitespace removed
+    pub fn isrc_trimmed(&self) -> Option<&str> {
+        self.isrc.as_deref().map(str::trim)
+    }
+    /// Returns the flags with surrounding whitespace removed, empty flags are skipped
+    pub fn flags_trimmed(&self) -> impl Iterator<Item = &str> {
+        self.flags
+            .iter()
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+    }
+    /// Returns a copy of the track with all the strings trimmed and empty flags removed
+    ///
+    /// Two tracks are [`eq_ignore_formatting`](Track::eq_ignore_formatting) iff their canonical forms are equal,
+    /// so the canonical form can be used as a key of a `HashSet` to dedup tracks
+    pub fn canonical(&self) -> Self {
+        Self {
+            id: self.id,
+            format: self.format_trimmed().to_owned(),
+            index: self.index.clone(),
+            pregap: self.pregap,
+            postgap: self.postgap,
+            title: self.title_trimmed().map(ToOwned::to_owned).collect(),
+            performer: self.performer_trimmed().map(ToOwned::to_owned).collect(),
+            songwriter: self.songwriter_trimmed().map(ToOwned::to_owned).collect(),
+            arranger: trim_all(&self.arranger).map(ToOwned::to_owned).collect(),
+            composer: trim_all(&self.composer).map(ToOwned::to_owned).collect(),
+            message: trim_all(&self.message).map(ToOwned::to_owned).collect(),
+            isrc: self.isrc_trimmed().map(ToOwned::to_owned),
+            flags: self.flags_trimmed().map(ToOwned::to_owned).collect(),
+            comments: self.comments.clone(),
+            unknown: self.unknown.clone(),
+        }
+    }
+    /// Returns true if the tracks are the same, except that the times of `INDEX`, `PREGAP` and `POSTGAP`
+    /// may be at most tolerance frames apart, see [`TimeStamp::approx_eq()`](TimeStamp::approx_eq)
+    pub fn approx_eq(&self, other: &Self, tolerance: u32) -> bool {
+        let gap_eq = |a: Option<TimeStamp>, b: Option<TimeStamp>| match (a, b) {
+            (Some(a), Some(b)) => a.approx_eq(&b, tolerance),
+            (a, b) => a == b,
+        };
+        self.id == other.id
+            && self.index.len() == other.index.len()
+            && self
+                .index
+                .iter()
+                .zip(&other.index)
+                .all(|(a, b)| a.approx_eq(b, tolerance))
+            && gap_eq(self.pregap, other.pregap)
+            && gap_eq(self.postgap, other.postgap)
+            && self.format == other.format
+            && self.title == other.title
+            && self.performer == other.performer
+            && self.songwriter == other.songwriter
+            && self.arranger == other.arranger
+            && self.composer == other.composer
+            && self.message == other.message
+            && self.isrc == other.isrc
+            && self.flags == other.flags
+            && self.comments == other.comments
+            && self.unknown == other.unknown
+    }
+    /// Compares two tracks ignoring whitespace around the strings and empty flags
+    ///
+    /// ```rust
+    /// use cuna::track::Track;
+    /// let mut a = Track::new(1, "AUDIO".to_owned());
+    /// a.push_title("Departures ".to_owned());
+    /// let mut b = Track::new(1, "AUDIO ".to_owned());
+    /// b.push_title(" Departures".to_owned());
+    /// assert_ne!(a, b);
+    /// assert!(a.eq_ignore_formatting(&b));
+    /// ```
+    pub fn eq_ignore_formatting(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.index == other.index
+            && self.pregap == other.pregap
+            && self.postgap == other.postgap
+            && self.format_trimmed() == other.format_trimmed()
+            && self.isrc_trimmed() == other.isrc_trimmed()
+            && self.title_trimmed().eq(other.title_trimmed())
+            && self.performer_trimmed().eq(other.performer_trimmed())
+            && self.songwriter_trimmed().eq(other.songwriter_trimmed())
+            && trim_all(&self.arranger).eq(trim_all(&other.arranger))
+            && trim_all(&self.composer).eq(trim_all(&other.composer))
+            && trim_all(&self.message).eq(trim_all(&other.message))
+            && self.flags_trimmed().eq(other.flags_trimmed())
+            && self.comments == other.comments
+            && self.unknown == other.unknown
+    }
 }
+fn trim_all(values: &[String]) -> impl Iterator<Item = &str> {
+    values.iter().map(|s| s.trim())
+}
+/// Lines with commands the parser does not know and their line numbers,
+/// see [`ParseOptions::keep_unknown`](crate::parser::ParseOptions::keep_unknown)
+///
+/// The writer puts each line back at its line number, as far as its scope allows.
+/// Line numbers are not compared, so the same lines moved by a line added above them are equal
+///
+/// ```rust
+/// use cuna::track::UnknownLines;
+/// let a: UnknownLines = vec![(2, "SESSION 1")].into_iter().collect();
+/// let b: UnknownLines = vec![(3, "SESSION 1")].into_iter().collect();
+/// assert_eq!(a, b);
+/// assert_eq!(a.lines().collect::<Vec<_>>(), ["SESSION 1"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct UnknownLines(pub Vec<(usize, String)>);
+impl UnknownLines {
+    pub fn push(&mut self, at: usize, line: String) {
+        self.0.push((at, line))
+    }
+    /// Returns the lines without their line numbers
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|(_, line)| line.as_str())
+    }
+    pub fn iter(&self) -> core::slice::Iter<'_, (usize, String)> {
+        self.0.iter()
+    }
+    pub fn iter_mut(&mut self) -> core::slice::IterMut<'_, (usize, String)> {
+        self.0.iter_mut()
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+}
+impl PartialEq for UnknownLines {
+    fn eq(&self, other: &Self) -> bool {
+        self.lines().eq(other.lines())
+    }
+}
+impl Eq for UnknownLines {}
+impl Hash for UnknownLines {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        self.lines().for_each(|line| line.hash(state));
+    }
+}
+impl<S: Into<String>> FromIterator<(usize, S)> for UnknownLines {
+    fn from_iter<I: IntoIterator<Item = (usize, S)>>(iter: I) -> Self {
+        Self(iter.into_iter().map(|(at, s)| (at, s.into())).collect())
+    }
+}
+#[cfg(feature = "parser")]
 impl FromStr for Track {
     type Err = InvalidArgument;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (tp, id) = delimited(utils::keyword("TRACK"), utils::number(2), tag(" "))(s)
+        let (tp, id) = delimited(utils::keyword("TRACK"), utils::number(2), utils::separator)(s)
             .map_err(|_| InvalidArgument::InvalidId)?;
         Ok(Self::new_unchecked(id, tp.to_owned()))
     }
@@ -181,8 +1061,46 @@ impl TrackInfo {
             name,
             format,
             tracks,
+            comments: Comment(Vec::new()),
+            unknown: UnknownLines(Vec::new()),
         }
     }
+    /// Returns the type of [`format`](TrackInfo::format)
+    ///
+    /// ```rust
+    /// use cuna::track::{FileFormat, TrackInfo};
+    /// let mut file = TrackInfo::new("a.bin".to_owned(), "BINARY".to_owned());
+    /// assert!(file.file_format().is_binary());
+    /// file.set_file_format(FileFormat::Wave);
+    /// assert_eq!(file.format, "WAVE");
+    /// ```
+    pub fn file_format(&self) -> FileFormat {
+        FileFormat::from_name(&self.format)
+    }
+    pub fn set_file_format(&mut self, format: FileFormat) {
+        self.format = format.to_string();
+    }
+    /// Returns true if the files are the same, comparing tracks with [`Track::approx_eq()`](Track::approx_eq)
+    pub fn approx_eq(&self, other: &Self, tolerance: u32) -> bool {
+        self.name == other.name
+            && self.format == other.format
+            && self.comments == other.comments
+            && self.unknown == other.unknown
+            && self.tracks.len() == other.tracks.len()
+            && self
+                .tracks
+                .iter()
+                .zip(&other.tracks)
+                .all(|(a, b)| a.approx_eq(b, tolerance))
+    }
+    /// Returns the `REM`s between `FILE` and the first `TRACK`
+    pub fn comments(&self) -> &Comment {
+        &self.comments
+    }
+    /// The mutable version of [`TrackInfo::comments()`](TrackInfo::comments)
+    pub fn comments_mut(&mut self) -> &mut Comment {
+        &mut self.comments
+    }
     /// Returns the last Track or None if self.tracks is empty
     pub fn last_track(&self) -> Option<&Track> {
         self.tracks.last()
@@ -195,6 +1113,32 @@ impl TrackInfo {
     pub fn push_track(&mut self, track: Track) {
         self.tracks.push(track)
     }
+    /// Consumes the TrackInfo and returns it with track appended, for building a `FILE` in one expression
+    ///
+    /// ```rust
+    /// use cuna::time::TimeStamp;
+    /// use cuna::track::{Track, TrackInfo};
+    /// let file = TrackInfo::new("a.flac".to_owned(), "WAVE".to_owned())
+    ///     .with_track(Track::new(1, "AUDIO".to_owned()).with_index(1, TimeStamp::new(0, 0, 0)))
+    ///     .with_track(Track::new(2, "AUDIO".to_owned()).with_index(1, TimeStamp::new(4, 15, 9)));
+    /// assert_eq!(file.tracks.len(), 2);
+    /// ```
+    pub fn with_track(mut self, track: Track) -> Self {
+        self.push_track(track);
+        self
+    }
+    /// An iterator over the `AUDIO` tracks
+    pub fn audio_tracks(&self) -> impl Iterator<Item = &Track> {
+        self.tracks.iter().filter(|track| track.is_audio())
+    }
+    /// An iterator over the tracks which are not `AUDIO`
+    pub fn data_tracks(&self) -> impl Iterator<Item = &Track> {
+        self.tracks.iter().filter(|track| track.is_data())
+    }
+    /// An iterator over the tracks with the flag
+    pub fn tracks_with_flag(&self, flag: Flag) -> impl Iterator<Item = &Track> {
+        self.tracks.iter().filter(move |track| track.has_flag(flag))
+    }
 }
 impl IntoIterator for TrackInfo {
     type Item = Track;
@@ -208,7 +1152,7 @@ impl IntoIterator for TrackInfo {
 impl<'a> IntoIterator for &'a TrackInfo {
     type Item = &'a Track;
 
-    type IntoIter = std::slice::Iter<'a, Track>;
+    type IntoIter = core::slice::Iter<'a, Track>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.tracks.iter()
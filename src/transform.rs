@@ -0,0 +1,234 @@
+//! Declarative fix-ups of whole cue sheets
+//!
+//! A [`Pipeline`] is a sequence of built-in [`Step`]s which [`Cuna::transform()`] runs in order,
+//! returning a [`Report`] of how much each step changed.
+//!
+//! ```rust
+//! # #[cfg(feature = "parser")] {
+//! use cuna::transform::Pipeline;
+//! use cuna::Cuna;
+//! let mut sheet = Cuna::new(r#"FILE "rip.wav" WAVE
+//!   TRACK 03 AUDIO
+//!     TITLE " Departures "
+//!     INDEX 01 00:00:00
+//!   TRACK 04 AUDIO
+//!     INDEX 01 04:15:44"#).unwrap();
+//! let pipeline = Pipeline::new()
+//!     .normalize()
+//!     .renumber()
+//!     .shift(75)
+//!     .retarget_extension("flac");
+//! let report = sheet.transform(&pipeline);
+//! assert_eq!(report.total(), 6);
+//! assert_eq!(sheet[0].name, "rip.flac");
+//! assert_eq!(sheet[0][0].id(), 1);
+//! assert_eq!(sheet[0][0].title(), &["Departures".to_owned()]);
+//! assert_eq!(sheet[0][1][0].begin_time().to_string(), "04:16:44");
+//! # }
+//! ```
+use crate::time::TimeStamp;
+use crate::track::Index;
+use crate::track::IndexNumber;
+use crate::Cuna;
+
+/// A built-in transformation of a cue sheet
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Step {
+    /// Trims whitespace around strings and removes empty flags, see [`Track::canonical()`](crate::track::Track::canonical)
+    Normalize,
+    /// Moves every `INDEX` by a number of frames, earlier if negative.
+    /// Times which would be before `00:00:00` become `00:00:00`
+    Shift(i64),
+    /// Numbers the `TRACK`s from 1 in order, numbers after 99 are left unchanged
+    Renumber,
+    /// Turns every `PREGAP` into audio in the file: `INDEX 00` is added where `INDEX 01` was,
+    /// and the track and the following ones in the same `FILE` are moved later by the pregap
+    MaterializePregaps,
+    /// Replaces the extension of every `FILE`, e.g. after converting WAVE files to FLAC
+    RetargetExtension(String),
+}
+/// A sequence of [`Step`]s
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Pipeline {
+    pub steps: Vec<Step>,
+}
+/// How many items a [`Step`] changed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StepReport {
+    pub step: Step,
+    /// The number of strings, indexes, tracks or files changed, depending on the step
+    pub changes: usize,
+}
+/// The result of running a [`Pipeline`], in the order of the steps
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Report {
+    pub steps: Vec<StepReport>,
+}
+
+impl Step {
+    /// Applies the step to a sheet, returning the number of changes
+    pub fn apply(&self, sheet: &mut Cuna) -> usize {
+        match self {
+            Self::Normalize => normalize(sheet),
+            Self::Shift(frames) => shift(sheet, *frames),
+            Self::Renumber => renumber(sheet),
+            Self::MaterializePregaps => materialize_pregaps(sheet),
+            Self::RetargetExtension(extension) => retarget_extension(sheet, extension),
+        }
+    }
+}
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+    pub fn normalize(self) -> Self {
+        self.step(Step::Normalize)
+    }
+    pub fn shift(self, frames: i64) -> Self {
+        self.step(Step::Shift(frames))
+    }
+    pub fn renumber(self) -> Self {
+        self.step(Step::Renumber)
+    }
+    pub fn materialize_pregaps(self) -> Self {
+        self.step(Step::MaterializePregaps)
+    }
+    pub fn retarget_extension(self, extension: impl Into<String>) -> Self {
+        self.step(Step::RetargetExtension(extension.into()))
+    }
+}
+impl From<Vec<Step>> for Pipeline {
+    fn from(steps: Vec<Step>) -> Self {
+        Self { steps }
+    }
+}
+impl Report {
+    /// The number of changes of all the steps
+    pub fn total(&self) -> usize {
+        self.steps.iter().map(|step| step.changes).sum()
+    }
+    /// Returns true if no step changed anything
+    pub fn is_unchanged(&self) -> bool {
+        self.total() == 0
+    }
+}
+impl Cuna {
+    /// Runs the steps of a pipeline in order
+    pub fn transform(&mut self, pipeline: &Pipeline) -> Report {
+        let steps = pipeline
+            .steps
+            .iter()
+            .map(|step| StepReport {
+                step: step.clone(),
+                changes: step.apply(self),
+            })
+            .collect();
+        Report { steps }
+    }
+}
+
+fn trim_all(strings: &mut [String]) -> usize {
+    strings.iter_mut().map(trim).filter(|&c| c).count()
+}
+fn trim(s: &mut String) -> bool {
+    if s.trim().len() == s.len() {
+        false
+    } else {
+        *s = s.trim().to_owned();
+        true
+    }
+}
+fn normalize(sheet: &mut Cuna) -> usize {
+    let header = &mut sheet.header;
+    let mut changes = trim_all(&mut header.title)
+        + trim_all(&mut header.performer)
+        + trim_all(&mut header.songwriter)
+        + trim_all(&mut header.arranger)
+        + trim_all(&mut header.composer)
+        + trim_all(&mut header.message);
+    for file in &mut sheet.files {
+        changes += trim(&mut file.name) as usize + trim(&mut file.format) as usize;
+        for track in &mut file.tracks {
+            let canonical = track.canonical();
+            if *track != canonical {
+                *track = canonical;
+                changes += 1;
+            }
+        }
+    }
+    changes
+}
+fn shift(sheet: &mut Cuna, frames: i64) -> usize {
+    let mut changes = 0;
+    for index in sheet.files.iter_mut().flat_map(|file| {
+        file.tracks
+            .iter_mut()
+            .flat_map(|track| track.index.iter_mut())
+    }) {
+        let shifted = (index.begin_time.as_frames() as i64 + frames).clamp(0, u32::MAX as i64);
+        let shifted = TimeStamp::from_frames(shifted as u32);
+        if shifted != index.begin_time {
+            index.begin_time = shifted;
+            changes += 1;
+        }
+    }
+    changes
+}
+fn renumber(sheet: &mut Cuna) -> usize {
+    let mut changes = 0;
+    for (id, track) in (1..=99).zip(sheet.files.iter_mut().flat_map(|file| &mut file.tracks)) {
+        if track.id != id {
+            track.id = id;
+            changes += 1;
+        }
+    }
+    changes
+}
+fn materialize_pregaps(sheet: &mut Cuna) -> usize {
+    let mut changes = 0;
+    for file in &mut sheet.files {
+        let mut offset = 0;
+        for track in &mut file.tracks {
+            let start = track.get_index(IndexNumber::START).map(|i| i.begin_time);
+            for index in &mut track.index {
+                index.begin_time = TimeStamp::from_frames(index.begin_time.as_frames() + offset);
+            }
+            if let (Some(pregap), Some(start)) = (track.pregap, start) {
+                if track.get_index(IndexNumber::PREGAP).is_none() {
+                    let begin = TimeStamp::from_frames(start.as_frames() + offset);
+                    track
+                        .index
+                        .insert(0, Index::with_number(IndexNumber::PREGAP, begin));
+                    for index in track.index.iter_mut().skip(1) {
+                        index.begin_time = TimeStamp::from_frames(
+                            index.begin_time.as_frames() + pregap.as_frames(),
+                        );
+                    }
+                    offset += pregap.as_frames();
+                    track.pregap = None;
+                    changes += 1;
+                }
+            }
+        }
+    }
+    changes
+}
+fn retarget_extension(sheet: &mut Cuna, extension: &str) -> usize {
+    let mut changes = 0;
+    for file in &mut sheet.files {
+        let stem = match file.name.rfind('.') {
+            Some(dot) if !file.name[dot..].contains(['/', '\\']) => &file.name[..dot],
+            _ => &file.name,
+        };
+        let name = format!("{}.{}", stem, extension);
+        if name != file.name {
+            file.name = name;
+            changes += 1;
+        }
+    }
+    changes
+}
@@ -1,29 +1,85 @@
+use crate::scan::closing_quote;
+use crate::time::TimeStamp;
 use nom::branch::alt;
 use nom::bytes::complete::tag;
 use nom::bytes::complete::tag_no_case;
-use nom::bytes::complete::take_until;
+use nom::bytes::complete::take_till;
+use nom::bytes::complete::take_while1;
 use nom::character::complete::digit0;
+use nom::character::complete::digit1;
+use nom::combinator::map_opt;
 use nom::combinator::map_res;
 use nom::combinator::rest;
 use nom::combinator::verify;
-use nom::sequence::delimited;
+use nom::error::Error;
+use nom::error::ErrorKind;
 use nom::sequence::terminated;
+use nom::sequence::tuple;
 use nom::IResult;
 use std::str::FromStr;
 
+/// Recognizes a keyword and the whitespace after it, case-insensitively
 pub fn keyword<'a>(kd: &'a str) -> impl Fn(&'a str) -> IResult<&'a str, &'a str> {
-    move |i: &str| terminated(tag_no_case(kd), tag(" "))(i)
+    move |i: &str| terminated(tag_no_case(kd), separator)(i)
 }
+/// Recognizes a run of whitespace separating arguments, e.g. `"\t  "`
+pub fn separator(i: &str) -> IResult<&str, &str> {
+    take_while1(char::is_whitespace)(i)
+}
+/// Recognizes a value between double quotes, returning it without the quotes
+///
+/// `\"` and `""` inside are escaped quotes, which are returned as they are
+///
+/// ```rust
+/// use cuna::utils::quote;
+/// assert_eq!(quote(r#""a.flac" WAVE"#), Ok((" WAVE", "a.flac")));
+/// assert_eq!(quote(r#""He said \"hi\"""#), Ok(("", r#"He said \"hi\""#)));
+/// assert_eq!(quote(r#""He said ""hi""""#), Ok(("", r#"He said ""hi"""#)));
+/// assert!(quote(r#""a.flac"#).is_err());
+/// ```
 pub fn quote(content: &str) -> IResult<&str, &str> {
-    delimited(tag(r#"""#), take_until(r#"""#), tag(r#"""#))(content)
+    let (i, _) = tag(r#"""#)(content)?;
+    match closing_quote(i) {
+        Some(end) => Ok((&i[end + 1..], &i[..end])),
+        None => Err(nom::Err::Error(Error::new(i, ErrorKind::TakeUntil))),
+    }
 }
 pub fn quote_opt(content: &str) -> IResult<&str, &str> {
     alt((quote, rest))(content)
 }
+/// Recognizes the characters before the next whitespace, and the whitespace
+///
+/// ```rust
+/// use cuna::utils::token;
+/// assert_eq!(token("INDEX\t 01  00:00:00"), Ok(("01  00:00:00", "INDEX")));
+/// assert!(token("INDEX").is_err());
+/// ```
 pub fn token(content: &str) -> IResult<&str, &str> {
-    terminated(take_until(" "), tag(" "))(content)
+    terminated(take_till(char::is_whitespace), separator)(content)
 }
 /// Takes digits and recognizes them as an n digit
 pub fn number<N: FromStr>(n: usize) -> impl Fn(&str) -> IResult<&str, N> {
     move |i: &str| map_res(verify(digit0, |d: &str| d.len() == n), |d: &str| d.parse())(i)
 }
+/// Recognizes the minutes of a timestamp, which have 1 to 3 digits
+pub fn minutes(i: &str) -> IResult<&str, u32> {
+    map_res(verify(digit1, |d: &str| d.len() <= 3), str::parse)(i)
+}
+/// Recognizes a `MM:SS:FF` timestamp
+///
+/// ```rust
+/// use cuna::time::TimeStamp;
+/// use cuna::utils::msf;
+/// assert_eq!(msf("04:15:44 rest"), Ok((" rest", TimeStamp::new(4, 15, 44))));
+/// assert!(msf("04:60:44").is_err());
+/// ```
+pub fn msf(i: &str) -> IResult<&str, TimeStamp> {
+    map_opt(
+        tuple((
+            terminated(minutes, tag(":")),
+            terminated(number(2), tag(":")),
+            number(2),
+        )),
+        |(m, s, f)| TimeStamp::from_msf_opt(m, s, f),
+    )(i)
+}
@@ -0,0 +1,244 @@
+//! Checking cue sheets against what strict players accept, and repairing them
+use crate::comment;
+use crate::error::ValidationError;
+use crate::time::TimeStamp;
+use crate::track::Index;
+use crate::track::IndexNumber;
+use crate::track::Isrc;
+use crate::track::Track;
+use crate::Cuna;
+
+/// How [`Cuna::repair()`](Cuna::repair) fixes a track with `INDEX 00` but no `INDEX 01`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FixPolicy {
+    /// Leave the track as it is
+    Keep,
+    /// Add `INDEX 01` at the time of `INDEX 00`, which is the default
+    #[default]
+    CopyPregapIndex,
+    /// Add `INDEX 01` at the time of `INDEX 00` plus the length of `PREGAP`, which is removed since it is now in the file.
+    /// Tracks without `PREGAP` are fixed as [`CopyPregapIndex`](FixPolicy::CopyPregapIndex)
+    UsePregapLength,
+}
+
+impl Cuna {
+    /// Returns the problems strict players refuse, in the order of tracks
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// use cuna::error::ValidationError;
+    /// use cuna::validation::FixPolicy;
+    /// let mut sheet = Cuna::new(r#"FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO
+    ///     INDEX 00 00:00:00
+    ///   TRACK 02 AUDIO
+    ///     INDEX 00 04:13:44"#).unwrap();
+    /// assert_eq!(
+    ///     sheet.validate(),
+    ///     [ValidationError::MissingStartIndex(1), ValidationError::MissingStartIndex(2)]
+    /// );
+    /// assert_eq!(sheet.repair(FixPolicy::CopyPregapIndex), 2);
+    /// assert!(sheet.validate().is_empty());
+    /// assert_eq!(sheet[0][1].start().unwrap().to_string(), "04:13:44");
+    /// # }
+    /// ```
+    ///
+    /// A `POSTGAP` on an `AUDIO` track which a data track follows is reported too,
+    /// since the gap would end up inside the session instead of after it.
+    /// [`repair()`](Cuna::repair) leaves it alone as moving it changes the disc layout
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let tracks: Vec<_> = self.tracks().collect();
+        let mut errors = Vec::new();
+        for (i, track) in tracks.iter().enumerate() {
+            if missing_start(track) {
+                errors.push(ValidationError::MissingStartIndex(track.id()));
+            }
+            let before_data = tracks.get(i + 1).is_some_and(|next| next.is_data());
+            if track.postgap().is_some() && track.is_audio() && before_data {
+                errors.push(ValidationError::PostgapBeforeData(track.id()));
+            }
+        }
+        errors
+    }
+    /// Returns the problems which make the sheet wrong for any player, see [`Cuna::from_parts()`]
+    pub(crate) fn validate_structure(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let mut last_id = None;
+        for (n, file) in self.files.iter().enumerate() {
+            if file.tracks.is_empty() {
+                errors.push(ValidationError::EmptyFile(n + 1));
+            }
+            let mut last_time = None;
+            for track in &file.tracks {
+                let id = track.id();
+                if last_id.is_some_and(|last| id <= last) {
+                    errors.push(ValidationError::TrackNotIncreasing(id));
+                }
+                last_id = Some(id);
+                let has_start = track.get_index(IndexNumber::START).is_some();
+                if !has_start && track.get_index(IndexNumber::PREGAP).is_none() {
+                    errors.push(ValidationError::MissingIndex(id));
+                }
+                let numbers_increase = track.index.windows(2).all(|w| w[0].id() < w[1].id());
+                let times: Vec<_> = last_time
+                    .into_iter()
+                    .chain(track.index.iter().map(|index| index.begin_time.as_frames()))
+                    .collect();
+                let times_increase = times.windows(2).all(|w| w[0] <= w[1]);
+                if !numbers_increase || !times_increase {
+                    errors.push(ValidationError::IndexNotIncreasing(id));
+                }
+                last_time = track
+                    .index
+                    .last()
+                    .map(|index| index.begin_time.as_frames())
+                    .or(last_time);
+            }
+        }
+        errors
+    }
+    /// Fixes the problems [`validate()`](Cuna::validate) finds with the policy, returning the number of fixed tracks
+    pub fn repair(&mut self, policy: FixPolicy) -> usize {
+        if policy == FixPolicy::Keep {
+            return 0;
+        }
+        let mut fixed = 0;
+        for track in self.files.iter_mut().flat_map(|file| &mut file.tracks) {
+            if !missing_start(track) {
+                continue;
+            }
+            let pregap = track
+                .get_index(IndexNumber::PREGAP)
+                .map(|index| index.begin_time)
+                .unwrap_or_default();
+            let start = match (policy, track.pregap) {
+                (FixPolicy::UsePregapLength, Some(length)) => {
+                    track.pregap = None;
+                    TimeStamp::from_frames(pregap.as_frames() + length.as_frames())
+                }
+                _ => pregap,
+            };
+            let at = track
+                .index
+                .iter()
+                .position(|index| index.number() > IndexNumber::PREGAP)
+                .unwrap_or(track.index.len());
+            track
+                .index
+                .insert(at, Index::with_number(IndexNumber::START, start));
+            fixed += 1;
+        }
+        fixed
+    }
+}
+
+/// GS1 prefixes of `CATALOG`s with the ISRC country codes issued in the same country
+const GS1_COUNTRIES: &[(u64, u64, &[&str])] = &[
+    (0, 19, &["US", "QM", "QZ", "CA"]),
+    (30, 39, &["US", "QM", "QZ"]),
+    (60, 139, &["US", "QM", "QZ", "CA"]),
+    (300, 379, &["FR"]),
+    (400, 440, &["DE"]),
+    (450, 459, &["JP"]),
+    (490, 499, &["JP"]),
+    (500, 509, &["GB", "UK"]),
+    (690, 699, &["CN"]),
+    (800, 839, &["IT"]),
+    (840, 849, &["ES"]),
+    (870, 879, &["NL"]),
+    (880, 880, &["KR"]),
+    (930, 939, &["AU"]),
+];
+
+impl Cuna {
+    /// Returns `CATALOG` and `ISRC` codes which are unlikely to be right, usually copy-paste mistakes
+    ///
+    /// Unlike [`validate()`](Cuna::validate), these are hints: players accept such sheets,
+    /// and compilations legitimately mix registrants. The checks are
+    /// - the check digit of `CATALOG`
+    /// - the form of each `ISRC`, `CCXXXYYNNNNN`
+    /// - an `ISRC` used by an earlier track
+    /// - an `ISRC` whose country and registrant differ from those of most tracks
+    /// - an `ISRC` registered after the year of `REM DATE`
+    /// - a `CATALOG` issued in a country none of the `ISRC`s are from, for the countries it knows
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// use cuna::error::ValidationError;
+    /// let sheet = Cuna::new(r#"REM DATE 2011
+    /// CATALOG 4547366179934
+    /// FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO
+    ///     ISRC JPU901100123
+    ///     INDEX 01 00:00:00
+    ///   TRACK 02 AUDIO
+    ///     ISRC JPU901100123
+    ///     INDEX 01 04:15:44
+    ///   TRACK 03 AUDIO
+    ///     ISRC JPU901200125
+    ///     INDEX 01 08:31:00"#).unwrap();
+    /// assert_eq!(
+    ///     sheet.validate_codes(),
+    ///     [ValidationError::DuplicateIsrc(2), ValidationError::IsrcAfterDate(3)]
+    /// );
+    /// # }
+    /// ```
+    pub fn validate_codes(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        if let Some(catalog) = self.header.catalog_code() {
+            if !catalog.is_valid_ean() {
+                errors.push(ValidationError::InvalidCatalog);
+            }
+        }
+        let isrcs: Vec<_> = self
+            .tracks()
+            .filter_map(|track| Some((track.id(), track.isrc_trimmed()?)))
+            .collect();
+        let registrants: Vec<_> = isrcs
+            .iter()
+            .filter(|(_, isrc)| is_valid_isrc(isrc))
+            .map(|(_, isrc)| &isrc[..5])
+            .collect();
+        let common = registrants.iter().find(|r| {
+            registrants.iter().filter(|other| other == r).count() * 2 > registrants.len()
+        });
+        let date = self.comments.get_number(comment::DATE);
+        for (i, &(id, isrc)) in isrcs.iter().enumerate() {
+            if !is_valid_isrc(isrc) {
+                errors.push(ValidationError::MalformedIsrc(id));
+            } else if isrcs[..i].iter().any(|(_, earlier)| *earlier == isrc) {
+                errors.push(ValidationError::DuplicateIsrc(id));
+            } else if common.is_some_and(|common| *common != &isrc[..5]) {
+                errors.push(ValidationError::IsrcRegistrantMismatch(id));
+            } else if date.is_some_and(|date| isrc_year(isrc) > date) {
+                errors.push(ValidationError::IsrcAfterDate(id));
+            }
+        }
+        let countries = self.header.catalog().and_then(|catalog| {
+            let prefix = catalog / 10_000_000_000;
+            GS1_COUNTRIES
+                .iter()
+                .find(|(first, last, _)| (*first..=*last).contains(&prefix))
+        });
+        if let Some((_, _, countries)) = countries {
+            let mut isrc_countries = registrants.iter().map(|r| &r[..2]).peekable();
+            if isrc_countries.peek().is_some() && isrc_countries.all(|c| !countries.contains(&c)) {
+                errors.push(ValidationError::CatalogCountryMismatch);
+            }
+        }
+        errors
+    }
+}
+
+fn is_valid_isrc(isrc: &str) -> bool {
+    isrc.parse::<Isrc>().is_ok()
+}
+/// Returns the year of a valid ISRC
+fn isrc_year(isrc: &str) -> u32 {
+    isrc.parse::<Isrc>().map_or(0, |isrc| isrc.year().into())
+}
+fn missing_start(track: &Track) -> bool {
+    track.get_index(IndexNumber::PREGAP).is_some() && track.get_index(IndexNumber::START).is_none()
+}
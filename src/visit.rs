@@ -0,0 +1,81 @@
+//! Reading a cue sheet as a stream of events, without building a [`Cuna`](crate::Cuna)
+//!
+//! A [`CueVisitor`] is called back for each command by [`Parser::drive()`](crate::parser::Parna::drive).
+//! The values are borrowed from the text, so computing a track count or a total time
+//! allocates nothing. Like [`Command`], quoted values are given without the quotes
+//! and keep escaped quotes like `\"`.
+//!
+//! ```rust
+//! use cuna::parser::Parser;
+//! use cuna::time::TimeStamp;
+//! use cuna::visit::CueVisitor;
+//!
+//! #[derive(Default)]
+//! struct Summary {
+//!     tracks: usize,
+//!     last_start: Option<TimeStamp>,
+//! }
+//! impl CueVisitor for Summary {
+//!     fn on_track(&mut self, _at: usize, _id: u8, _format: &str) {
+//!         self.tracks += 1;
+//!     }
+//!     fn on_index(&mut self, _at: usize, id: u8, timestamp: TimeStamp) {
+//!         if id == 1 {
+//!             self.last_start = Some(timestamp);
+//!         }
+//!     }
+//! }
+//!
+//! let cue = "FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    INDEX 01 04:15:44";
+//! let mut summary = Summary::default();
+//! Parser::new(cue).drive(&mut summary).unwrap();
+//! assert_eq!(summary.tracks, 2);
+//! assert_eq!(summary.last_start, Some(TimeStamp::new(4, 15, 44)));
+//! ```
+use crate::parser::Command;
+use crate::time::TimeStamp;
+
+/// Callbacks for the commands of a cue sheet, each given the number of its line starting from 1
+///
+/// Every callback does nothing by default, so a visitor only implements the ones it needs
+#[allow(unused_variables)]
+pub trait CueVisitor {
+    fn on_rem(&mut self, at: usize, rem: &str) {}
+    fn on_title(&mut self, at: usize, title: &str) {}
+    fn on_performer(&mut self, at: usize, performer: &str) {}
+    fn on_songwriter(&mut self, at: usize, songwriter: &str) {}
+    fn on_arranger(&mut self, at: usize, arranger: &str) {}
+    fn on_composer(&mut self, at: usize, composer: &str) {}
+    fn on_message(&mut self, at: usize, message: &str) {}
+    fn on_catalog(&mut self, at: usize, catalog: u64) {}
+    fn on_cdtextfile(&mut self, at: usize, cdtextfile: &str) {}
+    fn on_file(&mut self, at: usize, name: &str, format: &str) {}
+    fn on_track(&mut self, at: usize, id: u8, format: &str) {}
+    fn on_index(&mut self, at: usize, id: u8, timestamp: TimeStamp) {}
+    fn on_pregap(&mut self, at: usize, pregap: &str) {}
+    fn on_postgap(&mut self, at: usize, postgap: &str) {}
+    fn on_isrc(&mut self, at: usize, isrc: &str) {}
+    fn on_flags(&mut self, at: usize, flags: &str) {}
+    /// Called for every command, calls the callback of the command by default
+    fn on_command(&mut self, at: usize, command: &Command<'_>) {
+        match *command {
+            Command::Rem(s) => self.on_rem(at, s),
+            Command::Title(s) => self.on_title(at, s),
+            Command::Performer(s) => self.on_performer(at, s),
+            Command::Songwriter(s) => self.on_songwriter(at, s),
+            Command::Arranger(s) => self.on_arranger(at, s),
+            Command::Composer(s) => self.on_composer(at, s),
+            Command::Message(s) => self.on_message(at, s),
+            Command::Catalog(catalog) => self.on_catalog(at, catalog),
+            Command::Cdtextfile(s) => self.on_cdtextfile(at, s),
+            Command::File(name, format) => self.on_file(at, name, format),
+            Command::Track(id, format) => self.on_track(at, id, format),
+            Command::Index(id, timestamp) => self.on_index(at, id, timestamp),
+            Command::Pregap(s) => self.on_pregap(at, s),
+            Command::Postgap(s) => self.on_postgap(at, s),
+            Command::Isrc(s) => self.on_isrc(at, s),
+            Command::Flags(s) => self.on_flags(at, s),
+            Command::Empty => {}
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Reparsing a cue sheet whenever its file changes, requires the `watch` feature
+//!
+//! The directory of the sheet is watched with [notify](https://docs.rs/notify), so sheets which editors
+//! save by replacing the file are followed too. After each change the sheet is parsed again
+//! and the callback gets the new sheet with what changed since the last sheet which parsed,
+//! see [`diff`](crate::diff), or the error. Saves which change nothing are not reported.
+//!
+//! ```rust,no_run
+//! use cuna::watch;
+//!
+//! let watcher = watch::watch("album.cue", |update| match update {
+//!     Ok(update) => println!("{} changes, {} tracks", update.changes.len(), update.sheet.tracks().count()),
+//!     Err(e) => eprintln!("{}", e),
+//! })
+//! .unwrap();
+//! // the file is watched until watcher is dropped
+//! # drop(watcher);
+//! ```
+use crate::diff::Change;
+use crate::error::Error;
+use crate::Cuna;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::fs;
+use std::path::Path;
+
+/// A new version of the watched sheet
+#[derive(Debug, Clone, Copy)]
+pub struct Update<'a> {
+    pub sheet: &'a Cuna,
+    /// What changed since the previous version, never empty
+    pub changes: &'a [Change],
+}
+/// Watches a cue sheet until it is dropped, see [`watch()`]
+#[derive(Debug)]
+pub struct Watcher {
+    _watcher: RecommendedWatcher,
+}
+
+/// Watches the cue sheet at path, calling callback on another thread after each change
+///
+/// The first version is the sheet when this is called, or an empty sheet if it does not parse
+pub fn watch<F>(path: impl AsRef<Path>, mut callback: F) -> notify::Result<Watcher>
+where
+    F: FnMut(Result<Update<'_>, Error>) + Send + 'static,
+{
+    let path = fs::canonicalize(path)?;
+    let dir = path.parent().unwrap_or(&path).to_path_buf();
+    let mut previous = Cuna::open(&path).unwrap_or_default();
+    let target = path.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => return callback(Err(Error::from(std::io::Error::other(e)))),
+        };
+        let written = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+        if !written || !event.paths.contains(&target) {
+            return;
+        }
+        match Cuna::open(&target) {
+            Ok(sheet) => {
+                let changes = previous.diff(&sheet);
+                if !changes.is_empty() {
+                    callback(Ok(Update {
+                        sheet: &sheet,
+                        changes: &changes,
+                    }));
+                    previous = sheet;
+                }
+            }
+            Err(e) => callback(Err(e)),
+        }
+    })?;
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+    Ok(Watcher { _watcher: watcher })
+}
@@ -0,0 +1,819 @@
+//! Writing cue sheets with configurable formatting
+use crate::comment;
+use crate::cst::LineEnding;
+use crate::cst::BOM;
+use crate::dialect::Dialect;
+#[cfg(feature = "encoding")]
+use crate::error::EncodeError;
+use crate::error::InvalidValue;
+use crate::parser::Command;
+use crate::time::HumanFormat;
+use crate::time::TimeStamp;
+use crate::track::Index;
+use crate::track::IndexNumber;
+use crate::track::Track;
+use crate::Cuna;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::fmt;
+use std::io;
+
+/// The case of command keywords like `TITLE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeywordCase {
+    Upper,
+    Lower,
+    /// Spell each keyword as in the sheet given to [`CueWriter::original_case()`](CueWriter::original_case),
+    /// uppercase if it does not appear there
+    Original,
+}
+/// When to quote `TITLE`, `PERFORMER`, `SONGWRITER`, `CDTEXTFILE` and the name of `FILE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Quote {
+    Always,
+    /// Only quote values which are empty or contain whitespace
+    WhenNeeded,
+}
+/// What to do with characters which would make a cue sheet invalid:
+/// double quotes in quoted values, and line breaks or other control characters except tabs in any value
+///
+/// The name of a `FILE` is never rewritten by [`Replace`](Sanitize::Replace) or [`Strip`](Sanitize::Strip),
+/// as it would no longer point to the file, its double quotes are escaped instead
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Sanitize {
+    /// Replace double quotes with `'` and control characters with spaces
+    Replace,
+    /// Remove the characters
+    Strip,
+    /// Escape double quotes as `\"` and backslashes as `\\`, which [`Cuna::new()`](crate::Cuna::new) reads back,
+    /// and replace control characters with spaces, which is the default.
+    /// Other tools may keep the backslashes
+    #[default]
+    Escape,
+    /// Fail to write, see [`CueWriter::try_format()`](CueWriter::try_format)
+    Reject,
+}
+/// How to write the gap before a track, as burning tools expect different conventions
+///
+/// `PREGAP` is silence which is not in the file, while the audio between `INDEX 00` and `INDEX 01` is,
+/// so converting between them changes where the gap is played from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Gaps {
+    /// Write `PREGAP` and `INDEX 00` as they are
+    #[default]
+    AsIs,
+    /// Write `INDEX 00` as a `PREGAP` of the length until `INDEX 01`, if the track has no `PREGAP`
+    Pregap,
+    /// Write `PREGAP` as an `INDEX 00` that much before `INDEX 01`, if the track has no `INDEX 00`
+    /// and `INDEX 01` is not earlier than the gap
+    Index00,
+}
+/// Serializes a [`Cuna`](crate::Cuna) as a cue sheet
+///
+/// ```rust
+/// # #[cfg(feature = "parser")] {
+/// use cuna::Cuna;
+/// use cuna::writer::{CueWriter, KeywordCase, Quote};
+///
+/// let sheet = Cuna::new(r#"TITLE "Departures"
+/// FILE "a.flac" WAVE
+///   TRACK 01 AUDIO
+///     INDEX 01 00:00:00"#).unwrap();
+/// let writer = CueWriter::new()
+///     .indent(4)
+///     .keyword_case(KeywordCase::Lower)
+///     .quote(Quote::WhenNeeded);
+/// assert_eq!(writer.format(&sheet), "title Departures
+/// file a.flac WAVE
+///     track 01 AUDIO
+///         index 01 00:00:00
+/// ");
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CueWriter {
+    indent: usize,
+    keyword_case: KeywordCase,
+    quote: Quote,
+    zero_pad: bool,
+    sanitize: Sanitize,
+    gaps: Gaps,
+    line_ending: LineEnding,
+    bom: bool,
+    annotations: Option<HumanFormat>,
+    spellings: Vec<String>,
+}
+/// Writes [`Command`]s one at a time to an `io::Write`, without building a [`Cuna`](crate::Cuna)
+///
+/// `TRACK`s are indented under `FILE`s and the commands after a `TRACK` under the `TRACK`.
+/// The commands are not checked, writing them in a valid order is up to the caller
+///
+/// ```rust
+/// use cuna::parser::Command;
+/// use cuna::time::TimeStamp;
+/// use cuna::writer::CommandWriter;
+///
+/// let mut writer = CommandWriter::new(Vec::new());
+/// writer.write_command(&Command::File("a.flac", "WAVE")).unwrap();
+/// for id in 1..=2 {
+///     writer.write_command(&Command::Track(id, "AUDIO")).unwrap();
+///     writer.write_command(&Command::Index(1, TimeStamp::new(id as u32 * 4, 0, 0))).unwrap();
+/// }
+/// assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), r#"FILE "a.flac" WAVE
+///   TRACK 01 AUDIO
+///     INDEX 01 04:00:00
+///   TRACK 02 AUDIO
+///     INDEX 01 08:00:00
+/// "#);
+/// ```
+#[derive(Debug)]
+pub struct CommandWriter<W> {
+    inner: W,
+    indent: usize,
+    depth: usize,
+}
+
+impl CueWriter {
+    /// Returns a writer producing the same layout as EAC:
+    /// uppercase keywords, quoted values and 2 spaces of indentation for each level
+    pub const fn new() -> Self {
+        Self {
+            indent: 2,
+            keyword_case: KeywordCase::Upper,
+            quote: Quote::Always,
+            zero_pad: true,
+            sanitize: Sanitize::Escape,
+            gaps: Gaps::AsIs,
+            line_ending: LineEnding::Lf,
+            bom: false,
+            annotations: None,
+            spellings: Vec::new(),
+        }
+    }
+    /// Returns a writer producing the layout of a dialect
+    pub fn for_dialect(dialect: &dyn Dialect) -> Self {
+        dialect.writer(Self::new())
+    }
+    /// Sets the number of spaces `TRACK`s and the commands in them are indented with
+    pub const fn indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+    pub const fn keyword_case(mut self, keyword_case: KeywordCase) -> Self {
+        self.keyword_case = keyword_case;
+        self
+    }
+    /// Sets the keyword case to [`KeywordCase::Original`](KeywordCase::Original)
+    /// and takes the spelling of each keyword from its first line in `source`
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::writer::CueWriter;
+    /// use cuna::Cuna;
+    ///
+    /// let source = "Title \"Departures\"\nfile \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    index 01 00:00:00";
+    /// let sheet = Cuna::new(source).unwrap();
+    /// let writer = CueWriter::new().original_case(source);
+    /// assert_eq!(writer.format(&sheet), format!("{}\n", source));
+    /// # }
+    /// ```
+    pub fn original_case(mut self, source: &str) -> Self {
+        self.keyword_case = KeywordCase::Original;
+        self.spellings.clear();
+        for line in source.split(['\r', '\n']) {
+            let keyword = line
+                .trim_start_matches('\u{feff}')
+                .split_whitespace()
+                .next();
+            if let Some(keyword) = keyword {
+                if !self
+                    .spellings
+                    .iter()
+                    .any(|spelling| spelling.eq_ignore_ascii_case(keyword))
+                {
+                    self.spellings.push(keyword.to_owned());
+                }
+            }
+        }
+        self
+    }
+    pub const fn quote(mut self, quote: Quote) -> Self {
+        self.quote = quote;
+        self
+    }
+    /// Sets whether numbers of `TRACK` and `INDEX` are padded to 2 digits (`TRACK 01`), which is the default
+    ///
+    /// Without padding they are written as `TRACK 1`, which some old tools expect but many burners reject
+    pub const fn zero_pad(mut self, zero_pad: bool) -> Self {
+        self.zero_pad = zero_pad;
+        self
+    }
+    /// Sets the line ending, `\n` by default, [`LineEnding::None`](LineEnding::None) is written as `\n`
+    ///
+    /// Some Windows burning software only reads `\r\n`
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::cst::LineEnding;
+    /// use cuna::writer::CueWriter;
+    /// use cuna::Cuna;
+    ///
+    /// let sheet = Cuna::new("PERFORMER \"EGOIST\"\nTITLE \"Departures\"").unwrap();
+    /// let writer = CueWriter::new().line_ending(LineEnding::CrLf).bom(true);
+    /// assert_eq!(writer.format(&sheet), "\u{feff}PERFORMER \"EGOIST\"\r\nTITLE \"Departures\"\r\n");
+    /// # }
+    /// ```
+    pub const fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = match line_ending {
+            LineEnding::None => LineEnding::Lf,
+            line_ending => line_ending,
+        };
+        self
+    }
+    /// Sets whether a UTF-8 BOM is written at the beginning, which is not by default
+    ///
+    /// [`encode()`](CueWriter::encode) only writes it for UTF-8
+    pub const fn bom(mut self, bom: bool) -> Self {
+        self.bom = bom;
+        self
+    }
+    /// Sets whether each track gets a `REM START` and a `REM LENGTH` in the given format,
+    /// for players which show comments, None by default
+    ///
+    /// The times come from the [`timeline`](crate::timeline) and the start is from the beginning
+    /// of the `FILE`. A `REM` missing its time is left out, and `REM START`s and `REM LENGTH`s
+    /// already in the track are replaced, so the annotations of a sheet written before are not repeated
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::time::HumanFormat;
+    /// use cuna::writer::CueWriter;
+    /// use cuna::Cuna;
+    ///
+    /// let sheet = Cuna::new(r#"FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO
+    ///     INDEX 01 00:00:00
+    ///   TRACK 02 AUDIO
+    ///     INDEX 01 04:31:12"#).unwrap();
+    /// let writer = CueWriter::new().annotations(Some(HumanFormat::MinutesSeconds));
+    /// let written = writer.format(&sheet);
+    /// assert_eq!(written, r#"FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO
+    ///     REM START 00:00
+    ///     REM LENGTH 04:31
+    ///     INDEX 01 00:00:00
+    ///   TRACK 02 AUDIO
+    ///     REM START 04:31
+    ///     INDEX 01 04:31:12
+    /// "#);
+    /// assert_eq!(writer.format(&Cuna::new(&written).unwrap()), written);
+    /// # }
+    /// ```
+    pub const fn annotations(mut self, annotations: Option<HumanFormat>) -> Self {
+        self.annotations = annotations;
+        self
+    }
+    /// Sets what to do with characters which would make the sheet invalid
+    ///
+    /// ```rust
+    /// use cuna::Cuna;
+    /// use cuna::writer::{CueWriter, Sanitize};
+    ///
+    /// let mut sheet = Cuna::default();
+    /// sheet.header.push_title("He said \"hi\"\n".to_owned());
+    /// assert_eq!(CueWriter::new().format(&sheet), "TITLE \"He said \\\"hi\\\" \"\n");
+    /// let replace = CueWriter::new().sanitize(Sanitize::Replace);
+    /// assert_eq!(replace.format(&sheet), "TITLE \"He said 'hi' \"\n");
+    /// let strip = CueWriter::new().sanitize(Sanitize::Strip);
+    /// assert_eq!(strip.format(&sheet), "TITLE \"He said hi\"\n");
+    /// let err = CueWriter::new().sanitize(Sanitize::Reject).try_format(&sheet).unwrap_err();
+    /// assert_eq!((err.keyword, err.character), ("TITLE", '"'));
+    /// ```
+    pub const fn sanitize(mut self, sanitize: Sanitize) -> Self {
+        self.sanitize = sanitize;
+        self
+    }
+    /// Sets how to write the gap before each track
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "parser")] {
+    /// use cuna::Cuna;
+    /// use cuna::writer::{CueWriter, Gaps};
+    ///
+    /// let sheet = Cuna::new(r#"FILE "a.flac" WAVE
+    ///   TRACK 01 AUDIO
+    ///     INDEX 01 00:00:00
+    ///   TRACK 02 AUDIO
+    ///     INDEX 00 04:13:50
+    ///     INDEX 01 04:15:44"#).unwrap();
+    /// let pregap = CueWriter::new().gaps(Gaps::Pregap).format(&sheet);
+    /// assert!(pregap.ends_with("    PREGAP 00:01:69\n    INDEX 01 04:15:44\n"));
+    /// let index = CueWriter::new().gaps(Gaps::Index00).format(&Cuna::new(&pregap).unwrap());
+    /// assert_eq!(index, sheet.to_string());
+    /// # }
+    /// ```
+    pub const fn gaps(mut self, gaps: Gaps) -> Self {
+        self.gaps = gaps;
+        self
+    }
+    /// Writes the cue sheet
+    ///
+    /// With [`Sanitize::Reject`](Sanitize::Reject), an invalid value is a `fmt::Error`,
+    /// use [`try_format()`](CueWriter::try_format) to know which value it is
+    ///
+    /// Commands are written in the order of the spec: `REM`s, `CATALOG`, `CDTEXTFILE`, `PERFORMER`, `TITLE`, `SONGWRITER`,
+    /// the CD-Text `ARRANGER`, `COMPOSER` and `MESSAGE`, then every `FILE` with its `REM`s and `TRACK`s,
+    /// each of which is followed by `TITLE`, `PERFORMER`, `SONGWRITER`, `ARRANGER`, `COMPOSER`, `MESSAGE`,
+    /// `REM`s, the [`annotations`](CueWriter::annotations), `FLAGS`, `ISRC`, `PREGAP`, `INDEX`s and `POSTGAP`
+    pub fn write(&self, sheet: &Cuna, w: &mut impl fmt::Write) -> fmt::Result {
+        self.write_checked(sheet, w).map_err(|_| fmt::Error)
+    }
+    fn write_checked(
+        &self,
+        sheet: &Cuna,
+        w: &mut impl fmt::Write,
+    ) -> Result<(), Option<InvalidValue>> {
+        if self.bom {
+            w.write_char(BOM).map_err(|_| None)?;
+        }
+        let mut out = Output {
+            w,
+            writer: self,
+            invalid: Cell::new(None),
+            lines: 0,
+            unknown: &[],
+            unknown_depth: 0,
+        };
+        out.write(sheet).map_err(|_| out.invalid.take())
+    }
+    /// Writes the cue sheet to an `AsyncWrite`, requires the `tokio` feature
+    #[cfg(feature = "tokio")]
+    pub async fn write_async<W>(&self, sheet: &Cuna, w: &mut W) -> io::Result<()>
+    where
+        W: tokio::io::AsyncWrite + Unpin + ?Sized,
+    {
+        use tokio::io::AsyncWriteExt;
+        let s = self
+            .try_format(sheet)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        w.write_all(s.as_bytes()).await
+    }
+    /// Writes the cue sheet in an encoding other than UTF-8, requires the `encoding` feature
+    ///
+    /// Characters the encoding does not have are an error instead of being replaced,
+    /// as are the values rejected by [`Sanitize::Reject`](Sanitize::Reject)
+    ///
+    /// ```rust
+    /// use cuna::error::EncodeError;
+    /// use cuna::writer::CueWriter;
+    /// use cuna::Cuna;
+    /// use encoding_rs::{SHIFT_JIS, WINDOWS_1252};
+    ///
+    /// let sheet = Cuna::new("TITLE \"エウテルペ\"").unwrap();
+    /// let bytes = CueWriter::new().encode(&sheet, SHIFT_JIS).unwrap();
+    /// assert_eq!(SHIFT_JIS.decode(&bytes).0, sheet.to_string());
+    /// let err = CueWriter::new().encode(&sheet, WINDOWS_1252).unwrap_err();
+    /// assert_eq!(err, EncodeError::Unmappable { character: 'エ', encoding: "windows-1252", line: 1, column: 8 });
+    /// ```
+    #[cfg(feature = "encoding")]
+    pub fn encode(
+        &self,
+        sheet: &Cuna,
+        encoding: &'static encoding_rs::Encoding,
+    ) -> Result<Vec<u8>, EncodeError> {
+        use encoding_rs::EncoderResult;
+        let text = self.try_format(sheet).map_err(EncodeError::InvalidValue)?;
+        let text = if encoding == encoding_rs::UTF_8 {
+            &text[..]
+        } else {
+            text.trim_start_matches(BOM)
+        };
+        let mut encoder = encoding.new_encoder();
+        let mut bytes = Vec::with_capacity(text.len());
+        let line_ending = self.line_ending.as_str();
+        for (at, line) in text.split_inclusive(line_ending).enumerate() {
+            let mut read = 0;
+            loop {
+                let needed = encoder
+                    .max_buffer_length_from_utf8_without_replacement(line.len() - read)
+                    .unwrap_or(line.len() * 4);
+                bytes.reserve(needed);
+                let (result, n) = encoder.encode_from_utf8_to_vec_without_replacement(
+                    &line[read..],
+                    &mut bytes,
+                    false,
+                );
+                read += n;
+                match result {
+                    EncoderResult::InputEmpty => break,
+                    EncoderResult::OutputFull => continue,
+                    EncoderResult::Unmappable(character) => {
+                        let before = &line[..read - character.len_utf8()];
+                        return Err(EncodeError::Unmappable {
+                            character,
+                            encoding: encoding.name(),
+                            line: at + 1,
+                            column: before.chars().count() + 1,
+                        });
+                    }
+                }
+            }
+        }
+        // returns stateful encodings like ISO-2022-JP to the initial state
+        bytes.reserve(
+            encoder
+                .max_buffer_length_from_utf8_without_replacement(0)
+                .unwrap_or(8),
+        );
+        let _ = encoder.encode_from_utf8_to_vec_without_replacement("", &mut bytes, true);
+        Ok(bytes)
+    }
+    /// Returns the cue sheet as a String
+    ///
+    /// # Panics
+    ///
+    /// Panics with [`Sanitize::Reject`](Sanitize::Reject) if a value can not be written,
+    /// use [`try_format()`](CueWriter::try_format) instead
+    pub fn format(&self, sheet: &Cuna) -> String {
+        match self.try_format(sheet) {
+            Ok(s) => s,
+            Err(e) => panic!("{}", e),
+        }
+    }
+    /// Returns the cue sheet as a String, or the first value rejected by [`Sanitize::Reject`](Sanitize::Reject)
+    pub fn try_format(&self, sheet: &Cuna) -> Result<String, InvalidValue> {
+        let mut s = String::new();
+        match self.write_checked(sheet, &mut s) {
+            Ok(()) => Ok(s),
+            Err(e) => Err(e.expect("writing to a String never fails")),
+        }
+    }
+    /// Returns the `PREGAP` and the time of `INDEX 00` to write for a track
+    fn gap(&self, track: &Track) -> (Option<TimeStamp>, Option<TimeStamp>) {
+        let pregap = track.pregap().copied();
+        let index00 = track.get_index(IndexNumber::PREGAP).map(|i| i.begin_time);
+        let start = track.get_index(IndexNumber::START).map(|i| i.begin_time);
+        match (self.gaps, pregap, index00, start) {
+            (Gaps::Pregap, None, Some(index00), Some(start)) => match start.checked_sub(index00) {
+                Some(gap) => (Some(gap), None),
+                None => (pregap, Some(index00)),
+            },
+            (Gaps::Index00, Some(gap), None, Some(start)) => match start.checked_sub(gap) {
+                Some(index00) => (None, Some(index00)),
+                None => (pregap, index00),
+            },
+            _ => (pregap, index00),
+        }
+    }
+    fn number(&self, n: u8) -> String {
+        if self.zero_pad {
+            format!("{:02}", n)
+        } else {
+            n.to_string()
+        }
+    }
+}
+impl Default for CueWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<W: io::Write> CommandWriter<W> {
+    /// Returns a writer indenting with 2 spaces for each level
+    pub const fn new(inner: W) -> Self {
+        Self {
+            inner,
+            indent: 2,
+            depth: 0,
+        }
+    }
+    /// Sets the number of spaces of each level of indentation
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+    /// Writes a command as a line, [`Command::Empty`](Command::Empty) is written as a blank line
+    pub fn write_command(&mut self, command: &Command) -> io::Result<()> {
+        let depth = match command {
+            Command::File(..) => {
+                self.depth = 1;
+                0
+            }
+            Command::Track(..) => {
+                self.depth = 2;
+                1
+            }
+            Command::Empty => return writeln!(self.inner),
+            _ => self.depth,
+        };
+        writeln!(self.inner, "{:2$}{}", "", command, depth * self.indent)
+    }
+    /// Writes every command of an iterator
+    pub fn write_all<'a>(
+        &mut self,
+        commands: impl IntoIterator<Item = &'a Command<'a>>,
+    ) -> io::Result<()> {
+        commands
+            .into_iter()
+            .try_for_each(|command| self.write_command(command))
+    }
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+struct Output<'a, W> {
+    w: &'a mut W,
+    writer: &'a CueWriter,
+    invalid: Cell<Option<InvalidValue>>,
+    /// The number of lines written
+    lines: usize,
+    /// The lines of unknown commands of the header, `FILE` or `TRACK` being written
+    /// which are not written yet, and their depth
+    unknown: &'a [(usize, String)],
+    unknown_depth: usize,
+}
+
+impl<'a, W: fmt::Write> Output<'a, W> {
+    fn write(&mut self, sheet: &'a Cuna) -> fmt::Result {
+        self.unknown = &sheet.header.unknown.0;
+        for comment in sheet.comments() {
+            self.line(0, "REM", self.plain("REM", comment)?)?;
+        }
+        if let Some(catalog) = sheet.catalog_code() {
+            self.line(0, "CATALOG", catalog)?;
+        }
+        if let Some(cdtextfile) = sheet.header.cdtextfile() {
+            self.line(0, "CDTEXTFILE", self.quoted("CDTEXTFILE", cdtextfile)?)?;
+        }
+        for performer in sheet.performer() {
+            self.line(0, "PERFORMER", self.quoted("PERFORMER", performer)?)?;
+        }
+        for title in sheet.title() {
+            self.line(0, "TITLE", self.quoted("TITLE", title)?)?;
+        }
+        for songwriter in sheet.songwriter() {
+            self.line(0, "SONGWRITER", self.quoted("SONGWRITER", songwriter)?)?;
+        }
+        self.cd_text(
+            0,
+            &sheet.header.arranger,
+            &sheet.header.composer,
+            &sheet.header.message,
+        )?;
+        let timeline = match self.writer.annotations {
+            Some(_) => sheet.timeline(),
+            None => Vec::new(),
+        };
+        let mut spans = timeline.iter();
+        for file in sheet.files() {
+            self.rest()?;
+            self.line(
+                0,
+                "FILE",
+                format_args!(
+                    "{} {}",
+                    self.quoted("FILE", &file.name)?,
+                    self.plain("FILE", &file.format)?
+                ),
+            )?;
+            self.unknown = &file.unknown.0;
+            self.unknown_depth = 1;
+            for comment in file.comments() {
+                self.line(1, "REM", self.plain("REM", comment)?)?;
+            }
+            for track in file {
+                self.rest()?;
+                self.line(
+                    1,
+                    "TRACK",
+                    format_args!(
+                        "{} {}",
+                        self.writer.number(track.id()),
+                        self.plain("TRACK", &track.format)?
+                    ),
+                )?;
+                self.unknown = &track.unknown.0;
+                self.unknown_depth = 2;
+                for title in track.title() {
+                    self.line(2, "TITLE", self.quoted("TITLE", title)?)?;
+                }
+                for performer in track.performer() {
+                    self.line(2, "PERFORMER", self.quoted("PERFORMER", performer)?)?;
+                }
+                for songwriter in track.songwriter() {
+                    self.line(2, "SONGWRITER", self.quoted("SONGWRITER", songwriter)?)?;
+                }
+                self.cd_text(2, track.arranger(), track.composer(), track.message())?;
+                let annotations = self.writer.annotations.zip(spans.next());
+                for comment in track.comments() {
+                    let (key, _) = comment::split(comment);
+                    let generated = key.eq_ignore_ascii_case(comment::START)
+                        || key.eq_ignore_ascii_case(comment::LENGTH);
+                    if annotations.is_none() || !generated {
+                        self.line(2, "REM", self.plain("REM", comment)?)?;
+                    }
+                }
+                if let Some((format, span)) = annotations {
+                    if let Some(start) = span.start {
+                        self.line(
+                            2,
+                            "REM",
+                            format_args!("{} {}", comment::START, start.human(format)),
+                        )?;
+                    }
+                    if let Some(length) = span.length {
+                        self.line(
+                            2,
+                            "REM",
+                            format_args!("{} {}", comment::LENGTH, length.human(format)),
+                        )?;
+                    }
+                }
+                if !track.flags().is_empty() {
+                    self.line(2, "FLAGS", self.plain("FLAGS", &track.flags().join(" "))?)?;
+                }
+                if let Some(isrc) = track.isrc() {
+                    self.line(2, "ISRC", self.plain("ISRC", isrc)?)?;
+                }
+                let (pregap, index00) = self.writer.gap(track);
+                if let Some(pregap) = pregap {
+                    self.line(2, "PREGAP", pregap)?;
+                }
+                let rewritten = pregap != track.pregap().copied()
+                    || index00 != track.get_index(IndexNumber::PREGAP).map(|i| i.begin_time);
+                if let (true, Some(index00)) = (rewritten, index00) {
+                    self.index(Index::with_number(IndexNumber::PREGAP, index00))?;
+                }
+                for index in &track.index {
+                    if !rewritten || index.id() != IndexNumber::PREGAP.get() {
+                        self.index(*index)?;
+                    }
+                }
+                if let Some(postgap) = track.postgap() {
+                    self.line(2, "POSTGAP", postgap)?;
+                }
+            }
+        }
+        self.rest()
+    }
+    fn line(&mut self, depth: usize, keyword: &str, args: impl fmt::Display) -> fmt::Result {
+        while self
+            .unknown
+            .first()
+            .is_some_and(|(at, _)| *at <= self.lines + 1)
+        {
+            self.unknown_line()?;
+        }
+        self.lines += 1;
+        write!(self.w, "{:1$}", "", depth * self.writer.indent)?;
+        match self.writer.keyword_case {
+            KeywordCase::Upper => self.w.write_str(keyword)?,
+            KeywordCase::Lower => self.w.write_str(&keyword.to_ascii_lowercase())?,
+            KeywordCase::Original => {
+                let spelling = self
+                    .writer
+                    .spellings
+                    .iter()
+                    .find(|spelling| spelling.eq_ignore_ascii_case(keyword));
+                self.w.write_str(spelling.map_or(keyword, String::as_str))?
+            }
+        }
+        write!(self.w, " {}{}", args, self.writer.line_ending)
+    }
+    /// Writes the CD-Text `ARRANGER`s, `COMPOSER`s and `MESSAGE`s
+    fn cd_text(
+        &mut self,
+        depth: usize,
+        arranger: &[String],
+        composer: &[String],
+        message: &[String],
+    ) -> fmt::Result {
+        let commands = [
+            ("ARRANGER", arranger),
+            ("COMPOSER", composer),
+            ("MESSAGE", message),
+        ];
+        for (keyword, values) in commands.iter() {
+            for value in values.iter() {
+                self.line(depth, keyword, self.quoted(keyword, value)?)?;
+            }
+        }
+        Ok(())
+    }
+    /// Writes the next line of an unknown command as it is
+    fn unknown_line(&mut self) -> fmt::Result {
+        let ((_, line), rest) = self.unknown.split_first().ok_or(fmt::Error)?;
+        self.unknown = rest;
+        self.lines += 1;
+        write!(
+            self.w,
+            "{:1$}{2}{3}",
+            "",
+            self.unknown_depth * self.writer.indent,
+            line,
+            self.writer.line_ending
+        )
+    }
+    /// Writes the lines of unknown commands left at the end of their scope
+    fn rest(&mut self) -> fmt::Result {
+        while !self.unknown.is_empty() {
+            self.unknown_line()?;
+        }
+        Ok(())
+    }
+    fn index(&mut self, index: Index) -> fmt::Result {
+        self.line(
+            2,
+            "INDEX",
+            format_args!("{} {}", self.writer.number(index.id()), index.begin_time),
+        )
+    }
+    fn quoted<'s>(&self, keyword: &'static str, s: &'s str) -> Result<Quoted<'s>, fmt::Error> {
+        let s = self.sanitize(keyword, s, true)?;
+        let quote = match self.writer.quote {
+            Quote::Always => true,
+            Quote::WhenNeeded => s.is_empty() || s.contains(char::is_whitespace),
+        };
+        Ok(Quoted(s, quote))
+    }
+    fn plain<'s>(&self, keyword: &'static str, s: &'s str) -> Result<Cow<'s, str>, fmt::Error> {
+        self.sanitize(keyword, s, false)
+    }
+    fn sanitize<'s>(
+        &self,
+        keyword: &'static str,
+        s: &'s str,
+        quoted: bool,
+    ) -> Result<Cow<'s, str>, fmt::Error> {
+        let sanitize = match self.writer.sanitize {
+            Sanitize::Replace | Sanitize::Strip if keyword == "FILE" => Sanitize::Escape,
+            sanitize => sanitize,
+        };
+        let invalid = |c: char| (quoted && c == '"') || (c.is_control() && c != '\t');
+        let character = match s.chars().find(|&c| invalid(c)) {
+            Some(c) => c,
+            None if quoted && sanitize == Sanitize::Escape && s.contains('\\') => {
+                return Ok(Cow::Owned(escape(s, quoted)))
+            }
+            None => return Ok(Cow::Borrowed(s)),
+        };
+        let s = match sanitize {
+            Sanitize::Replace => s
+                .chars()
+                .map(|c| match c {
+                    '"' if quoted => '\'',
+                    c if invalid(c) => ' ',
+                    c => c,
+                })
+                .collect(),
+            Sanitize::Strip => s.chars().filter(|&c| !invalid(c)).collect(),
+            Sanitize::Escape => escape(s, quoted),
+            Sanitize::Reject => {
+                self.invalid.set(Some(InvalidValue {
+                    keyword,
+                    value: s.to_owned(),
+                    character,
+                }));
+                return Err(fmt::Error);
+            }
+        };
+        Ok(Cow::Owned(s))
+    }
+}
+
+/// Escapes double quotes as `\"` and backslashes as `\\` if the value is quoted
+/// and replaces control characters except tabs with spaces
+pub(crate) fn escape(s: &str, quoted: bool) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    for c in s.chars() {
+        match c {
+            '"' if quoted => escaped.push_str(r#"\""#),
+            '\\' if quoted => escaped.push_str(r"\\"),
+            c if c.is_control() && c != '\t' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+struct Quoted<'a>(Cow<'a, str>, bool);
+
+impl fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.1 {
+            write!(f, r#""{}""#, self.0)
+        } else {
+            f.write_str(&self.0)
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Converting cue sheets from and to YAML, requires the `yaml` feature
+//!
+//! The shape is the same as the [JSON one](crate::json)
+use crate::Cuna;
+
+impl Cuna {
+    /// Serializes the cue sheet as YAML
+    ///
+    /// ```rust
+    /// use cuna::Cuna;
+    /// let sheet = Cuna::new("REM GENRE Pop\nFILE \"a.flac\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00").unwrap();
+    /// let yaml = sheet.to_yaml().unwrap();
+    /// assert!(yaml.contains("- GENRE Pop"));
+    /// assert_eq!(Cuna::from_yaml(&yaml).unwrap(), sheet);
+    /// ```
+    pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+        serde_yaml::to_string(self)
+    }
+    /// Deserializes a cue sheet from YAML
+    pub fn from_yaml(s: &str) -> Result<Self, serde_yaml::Error> {
+        serde_yaml::from_str(s)
+    }
+}
@@ -0,0 +1,126 @@
+//! Public API snapshot of the stable modules, see `cuna::stability`
+//!
+//! Run with `CUNA_BLESS=1` to update `tests/public-api.txt` after an intended change.
+use cuna::stability::{self, Stability};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SNAPSHOT: &str = "tests/public-api.txt";
+
+fn sources(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            sources(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+/// The root module of a source file, like `export` for `src/export/xml.rs`
+fn root_module(path: &Path) -> String {
+    let relative = path.strip_prefix("src").unwrap();
+    let first = relative.components().next().unwrap();
+    let first = first.as_os_str().to_str().unwrap();
+    first.trim_end_matches(".rs").to_owned()
+}
+/// `pub` items of a file with their signatures on one line
+fn public_items(source: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut lines = source.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if !line.starts_with("pub ") {
+            continue;
+        }
+        let mut item = line.to_owned();
+        let is_fn = item.contains("fn ");
+        let ended = |item: &str| {
+            item.ends_with('{') || item.ends_with(';') || (!is_fn && item.ends_with(','))
+        };
+        while !ended(&item) {
+            match lines.next() {
+                Some(next) => {
+                    if !item.ends_with('(') && !next.starts_with(')') {
+                        item.push(' ');
+                    }
+                    item.push_str(next);
+                }
+                None => break,
+            }
+        }
+        if item.starts_with("pub const ") || item.starts_with("pub static ") {
+            if let Some(value) = item.find(" = ") {
+                item.truncate(value);
+            }
+        }
+        let item = item.trim_end_matches('{').trim_end().trim_end_matches(',');
+        items.push(item.replace(",)", ")"));
+    }
+    items
+}
+fn snapshot() -> String {
+    let mut files = Vec::new();
+    sources(Path::new("src"), &mut files);
+    files.sort();
+    let mut snapshot = String::new();
+    for file in files {
+        if stability::of(&root_module(&file)) == Some(Stability::Experimental) {
+            continue;
+        }
+        let items = public_items(&fs::read_to_string(&file).unwrap());
+        if items.is_empty() {
+            continue;
+        }
+        let name = file.to_str().unwrap().replace('\\', "/");
+        snapshot.push_str(&format!("# {}\n", name));
+        for item in items {
+            snapshot.push_str(&item);
+            snapshot.push('\n');
+        }
+    }
+    snapshot
+}
+
+#[test]
+fn public_api() {
+    let current = snapshot();
+    if std::env::var_os("CUNA_BLESS").is_some() {
+        fs::write(SNAPSHOT, &current).unwrap();
+        return;
+    }
+    let recorded = fs::read_to_string(SNAPSHOT).unwrap_or_default();
+    let changed = current
+        .lines()
+        .filter(|line| !recorded.lines().any(|l| l == *line))
+        .map(|line| format!("+ {}", line))
+        .chain(
+            recorded
+                .lines()
+                .filter(|line| !current.lines().any(|l| l == *line))
+                .map(|line| format!("- {}", line)),
+        )
+        .collect::<Vec<_>>();
+    assert!(
+        current == recorded,
+        "the public API changed, run with CUNA_BLESS=1 if it is intended:\n{}",
+        changed.join("\n")
+    );
+}
+#[test]
+fn modules_have_stability() {
+    let lib = fs::read_to_string("src/lib.rs").unwrap();
+    for line in lib.lines().filter(|line| line.starts_with("pub mod ")) {
+        let module = line["pub mod ".len()..].trim_end_matches(';');
+        assert!(
+            stability::of(module).is_some(),
+            "`{}` is missing in cuna::stability::MODULES",
+            module
+        );
+    }
+    assert_eq!(
+        stability::MODULES.len(),
+        lib.lines()
+            .filter(|line| line.starts_with("pub mod "))
+            .count()
+    );
+}
@@ -1,3 +1,4 @@
+#![cfg(feature = "parser")]
 type Result = std::result::Result<(), cuna::error::Error>;
 
 const CUE: &str = include_str!(r"EGOIST - Departures ～あなたにおくるアイの歌～.cue");
@@ -10,7 +11,7 @@ mod time {
     #[test]
     fn create() {
         let timestamp = TimeStamp::new(61, 29, 73);
-        assert_eq!(TimeStamp::from_msf_opt(61, 29, 73), Some(timestamp.clone()));
+        assert_eq!(TimeStamp::from_msf_opt(61, 29, 73), Some(timestamp));
         assert_eq!(TimeStamp::from_msf_opt(61, 29, 77), None);
         assert_eq!(TimeStamp::from_msf(61, 28, 73 + 75), timestamp);
     }
@@ -28,6 +29,45 @@ mod time {
         Ok(())
     }
     #[test]
+    fn parse_error_column() {
+        use cuna::error::InvalidArgument::MalformedTimestamp;
+        let err = |component, column| Err(MalformedTimestamp { component, column });
+        assert_eq!("xd".parse::<TimeStamp>(), err(Component::Minutes, 1));
+        assert_eq!("100:61:00".parse::<TimeStamp>(), err(Component::Seconds, 5));
+        assert_eq!("1:01:0".parse::<TimeStamp>(), err(Component::Frames, 6));
+        assert_eq!("1:01:00 ".parse::<TimeStamp>(), err(Component::Frames, 6));
+        assert_eq!(
+            "99999999999:00:00".parse::<TimeStamp>(),
+            err(Component::Minutes, 1)
+        );
+    }
+    #[test]
+    fn minutes_beyond_99() -> Result {
+        use cuna::error::{InvalidArgument, ParseError};
+        use cuna::parser::ParseOptions;
+        use cuna::Cuna;
+        let cue = "FILE \"a.bin\" BINARY\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    INDEX 01 100:23:45";
+        let sheet = Cuna::new(cue)?;
+        assert_eq!(sheet[0][1].start(), Some(TimeStamp::new(100, 23, 45)));
+        assert!(sheet.to_string().contains("INDEX 01 100:23:45"));
+        assert!(Cuna::new(&cue.replace("100:", "1000:")).is_err());
+        let options = ParseOptions {
+            red_book_minutes: true,
+            ..ParseOptions::default()
+        };
+        let err = Cuna::parse_with(cue, &options).unwrap_err();
+        assert_eq!(err.pos(), Some(5));
+        assert_eq!(
+            err.kind(),
+            &ParseError::InvalidArgument(InvalidArgument::MalformedTimestamp {
+                component: Component::Minutes,
+                column: 1
+            })
+        );
+        assert!(Cuna::parse_with(&cue.replace("100:", "99:"), &options).is_ok());
+        Ok(())
+    }
+    #[test]
     fn modify() {
         let mut timestamp = TimeStamp::new(21, 29, 73);
         timestamp.set_frames(21);
@@ -64,6 +104,7 @@ mod time {
 mod command {
     use super::*;
     use cuna::parser::Command;
+    use cuna::time::TimeStamp;
 
     #[test]
     fn new() -> Result {
@@ -72,11 +113,19 @@ mod command {
         Ok(())
     }
     #[test]
+    fn display_padded() -> Result {
+        assert_eq!(Command::Track(1, "AUDIO").to_string(), "TRACK 01 AUDIO");
+        let index = Command::Index(2, TimeStamp::new(1, 2, 3));
+        assert_eq!(index.to_string(), "INDEX 02 01:02:03");
+        Ok(())
+    }
+    #[test]
     fn display() -> Result {
         let cmds = r#"REM COMMENT ExactAudioCopy v0.99pb5
         PERFORMER "Supercell"
         TITLE "My Dearest"
-        FILE "Supercell - My Dearest.flac" WAVE"#;
+        FILE "Supercell - My Dearest.flac" WAVE
+        FLAGS DCP PRE"#;
         for (cmd, ori) in cmds.lines().map(Command::new).zip(cmds.lines()) {
             assert_eq!(cmd?.to_string(), ori.trim().to_string())
         }
@@ -89,6 +138,96 @@ mod cue_sheet {
     use cuna::CueSheet;
     use std::str::FromStr;
 
+    #[test]
+    fn replay_gain() -> Result {
+        use cuna::comment::ReplayGain;
+        let mut sheet = CueSheet::from_str(CUE)?;
+        assert_eq!(sheet.replay_gain(), ReplayGain::default());
+        let gain = ReplayGain {
+            gain: Some(-8.5),
+            peak: Some(1.0),
+        };
+        sheet.comments.set_album_gain(gain);
+        sheet.files[0].tracks[1]
+            .comments_mut()
+            .set_track_gain(ReplayGain {
+                gain: Some(-6.25),
+                peak: None,
+            });
+        let sheet = CueSheet::from_str(&sheet.to_string())?;
+        assert_eq!(sheet.replay_gain(), gain);
+        assert_eq!(sheet[0][1].replay_gain().gain, Some(-6.25));
+        assert_eq!(sheet[0][1].replay_gain().peak, None);
+        assert_eq!(sheet[0][0].replay_gain(), ReplayGain::default());
+        Ok(())
+    }
+    #[test]
+    fn cd_text() -> Result {
+        let cue = "COMPOSER \"ryo\"\nMESSAGE \"Made in Japan\"\nFILE \"a.wav\" WAVE\n  TRACK 01 AUDIO\n    ARRANGER \"ryo\"\n    INDEX 01 00:00:00";
+        let sheet = CueSheet::from_str(cue)?;
+        assert_eq!(sheet.composer(), &["ryo".to_owned()]);
+        assert_eq!(sheet.message(), &["Made in Japan".to_owned()]);
+        assert!(sheet.arranger().is_empty());
+        assert_eq!(sheet[0][0].arranger(), &["ryo".to_owned()]);
+        assert_eq!(CueSheet::from_str(&sheet.to_string())?, sheet);
+        Ok(())
+    }
+    #[cfg(feature = "date")]
+    #[test]
+    fn date() -> Result {
+        use cuna::date::Date;
+        use cuna::error::WarningKind;
+        let sheet = CueSheet::from_str(CUE)?;
+        assert_eq!(sheet.date(), Date::new(2011, None, None));
+        let mut parser = cuna::parser::Parser::new("REM DATE 2011-02-29\nTITLE \"Departures\"");
+        let mut sheet = CueSheet::default();
+        parser.parse(&mut sheet)?;
+        assert_eq!(sheet.date(), None);
+        assert!(sheet.comments.date().unwrap().is_err());
+        assert_eq!(
+            parser.warnings()[0].kind(),
+            &WarningKind::MalformedDate("2011-02-29".to_owned())
+        );
+        assert_eq!("2012-02-29".parse::<Date>()?.to_string(), "2012-02-29");
+        assert_eq!(
+            "2003/05".parse::<Date>()?,
+            Date::new(2003, Some(5), None).unwrap()
+        );
+        assert!("03-05-14".parse::<Date>().is_err());
+        assert!("2003-5-14".parse::<Date>().is_err());
+        Ok(())
+    }
+    #[test]
+    fn metadata() -> Result {
+        let sheet = CueSheet::from_str(CUE)?;
+        let metadata = sheet.metadata();
+        assert_eq!(metadata.genres, ["Pop"]);
+        assert_eq!(metadata.year, Some(2011));
+        assert_eq!(metadata.discid, Some(0x59063607));
+        assert_eq!(metadata.comment.as_deref(), Some("ExactAudioCopy v0.99pb5"));
+        assert_eq!(sheet.comments.len(), 4);
+        let sheet = CueSheet::from_str("REM DISCID 5906360Z\nREM DATE unknown")?;
+        assert_eq!(sheet.metadata().discid, None);
+        assert_eq!(sheet.metadata().year, None);
+        assert_eq!(sheet.metadata().date.as_deref(), Some("unknown"));
+        Ok(())
+    }
+    #[test]
+    fn leading_zero_catalog() -> Result {
+        let cue = "CATALOG 0075678164125\nTITLE \"a\"\n";
+        let sheet = CueSheet::from_str(cue)?;
+        assert_eq!(sheet.catalog(), Some(75678164125));
+        let catalog = sheet.catalog_code().unwrap();
+        assert_eq!(catalog.as_str(), "0075678164125");
+        assert!(catalog.is_valid_ean());
+        assert_eq!(sheet.to_string(), cue);
+        assert!(sheet.validate_codes().is_empty());
+        let mut header = sheet.header.clone();
+        assert!(header.set_catalog(75678164125).is_err());
+        header.set_catalog_code("0075678164126".parse()?);
+        assert!(!header.catalog_code().unwrap().is_valid_ean());
+        Ok(())
+    }
     #[test]
     fn new() -> Result {
         let sheet = CueSheet::from_str(CUE)?;
@@ -109,6 +248,92 @@ mod cue_sheet {
         Ok(())
     }
     #[test]
+    fn genres() -> Result {
+        use cuna::comment::GenreStyle;
+        let mut sheet =
+            CueSheet::from_str(&CUE.replace("GENRE Pop", "GENRE \"Pop; J-Pop\"\nREM GENRE Anime"))?;
+        assert_eq!(sheet.comments.genres(), ["Pop", "J-Pop", "Anime"]);
+        let genres = sheet.comments.genres();
+        sheet.comments.set_genres(&genres, GenreStyle::Repeated);
+        assert_eq!(
+            sheet.comments.0[..3],
+            ["GENRE Pop", "GENRE J-Pop", "GENRE Anime"]
+        );
+        let written = CueSheet::from_str(&sheet.to_string())?;
+        assert_eq!(written.comments.genres(), ["Pop", "J-Pop", "Anime"]);
+        sheet.comments.set_genres::<&str>(&[], GenreStyle::Joined);
+        assert!(sheet.comments.genres().is_empty());
+        assert_eq!(sheet.comments.get("DATE"), Some("2011"));
+        Ok(())
+    }
+    #[test]
+    fn decimal_comments() -> Result {
+        let localized = CUE.replace(
+            "REM DATE 2011",
+            "REM DATE 2011\nREM REPLAYGAIN_ALBUM_GAIN -7,11 dB\nREM REPLAYGAIN_ALBUM_PEAK 0,988525",
+        );
+        let mut sheet = CueSheet::from_str(&localized)?;
+        let gain = sheet.comments.get_decimal("replaygain_album_gain");
+        assert_eq!(gain, Some(-7.11));
+        let peak = sheet.comments.get_decimal("REPLAYGAIN_ALBUM_PEAK").unwrap();
+        sheet
+            .comments
+            .set_decimal("REPLAYGAIN_ALBUM_GAIN", gain.unwrap(), 2, Some("dB"));
+        sheet
+            .comments
+            .set_decimal("REPLAYGAIN_ALBUM_PEAK", peak, 6, None);
+        let written = sheet.to_string();
+        assert!(written
+            .contains("REM REPLAYGAIN_ALBUM_GAIN -7.11 dB\nREM REPLAYGAIN_ALBUM_PEAK 0.988525\n"));
+        let reparsed = CueSheet::from_str(&written)?;
+        assert_eq!(
+            reparsed.comments.get_decimal("REPLAYGAIN_ALBUM_PEAK"),
+            Some(peak)
+        );
+        assert_eq!(reparsed.comments.get_decimal("DATE"), Some(2011.0));
+        Ok(())
+    }
+    #[test]
+    fn from_bytes_lossy() -> Result {
+        use cuna::error::WarningKind;
+        let (sheet, warnings) = CueSheet::from_bytes_lossy(CUE.as_bytes())?;
+        assert_eq!(sheet, CueSheet::from_str(CUE)?);
+        assert!(warnings.is_empty());
+        // a title cut in the middle of a character and a Latin-1 é
+        let mut bytes = CUE.as_bytes().to_vec();
+        let performer = CUE.rfind("EGOIST").unwrap();
+        bytes.insert(performer, 0xe9);
+        let title = CUE.find("エウテルペ").unwrap();
+        bytes.drain(title + 1..title + 3);
+        let (sheet, warnings) = CueSheet::from_bytes_lossy(&bytes)?;
+        assert_eq!(sheet[0][1].title(), &["\u{fffd}ウテルペ".to_owned()]);
+        assert_eq!(sheet[0][6].performer(), &["\u{fffd}EGOIST".to_owned()]);
+        let invalid: Vec<_> = warnings
+            .iter()
+            .map(|w| (w.kind().clone(), w.pos()))
+            .collect();
+        assert_eq!(
+            invalid,
+            [
+                (
+                    WarningKind::InvalidBytes {
+                        offset: title,
+                        len: 1
+                    },
+                    Some(14)
+                ),
+                (
+                    WarningKind::InvalidBytes {
+                        offset: performer - 2,
+                        len: 1
+                    },
+                    Some(35)
+                ),
+            ]
+        );
+        Ok(())
+    }
+    #[test]
     fn from_buf_read() -> Result {
         let sheet = CueSheet::from_buf_read(&mut CUE.to_string().as_ref())?;
         assert_eq!(sheet.comments[0], "GENRE Pop");
@@ -125,6 +350,143 @@ mod cue_sheet {
             sheet.last_track().unwrap().performer(),
             &vec!["EGOIST".to_owned()]
         );
+        let file = std::fs::File::open("tests/EGOIST - Departures ～あなたにおくるアイの歌～.cue")?;
+        let read = CueSheet::from_reader(std::io::BufReader::new(file))?;
+        assert_eq!(read, sheet);
+        Ok(())
+    }
+    #[cfg(feature = "tokio")]
+    #[tokio::test(flavor = "current_thread")]
+    async fn write_to_async() -> Result {
+        use cuna::writer::{CueWriter, Sanitize};
+        let sheet = CueSheet::from_str(CUE)?;
+        let mut expected = String::new();
+        sheet.write_to(&mut expected).unwrap();
+        let mut buf = Vec::new();
+        sheet.write_to_async(&mut buf).await.unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+        let mut sheet = CueSheet::default();
+        sheet.header.push_title("a\nb".to_owned());
+        let writer = CueWriter::new().sanitize(Sanitize::Reject);
+        let err = writer
+            .write_async(&sheet, &mut Vec::new())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        Ok(())
+    }
+    #[cfg(feature = "tokio")]
+    #[tokio::test(flavor = "current_thread")]
+    async fn from_async_reader() -> Result {
+        let sheet = CueSheet::from_async_reader(CUE.as_bytes()).await?;
+        assert_eq!(sheet, CueSheet::from_str(CUE)?);
+        let broken = b"TITLE \"a\"\nTITLE \"\xff\"";
+        let err = CueSheet::from_async_reader(&broken[..]).await.unwrap_err();
+        assert_eq!(err.pos(), Some(2));
+        // the BOM of CUE is written as a UTF-16 one
+        let le: Vec<u8> = CUE.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let be: Vec<u8> = CUE.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(CueSheet::from_async_reader(&le[..]).await?, sheet);
+        let one_byte = tokio::io::BufReader::with_capacity(1, &be[..]);
+        assert_eq!(CueSheet::from_async_reader(one_byte).await?, sheet);
+        Ok(())
+    }
+    #[test]
+    fn utf16() -> Result {
+        use cuna::error::{ParseError, WarningKind};
+        use std::io::BufReader;
+        let expected = CueSheet::from_str(CUE)?;
+        // the BOM of CUE is written as a UTF-16 one
+        let le: Vec<u8> = CUE.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let be: Vec<u8> = CUE.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(&le[..2], [0xff, 0xfe]);
+        assert_eq!(CueSheet::from_buf_read(&mut &le[..])?, expected);
+        assert_eq!(CueSheet::from_buf_read(&mut &be[..])?, expected);
+        let mut one_byte = BufReader::with_capacity(1, &le[..]);
+        assert_eq!(CueSheet::from_buf_read(&mut one_byte)?, expected);
+        assert_eq!(CueSheet::from_bytes_lossy(&le)?, (expected, Vec::new()));
+
+        let emoji: Vec<u8> = "\u{feff}TITLE \"🎵\""
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let sheet = CueSheet::from_buf_read(&mut &emoji[..])?;
+        assert_eq!(sheet.title(), &["🎵".to_owned()]);
+        // an unpaired surrogate in place of the second half of the emoji
+        let mut broken = emoji.clone();
+        broken[18..20].copy_from_slice(&0xd800u16.to_le_bytes());
+        let error = CueSheet::from_buf_read(&mut &broken[..]).unwrap_err();
+        assert!(matches!(error.kind(), ParseError::IoError(_)));
+        let (sheet, warnings) = CueSheet::from_bytes_lossy(&broken)?;
+        assert_eq!(sheet.title(), &["\u{fffd}\u{fffd}".to_owned()]);
+        let kinds: Vec<_> = warnings.iter().map(|w| w.kind().clone()).collect();
+        assert_eq!(
+            kinds,
+            [
+                WarningKind::InvalidBytes { offset: 16, len: 2 },
+                WarningKind::InvalidBytes { offset: 18, len: 2 }
+            ]
+        );
+        Ok(())
+    }
+    #[test]
+    fn reset() -> Result {
+        use cuna::parser::Parser;
+        let mut sheet = CueSheet::from_str(CUE)?;
+        let capacity = (sheet.header.title.capacity(), sheet.files.capacity());
+        sheet.reset();
+        assert_eq!(sheet, CueSheet::default());
+        assert_eq!(
+            (sheet.header.title.capacity(), sheet.files.capacity()),
+            capacity
+        );
+        Parser::new(cuna::trim_utf8_header(CUE)).parse(&mut sheet)?;
+        assert_eq!(sheet, CueSheet::from_str(CUE)?);
+        Ok(())
+    }
+    #[test]
+    fn header_only() -> Result {
+        let full = CueSheet::from_str(CUE)?;
+        let header = CueSheet::parse_header_only(CUE)?;
+        assert_eq!(header.header, full.header);
+        assert_eq!(header.comments, full.comments);
+        assert!(header.files.is_empty());
+        let broken = CUE.replace("INDEX 01 00:00:00", "INDEX 01 ??");
+        assert!(CueSheet::from_str(&broken).is_err());
+        assert_eq!(CueSheet::parse_header_only(&broken)?, header);
+        Ok(())
+    }
+    #[test]
+    fn scoped_comments() -> Result {
+        use cuna::comment::Scope;
+        let cue = "REM GENRE Pop\nFILE \"a.flac\" WAVE\n  REM SOURCE vinyl\n  TRACK 01 AUDIO\n    TITLE \"Departures\"\n    REM REPLAYGAIN_TRACK_GAIN -7.11 dB\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    REM DATE 2011\n    INDEX 01 04:15:44\n";
+        let mut parser = cuna::parser::Parser::new(cue);
+        let mut sheet = CueSheet::default();
+        parser.parse(&mut sheet)?;
+        assert_eq!(sheet.comments.0, ["GENRE Pop", "DATE 2011"]);
+        assert_eq!(sheet[0].comments()[0], "SOURCE vinyl");
+        assert_eq!(
+            sheet[0][0].comments().get_decimal("REPLAYGAIN_TRACK_GAIN"),
+            Some(-7.11)
+        );
+        assert!(sheet[0][1].comments().is_empty());
+        assert_eq!(parser.warnings().len(), 1);
+        assert_eq!(
+            sheet
+                .scoped_comments()
+                .map(|(scope, _)| scope)
+                .collect::<Vec<_>>(),
+            [
+                Scope::Header,
+                Scope::Header,
+                Scope::File(0),
+                Scope::Track { file: 0, track: 0 }
+            ]
+        );
+        let written = sheet.to_string();
+        assert!(written.contains("  REM SOURCE vinyl\n  TRACK 01"));
+        assert!(written.contains("Departures\"\n    REM REPLAYGAIN_TRACK_GAIN"));
+        assert_eq!(CueSheet::from_str(&written)?, sheet);
         Ok(())
     }
     #[test]
@@ -140,6 +502,181 @@ mod cue_sheet {
         assert_eq!(track[0], "INDEX 01 08:04:33".parse()?);
         Ok(())
     }
+    #[test]
+    fn write() -> Result {
+        let sheet = CueSheet::from_str(CUE)?;
+        let written = sheet.to_string();
+        assert_eq!(CueSheet::from_str(&written)?, sheet);
+        assert_eq!(
+            written.lines().nth(6),
+            Some("FILE \"EGOIST - Departures ～あなたにおくるアイの歌～.flac\" WAVE")
+        );
+        assert_eq!(written.lines().last(), Some("    INDEX 01 24:55:13"));
+        Ok(())
+    }
+    #[test]
+    fn rem_block() -> Result {
+        use cuna::comment::RemBlock;
+        let sheet = CueSheet::from_str(CUE)?;
+        let block = RemBlock::from_comment(&sheet.comments).build();
+        assert_eq!(block[3], r#"COMMENT "ExactAudioCopy v0.99pb5""#);
+        assert_eq!(block.get("COMMENT"), sheet.comments.get("COMMENT"));
+        assert_eq!(sheet.comments.standard_rem_block(), sheet.comments);
+        Ok(())
+    }
+    #[test]
+    fn anonymize() -> Result {
+        let mut sheet = CueSheet::from_str(CUE)?;
+        let removed = sheet.anonymize();
+        assert_eq!(removed[0], "COMMENT ExactAudioCopy v0.99pb5");
+        assert_eq!(sheet.comments.len(), 3);
+        assert_eq!(sheet.comments.get("DISCID"), Some("59063607"));
+        let mut sheet = CueSheet::from_str("REM GENRE \"Jあ\"\nREM SOURCE \"C:\\rips\"")?;
+        assert_eq!(sheet.anonymize().0, ["SOURCE \"C:\\rips\""]);
+        assert_eq!(sheet.comments.get("GENRE"), Some("Jあ"));
+        Ok(())
+    }
+    #[test]
+    fn completeness() -> Result {
+        use cuna::completeness::Field;
+        let sheet = CueSheet::from_str(CUE)?;
+        let completeness = sheet.completeness();
+        assert_eq!(completeness.missing_in_header().count(), 0);
+        assert_eq!(
+            completeness.missing_in_track(3).collect::<Vec<_>>(),
+            [Field::Isrc]
+        );
+        assert!((completeness.score - 5.0 / 6.0).abs() < f32::EPSILON);
+        Ok(())
+    }
+    #[test]
+    fn one_liner() -> Result {
+        use cuna::time::TimeStamp;
+        let empty = CueSheet::default();
+        assert_eq!(empty.one_liner(), "Untitled (0 tracks)");
+        assert_eq!(
+            empty.one_liner_with_lengths(&[TimeStamp::new(1, 0, 0)]),
+            "Untitled (0 tracks)"
+        );
+        let untitled =
+            CueSheet::from_str("FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00")?;
+        assert_eq!(untitled.one_liner(), "a.flac (1 track)");
+        assert_eq!(
+            untitled.one_liner_with_lengths(&[TimeStamp::new(4, 15, 44)]),
+            "a.flac (1 track, 04:15)"
+        );
+        let sheet = CueSheet::from_str(CUE)?;
+        let mut two_files = sheet.clone();
+        two_files.files.push(sheet[0].clone());
+        assert_eq!(
+            two_files.one_liner(),
+            "ギルティクラウン ED — Departures ～あなたにおくるアイの歌～ (14 tracks)"
+        );
+        let lengths = [TimeStamp::new(25, 0, 0), TimeStamp::new(26, 30, 0)];
+        assert_eq!(
+            two_files.one_liner_with_lengths(&lengths[..1]),
+            two_files.one_liner()
+        );
+        assert_eq!(
+            two_files.one_liner_with_lengths(&lengths),
+            "ギルティクラウン ED — Departures ～あなたにおくるアイの歌～ (14 tracks, 51:30)"
+        );
+        Ok(())
+    }
+    #[test]
+    fn coalesce_files() -> Result {
+        let sheet = |files: &[(&str, &str)]| {
+            let mut cue = String::new();
+            for (i, (name, format)) in files.iter().enumerate() {
+                cue += &format!(
+                    "FILE \"{}\" {}\nREM FILE {}\n  TRACK {:02} AUDIO\n    INDEX 01 00:00:00\n",
+                    name,
+                    format,
+                    i,
+                    i + 1
+                );
+            }
+            CueSheet::from_str(&cue)
+        };
+        let mut apart = sheet(&[("a.wav", "WAVE"), ("b.wav", "WAVE"), ("a.wav", "WAVE")])?;
+        assert_eq!(apart.coalesce_files(), 0);
+        assert_eq!(apart.files.len(), 3);
+        let mut formats = sheet(&[("a.bin", "BINARY"), ("a.bin", "MOTOROLA")])?;
+        assert_eq!(formats.coalesce_files(), 0);
+        let mut case = sheet(&[("a.wav", "WAVE"), ("A.wav", "WAVE")])?;
+        assert_eq!(case.coalesce_files(), 0);
+        let mut merged = sheet(&[("a.wav", "WAVE"), ("a.wav", "WAVE"), ("b.wav", "WAVE")])?;
+        assert_eq!(merged.coalesce_files(), 1);
+        assert_eq!(merged.files.len(), 2);
+        let ids: Vec<_> = merged[0].tracks.iter().map(|track| track.id()).collect();
+        assert_eq!(ids, [1, 2]);
+        assert_eq!(
+            merged[0].comments.iter().collect::<Vec<_>>(),
+            ["FILE 0", "FILE 1"]
+        );
+        assert_eq!(merged[1].comments.iter().collect::<Vec<_>>(), ["FILE 2"]);
+        Ok(())
+    }
+    #[test]
+    fn coalesce_unknown() -> Result {
+        let mut sheet = CueSheet::from_str(
+            "FILE \"a.wav\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00\nFILE \"a.wav\" WAVE\nSESSION 2\n  TRACK 02 AUDIO\n    INDEX 01 04:00:00",
+        )?;
+        assert_eq!(sheet.coalesce_files(), 1);
+        assert_eq!(sheet[0].unknown.lines().collect::<Vec<_>>(), ["SESSION 2"]);
+        let written = sheet.to_string();
+        assert!(written.contains("SESSION 2\n"));
+        assert_eq!(CueSheet::from_str(&written)?, sheet);
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod builder {
+    use super::*;
+    use cuna::header::Header;
+    use cuna::time::TimeStamp;
+    use cuna::track::{Track, TrackInfo};
+    use cuna::Cuna;
+
+    #[test]
+    fn fluent() -> Result {
+        let header = Header::default()
+            .with_performer("EGOIST")
+            .with_title("Departures")
+            .with_catalog(4547366179934)?;
+        let file = TrackInfo::new("a.flac".to_owned(), "WAVE".to_owned())
+            .with_track(
+                Track::new(1, "AUDIO".to_owned())
+                    .with_title("Departures")
+                    .with_isrc("JPU901200001")
+                    .with_index(1, TimeStamp::new(0, 0, 0)),
+            )
+            .with_track(
+                Track::new(2, "AUDIO".to_owned())
+                    .with_flag("DCP")
+                    .with_index(0, TimeStamp::new(4, 12, 21))
+                    .with_index(1, TimeStamp::new(4, 15, 9))
+                    .with_postgap(TimeStamp::new(0, 2, 0)),
+            );
+        let built = Cuna::with_parts(header, vec![file], Default::default());
+        let parsed = Cuna::new(
+            r#"PERFORMER "EGOIST"
+TITLE "Departures"
+CATALOG 4547366179934
+FILE "a.flac" WAVE
+  TRACK 01 AUDIO
+    TITLE "Departures"
+    ISRC JPU901200001
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    FLAGS DCP
+    INDEX 00 04:12:21
+    INDEX 01 04:15:09
+    POSTGAP 00:02:00"#,
+        )?;
+        assert_eq!(built, parsed);
+        Ok(())
+    }
 }
 #[cfg(test)]
 mod parser {
@@ -147,6 +684,127 @@ mod parser {
     use cuna::parser::Parna;
     use cuna::Cuna;
 
+    #[test]
+    fn commands() -> Result {
+        let commands: Vec<_> = Parna::new(CUE).filter_map(|c| c.ok()).collect();
+        let indexes = commands
+            .iter()
+            .filter(|(_, c)| matches!(c, cuna::parser::Command::Index(..)))
+            .count();
+        assert_eq!(indexes, 7);
+        assert_eq!(commands[0].0, 1);
+        assert_eq!(commands.last().unwrap().0, CUE.lines().count());
+        let options = cuna::parser::ParseOptions {
+            limits: cuna::parser::Limits {
+                max_lines: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut parser = Parna::new(CUE).with_options(options.clone());
+        assert!(parser.next().unwrap().is_ok());
+        assert!(parser.next().unwrap().is_ok());
+        let err = parser.next().unwrap().unwrap_err();
+        assert_eq!(err.pos(), Some(3));
+        assert!(parser.next().is_none());
+        // the lines after the error are not read
+        let read = std::cell::Cell::new(0);
+        let lines = CUE.lines().inspect(|_| read.set(read.get() + 1));
+        let mut parser = Parna::from_lines(lines).with_options(options);
+        assert_eq!(parser.by_ref().count(), 3);
+        assert_eq!(read.get(), 3);
+        let lines = [
+            "TITLE \"Departures\"\rPERFORMER \"EGOIST\"",
+            "REM DATE 2011",
+        ];
+        let commands: Vec<_> =
+            Parna::from_lines(lines.iter().copied()).collect::<std::result::Result<_, _>>()?;
+        assert_eq!(
+            commands.iter().map(|(at, _)| *at).collect::<Vec<_>>(),
+            [1, 1, 2]
+        );
+        assert_eq!(commands[1].1, cuna::parser::Command::Performer("EGOIST"));
+        Ok(())
+    }
+    #[test]
+    fn drive() -> Result {
+        #[derive(Default)]
+        struct Count {
+            files: usize,
+            rems: Vec<usize>,
+        }
+        impl cuna::visit::CueVisitor for Count {
+            fn on_file(&mut self, _: usize, _: &str, _: &str) {
+                self.files += 1;
+            }
+            fn on_rem(&mut self, at: usize, _: &str) {
+                self.rems.push(at);
+            }
+        }
+        let mut count = Count::default();
+        Parna::new(CUE).drive(&mut count)?;
+        assert_eq!(count.files, 1);
+        assert_eq!(count.rems, [1, 2, 3, 4]);
+        let err = Parna::new("REM a\nINDEX 1\nREM b").drive(&mut count);
+        assert_eq!(err.unwrap_err().pos(), Some(2));
+        assert_eq!(count.rems.len(), 5);
+        Ok(())
+    }
+    #[test]
+    fn line_results() -> Result {
+        use std::io::BufRead;
+        let mut sheet = Cuna::default();
+        Parna::from_line_results(CUE.as_bytes().lines()).parse(&mut sheet)?;
+        assert_eq!(sheet, Cuna::new(CUE)?);
+        let lines = CUE.lines().map(|l| Ok(l.to_owned())).take(3);
+        let broken = lines.chain(std::iter::once(Err(std::io::Error::other("lost"))));
+        let err = Parna::from_line_results(broken)
+            .parse(&mut Cuna::default())
+            .unwrap_err();
+        assert_eq!(err.pos(), Some(4));
+        Ok(())
+    }
+    #[test]
+    fn stop_after() -> Result {
+        use cuna::parser::{ParseOptions, StopAfter};
+        let options = ParseOptions {
+            stop_after: Some(StopAfter::Track(3)),
+            ..ParseOptions::default()
+        };
+        let (sheet, _) = Cuna::parse_with(CUE, &options)?;
+        let full = Cuna::new(CUE)?;
+        assert_eq!(sheet.tracks().count(), 3);
+        assert_eq!(sheet.last_track(), full[0].tracks.get(2));
+        let (read, _) = Cuna::from_buf_read_with(&mut CUE.as_bytes(), &options)?;
+        assert_eq!(read, sheet);
+        let cue =
+            "FILE \"a.wav\" WAVE\nTRACK 01 AUDIO\nTRACK 02 AUDIO\nFILE \"b.wav\" WAVE\nFILE ?";
+        let options = ParseOptions {
+            stop_after: Some(StopAfter::File(0)),
+            ..ParseOptions::default()
+        };
+        let (sheet, _) = Cuna::parse_with(cue, &options)?;
+        assert_eq!(sheet.files().len(), 1);
+        assert_eq!(sheet.tracks().count(), 2);
+        // the parser stays stopped on later calls
+        let options = ParseOptions {
+            stop_after: Some(StopAfter::Track(1)),
+            ..ParseOptions::default()
+        };
+        let mut parser = Parna::new(cue).with_options(options.clone());
+        let mut sheet = Cuna::default();
+        parser.parse_next_n_lines(3, &mut sheet)?;
+        parser.parse_next_n_lines(3, &mut sheet)?;
+        parser.parse(&mut sheet)?;
+        assert_eq!(sheet.tracks().count(), 1);
+        let lines = cue.lines().map(|line| Ok(line.to_owned()));
+        let mut parser = Parna::from_line_results(lines).with_options(options);
+        let mut sheet = Cuna::default();
+        parser.parse(&mut sheet)?;
+        parser.parse(&mut sheet)?;
+        assert_eq!(sheet.tracks().count(), 1);
+        Ok(())
+    }
     #[test]
     fn current_line() {
         let mut parser = Parna::new(cuna::trim_utf8_header(CUE));
@@ -175,4 +833,2158 @@ mod parser {
         assert!(sheet[0].tracks.is_empty());
         Ok(())
     }
+    #[test]
+    fn misplaced_header_rem() -> Result {
+        use cuna::error::WarningKind;
+        let cue = "FILE \"a.flac\" WAVE\nREM TRACKTOTAL \"12\"\nREM foo bar";
+        let mut parser = Parna::new(cue);
+        let mut sheet = Cuna::default();
+        parser.parse(&mut sheet)?;
+        assert_eq!(sheet.comments.get_number("TRACKTOTAL"), Some(12));
+        assert_eq!(parser.warnings().len(), 1);
+        assert_eq!(
+            parser.warnings()[0].kind(),
+            &WarningKind::MisplacedHeaderRem(r#"TRACKTOTAL "12""#.to_owned())
+        );
+        Ok(())
+    }
+    #[test]
+    fn trim_values() -> Result {
+        use cuna::parser::ParseOptions;
+        let cue =
+            "FILE \"a.flac\" WAVE\nTRACK 01 AUDIO\nTITLE \" Departures \"\nPERFORMER \"EGOIST \"";
+        let mut loose = Cuna::default();
+        Parna::new(cue).parse(&mut loose)?;
+        let mut trimmed = Cuna::default();
+        let options = ParseOptions {
+            trim_values: true,
+            ..ParseOptions::default()
+        };
+        Parna::new(cue).with_options(options).parse(&mut trimmed)?;
+        assert_eq!(loose[0][0].title, vec![" Departures ".to_owned()]);
+        assert_eq!(trimmed[0][0].title, vec!["Departures".to_owned()]);
+        assert_ne!(loose[0][0], trimmed[0][0]);
+        assert!(loose[0][0].eq_ignore_formatting(&trimmed[0][0]));
+        assert_eq!(loose[0][0].canonical(), trimmed[0][0]);
+        Ok(())
+    }
+    #[test]
+    fn parse_tolerant() {
+        use cuna::error::{InvalidArgument, Limit, ParseError};
+        use cuna::parser::ParseOptions;
+        let cue = "REM GENRE Pop\nFILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:90\n    INDEX 01 00:00:00\n  CATALOG 123\n  TRACK 02 AUDIO\n    INDEX 01 04:15:44";
+        let (sheet, errors) = Cuna::parse_tolerant(cue, &ParseOptions::default());
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].pos(), Some(4));
+        assert!(matches!(
+            errors[0].kind(),
+            ParseError::InvalidArgument(InvalidArgument::MalformedTimestamp { .. })
+        ));
+        assert_eq!(errors[1].pos(), Some(6));
+        assert_eq!(sheet.tracks().count(), 2);
+        assert_eq!(sheet[0][0].start().unwrap().to_string(), "00:00:00");
+        let mut options = ParseOptions::default();
+        options.limits.max_tracks = 1;
+        let (sheet, errors) = Cuna::parse_tolerant(&format!("CATALOG 1\n{}", cue), &options);
+        assert_eq!(errors.len(), 4);
+        assert_eq!(
+            errors[3].kind(),
+            &ParseError::LimitExceeded(Limit::Tracks(1))
+        );
+        assert_eq!(sheet.comments.len(), 1);
+    }
+    #[test]
+    fn recovery_policy() -> Result {
+        use cuna::error::WarningKind;
+        use cuna::parser::{ParseOptions, RecoveryPolicy};
+        use std::io::Cursor;
+        let cue = r#"TITLE "Departures"
+CATALOG 123
+FILE "a.flac" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 04:15:90
+FILE "b.flac" WAVE
+  TRACK 03 AUDIO
+    INDEX 01 00:00:00
+FILE "c.flac" BINARY
+  TRACK 4 AUDIO
+    INDEX 01 00:00:00
+  TRACK 05 AUDIO
+    INDEX 01 00:00:00"#;
+        let parse = |recovery| {
+            let options = ParseOptions {
+                recovery,
+                ..ParseOptions::default()
+            };
+            let mut parser = Parna::new(cue).with_options(options);
+            let mut sheet = Cuna::default();
+            parser
+                .parse(&mut sheet)
+                .map(|_| (sheet, parser.take_warnings()))
+        };
+        let ids = |sheet: &Cuna| sheet.tracks().map(|t| t.id()).collect::<Vec<_>>();
+        assert_eq!(parse(RecoveryPolicy::Abort).unwrap_err().pos(), Some(2));
+        let (sheet, warnings) = parse(RecoveryPolicy::SkipLine)?;
+        assert_eq!(ids(&sheet), [1, 2, 3, 5]);
+        assert_eq!(warnings.len(), 4);
+        let (sheet, warnings) = parse(RecoveryPolicy::SkipTrack)?;
+        assert_eq!(ids(&sheet), [1, 3, 5]);
+        assert_eq!(warnings[1].pos(), Some(7));
+        assert!(matches!(
+            warnings[1].kind(),
+            WarningKind::Recovered {
+                policy: RecoveryPolicy::SkipTrack,
+                ..
+            }
+        ));
+        let (sheet, warnings) = parse(RecoveryPolicy::SkipFile)?;
+        assert_eq!(ids(&sheet), [3]);
+        assert_eq!(sheet.title(), &["Departures".to_owned()]);
+        let options = ParseOptions {
+            recovery: RecoveryPolicy::SkipFile,
+            ..ParseOptions::default()
+        };
+        let (read, read_warnings) = Cuna::from_buf_read_with(&mut Cursor::new(cue), &options)?;
+        assert_eq!(read, sheet);
+        assert_eq!(read_warnings, warnings);
+        let (parsed, parsed_warnings) = Cuna::parse_with(cue, &options)?;
+        assert_eq!(parsed, sheet);
+        assert_eq!(parsed_warnings, warnings);
+        Ok(())
+    }
+    #[test]
+    fn decimal_seconds() -> Result {
+        use cuna::error::WarningKind;
+        use cuna::parser::ParseOptions;
+        use cuna::time::{Rounding, TimeStamp};
+        let cue = "FILE \"a.mka\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00.000\n  TRACK 02 AUDIO\n    PREGAP 00:02.5\n    INDEX 01 04:15.590";
+        assert!(Cuna::new(cue).is_err());
+        let options = ParseOptions {
+            decimal_seconds: Some(Rounding::Nearest),
+            ..ParseOptions::default()
+        };
+        let mut parser = Parna::new(cue).with_options(options);
+        let mut sheet = Cuna::default();
+        parser.parse(&mut sheet)?;
+        assert_eq!(sheet[0][1].pregap(), Some(&TimeStamp::new(0, 2, 38)));
+        assert_eq!(sheet[0][1].start(), Some(TimeStamp::new(4, 15, 44)));
+        assert_eq!(parser.warnings().len(), 3);
+        assert_eq!(parser.warnings()[2].pos(), Some(6));
+        assert_eq!(
+            parser.warnings()[2].kind(),
+            &WarningKind::DecimalTimestamp {
+                timestamp: "04:15.590".to_owned(),
+                rounded: TimeStamp::new(4, 15, 44)
+            }
+        );
+        Ok(())
+    }
+    #[test]
+    fn hms_timestamps() -> Result {
+        use cuna::parser::ParseOptions;
+        use cuna::time::{Rounding, TimeStamp};
+        let cue = "FILE \"a.mka\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 0:00:00\n  TRACK 02 AUDIO\n    INDEX 01 1:02:03\n  TRACK 03 AUDIO\n    INDEX 01 1:05:00.5";
+        let sheet = Cuna::new(&cue.replace("1:05:00.5", "1:05:00"))?;
+        assert_eq!(sheet[0][1].start(), Some(TimeStamp::new(1, 2, 3)));
+        let options = ParseOptions {
+            hms_timestamps: true,
+            ..ParseOptions::default()
+        };
+        assert!(Cuna::parse_with(cue, &options).is_err());
+        let options = ParseOptions {
+            decimal_seconds: Some(Rounding::Up),
+            ..options
+        };
+        let mut parser = Parna::new(cue).with_options(options);
+        let mut sheet = Cuna::default();
+        parser.parse(&mut sheet)?;
+        assert_eq!(sheet[0][1].start(), Some(TimeStamp::new(62, 3, 0)));
+        assert_eq!(sheet[0][2].start(), Some(TimeStamp::new(65, 0, 38)));
+        assert_eq!(parser.warnings().len(), 3);
+        let options = ParseOptions {
+            decimal_seconds: Some(Rounding::Nearest),
+            ..ParseOptions::default()
+        };
+        let (sheet, _) = Cuna::parse_with(cue, &options)?;
+        assert_eq!(sheet[0][1].start(), Some(TimeStamp::new(1, 2, 3)));
+        assert_eq!(sheet[0][2].start(), Some(TimeStamp::new(65, 0, 38)));
+        Ok(())
+    }
+    #[test]
+    fn command_order() -> Result {
+        use cuna::error::WarningKind;
+        use cuna::parser::{CommandOrder, ParseOptions};
+        use cuna::time::TimeStamp;
+        let cue = "FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00\n    POSTGAP 00:02:00\n    INDEX 02 00:30:00\n  TRACK 02 AUDIO\n    INDEX 01 04:15:44\n    PREGAP 00:01:00\n    PREGAP 00:01:00";
+        let options = ParseOptions {
+            ordering: CommandOrder::Permissive,
+            ..ParseOptions::default()
+        };
+        let mut parser = Parna::new(cue).with_options(options);
+        let mut sheet = Cuna::default();
+        let err = parser.parse(&mut sheet).unwrap_err();
+        assert_eq!(err.pos(), Some(9));
+        assert_eq!(sheet[0][0].index.len(), 2);
+        assert_eq!(sheet[0][1].pregap(), Some(&TimeStamp::new(0, 1, 0)));
+        let kinds: Vec<_> = parser.warnings().iter().map(|w| w.kind().clone()).collect();
+        assert_eq!(
+            kinds,
+            [
+                WarningKind::OutOfOrder {
+                    keyword: "INDEX",
+                    after: "POSTGAP"
+                },
+                WarningKind::OutOfOrder {
+                    keyword: "PREGAP",
+                    after: "INDEX"
+                },
+            ]
+        );
+        assert!(sheet
+            .to_string()
+            .contains("PREGAP 00:01:00\n    INDEX 01 04:15:44"));
+        Ok(())
+    }
+    #[test]
+    fn duplicates() -> Result {
+        use cuna::error::WarningKind;
+        use cuna::parser::{DuplicatePolicy, ParseOptions};
+        use cuna::time::TimeStamp;
+        let cue = "FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    FLAGS DCP\n    FLAGS PRE\n    ISRC JPU901100123\n    ISRC JPU901100124\n    PREGAP 00:01:00\n    PREGAP 00:02:00\n    INDEX 01 00:00:00";
+        assert!(Cuna::new(cue).is_err());
+        let parse = |duplicates| {
+            let options = ParseOptions {
+                duplicates,
+                ..ParseOptions::default()
+            };
+            let mut parser = Parna::new(cue).with_options(options);
+            let mut sheet = Cuna::default();
+            parser
+                .parse(&mut sheet)
+                .map(|_| (sheet, parser.warnings().to_vec()))
+        };
+        let (first, warnings) = parse(DuplicatePolicy::KeepFirst)?;
+        assert!(warnings.is_empty());
+        assert_eq!(first[0][0].flags(), &["DCP".to_owned()]);
+        assert_eq!(first[0][0].isrc(), Some("JPU901100123"));
+        let (last, _) = parse(DuplicatePolicy::KeepLast)?;
+        assert_eq!(last[0][0].flags(), &["PRE".to_owned()]);
+        assert_eq!(last[0][0].isrc(), Some("JPU901100124"));
+        assert_eq!(last[0][0].pregap(), Some(&TimeStamp::new(0, 2, 0)));
+        let (warned, warnings) = parse(DuplicatePolicy::KeepFirstAndWarn)?;
+        assert_eq!(warned, first);
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(warnings[1].pos(), Some(6));
+        assert_eq!(
+            warnings[1].kind(),
+            &WarningKind::DuplicateCommand("ISRC JPU901100124".to_owned())
+        );
+        Ok(())
+    }
+    #[test]
+    fn error_context() {
+        let context = |cue: &str| {
+            let err = Cuna::new(cue).unwrap_err();
+            err.context().map(|c| (c.file.clone(), c.track))
+        };
+        assert_eq!(context("TITLE \"a\nFILE"), None);
+        assert_eq!(context("FILE \"a.flac\" WAVE\nFILE b.flac"), None);
+        assert_eq!(
+            context("FILE \"a.flac\" WAVE\nTRACK 01 AUDIO\nTRACK 2 AUDIO"),
+            Some(("a.flac".to_owned(), None))
+        );
+        let cue = CUE.replace("INDEX 01 16:50:72", "INDEX 01 16:50:75");
+        assert_eq!(
+            context(&cue),
+            Some((
+                "EGOIST - Departures ～あなたにおくるアイの歌～.flac".to_owned(),
+                Some(5)
+            ))
+        );
+        let err = Cuna::new(&cue).unwrap_err();
+        assert!(err.to_string().ends_with("～.flac' TRACK 05"));
+    }
+    #[test]
+    fn push_parser() -> Result {
+        use cuna::error::{Limit, ParseError};
+        use cuna::parser::{ParseOptions, PushParser};
+        for size in [1, 7, 4096] {
+            let mut parser = PushParser::new();
+            for chunk in CUE.as_bytes().chunks(size) {
+                parser.feed(chunk)?;
+            }
+            assert_eq!(parser.finish()?, Cuna::new(CUE)?);
+        }
+        let mut options = ParseOptions::default();
+        options.limits.max_line_length = 16;
+        let mut parser = PushParser::new().with_options(options);
+        parser.feed(b"REM DATE 2011\n")?;
+        let err = parser.feed(b"TITLE \"Departures ~").unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseError::LimitExceeded(Limit::LineLength(16))
+        );
+        assert_eq!(err.pos(), Some(2));
+        let mut parser = PushParser::new();
+        parser.feed(b"REM DATE 2011\nTITLE \"Caf\xe9\"")?;
+        assert_eq!(parser.finish().unwrap_err().pos(), Some(2));
+        Ok(())
+    }
+    #[test]
+    fn aliases() -> Result {
+        use cuna::parser::ParseOptions;
+        let cue = "artist \"EGOIST\"\nFILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    Song  Writer \"ryo\"\n    flag DCP\n    INDEX 01 00:00:00\nSONG_WRITER \"ryo\"";
+        assert!(Cuna::new(cue)?.performer().is_empty());
+        let options = ParseOptions {
+            aliases: vec![
+                ("ARTIST".to_owned(), "PERFORMER".to_owned()),
+                ("SONG_WRITER".to_owned(), "REM SONGWRITER".to_owned()),
+            ],
+            ..ParseOptions::default()
+        };
+        let (sheet, _) = Cuna::parse_with(cue, &options)?;
+        assert_eq!(sheet.performer(), &["EGOIST".to_owned()]);
+        assert_eq!(sheet[0][0].songwriter(), &["ryo".to_owned()]);
+        assert_eq!(sheet[0][0].flags(), &["DCP".to_owned()]);
+        assert_eq!(sheet[0][0].comments()[0], "SONGWRITER \"ryo\"");
+        Ok(())
+    }
+    #[test]
+    fn inline_comments() -> Result {
+        use cuna::comment::Scope;
+        use cuna::error::WarningKind;
+        use cuna::parser::{ParseOptions, Parser};
+        let cue = r#"; ripped 2003
+REM COMMENT http://example.com ; kept
+PERFORMER "EGOIST" // from the booklet
+FILE "a.flac" WAVE
+  TRACK 01 AUDIO
+    TITLE "He said \"a; b\" // c" ;ok
+    ISRC JPU901100123 ;checked
+    INDEX 01 00:00:00   ;  gap //removed"#;
+        assert!(Cuna::new(cue).is_err());
+        let options = ParseOptions {
+            inline_comments: true,
+            ..ParseOptions::default()
+        };
+        let mut parser = Parser::new(cue).with_options(options);
+        let mut sheet = Cuna::default();
+        parser.parse(&mut sheet)?;
+        assert_eq!(sheet.performer(), &["EGOIST".to_owned()]);
+        assert_eq!(sheet[0][0].title(), &[r#"He said "a; b" // c"#.to_owned()]);
+        assert_eq!(sheet[0][0].isrc(), Some("JPU901100123"));
+        assert_eq!(sheet[0][0].start().unwrap().as_frames(), 0);
+        assert_eq!(sheet.comments[1], "COMMENT http://example.com ; kept");
+        let comments: Vec<_> = parser
+            .warnings()
+            .iter()
+            .map(|w| match w.kind() {
+                WarningKind::InlineComment(comment) => (w.pos().unwrap(), comment.as_str()),
+                kind => panic!("{}", kind),
+            })
+            .collect();
+        assert_eq!(
+            comments,
+            [
+                (1, "ripped 2003"),
+                (3, "from the booklet"),
+                (6, "ok"),
+                (7, "checked"),
+                (8, "gap //removed")
+            ]
+        );
+        // the comments are kept where they were and written back as `REM`s
+        let comments: Vec<_> = sheet.scoped_comments().collect();
+        assert_eq!(
+            comments,
+            [
+                (Scope::Header, "ripped 2003"),
+                (Scope::Header, "COMMENT http://example.com ; kept"),
+                (Scope::Header, "from the booklet"),
+                (Scope::Track { file: 0, track: 0 }, "ok"),
+                (Scope::Track { file: 0, track: 0 }, "checked"),
+                (Scope::Track { file: 0, track: 0 }, "gap //removed"),
+            ]
+        );
+        let written = Cuna::new(&sheet.to_string())?;
+        assert!(written.scoped_comments().eq(sheet.scoped_comments()));
+        Ok(())
+    }
+    #[test]
+    fn keep_unknown() -> Result {
+        use cuna::parser::ParseOptions;
+        let cue = r#"REM GENRE Pop
+DISCID 1234
+TITLE "Departures"
+FILE "a.bin" BINARY
+  SESSION 1
+  TRACK 01 MODE1/2352
+    X-VENDOR "value"
+    index 01 00:00:00
+"#;
+        let options = ParseOptions {
+            keep_unknown: false,
+            ..ParseOptions::default()
+        };
+        assert!(Cuna::parse_with(cue, &options).is_err());
+        let sheet = Cuna::new(cue)?;
+        assert_eq!(sheet.header.unknown.0, [(2, "DISCID 1234".to_owned())]);
+        assert_eq!(sheet[0].unknown.0, [(5, "SESSION 1".to_owned())]);
+        assert_eq!(
+            sheet[0][0].unknown.0,
+            [(7, r#"X-VENDOR "value""#.to_owned())]
+        );
+        let written = sheet.to_string();
+        assert_eq!(written, cue.replace("index", "INDEX"));
+        assert_eq!(Cuna::new(&written)?, sheet);
+        let moved = Cuna::new(&format!("REM DATE 2011\n{}", cue))?;
+        assert_eq!(moved.header.unknown.0[0].0, 3);
+        assert_eq!(moved[0], sheet[0]);
+        assert_eq!(moved.to_string(), format!("REM DATE 2011\n{}", written));
+        Ok(())
+    }
+    #[test]
+    fn escaped_quotes() -> Result {
+        let sheet = Cuna::new(
+            r#"TITLE "He said \"hi\""
+PERFORMER "The ""Band"""
+FILE "a \"b\".flac" WAVE
+  TRACK 01 AUDIO
+    TITLE "C:\Music\"
+    SONGWRITER "a" b"
+    INDEX 01 00:00:00"#,
+        )?;
+        assert_eq!(sheet.title(), &[r#"He said "hi""#.to_owned()]);
+        assert_eq!(sheet.performer(), &[r#"The "Band""#.to_owned()]);
+        assert_eq!(sheet[0].name, r#"a "b".flac"#);
+        assert_eq!(sheet[0].format, "WAVE");
+        assert_eq!(sheet[0][0].title(), &[r"C:\Music\".to_owned()]);
+        assert_eq!(sheet[0][0].songwriter(), &[r#"a" b"#.to_owned()]);
+        Ok(())
+    }
+    #[test]
+    fn carriage_returns() -> Result {
+        use cuna::error::{Limit, ParseError};
+        use cuna::parser::{Limits, ParseOptions};
+        let crlf = CUE.replace('\n', "\r\n");
+        let sheet = Cuna::new(CUE)?;
+        assert_eq!(Cuna::new(&crlf)?, sheet);
+        assert_eq!(Cuna::from_buf_read(&mut crlf.as_bytes())?, sheet);
+        let cr = CUE.replace('\n', "\r");
+        assert_eq!(Cuna::new(&cr)?, sheet);
+        assert_eq!(Cuna::from_buf_read(&mut cr.as_bytes())?, sheet);
+        let mut parser = cuna::parser::PushParser::new();
+        for chunk in crlf.as_bytes().chunks(7) {
+            parser.feed(chunk)?;
+        }
+        assert_eq!(parser.finish()?, sheet);
+        let options = ParseOptions {
+            limits: Limits {
+                max_lines: 10,
+                ..Limits::default()
+            },
+            ..ParseOptions::default()
+        };
+        let many = "REM a\r".repeat(100);
+        let err = Cuna::parse_with(&many, &options).unwrap_err();
+        assert_eq!(err.kind(), &ParseError::LimitExceeded(Limit::Lines(10)));
+        assert_eq!(err.pos(), Some(11));
+        let err = Cuna::from_buf_read_with(&mut many.as_bytes(), &options).unwrap_err();
+        assert_eq!(err.pos(), Some(11));
+        let err = Cuna::new("REM a\rTITLE \"b\"\rINDEX 01 00:00:00").unwrap_err();
+        assert_eq!(err.pos(), Some(3));
+        Ok(())
+    }
+    #[test]
+    fn lenient_whitespace() -> Result {
+        let cue = "FILE  \"a.flac\"\tWAVE\nTRACK\t01\tAUDIO\n\tINDEX  01  00:00:00\nREM\tGENRE Pop";
+        let sheet = Cuna::new(cue)?;
+        assert_eq!(sheet[0].name, "a.flac");
+        assert_eq!(sheet[0][0].format, "AUDIO");
+        assert_eq!(sheet[0][0][0], "INDEX\t01 00:00:00".parse()?);
+        assert_eq!(sheet.comments[0], "GENRE Pop");
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod document {
+    use super::*;
+    use cuna::document::*;
+
+    #[test]
+    fn round_trip() -> Result {
+        let document = Document::parse(CUE)?;
+        assert_eq!(document.to_string(), CUE);
+        assert!(document.has_bom());
+        assert_eq!(document.sheet(), &cuna::Cuna::new(CUE)?);
+        let cue = "TITLE \"a\"\r\n\n  REM  x \r\rfile \"a.flac\" WAVE";
+        let document = Document::parse(cue)?;
+        assert_eq!(document.to_string(), cue);
+        let endings: Vec<_> = document.lines().iter().map(Line::ending).collect();
+        assert_eq!(
+            endings,
+            [
+                LineEnding::CrLf,
+                LineEnding::Lf,
+                LineEnding::Cr,
+                LineEnding::Cr,
+                LineEnding::None
+            ]
+        );
+        assert_eq!(document.lines()[2].content(), "REM  x");
+        assert_eq!(document.lines()[2].trailing(), " ");
+        let cue = "\u{feff}\u{feff}REM x";
+        let document = Document::parse(cue)?;
+        assert!(document.has_bom());
+        assert_eq!(document.lines()[0].content(), "\u{feff}REM x");
+        assert_eq!(document.to_string(), cue);
+        Ok(())
+    }
+    #[test]
+    fn apply_edit() -> Result {
+        let mut document = Document::parse(CUE)?;
+        let at = CUE.find("TRACK 03").unwrap();
+        let at = at + CUE[at..].find("INDEX 01").unwrap();
+        let edit = "REM X\n    INDEX 01";
+        let reparsed = document.apply_edit(at..at + 8, edit)?;
+        let cue = format!("{}{}{}", &CUE[..at], edit, &CUE[at + 8..]);
+        assert_eq!(document.to_string(), cue);
+        assert_eq!(document.sheet(), &cuna::Cuna::new(&cue)?);
+        let track = document.lines()[reparsed.start].content();
+        assert_eq!(track, "TRACK 03 AUDIO");
+        assert_eq!(reparsed.len(), 5);
+
+        let at = cue.find("TRACK 02").unwrap();
+        let reparsed = document.apply_edit(at..at + 8, "TRACK 12")?;
+        assert_eq!(reparsed, 0..document.lines().len());
+        assert_eq!(document.sheet()[0][1].id(), 12);
+
+        let before = document.to_string();
+        let at = before.find("INDEX 01 04").unwrap();
+        assert!(document.apply_edit(at..at + 8, "INDEX 01 xx").is_err());
+        assert_eq!(document.to_string(), before);
+        Ok(())
+    }
+    #[test]
+    fn recovery() -> Result {
+        use cuna::parser::{ParseOptions, RecoveryPolicy};
+        let options = ParseOptions {
+            recovery: RecoveryPolicy::SkipLine,
+            ..ParseOptions::default()
+        };
+        let cue = "FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 xx\n    INDEX 01 00:00:00";
+        assert!(Document::parse(cue).is_err());
+        let mut document = Document::parse_with(cue, &options)?;
+        assert_eq!(document.to_string(), cue);
+        assert_eq!(document.sheet()[0][0].index.len(), 1);
+        assert_eq!(document.warnings()[0].pos(), Some(3));
+        let at = cue.find("xx").unwrap();
+        document.apply_edit_with(at..at + 2, "yy", &options)?;
+        assert_eq!(document.sheet()[0][0].index.len(), 1);
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod cst {
+    use super::*;
+    use cuna::cst::*;
+
+    #[test]
+    fn spans() {
+        let cst = Cst::new(CUE);
+        assert!(cst.has_bom());
+        assert_eq!(cst.to_string(), CUE);
+        let covered: usize = cst.nodes().iter().map(|node| node.span.len()).sum();
+        assert_eq!(covered + 3, CUE.len());
+        let title = &cst.nodes()[13];
+        assert_eq!(cst.keyword(title), "TITLE");
+        assert_eq!(cst.text(title.indent.clone()), "    ");
+        assert_eq!(cst.value(&title.args[0]), "エウテルペ");
+        let cst = Cst::new("REM  \"a b\" c \t\r\n  \nTITLE \"x");
+        let rem = &cst.nodes()[0];
+        assert_eq!(rem.args.len(), 2);
+        assert_eq!(rem.args[0].kind, TokenKind::Quoted);
+        assert_eq!(cst.text(rem.trailing.clone()), " \t");
+        assert!(cst.nodes()[1].is_blank());
+        assert_eq!(cst.nodes()[1].indent, 16..18);
+        assert_eq!(cst.nodes()[2].args[0].kind, TokenKind::Unterminated);
+        assert_eq!(cst.value(&cst.nodes()[2].args[0]), "x");
+    }
+    #[test]
+    fn edit() {
+        use cuna::error::EditError;
+        use cuna::time::TimeStamp;
+        let mut cst = Cst::new(CUE);
+        cst.set_track_title(2, "Euterpe").unwrap();
+        cst.replace_index_time(3, 1, TimeStamp::new(8, 4, 0))
+            .unwrap();
+        let changed: Vec<_> = CUE
+            .lines()
+            .zip(cst.source().lines())
+            .filter(|(a, b)| a != b)
+            .map(|(_, b)| b)
+            .collect();
+        assert_eq!(changed, [r#"    TITLE "Euterpe""#, "    INDEX 01 08:04:00"]);
+        assert_eq!(
+            cst.replace_index_time(3, 2, TimeStamp::new(0, 0, 0)),
+            Err(EditError::NoSuchIndex { track: 3, index: 2 })
+        );
+        assert_eq!(cst.set_track_title(42, ""), Err(EditError::NoSuchTrack(42)));
+        cst.set_track_title(2, "He said \"hi\"\n").unwrap();
+        assert!(cst.source().contains("    TITLE \"He said \\\"hi\\\" \"\n"));
+        let sheet = cuna::Cuna::new(cst.source()).unwrap();
+        assert_eq!(sheet[0][1].title(), &["He said \"hi\" ".to_owned()]);
+    }
+}
+#[cfg(test)]
+mod transform {
+    use super::*;
+    use cuna::time::TimeStamp;
+    use cuna::transform::*;
+    use cuna::Cuna;
+
+    #[test]
+    fn materialize_pregaps() -> Result {
+        let mut sheet = Cuna::new(
+            r#"FILE "a.wav" WAVE
+TRACK 01 AUDIO
+INDEX 01 00:00:00
+TRACK 02 AUDIO
+PREGAP 00:02:00
+INDEX 01 01:00:00
+TRACK 03 AUDIO
+INDEX 01 02:00:00"#,
+        )?;
+        let report = sheet.transform(&Pipeline::new().materialize_pregaps().shift(-100));
+        assert_eq!(report.steps[0].changes, 1);
+        assert_eq!(report.steps[1].changes, 3);
+        assert_eq!(sheet[0][1].pregap(), None);
+        let times: Vec<_> = sheet
+            .tracks()
+            .flat_map(|track| &track.index)
+            .map(|index| (index.id(), *index.begin_time()))
+            .collect();
+        assert_eq!(
+            times,
+            [
+                (1, TimeStamp::new(0, 0, 0)),
+                (0, TimeStamp::new(0, 58, 50)),
+                (1, TimeStamp::new(1, 0, 50)),
+                (1, TimeStamp::new(2, 0, 50)),
+            ]
+        );
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod validation {
+    use super::*;
+    use cuna::error::ValidationError;
+    use cuna::time::TimeStamp;
+    use cuna::track::TrackInfo;
+    use cuna::validation::FixPolicy;
+    use cuna::Cuna;
+
+    #[test]
+    fn repair_index_00_only() -> Result {
+        let cue = r#"FILE "a.flac" WAVE
+TRACK 01 AUDIO
+INDEX 01 00:00:00
+TRACK 02 AUDIO
+PREGAP 00:02:00
+INDEX 00 04:13:44"#;
+        let mut sheet = Cuna::new(cue)?;
+        assert_eq!(sheet.validate(), [ValidationError::MissingStartIndex(2)]);
+        assert_eq!(sheet.clone().repair(FixPolicy::Keep), 0);
+        assert_eq!(sheet.repair(FixPolicy::UsePregapLength), 1);
+        assert!(sheet.validate().is_empty());
+        assert_eq!(sheet[0][1].pregap(), None);
+        assert_eq!(sheet[0][1].start(), Some(TimeStamp::new(4, 15, 44)));
+        Ok(())
+    }
+    #[test]
+    fn postgap_before_data() -> Result {
+        let cue = r#"FILE "a.bin" BINARY
+TRACK 01 AUDIO
+INDEX 01 00:00:00
+POSTGAP 00:02:00
+TRACK 02 MODE1/2352
+INDEX 01 04:13:44
+POSTGAP 00:02:00"#;
+        let mut sheet = Cuna::new(cue)?;
+        assert_eq!(sheet.validate(), [ValidationError::PostgapBeforeData(1)]);
+        assert_eq!(sheet.repair(FixPolicy::CopyPregapIndex), 0);
+        sheet.files[0].tracks.pop();
+        assert!(sheet.validate().is_empty());
+        Ok(())
+    }
+    #[test]
+    fn from_parts() -> Result {
+        let sheet = Cuna::new(CUE)?;
+        let Cuna {
+            header,
+            mut files,
+            comments,
+        } = sheet.clone();
+        let rebuilt = Cuna::from_parts(header.clone(), files.clone(), comments.clone());
+        assert_eq!(rebuilt.as_ref(), Ok(&sheet));
+        files[0].tracks.swap(0, 1);
+        files.push(TrackInfo::new("b.flac".to_owned(), "WAVE".to_owned()));
+        let issues = Cuna::from_parts(header, files, comments).unwrap_err();
+        assert_eq!(
+            issues.0,
+            [
+                ValidationError::TrackNotIncreasing(1),
+                ValidationError::IndexNotIncreasing(1),
+                ValidationError::EmptyFile(2),
+            ]
+        );
+        assert!(issues
+            .to_string()
+            .starts_with("`TRACK 01` is not numbered higher"));
+        Ok(())
+    }
+    #[test]
+    fn codes() -> Result {
+        let cue = r#"CATALOG 4547366179935
+FILE "a.flac" WAVE
+  TRACK 01 AUDIO
+    ISRC USRC17607839
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    ISRC USRC17607840
+    INDEX 01 04:15:44
+  TRACK 03 AUDIO
+    ISRC GBAYE0601498
+    INDEX 01 08:31:00
+  TRACK 04 AUDIO
+    ISRC US-RC1-76-07841
+    INDEX 01 12:00:00"#;
+        let mut sheet = Cuna::new(cue)?;
+        assert_eq!(
+            sheet.validate_codes(),
+            [
+                ValidationError::InvalidCatalog,
+                ValidationError::IsrcRegistrantMismatch(3),
+                ValidationError::MalformedIsrc(4),
+                ValidationError::CatalogCountryMismatch,
+            ]
+        );
+        assert!(sheet.validate().is_empty());
+        sheet.header.set_catalog(4988002610013)?;
+        assert_eq!(sheet.validate_codes().len(), 3);
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod track_types {
+    use super::*;
+    use cuna::track::TrackMode;
+
+    #[test]
+    fn modes() -> Result {
+        let sheet = cuna::Cuna::new(CUE)?;
+        assert!(sheet.tracks().all(|t| t.mode() == TrackMode::Audio));
+        for name in [
+            "AUDIO",
+            "CDG",
+            "MODE1/2048",
+            "MODE1/2352",
+            "MODE2/2336",
+            "MODE2/2352",
+            "CDI/2336",
+            "CDI/2352",
+        ] {
+            let mode = TrackMode::from_name(name);
+            assert_eq!(mode.to_string(), name);
+            assert!(mode.sector_size().is_some());
+        }
+        assert_eq!(TrackMode::from_name(" cdi/2336 "), TrackMode::Cdi2336);
+        assert_eq!(TrackMode::from_name("MODE1").sector_size(), None);
+        Ok(())
+    }
+    #[test]
+    fn flags() {
+        use cuna::track::{Flag, TrackFlags};
+        let mut flags = TrackFlags::from_names(["SCMS", " ", "4ch", "x", "X"]);
+        assert_eq!(
+            flags.iter().collect::<Vec<_>>(),
+            [Flag::FourChannel, Flag::Scms]
+        );
+        assert_eq!(flags.unknown(), ["x"]);
+        assert!(flags.remove(Flag::Scms));
+        assert!(!flags.remove(Flag::Scms));
+        assert_eq!(flags.to_string(), "4CH x");
+        assert!(TrackFlags::new().is_empty());
+    }
+    #[test]
+    fn isrc() -> Result {
+        use cuna::error::{InvalidArgument, ParseError};
+        use cuna::parser::ParseOptions;
+        use cuna::track::Isrc;
+        let isrc: Isrc = "GBAYE0601498".parse().unwrap();
+        assert_eq!(
+            (isrc.country(), isrc.year(), isrc.designation()),
+            ("GB", 2006, 1498)
+        );
+        assert_eq!(isrc.to_string(), "GBAYE0601498");
+        for malformed in [
+            "usrc17607839",
+            "USRC1760783",
+            "USRC1760783X",
+            "US-RC1-76-0783",
+        ] {
+            assert_eq!(
+                malformed.parse::<Isrc>(),
+                Err(InvalidArgument::MalformedIsrc)
+            );
+        }
+        let cue = "FILE \"a.wav\" WAVE\nTRACK 01 AUDIO\nISRC JPSR0110000\nINDEX 01 00:00:00";
+        assert!(cuna::Cuna::new(cue).is_ok());
+        let options = ParseOptions {
+            strict_isrc: true,
+            ..ParseOptions::default()
+        };
+        let err = cuna::Cuna::parse_with(cue, &options).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ParseError::InvalidArgument(InvalidArgument::MalformedIsrc)
+        ));
+        assert_eq!(err.pos(), Some(3));
+        let (sheet, _) = cuna::Cuna::parse_with(&cue.replace("0110000", "01100001 "), &options)?;
+        assert_eq!(
+            sheet[0][0].parsed_isrc(),
+            Some(Ok("JPSR01100001".parse().unwrap()))
+        );
+        Ok(())
+    }
+    #[test]
+    fn file_formats() -> Result {
+        use cuna::track::FileFormat;
+        let sheet = cuna::Cuna::new(CUE)?;
+        assert_eq!(sheet[0].file_format(), FileFormat::Wave);
+        for name in ["WAVE", "MP3", "AIFF", "BINARY", "MOTOROLA"] {
+            let format = FileFormat::from_name(&name.to_ascii_lowercase());
+            assert_eq!(format.to_string(), name);
+            assert_ne!(format.is_audio(), format.is_binary());
+        }
+        let other = FileFormat::from_name(" FLAC");
+        assert_eq!(other.as_str(), "FLAC");
+        assert!(!other.is_audio() && !other.is_binary());
+        Ok(())
+    }
+    #[test]
+    fn approx_eq() -> Result {
+        use cuna::time::TimeStamp;
+        use cuna::track::Index;
+        let index = Index::new(1, TimeStamp::new(4, 15, 44));
+        let late = Index::new(1, TimeStamp::new(4, 15, 46));
+        assert!(index.approx_eq(&late, 2));
+        assert!(!index.approx_eq(&late, 1));
+        assert!(!index.approx_eq(&Index::new(2, TimeStamp::new(4, 15, 44)), 2));
+
+        let sheet = cuna::Cuna::new(CUE)?;
+        let track = sheet[0][1].clone();
+        let mut moved = track.clone();
+        moved.index[0] = late;
+        assert!(track.approx_eq(&moved, 2));
+        assert!(!track.approx_eq(&moved, 1));
+        assert!(!track.approx_eq(&sheet[0][2], u32::MAX));
+        let mut more = track.clone();
+        more.push_index(Index::new(0, TimeStamp::new(4, 13, 50)));
+        assert!(!track.approx_eq(&more, u32::MAX));
+        assert!(!more.approx_eq(&track, u32::MAX));
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod index_number {
+    use cuna::track::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn create() {
+        assert!(IndexNumber::PREGAP.is_pregap());
+        assert!(IndexNumber::START.is_start());
+        assert!(IndexNumber::sub(2).is_sub());
+        assert_eq!(IndexNumber::new_opt(100), None);
+        assert_eq!(IndexNumber::try_from(1), Ok(IndexNumber::START));
+    }
+    #[test]
+    #[should_panic]
+    fn sub_panic() {
+        IndexNumber::sub(1);
+    }
+    #[test]
+    fn display() {
+        assert_eq!(IndexNumber::PREGAP.to_string(), "00");
+        assert_eq!(IndexNumber::sub(12).to_string(), "12");
+    }
+    #[test]
+    fn index() -> super::Result {
+        let index: Index = "INDEX 00 01:02:03".parse()?;
+        assert_eq!(index.number(), IndexNumber::PREGAP);
+        assert_eq!(u8::from(index.number()), 0);
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod export {
+    use super::*;
+    use cuna::export::chapters;
+    use cuna::Cuna;
+
+    #[test]
+    fn chapters() -> Result {
+        let sheet = Cuna::new(CUE)?;
+        let chapters = chapters::chapters(&sheet);
+        assert_eq!(chapters.len(), 7);
+        assert_eq!(chapters[1].title, "エウテルペ");
+        let podlove = chapters::podlove(&sheet);
+        assert_eq!(podlove.lines().nth(1), Some("00:04:15.586 エウテルペ"));
+        let ogg = chapters::ogg(&sheet);
+        assert_eq!(ogg.lines().nth(3), Some("CHAPTER002NAME=エウテルペ"));
+        Ok(())
+    }
+    #[test]
+    fn csv() -> Result {
+        let sheet = Cuna::new(CUE)?;
+        let mut csv = Vec::new();
+        sheet.to_csv(&mut csv)?;
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 8);
+        assert_eq!(
+            csv.lines().nth(2),
+            Some("2,エウテルペ,EGOIST,,04:15:44,03:48:64")
+        );
+        Ok(())
+    }
+    #[test]
+    fn rows() -> Result {
+        use cuna::export::rows::{DiscRow, IndexRow, TrackRow};
+        let sheet = Cuna::new(CUE)?;
+        let rows = sheet.to_rows();
+        assert_eq!(rows.disc.date.as_deref(), Some("2011"));
+        assert_eq!(rows.disc.performer.as_deref(), Some("ギルティクラウン ED"));
+        assert_eq!(rows.tracks.len(), 7);
+        assert_eq!(rows.tracks[1].title.as_deref(), Some("エウテルペ"));
+        assert_eq!(rows.tracks[1].start, Some(19169));
+        assert_eq!(rows.tracks[6].length, None);
+        let positions: Vec<_> = rows.indices.iter().map(|i| i.track).collect();
+        assert_eq!(positions, [1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(DiscRow::COLUMNS.len(), 9);
+        assert_eq!(TrackRow::COLUMNS.len(), 14);
+        assert_eq!(IndexRow::COLUMNS, ["track", "number", "time"]);
+        Ok(())
+    }
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite() -> Result {
+        use cuna::export::rows::SCHEMA;
+        use rusqlite::Connection;
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        let sheet = Cuna::new(CUE)?;
+        let first = sheet.to_rows().insert(&conn).unwrap();
+        let second = sheet.to_rows().insert(&conn).unwrap();
+        assert_ne!(first, second);
+        let count = |sql: &str| -> i64 { conn.query_row(sql, [second], |row| row.get(0)).unwrap() };
+        assert_eq!(count("SELECT COUNT(*) FROM tracks WHERE disc = ?"), 7);
+        assert_eq!(
+            count("SELECT time FROM indices WHERE disc = ? AND track = 2"),
+            19169
+        );
+        assert_eq!(count("SELECT catalog IS NULL FROM discs WHERE id = ?"), 1);
+        Ok(())
+    }
+    #[test]
+    fn m3u() -> Result {
+        use cuna::time::TimeStamp;
+        let sheet = Cuna::new(CUE)?;
+        let mut m3u = String::new();
+        sheet
+            .write_m3u_with_lengths(&[TimeStamp::new(25, 0, 0)], &mut m3u)
+            .unwrap();
+        let lines = m3u.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2 + 7 * 2);
+        assert_eq!(lines[4], "#EXTINF:229,EGOIST - エウテルペ");
+        assert_eq!(
+            lines[15],
+            "EGOIST - Departures ～あなたにおくるアイの歌～.flac"
+        );
+        assert!(!m3u.contains("#EXTINF:-1"));
+        Ok(())
+    }
+    #[test]
+    fn gst_toc() -> Result {
+        use cuna::export::gst;
+        let sheet = Cuna::new(CUE)?;
+        let toc = gst::toc(&sheet);
+        assert_eq!(toc.entries.len(), 1);
+        let chapters = &toc.entries[0].children;
+        assert_eq!(chapters.len(), 7);
+        assert!(chapters.windows(2).all(|w| w[0].stop == w[1].start));
+        assert_eq!(chapters[6].stop, None);
+        Ok(())
+    }
+    #[cfg(feature = "xml")]
+    #[test]
+    fn xml() -> Result {
+        let sheet = Cuna::new(CUE)?;
+        let xml = sheet.to_xml();
+        assert_eq!(xml.matches("<track ").count(), 7);
+        assert!(xml.contains("\n  <rem>GENRE Pop</rem>\n"));
+        assert!(xml.contains(r#"      <index number="01">04:15:44</index>"#));
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod format {
+    use super::*;
+    use cuna::format::canonicalize;
+
+    #[test]
+    fn idempotent() -> Result {
+        let canonical = canonicalize(CUE)?;
+        assert_eq!(canonicalize(&canonical)?, canonical);
+        assert_eq!(cuna::Cuna::new(&canonical)?, cuna::Cuna::new(CUE)?);
+        let messy = "  rem COMMENT x\r\ntitle \" a \"\nFILE \"a.flac\" Wave\n";
+        assert_eq!(
+            canonicalize(messy)?,
+            "REM COMMENT x\nTITLE \" a \"\nFILE \"a.flac\" WAVE\n"
+        );
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod writer {
+    use super::*;
+    use cuna::writer::*;
+    use cuna::Cuna;
+
+    #[test]
+    fn options() -> Result {
+        let sheet = Cuna::new(CUE)?;
+        let default = CueWriter::default().format(&sheet);
+        assert_eq!(default, sheet.to_string());
+        let writer = CueWriter::new()
+            .indent(1)
+            .keyword_case(KeywordCase::Lower)
+            .quote(Quote::WhenNeeded);
+        let written = writer.format(&sheet);
+        assert_eq!(written.lines().nth(12), Some("  title エウテルペ"));
+        assert_eq!(Cuna::new(&written)?, sheet);
+        Ok(())
+    }
+    #[test]
+    fn original_case() -> Result {
+        let source = CUE.replace("TITLE", "Title").replace("INDEX", "index");
+        let sheet = Cuna::new(&source)?;
+        let written = CueWriter::new().original_case(&source).format(&sheet);
+        assert_eq!(written.lines().nth(12), Some("    Title \"エウテルペ\""));
+        assert!(written.contains("    index 01 00:00:00"));
+        assert!(written.contains("FILE \""));
+        let written = CueWriter::new()
+            .keyword_case(KeywordCase::Original)
+            .format(&sheet);
+        assert_eq!(written, sheet.to_string());
+        Ok(())
+    }
+    #[test]
+    fn annotations() -> Result {
+        use cuna::time::HumanFormat;
+        let sheet = Cuna::new(CUE)?;
+        let writer = CueWriter::new().annotations(Some(HumanFormat::Auto));
+        let written = writer.format(&sheet);
+        let lengths: Vec<_> = written
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("REM LENGTH "))
+            .collect();
+        assert_eq!(lengths.len(), sheet.tracks().count() - 1);
+        assert_eq!(lengths[0], "04:15");
+        let annotated = Cuna::new(&written)?;
+        assert_eq!(annotated[0][1].comments().get("START"), Some("04:15"));
+        assert_eq!(writer.format(&annotated), written);
+        assert_eq!(CueWriter::new().format(&annotated), written);
+        Ok(())
+    }
+    #[test]
+    fn zero_pad() -> Result {
+        let sheet = Cuna::new(CUE)?;
+        let padded = CueWriter::new().format(&sheet);
+        assert_eq!(padded.lines().nth(7), Some("  TRACK 01 AUDIO"));
+        assert_eq!(padded.lines().nth(10), Some("    INDEX 01 00:00:00"));
+        let legacy = CueWriter::new().zero_pad(false).format(&sheet);
+        assert_eq!(legacy.lines().nth(7), Some("  TRACK 1 AUDIO"));
+        assert_eq!(legacy.lines().nth(10), Some("    INDEX 1 00:00:00"));
+        Ok(())
+    }
+    #[test]
+    fn command_writer() -> Result {
+        use cuna::parser::Command;
+        let commands = cuna::trim_utf8_header(CUE)
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(Command::new)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut writer = CommandWriter::new(Vec::new());
+        writer.write_all(&commands)?;
+        let written = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(written, Cuna::new(CUE)?.to_string());
+        Ok(())
+    }
+    #[test]
+    fn line_ending_and_bom() -> Result {
+        use cuna::cst::LineEnding;
+        let sheet = Cuna::new(CUE)?;
+        let written = CueWriter::new()
+            .line_ending(LineEnding::CrLf)
+            .bom(true)
+            .format(&sheet);
+        assert!(written.starts_with("\u{feff}REM GENRE Pop\r\n"));
+        assert_eq!(written.matches("\r\n").count(), written.lines().count());
+        assert_eq!(Cuna::new(&written)?, sheet);
+        Ok(())
+    }
+    #[test]
+    fn gaps() -> Result {
+        let sheet = Cuna::new(
+            r#"FILE "a.flac" WAVE
+  TRACK 01 AUDIO
+    PREGAP 00:02:00
+    INDEX 01 00:01:00
+  TRACK 02 AUDIO
+    PREGAP 00:02:00
+    INDEX 01 04:15:44"#,
+        )?;
+        let written = CueWriter::new().gaps(Gaps::Index00).format(&sheet);
+        let lines = written.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[2..4],
+            ["    PREGAP 00:02:00", "    INDEX 01 00:01:00"]
+        );
+        assert_eq!(
+            lines[5..],
+            ["    INDEX 00 04:13:44", "    INDEX 01 04:15:44"]
+        );
+        assert_eq!(
+            CueWriter::new().gaps(Gaps::Pregap).format(&sheet),
+            sheet.to_string()
+        );
+        Ok(())
+    }
+    #[test]
+    fn sanitize() -> Result {
+        let mut sheet = Cuna::new(CUE)?;
+        sheet.files[0].tracks[0].push_title("Line\r\nbreak \"quoted\"".to_owned());
+        let replaced = CueWriter::new().sanitize(Sanitize::Replace).format(&sheet);
+        assert!(replaced.contains("    TITLE \"Line  break 'quoted'\"\n"));
+        assert_eq!(Cuna::new(&replaced)?.files().len(), 1);
+        let stripped = CueWriter::new().sanitize(Sanitize::Strip).format(&sheet);
+        assert!(stripped.contains("    TITLE \"Linebreak quoted\"\n"));
+        let err = CueWriter::new()
+            .sanitize(Sanitize::Reject)
+            .try_format(&sheet)
+            .unwrap_err();
+        assert_eq!((err.keyword, err.character), ("TITLE", '\r'));
+        let escaped = CueWriter::new().format(&sheet);
+        assert!(escaped.contains(
+            r#"    TITLE "Line  break \"quoted\""
+"#
+        ));
+        assert_eq!(
+            Cuna::new(&escaped)?[0][0].title()[1],
+            "Line  break \"quoted\""
+        );
+        // escaped quotes survive parsing and writing with the default writer
+        let parsed = Cuna::new(r#"TITLE "He said \"hi\"""#)?;
+        assert_eq!(Cuna::new(&parsed.to_string())?, parsed);
+        assert_eq!(
+            CueWriter::new()
+                .sanitize(Sanitize::Reject)
+                .try_format(&Cuna::new(CUE)?),
+            Ok(Cuna::new(CUE)?.to_string())
+        );
+        let mut sheet = Cuna::new(CUE)?;
+        sheet.files[0].name = "a \"b\".flac".to_owned();
+        for sanitize in [Sanitize::Replace, Sanitize::Strip].iter().copied() {
+            let written = CueWriter::new().sanitize(sanitize).format(&sheet);
+            assert_eq!(Cuna::new(&written)?[0].name, sheet[0].name);
+        }
+        sheet.files[0].name = r"C:\music\".to_owned();
+        sheet.files[0].tracks[0].push_title(r"a \\ b\".to_owned());
+        let written = sheet.to_string();
+        assert!(written.contains(r#"FILE "C:\\music\\" WAVE"#));
+        assert_eq!(Cuna::new(&written)?, sheet);
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod limits {
+    use cuna::error::{Limit, ParseError};
+    use cuna::parser::{Limits, ParseOptions};
+    use cuna::Cuna;
+    use std::io::Cursor;
+
+    #[test]
+    fn long_line() {
+        let line = format!("TITLE \"{}\"", "a".repeat(Limits::DEFAULT_MAX_LINE_LENGTH));
+        let err = Cuna::new(&line).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseError::LimitExceeded(Limit::LineLength(Limits::DEFAULT_MAX_LINE_LENGTH))
+        );
+        let err = Cuna::from_buf_read(&mut Cursor::new(line.as_bytes())).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseError::LimitExceeded(Limit::LineLength(Limits::DEFAULT_MAX_LINE_LENGTH))
+        );
+        // the limit cuts a character in the middle
+        let mut options = ParseOptions::default();
+        options.limits.max_line_length = 10;
+        let line = "TITLE \"アアアア\"\n";
+        let err =
+            Cuna::from_buf_read_with(&mut Cursor::new(line.as_bytes()), &options).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseError::LimitExceeded(Limit::LineLength(10))
+        );
+        assert_eq!(err.pos(), Some(1));
+    }
+    #[test]
+    fn many_files() {
+        let cue = "FILE \"a.wav\" WAVE\n".repeat(1_000_000);
+        let err = Cuna::new(&cue).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseError::LimitExceeded(Limit::Files(Limits::DEFAULT_MAX_FILES))
+        );
+        assert_eq!(err.pos(), Some(Limits::DEFAULT_MAX_FILES + 1));
+    }
+    #[test]
+    fn many_tracks_and_lines() {
+        let cue = format!("FILE \"a.wav\" WAVE\n{}", "TRACK 01 AUDIO\n".repeat(2000));
+        let mut options = ParseOptions::default();
+        options.limits.max_tracks = 10;
+        let err = Cuna::parse_with(&cue, &options).unwrap_err();
+        assert_eq!(err.kind(), &ParseError::LimitExceeded(Limit::Tracks(10)));
+        let files = "FILE \"a.wav\" WAVE\nTRACK 01 AUDIO\nTRACK 02 AUDIO\n".repeat(10);
+        let err = Cuna::parse_with(&files, &options).unwrap_err();
+        assert_eq!(err.kind(), &ParseError::LimitExceeded(Limit::Tracks(10)));
+        assert_eq!(err.pos(), Some(17));
+        options.limits = Limits::none();
+        options.limits.max_lines = 100;
+        let err = Cuna::parse_with(&cue, &options).unwrap_err();
+        assert_eq!(err.kind(), &ParseError::LimitExceeded(Limit::Lines(100)));
+        assert!(Cuna::parse_with(
+            &cue,
+            &ParseOptions {
+                limits: Limits::none(),
+                ..ParseOptions::default()
+            }
+        )
+        .is_ok());
+    }
+    #[test]
+    fn untrusted() {
+        let options = ParseOptions {
+            limits: Limits::untrusted(),
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            Cuna::parse_with(super::CUE, &options)
+                .ok()
+                .map(|(sheet, _)| sheet),
+            Cuna::new(super::CUE).ok()
+        );
+        let line = format!("REM COMMENT {}\n", "a".repeat(5000));
+        let err =
+            Cuna::from_buf_read_with(&mut Cursor::new(line.as_bytes()), &options).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseError::LimitExceeded(Limit::LineLength(4096))
+        );
+        let cue = "REM COMMENT a\n".repeat(5000);
+        let err = Cuna::parse_with(&cue, &options).unwrap_err();
+        assert_eq!(err.kind(), &ParseError::LimitExceeded(Limit::Lines(4096)));
+    }
+    /// Feeds pseudo-random lines built from cue fragments to the parser, which must never panic
+    #[test]
+    fn random_input() {
+        const FRAGMENTS: &[&str] = &[
+            "FILE ",
+            "TRACK ",
+            "INDEX ",
+            "REM ",
+            "TITLE ",
+            "PREGAP ",
+            "POSTGAP ",
+            "FLAGS ",
+            "ISRC ",
+            "CATALOG ",
+            "\"",
+            " ",
+            "01",
+            "99",
+            "100",
+            "00:00:00",
+            "99:59:74",
+            ":",
+            "AUDIO",
+            "WAVE",
+            "\t",
+            "\r",
+            "ア",
+            "1234567890123",
+        ];
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for _ in 0..2000 {
+            let mut cue = String::new();
+            for _ in 0..(next() % 20) {
+                for _ in 0..(next() % 6) {
+                    cue.push_str(FRAGMENTS[(next() % FRAGMENTS.len() as u64) as usize]);
+                }
+                cue.push('\n');
+            }
+            let _ = Cuna::new(&cue);
+            let _ = Cuna::from_buf_read(&mut Cursor::new(cue.as_bytes()));
+        }
+    }
+}
+#[cfg(test)]
+mod determinism {
+    use super::*;
+    use cuna::Cuna;
+
+    #[test]
+    fn input_order() -> Result {
+        let cue = r#"REM DATE 2011
+REM GENRE Pop
+TITLE "B"
+TITLE "A"
+FILE "b.flac" WAVE
+  TRACK 02 AUDIO
+    FLAGS PRE DCP
+    INDEX 01 00:00:00
+    INDEX 00 00:00:00
+FILE "a.flac" WAVE
+  TRACK 01 AUDIO
+    PERFORMER "Y"
+    PERFORMER "X"
+    INDEX 01 00:00:00"#;
+        let sheet = Cuna::new(cue)?;
+        assert_eq!(sheet.comments.0, ["DATE 2011", "GENRE Pop"]);
+        assert_eq!(sheet.title(), &["B".to_owned(), "A".to_owned()]);
+        let files: Vec<_> = sheet.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(files, ["b.flac", "a.flac"]);
+        let ids: Vec<_> = sheet.tracks().map(|t| t.id()).collect();
+        assert_eq!(ids, [2, 1]);
+        let indices: Vec<_> = sheet[0][0].index.iter().map(|i| i.id()).collect();
+        assert_eq!(indices, [1, 0]);
+        assert_eq!(sheet[0][0].flags(), &["PRE".to_owned(), "DCP".to_owned()]);
+        assert_eq!(sheet[1][0].performer(), &["Y".to_owned(), "X".to_owned()]);
+        Ok(())
+    }
+    #[test]
+    fn deterministic_output() -> Result {
+        let sheet = Cuna::new(CUE)?;
+        let written = sheet.to_string();
+        assert_eq!(sheet.clone().to_string(), written);
+        let reparsed = Cuna::new(&written)?;
+        assert_eq!(reparsed, sheet);
+        assert_eq!(reparsed.to_string(), written);
+        #[cfg(feature = "json")]
+        assert_eq!(
+            Cuna::from_json(&sheet.to_json().unwrap())
+                .unwrap()
+                .to_json()
+                .unwrap(),
+            sheet.to_json().unwrap()
+        );
+        Ok(())
+    }
+}
+#[cfg(all(test, feature = "unstable"))]
+mod experimental {
+    use super::*;
+    use cuna::experimental::merge;
+    use cuna::Cuna;
+
+    #[test]
+    fn merge_discs() -> Result {
+        let sheet = Cuna::new(CUE)?;
+        let merged = merge(vec![sheet.clone(), sheet.clone()]).unwrap();
+        assert_eq!(merged.header, sheet.header);
+        assert_eq!(merged.files().len(), 2);
+        let ids: Vec<_> = merged.tracks().map(|track| track.id()).collect();
+        assert_eq!(ids, (1..=14).collect::<Vec<_>>());
+        assert_eq!(merge(Vec::new()), None);
+        assert_eq!(merge(vec![sheet; 30]), None);
+        Ok(())
+    }
+}
+#[cfg(all(test, feature = "json"))]
+mod json {
+    use super::*;
+    use cuna::Cuna;
+
+    #[test]
+    fn round_trip() -> Result {
+        let sheet = Cuna::new(CUE)?;
+        let json = sheet.to_json_pretty().unwrap();
+        assert!(json.contains(r#""begin_time": "04:15:44""#));
+        assert_eq!(Cuna::from_json(&json).unwrap(), sheet);
+        Ok(())
+    }
+    #[test]
+    fn leading_zero_catalog() -> Result {
+        let sheet = Cuna::new("CATALOG 0075678164125")?;
+        let json = sheet.to_json().unwrap();
+        assert!(json.contains(r#""catalog":"0075678164125""#));
+        assert_eq!(Cuna::from_json(&json).unwrap(), sheet);
+        let json = json.replace("0075678164125", "75678164125");
+        assert!(Cuna::from_json(&json).is_err());
+        Ok(())
+    }
+}
+#[cfg(all(test, feature = "toml"))]
+mod toml {
+    use super::*;
+    use cuna::Cuna;
+
+    #[test]
+    fn round_trip() -> Result {
+        let sheet = Cuna::new(CUE)?;
+        let toml = sheet.to_toml().unwrap();
+        assert!(toml.contains(r#"begin_time = "04:15:44""#));
+        assert_eq!(Cuna::from_toml(&toml).unwrap(), sheet);
+        let sheet = Cuna::new("CATALOG 0075678164125")?;
+        let toml = sheet.to_toml().unwrap();
+        assert!(toml.contains(r#"catalog = "0075678164125""#));
+        assert_eq!(Cuna::from_toml(&toml).unwrap(), sheet);
+        Ok(())
+    }
+}
+#[cfg(all(test, feature = "yaml"))]
+mod yaml {
+    use super::*;
+    use cuna::Cuna;
+
+    #[test]
+    fn round_trip() -> Result {
+        let sheet = Cuna::new(CUE)?;
+        let yaml = sheet.to_yaml().unwrap();
+        assert!(yaml.contains("begin_time: 04:15:44"));
+        assert_eq!(Cuna::from_yaml(&yaml).unwrap(), sheet);
+        let sheet = Cuna::new("CATALOG 0075678164125")?;
+        let yaml = sheet.to_yaml().unwrap();
+        assert!(yaml.contains("catalog: '0075678164125'"));
+        assert_eq!(Cuna::from_yaml(&yaml).unwrap(), sheet);
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod dialect {
+    use super::*;
+    use cuna::dialect::*;
+    use cuna::error::{Limit, ParseError};
+    use cuna::parser::Parser;
+    use cuna::quirks::Tool;
+    use cuna::writer::CueWriter;
+    use cuna::Cuna;
+
+    #[test]
+    fn strict() {
+        let cue = format!("FILE \"a.wav\" WAVE\n{}", "TRACK 01 AUDIO\n".repeat(100));
+        assert!(Cuna::new(&cue).is_ok());
+        let mut parser = Parser::new(&cue).with_dialect(Strict);
+        let err = parser.parse(&mut Cuna::default()).unwrap_err();
+        assert_eq!(err.kind(), &ParseError::LimitExceeded(Limit::Tracks(99)));
+    }
+    #[test]
+    fn cdrdao() -> Result {
+        let cue = "FILE \"a.wav\" WAVE\nDISC_ID \"XY12345\"\nSESSION 2";
+        let mut parser = Parser::new(cue).with_dialect(Cdrdao);
+        let mut sheet = Cuna::default();
+        parser.parse(&mut sheet)?;
+        assert_eq!(sheet[0].unknown.0, [(3, "SESSION 2".to_owned())]);
+        assert_eq!(parser.warnings()[0].pos(), Some(2));
+        let sheet = Cuna::new(CUE)?;
+        assert_eq!(
+            CueWriter::for_dialect(&Eac).format(&sheet),
+            sheet.to_string()
+        );
+        Ok(())
+    }
+    #[test]
+    fn profile() -> Result {
+        let (sheet, _) = Cuna::parse_with(CUE, &Profile::Eac.parse_options())?;
+        assert_eq!(sheet, Cuna::new(CUE)?);
+        assert_eq!(Profile::Eac.writer().format(&sheet), sheet.to_string());
+        let cue = "FILE \"a.wav\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nPREGAP 00:02:00";
+        assert!(Cuna::parse_with(cue, &Profile::Strict.parse_options()).is_err());
+        let (sheet, _) = Cuna::parse_with(cue, &Profile::Xld.parse_options())?;
+        assert!(sheet[0][0].pregap().is_some());
+        assert_eq!(Profile::Foobar2000.dialect().name(), "EAC");
+        assert_eq!(Tool::Xld.profile(), Some(Profile::Xld));
+        assert_eq!(Tool::Cdrdao.dialect().unwrap().name(), "cdrdao");
+        assert_eq!(Tool::Unknown.profile(), None);
+        let options = Profile::Strict.parse_options();
+        assert_eq!(options.dialect.unwrap().name(), "Strict");
+        assert!(options.red_book_minutes);
+        assert_eq!(options.limits.max_tracks, 99);
+        Ok(())
+    }
+    #[test]
+    fn command_handler() -> Result {
+        #[derive(Default)]
+        struct Karaoke {
+            graphics: Vec<(usize, String)>,
+        }
+        impl CommandHandler for Karaoke {
+            fn handle(
+                &mut self,
+                keyword: &str,
+                args: &str,
+                sheet: &mut Cuna,
+            ) -> std::result::Result<Extra, ParseError> {
+                if !keyword.eq_ignore_ascii_case("CDG") {
+                    return Ok(Extra::Unknown);
+                }
+                let files = sheet.files().len();
+                self.graphics
+                    .push((files, args.trim_matches('"').to_owned()));
+                Ok(Extra::Handled)
+            }
+        }
+        let cue =
+            "FILE \"a.bin\" BINARY\ncdg \"a.cdg\"\nTRACK 01 AUDIO\nSIZE_INFO \"1\"\nSESSION 2";
+        let mut karaoke = Karaoke::default();
+        let mut parser = Parser::new(cue).with_dialect(Cdrdao);
+        let mut sheet = Cuna::default();
+        parser.parse_with_handler(&mut sheet, &mut karaoke)?;
+        assert!(sheet[0][0].unknown.lines().eq(["SESSION 2"]));
+        assert_eq!(karaoke.graphics, [(1, "a.cdg".to_owned())]);
+        // the dialect still gets what the handler does not know
+        assert_eq!(parser.warnings().len(), 1);
+        assert!(Cuna::new(cue)?[0].unknown.lines().eq(["cdg \"a.cdg\""]));
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod split {
+    use super::*;
+    use cuna::time::TimeStamp;
+    use cuna::timeline::PostgapPolicy;
+    use cuna::Cuna;
+    use std::fs;
+    use std::path::Path;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    /// Writes a 16-bit stereo 44.1 kHz WAVE file with `frames` CD frames of silence
+    fn wave(path: PathBuf, frames: u32) {
+        let size = frames * 588 * 4;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + size).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&[1, 0, 2, 0]);
+        bytes.extend_from_slice(&44100u32.to_le_bytes());
+        bytes.extend_from_slice(&(44100u32 * 4).to_le_bytes());
+        bytes.extend_from_slice(&[4, 0, 16, 0]);
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&size.to_le_bytes());
+        bytes.resize(bytes.len() + size as usize, 0);
+        fs::write(path, bytes).unwrap();
+    }
+    #[test]
+    fn verify_split() -> Result {
+        let dir = std::env::temp_dir().join(format!("cuna-split-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let sheet = Cuna::new(
+            "FILE \"a.wav\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 01 00:02:00\nTRACK 03 AUDIO\nINDEX 01 00:03:00",
+        )?;
+        wave(dir.join("01.wav"), 150);
+        wave(dir.join("02.wav"), 77);
+        fs::write(dir.join("cover.jpg"), b"")?;
+        let report = sheet.verify_split(&dir, 1, &[])?;
+        assert_eq!(report.tracks[1].drift(), Some(2));
+        assert_eq!(report.drifted().count(), 1);
+        assert_eq!(report.missing, [3]);
+        assert!(!report.is_match());
+        wave(dir.join("02.wav"), 76);
+        wave(dir.join("03.wav"), 10);
+        assert!(sheet.verify_split(&dir, 1, &[])?.is_match());
+        // the length of the last track comes from the length of its FILE
+        let report = sheet.verify_split(&dir, 1, &[TimeStamp::new(0, 4, 0)])?;
+        assert_eq!(report.tracks[2].expected, Some(TimeStamp::new(0, 1, 0)));
+        assert_eq!(report.tracks[2].drift(), Some(-65));
+        assert!(!report.is_match());
+        assert!(sheet
+            .verify_split(&dir, 1, &[TimeStamp::new(0, 3, 10)])?
+            .is_match());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+    #[test]
+    fn postgap_policy() -> Result {
+        let dir = std::env::temp_dir().join(format!("cuna-postgap-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let sheet = Cuna::new(
+            "FILE \"a.wav\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nPOSTGAP 00:01:00\nTRACK 02 AUDIO\nINDEX 01 00:02:00",
+        )?;
+        fs::write(dir.join("01.wav"), b"")?;
+        fs::write(dir.join("02.wav"), b"")?;
+        let probe = |_: &Path| Some(Duration::from_secs(3));
+        let report = sheet.verify_split_with(&dir, 0, &[], probe)?;
+        assert_eq!(report.tracks[0].drift(), Some(75));
+        let append = PostgapPolicy::Append;
+        let report = sheet.verify_split_with_policy(&dir, 0, &[], append, probe)?;
+        assert_eq!(report.tracks[0].expected, Some(TimeStamp::new(0, 3, 0)));
+        assert!(report.is_match());
+        // the last track ends with its file and its POSTGAP
+        let sheet = Cuna::new(
+            "FILE \"a.wav\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 01 00:03:00\nPOSTGAP 00:01:00",
+        )?;
+        let lengths = [TimeStamp::new(0, 5, 0)];
+        let report = sheet.verify_split_with_policy(&dir, 0, &lengths, append, probe)?;
+        assert_eq!(report.tracks[1].expected, Some(TimeStamp::new(0, 3, 0)));
+        assert!(report.is_match());
+        let report =
+            sheet.verify_split_with_policy(&dir, 0, &lengths, PostgapPolicy::Ignore, probe)?;
+        assert_eq!(report.tracks[1].drift(), Some(75));
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod message {
+    use cuna::error::{Warning, WarningKind};
+    use cuna::message::*;
+
+    #[test]
+    fn closure_catalog() {
+        let warning = Warning::new(WarningKind::IgnoredCommand("ARRANGER".to_owned()), 4);
+        let message = warning.message();
+        assert_eq!(message.id, "warning.ignored_command");
+        assert_eq!(message.param("keyword"), Some("ARRANGER"));
+        assert_eq!(message.param("line"), Some("4"));
+        let catalog = |id: &str| match id {
+            "warning.ignored_command" => {
+                Some("{keyword} ignoré (ligne {line}) {unknown}".to_owned())
+            }
+            _ => None,
+        };
+        assert_eq!(
+            localize(&warning, &catalog),
+            "ARRANGER ignoré (ligne 4) {unknown}"
+        );
+    }
+}
+#[cfg(test)]
+mod mojibake {
+    use cuna::mojibake::*;
+
+    #[test]
+    fn latin1() {
+        assert_eq!(detect("Departures"), None);
+        assert_eq!(detect("Ã¼ber").unwrap().repaired.as_deref(), Some("über"));
+        // a legitimate Latin-1 text is not valid UTF-8
+        assert_eq!(detect("Mötley Crüe"), None);
+        let replaced = detect("\u{fffd}\u{fffd}").unwrap();
+        assert_eq!(
+            (replaced.kind, replaced.repaired),
+            (Mojibake::Replaced, None)
+        );
+    }
+    #[test]
+    #[cfg(feature = "encoding")]
+    fn shift_jis() {
+        let repair = detect("ƒGƒSƒCƒXƒg").unwrap();
+        assert_eq!(repair.kind, Mojibake::ShiftJisAsLatin1);
+        assert_eq!(repair.repaired.as_deref(), Some("エゴイスト"));
+    }
+}
+#[cfg(all(test, feature = "encoding"))]
+mod decode {
+    use super::*;
+    use cuna::decode::*;
+    use cuna::error::ParseError;
+    use cuna::Cuna;
+    use encoding_rs::{GBK, SHIFT_JIS, UTF_16BE, UTF_8, WINDOWS_1252};
+
+    #[test]
+    fn from_bytes() -> Result {
+        let expected = Cuna::new(CUE)?;
+        assert_eq!(Cuna::from_bytes(CUE.as_bytes())?, expected);
+        let cue = cuna::trim_utf8_header(CUE);
+        let (sjis, _, _) = SHIFT_JIS.encode(cue);
+        assert_eq!(Cuna::from_bytes(&sjis)?, expected);
+        let mut utf16: Vec<u8> = vec![0xfe, 0xff];
+        utf16.extend(cue.encode_utf16().flat_map(u16::to_be_bytes));
+        assert_eq!(decode(&utf16, &[]).unwrap().1, UTF_16BE);
+        assert_eq!(Cuna::from_bytes(&utf16)?, expected);
+        Ok(())
+    }
+    #[test]
+    fn fallback() -> Result {
+        let (gbk, _, _) = GBK.encode("TITLE \"离开\"");
+        // GBK is only tried after Shift-JIS, which this text is not valid in
+        let sheet = Cuna::from_bytes(&gbk)?;
+        assert_eq!(sheet.title(), &["离开".to_owned()]);
+        assert_eq!(decode(b"TITLE", FALLBACK).unwrap().1, UTF_8);
+        assert_eq!(decode(b"\xe9", FALLBACK).unwrap().1, WINDOWS_1252);
+        let error = Cuna::from_bytes_with(b"\xe9", &[], &Default::default()).unwrap_err();
+        assert_eq!(error.kind(), &ParseError::UnknownEncoding);
+        Ok(())
+    }
+    #[test]
+    fn encode() -> Result {
+        use cuna::cst::LineEnding;
+        use cuna::error::EncodeError;
+        use cuna::writer::{CueWriter, Sanitize};
+        let sheet = Cuna::new(CUE)?;
+        let endings = [LineEnding::Lf, LineEnding::CrLf, LineEnding::Cr];
+        for line_ending in endings.iter().copied() {
+            let writer = CueWriter::new().line_ending(line_ending);
+            let bytes = writer.encode(&sheet, SHIFT_JIS).unwrap();
+            assert_eq!(SHIFT_JIS.decode(&bytes).0, writer.format(&sheet));
+            match writer.encode(&sheet, WINDOWS_1252).unwrap_err() {
+                EncodeError::Unmappable {
+                    character,
+                    line,
+                    column,
+                    ..
+                } => assert_eq!((character, line, column), ('ギ', 5, 12)),
+                err => panic!("{}", err),
+            }
+        }
+        let mut sheet = Cuna::default();
+        sheet.header.push_title("a\nb".to_owned());
+        let err = CueWriter::new()
+            .sanitize(Sanitize::Reject)
+            .encode(&sheet, SHIFT_JIS)
+            .unwrap_err();
+        assert!(matches!(err, EncodeError::InvalidValue(invalid) if invalid.character == '\n'));
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod partition {
+    use super::*;
+    use cuna::partition::*;
+    use cuna::time::TimeStamp;
+    use cuna::track::{Track, TrackInfo};
+    use cuna::Cuna;
+
+    #[test]
+    fn max_tracks() -> Result {
+        let sheet = Cuna::new(CUE)?;
+        let partition = sheet.partition(&PartitionOptions::default());
+        assert_eq!(partition.sheets, std::slice::from_ref(&sheet));
+        let options = PartitionOptions {
+            max_tracks: 3,
+            ..PartitionOptions::default()
+        };
+        let partition = sheet.partition(&options);
+        let counts: Vec<_> = partition
+            .sheets
+            .iter()
+            .map(|s| s.tracks().count())
+            .collect();
+        assert_eq!(counts, [3, 3, 1]);
+        assert!(partition.sheets.iter().all(|s| s.title() == sheet.title()));
+        assert_eq!(partition.sheets[2][0].name, sheet[0].name);
+        let last = partition.mapping[6];
+        assert_eq!((last.original, last.sheet, last.number), (7, 2, 1));
+        assert_eq!(partition.sheets[1][0][0].index, sheet[0][3].index);
+        Ok(())
+    }
+    #[test]
+    fn max_length() {
+        let mut sheet = Cuna::default();
+        for (name, minutes) in [("a.flac", &[0, 30, 50][..]), ("b.flac", &[0, 10, 30])] {
+            let mut file = TrackInfo::new(name.to_owned(), "WAVE".to_owned());
+            for &m in minutes {
+                file.push_track(
+                    Track::new(1, "AUDIO".to_owned()).with_index(1, TimeStamp::new(m, 0, 0)),
+                );
+            }
+            sheet.push_file(file);
+        }
+        let options = PartitionOptions::max_length(TimeStamp::new(40, 0, 0));
+        let partition = sheet.partition(&options);
+        let parts: Vec<Vec<_>> = partition
+            .sheets
+            .iter()
+            .map(|s| {
+                s.files
+                    .iter()
+                    .map(|f| (f.name.as_str(), f.tracks.len()))
+                    .collect()
+            })
+            .collect();
+        // the last track of a.flac has an unknown length
+        assert_eq!(
+            parts,
+            [
+                vec![("a.flac", 1)],
+                vec![("a.flac", 2), ("b.flac", 1)],
+                vec![("b.flac", 2)]
+            ]
+        );
+        let numbers: Vec<_> = partition.sheets[1].tracks().map(|t| t.id()).collect();
+        assert_eq!(numbers, [1, 2, 3]);
+        assert!(Cuna::default().partition(&options).mapping.is_empty());
+    }
+}
+#[cfg(test)]
+mod empty {
+    use super::*;
+    use cuna::error::ValidationError;
+    use cuna::export::chapters;
+    use cuna::transform::Pipeline;
+    use cuna::Cuna;
+
+    const SHEETS: &[&str] = &[
+        "",
+        "REM DATE 2011\nTITLE \"Departures\"\nCATALOG 4547366179934",
+        "FILE \"a.flac\" WAVE",
+        "TITLE \"Departures\"\nFILE \"a.flac\" WAVE\nFILE \"b.flac\" WAVE",
+    ];
+
+    #[test]
+    fn derived() -> Result {
+        for cue in SHEETS {
+            let sheet = Cuna::new(cue)?;
+            assert!(sheet.is_empty());
+            assert_eq!(Cuna::new(&sheet.to_string())?, sheet);
+            assert_eq!(sheet.tracks().count(), 0);
+            assert!(sheet.last_track().is_none());
+            assert!(sheet.timeline().is_empty());
+            assert!(sheet.validate().is_empty());
+            assert!(sheet.diff(&sheet).is_empty());
+            assert!(chapters::chapters(&sheet).is_empty());
+            assert!(sheet.to_rows().tracks.is_empty());
+            let partition = sheet.partition(&Default::default());
+            assert_eq!(partition.sheets, std::slice::from_ref(&sheet));
+            let mut csv = Vec::new();
+            sheet.to_csv(&mut csv)?;
+            assert_eq!(csv.iter().filter(|&&b| b == b'\n').count(), 1);
+            sheet.to_m3u();
+            #[cfg(feature = "xml")]
+            sheet.to_xml();
+            sheet.one_liner();
+            sheet.completeness();
+            let mut transformed = sheet.clone();
+            let pipeline = Pipeline::new().normalize().renumber().shift(-75);
+            assert!(transformed.transform(&pipeline).is_unchanged());
+        }
+        Ok(())
+    }
+    #[test]
+    fn from_parts() -> Result {
+        for cue in &SHEETS[..2] {
+            let sheet = Cuna::new(cue)?;
+            let (header, files, comments) = (sheet.header, sheet.files, sheet.comments);
+            assert!(Cuna::from_parts(header, files, comments).is_ok());
+        }
+        let sheet = Cuna::new(SHEETS[3])?;
+        let issues = Cuna::from_parts(sheet.header, sheet.files, sheet.comments).unwrap_err();
+        assert_eq!(
+            issues.0,
+            [ValidationError::EmptyFile(1), ValidationError::EmptyFile(2)]
+        );
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod toc {
+    use super::*;
+    use cuna::time::TimeStamp;
+    use cuna::toc::*;
+    use cuna::Cuna;
+
+    #[test]
+    fn multiple_files() -> Result {
+        let sheet = Cuna::new(
+            r#"FILE "1.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+FILE "2.wav" WAVE
+  TRACK 02 AUDIO
+    PREGAP 00:02:00
+    INDEX 01 00:00:00"#,
+        )?;
+        let toc = Toc::new(vec![0, 13650], 31650);
+        let unknown = sheet.verify_against_toc(&toc);
+        assert_eq!(unknown.tracks[1].cue, None);
+        assert_eq!(unknown.leadout_offset, None);
+        assert_eq!(unknown.is_match(), None);
+        let lengths = [TimeStamp::new(3, 0, 0), TimeStamp::new(4, 0, 0)];
+        let report = sheet.verify_against_toc_with_lengths(&toc, &lengths);
+        assert_eq!(report.tracks[1].offset(), Some(0));
+        assert_eq!(report.leadout_offset, Some(0));
+        assert_eq!(report.is_match(), Some(true));
+        let other = Toc::new(vec![0, 13600], 31650);
+        let report = sheet.verify_against_toc_with_lengths(&other, &lengths);
+        assert_eq!(report.common_offset(), None);
+        assert_eq!(report.mismatched().map(|c| c.id).collect::<Vec<_>>(), [2]);
+        assert_eq!(report.is_match(), Some(false));
+        let last = Toc {
+            first_track: 255,
+            starts: vec![0, 13650],
+            leadout: 31650,
+        };
+        assert_eq!(sheet.verify_against_toc(&last).missing, [255]);
+        Ok(())
+    }
+    #[test]
+    fn from_toc() {
+        let toc = Toc {
+            first_track: 3,
+            starts: vec![0, 19169],
+            leadout: 36379,
+        };
+        let sheet = Cuna::from_toc(&toc, &TocMetadata::default()).unwrap();
+        assert_eq!(sheet[0][1].id(), 4);
+        assert_eq!(sheet.verify_against_toc(&toc).is_match(), Some(true));
+        let err = Cuna::from_toc_points(&[0, 19169], 19169, &TocMetadata::default());
+        assert_eq!(err, Err(cuna::error::TocError::NotIncreasing(2)));
+        let empty = Cuna::from_toc_points(&[], 0, &TocMetadata::default());
+        assert_eq!(empty, Err(cuna::error::TocError::NoTracks));
+    }
+}
+#[cfg(test)]
+mod paths {
+    use super::*;
+    use cuna::paths::rebase;
+    use cuna::Cuna;
+
+    #[test]
+    fn rebase_relative() {
+        assert_eq!(rebase("a.flac", "/music/Album", "/music"), "Album/a.flac");
+        assert_eq!(
+            rebase("./a.flac", "/music/Album", "/backup/Album"),
+            "../../music/Album/a.flac"
+        );
+        assert_eq!(
+            rebase("../b/a.flac", "music/a", "music/a/cue"),
+            "../../b/a.flac"
+        );
+        assert_eq!(rebase("a.flac", "/music", "../cue"), "/music/a.flac");
+    }
+    #[test]
+    fn rebase_windows() {
+        assert_eq!(rebase(r"CD1\a.wav", r"C:\Rips", r"c:\rips\CD1"), r"a.wav");
+        assert_eq!(rebase(r"a.wav", r"C:\Rips", r"D:\Rips"), r"C:\Rips\a.wav");
+        assert_eq!(rebase(r"D:\a.wav", r"C:\Rips", r"C:\"), r"D:\a.wav");
+    }
+    #[test]
+    fn rebase_paths() -> Result {
+        let sheet = Cuna::new("FILE \"1.wav\" WAVE\nFILE \"../2.wav\" WAVE")?;
+        let moved = sheet.rebase_paths("/music/Album/cd", "/music");
+        assert_eq!(moved[0].name, "Album/cd/1.wav");
+        assert_eq!(moved[1].name, "Album/2.wav");
+        assert_eq!(moved.header, sheet.header);
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod scan {
+    use super::*;
+    use cuna::scan;
+    use cuna::Cuna;
+
+    #[test]
+    fn track_starts_match_cuna() -> Result {
+        let sheet = Cuna::new(CUE)?;
+        let starts: Vec<_> = scan::track_starts(CUE).collect();
+        assert_eq!(starts.len(), sheet.tracks().count());
+        for (scanned, track) in starts.iter().zip(sheet.tracks()) {
+            assert_eq!(scanned.file, sheet[0].name);
+            assert_eq!(scanned.track, track.id());
+            assert_eq!(Some(scanned.start), track.start());
+        }
+        Ok(())
+    }
+    #[test]
+    fn track_starts_skip_broken_lines() {
+        let cue = "FILE \"a.wav\" WAVE\nTRACK 01 AUDIO\nINDEX 00 00:00:00\nTRACK 02 AUDIO\nINDEX 01 00:02:90\nTRACK 03 AUDIO\nFILE \"b \\\"c\\\".wav\" WAVE\nTRACK 04 AUDIO\nINDEX 01 01:00:00";
+        let starts: Vec<_> = scan::track_starts(cue)
+            .map(|s| (s.file, s.track, s.start.to_string()))
+            .collect();
+        assert_eq!(
+            starts,
+            [
+                ("a.wav", 1, "00:00:00".to_owned()),
+                (r#"b \"c\".wav"#, 4, "01:00:00".to_owned())
+            ]
+        );
+        let numbers: Vec<_> = scan::lines("\r\n\rTITLE x\r\rREM\n")
+            .map(|l| l.number)
+            .collect();
+        assert_eq!(numbers, [3, 5]);
+    }
+}
+#[cfg(test)]
+mod diff {
+    use super::*;
+    use cuna::diff::Change;
+    use cuna::Cuna;
+
+    #[test]
+    fn changes() -> Result {
+        let old = Cuna::new(CUE)?;
+        assert!(old.diff(&old).is_empty());
+        let mut new = old.clone();
+        new.comments.push("DISCNUMBER 1".to_owned());
+        new.files[0].tracks.remove(1);
+        new.files[0].tracks[2].title.clear();
+        new.files[0].name = "a.flac".to_owned();
+        new.push_file(old.files[0].clone());
+        assert_eq!(
+            old.diff(&new),
+            [
+                Change::CommentsChanged,
+                Change::FileChanged(0),
+                Change::TrackRemoved { file: 0, track: 2 },
+                Change::TrackChanged { file: 0, track: 4 },
+                Change::FileAdded(1),
+            ]
+        );
+        assert_eq!(new.diff(&old).last(), Some(&Change::FileRemoved(1)));
+        Ok(())
+    }
+    #[test]
+    fn moved_unknown() -> Result {
+        let cue = "SESSION 1\nFILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    SESSION 2\n    INDEX 01 00:00:00";
+        let old = Cuna::new(cue)?;
+        let new = Cuna::new(&format!("TITLE \"Departures\"\n{}", cue))?;
+        assert_eq!(old.diff(&new), [Change::HeaderChanged]);
+        Ok(())
+    }
+    #[cfg(feature = "watch")]
+    #[test]
+    fn watch() {
+        use std::sync::mpsc;
+        use std::time::Duration;
+        let dir = std::env::temp_dir().join(format!("cuna-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.cue");
+        std::fs::write(&path, CUE).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let watcher = cuna::watch::watch(&path, move |update| {
+            let update = update.map(|u| (u.changes.to_vec(), u.sheet.tracks().count()));
+            tx.send(update.map_err(|e| e.to_string())).unwrap();
+        })
+        .unwrap();
+        // saved like editors do, so the sheet is never read half written
+        let saved = dir.join("a.cue.tmp");
+        std::fs::write(&saved, CUE.replace("\"エウテルペ\"", "\"Euterpe\"")).unwrap();
+        std::fs::rename(&saved, &path).unwrap();
+        let update = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(
+            update,
+            Ok((vec![Change::TrackChanged { file: 0, track: 2 }], 7))
+        );
+        drop(watcher);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
+#[cfg(test)]
+mod lazy {
+    use super::*;
+    use cuna::error::{Limit, ParseError};
+    use cuna::lazy::LazySheet;
+    use cuna::parser::ParseOptions;
+    use cuna::Cuna;
+
+    #[test]
+    fn tracks() -> Result {
+        let full = Cuna::new(CUE)?;
+        let lazy = LazySheet::new(CUE)?;
+        assert_eq!(lazy.tracks().len(), 7);
+        for (at, track) in full.tracks().enumerate() {
+            assert_eq!(&lazy.track(at)?.0, track);
+            assert_eq!(lazy.find_track(track.id()), Some(at));
+        }
+        let (mut file, _) = lazy.file(0)?;
+        file.tracks = full[0].tracks.clone();
+        assert_eq!(file, full[0]);
+        let (header, _) = lazy.header()?;
+        assert_eq!(
+            (header.header, header.comments),
+            (full.header, full.comments)
+        );
+        let late = CUE.replacen("    PERFORMER \"EGOIST\"", "    REM DATE 2011", 1);
+        let (_, warnings) = LazySheet::new(&late)?.track(0)?;
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings,
+            Cuna::parse_with(&late, &ParseOptions::default())?.1
+        );
+        Ok(())
+    }
+    #[test]
+    fn errors() -> Result {
+        let broken = CUE.replace("INDEX 01 08:04:33", "INDEX 01 ??");
+        let lazy = LazySheet::new(&broken)?;
+        assert!(lazy.track(1).is_ok());
+        let full = Cuna::new(&broken).unwrap_err();
+        assert_eq!(lazy.track(2).unwrap_err().pos(), full.pos());
+        let mut options = ParseOptions::default();
+        options.limits.max_tracks = 5;
+        let err = LazySheet::with_options(CUE, options).unwrap_err();
+        assert_eq!(err.kind(), &ParseError::LimitExceeded(Limit::Tracks(5)));
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod quirks {
+    use super::*;
+    use cuna::document::Document;
+    use cuna::quirks::{Quirk, Tool};
+    use cuna::validation::FixPolicy;
+    use cuna::Cuna;
+
+    #[test]
+    fn eac() -> Result {
+        let sheet = Cuna::new(CUE)?;
+        let quirks = sheet.quirks();
+        assert_eq!(quirks.tool, Tool::Eac);
+        assert_eq!(quirks.quirks, [Quirk::WaveForCompressed]);
+        assert_eq!(quirks.fix_policy(), FixPolicy::CopyPregapIndex);
+        assert_eq!(quirks.writer().format(&sheet), sheet.to_string());
+        assert_eq!(Document::parse(CUE)?.quirks(), quirks);
+        Ok(())
+    }
+    #[test]
+    fn layout() -> Result {
+        let cue = "REM COMMENT \"X Lossless Decoder version 20230627\"\nFILE \"a.wav\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nPREGAP 00:02:00\nINDEX 00 04:13:50\n";
+        let document = Document::parse(cue)?;
+        let quirks = document.quirks();
+        assert_eq!(quirks.tool, Tool::Xld);
+        assert_eq!(
+            quirks.quirks,
+            [
+                Quirk::PregapCommands,
+                Quirk::MissingStartIndex,
+                Quirk::Unindented
+            ]
+        );
+        assert_eq!(quirks.fix_policy(), FixPolicy::UsePregapLength);
+        assert_eq!(quirks.writer().format(document.sheet()), cue);
+        Ok(())
+    }
 }